@@ -0,0 +1,17 @@
+#!/usr/bin/env bash
+#
+# Builds the client surface for wasm32-unknown-unknown, as browser frontends use it
+#
+
+here="$(dirname "$0")"
+
+cd "$here"/..
+
+set -e
+
+rustup target add wasm32-unknown-unknown
+
+set -x
+cargo build --target wasm32-unknown-unknown --lib --features client
+cargo build --target wasm32-unknown-unknown --lib --features client,wasm
+cargo clippy --target wasm32-unknown-unknown --lib --features client,wasm -- -D warnings
@@ -0,0 +1,52 @@
+//! Writes the borsh layouts of the instruction args and accounts to layouts.json, or to the given path.
+//! With --check it fails instead if the file differs from the layouts, so layout changes are always deliberate
+use std::{env, fs, process};
+use yoyo_bets::{
+    layouts::{layouts_json, ACCOUNTS},
+    state::{MAX_BET_DATA_LENGTH, MAX_BETTING_MARKET_DATA_LEN, MAX_ACCEPTED_BET_DATA_LEN, MAX_CREATOR_EXPOSURE_DATA_LEN, MAX_USER_STATS_DATA_LEN, MAX_FEED_EXPOSURE_DATA_LEN, MAX_RECEIPT_DATA_LEN, MAX_ACCEPT_ORDER_DATA_LEN, MAX_PROGRAM_INFO_DATA_LEN, MAX_POOL_DATA_LEN, MAX_POOL_POSITION_DATA_LEN},
+    utils::DISCRIMINATOR_LEN
+};
+
+fn main() {
+    let mut check = false;
+    let mut path = "layouts.json".to_string();
+    for arg in env::args().skip(1) {
+        if arg == "--check" {
+            check = true;
+        } else {
+            path = arg;
+        }
+    }
+
+    // the layouts and the size constants must describe the same accounts
+    let size_constants = [
+        ("Bet", MAX_BET_DATA_LENGTH),
+        ("BettingMarket", MAX_BETTING_MARKET_DATA_LEN),
+        ("AcceptedBet", MAX_ACCEPTED_BET_DATA_LEN),
+        ("CreatorExposure", MAX_CREATOR_EXPOSURE_DATA_LEN),
+        ("UserStats", MAX_USER_STATS_DATA_LEN),
+        ("FeedExposure", MAX_FEED_EXPOSURE_DATA_LEN),
+        ("Receipt", MAX_RECEIPT_DATA_LEN),
+        ("AcceptOrder", MAX_ACCEPT_ORDER_DATA_LEN),
+        ("ProgramInfo", MAX_PROGRAM_INFO_DATA_LEN),
+        ("Pool", MAX_POOL_DATA_LEN),
+        ("PoolPosition", MAX_POOL_POSITION_DATA_LEN),
+    ];
+    for (name, size) in size_constants.iter() {
+        let layout = ACCOUNTS.iter().find(|layout| layout.name == *name).expect("account layout missing");
+        if layout.max_len() + DISCRIMINATOR_LEN != *size {
+            eprintln!("{} layout is {} bytes, its size constant is {}", name, layout.max_len() + DISCRIMINATOR_LEN, size);
+            process::exit(1);
+        }
+    }
+
+    let json = layouts_json();
+    if check {
+        if fs::read_to_string(&path).ok().as_deref() != Some(json.as_str()) {
+            eprintln!("{} is out of date, run cargo run --features client --bin export-layouts", path);
+            process::exit(1);
+        }
+    } else {
+        fs::write(&path, json).expect("failed to write layouts");
+    }
+}
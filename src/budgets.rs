@@ -0,0 +1,64 @@
+//! Compute unit limits for clients to request per instruction, and the compute checkpoints debug builds log.
+//! The limits are the worst case path of each instruction (the token payment path creates escrow and winner ATAs)
+//! with a margin of about 30%, so a keeper requesting them doesn't fail on a busy account
+use solana_program::{
+    instruction::Instruction,
+    pubkey::Pubkey
+};
+
+/// The ComputeBudget program, the same id on every cluster
+pub mod compute_budget_program {
+    solana_program::declare_id!("ComputeBudget111111111111111111111111111111");
+}
+
+pub const CU_INIT_BETTING_MARKET: u32 = 40_000;
+pub const CU_CREATE_BET_SOL: u32 = 60_000;
+pub const CU_CREATE_BET_TOKEN: u32 = 80_000;
+pub const CU_ACCEPT_BET_SOL: u32 = 70_000;
+pub const CU_ACCEPT_BET_TOKEN: u32 = 95_000;
+pub const CU_CANCEL_BET_SOL: u32 = 30_000;
+pub const CU_CANCEL_BET_TOKEN: u32 = 45_000;
+pub const CU_FINALIZE_SOL: u32 = 70_000;
+pub const CU_FINALIZE_TOKEN: u32 = 110_000; // includes creating the winner's and the fee wallet's ATAs
+pub const CU_SETTLE_TOKEN: u32 = 110_000;
+pub const CU_DISTRIBUTE_TOKEN: u32 = 60_000;
+pub const CU_ACCEPT_MULTIPLE_PER_GROUP: u32 = CU_ACCEPT_BET_TOKEN; // AcceptMultiple requests this per bet it fills
+// the most compute a transaction can request
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+// the ComputeBudget instruction tag of SetComputeUnitLimit
+const SET_COMPUTE_UNIT_LIMIT_TAG: u8 = 2;
+
+/// The limit to request for CreateBet with the payment mint
+pub fn create_bet_units(payment_mint: &Pubkey) -> u32 {
+    if *payment_mint == spl_token::native_mint::id() { CU_CREATE_BET_SOL } else { CU_CREATE_BET_TOKEN }
+}
+
+/// The limit to request for AcceptBet with the payment mint
+pub fn accept_bet_units(payment_mint: &Pubkey) -> u32 {
+    if *payment_mint == spl_token::native_mint::id() { CU_ACCEPT_BET_SOL } else { CU_ACCEPT_BET_TOKEN }
+}
+
+/// Creates a ComputeBudget SetComputeUnitLimit Instruction, capped at MAX_COMPUTE_UNIT_LIMIT
+pub fn set_compute_unit_limit(units: u32) -> Instruction {
+    let mut data = vec![SET_COMPUTE_UNIT_LIMIT_TAG];
+    data.extend_from_slice(&units.min(MAX_COMPUTE_UNIT_LIMIT).to_le_bytes());
+    Instruction {
+        program_id: compute_budget_program::id(),
+        accounts: vec![],
+        data
+    }
+}
+
+/// Logs the remaining compute units after the label, in debug builds only so deployed programs don't pay for it.
+/// Benchmarks subtract consecutive checkpoints to attribute an instruction's cost
+#[inline(always)]
+pub fn compute_checkpoint(label: &str) {
+    #[cfg(debug_assertions)]
+    {
+        solana_program::msg!("Compute checkpoint: {}", label);
+        solana_program::log::sol_log_compute_units();
+    }
+    #[cfg(not(debug_assertions))]
+    let _ = label;
+}
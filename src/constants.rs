@@ -0,0 +1,21 @@
+//! Known program ids per cluster
+
+/// Pyth oracle program on mainnet-beta
+pub mod pyth_mainnet {
+    solana_program::declare_id!("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH");
+}
+
+/// Pyth oracle program on devnet
+pub mod pyth_devnet {
+    solana_program::declare_id!("gSbePebfvPy7tRqimPoVecS2UsBvYv46ynrzWocc92s");
+}
+
+/// Pyth oracle program on testnet
+pub mod pyth_testnet {
+    solana_program::declare_id!("8tfDNiaEyrV6Q1U4DEXrEigs9DoDtkugzFbybENEbCDz");
+}
+
+/// True if the id is the Pyth oracle program of a known cluster
+pub fn is_known_pyth_program(program_id: &solana_program::pubkey::Pubkey) -> bool {
+    *program_id == pyth_mainnet::id() || *program_id == pyth_devnet::id() || *program_id == pyth_testnet::id()
+}
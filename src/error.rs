@@ -48,7 +48,7 @@ pub enum BetError {
     #[error("Invalid accounts")]
     InvalidAccounts,
 
-    // Invalid tournament account
+    // Invalid bet account
     #[error("Invalid bet account")]
     InvalidBetAccount,
 
@@ -103,6 +103,427 @@ pub enum BetError {
     // Before bet expiry time
     #[error("Before expiry time")]
     BeforeExpiryTime,
+
+    // Expiration time beyond the market's maximum horizon
+    #[error("Expiration time too far in the future")]
+    ExpirationTooFar,
+
+    // Client view of the bet doesn't match on-chain state
+    #[error("State mismatch")]
+    StateMismatch,
+
+    // Betting market account doesn't match the bet
+    #[error("Wrong betting market account")]
+    WrongBettingMarket,
+
+    // Escrow account doesn't match the bet
+    #[error("Wrong escrow account")]
+    WrongEscrowAccount,
+
+    // Oracle account doesn't match the bet
+    #[error("Wrong oracle account")]
+    WrongOracleAccount,
+
+    // Payment account doesn't match the bet
+    #[error("Wrong payment account")]
+    WrongPaymentAccount,
+
+    // Bet escrow no longer holds or delegates enough tokens
+    #[error("Escrow not funded")]
+    EscrowNotFunded,
+
+    // Token program isn't spl-token / Token-2022 or isn't the market's token program
+    #[error("Invalid token program")]
+    InvalidTokenProgram,
+
+    // Mint has an extension that breaks escrow accounting (transfer fee, transfer hook, non-transferable, permanent delegate)
+    #[error("Unsupported mint extension")]
+    UnsupportedMintExtension,
+
+    // Price account isn't on the market's oracle allowlist
+    #[error("Oracle not allowed")]
+    OracleNotAllowed,
+
+    // Price account is already on the market's oracle allowlist
+    #[error("Oracle already allowed")]
+    OracleAlreadyAllowed,
+
+    // Market's oracle allowlist has no free slots
+    #[error("Oracle allowlist full")]
+    OracleAllowlistFull,
+
+    // More accepted mints given than a market can hold, or a mint given twice
+    #[error("Invalid accepted mints")]
+    InvalidAcceptedMints,
+
+    // Bet account doesn't match the accepted bet
+    #[error("Wrong bet account")]
+    WrongBetAccount,
+
+    // Minimum fill is larger than the bet size
+    #[error("Invalid minimum fill")]
+    InvalidMinFill,
+
+    // Fill is below the bet's minimum fill and doesn't take the remaining capacity
+    #[error("Fill below minimum")]
+    FillBelowMinimum,
+
+    // Acceptor cap must allow at least one acceptor
+    #[error("Invalid max acceptors")]
+    InvalidMaxAcceptors,
+
+    // Bet already has its maximum number of acceptors
+    #[error("Max acceptors reached")]
+    MaxAcceptorsReached,
+
+    // Accept is within the market's cutoff before expiration
+    #[error("Too close to expiry")]
+    TooCloseToExpiry,
+
+    // Accept executed after the acceptor's deadline
+    #[error("Accept deadline exceeded")]
+    AcceptDeadlineExceeded,
+
+    // Bet tag is longer than MAX_TAG_LEN bytes
+    #[error("Tag too long")]
+    TagTooLong,
+
+    // Expiration time is closer to creation than the market's minimum duration
+    #[error("Duration too short")]
+    DurationTooShort,
+
+    // Creator's last bet is more recent than the market's min seconds between bets
+    #[error("Creating bets too fast")]
+    CreatingTooFast,
+
+    // Creator already has the market's max number of open bets
+    #[error("Too many open bets")]
+    TooManyOpenBets,
+
+    // Pyth account isn't owned by the market's pyth program
+    #[error("Oracle account has the wrong owner")]
+    OracleWrongOwner,
+
+    // Pyth account doesn't start with the pyth magic number
+    #[error("Oracle account has a bad magic number")]
+    OracleBadMagic,
+
+    // Pyth account isn't the supported pyth version
+    #[error("Oracle account has the wrong version")]
+    OracleWrongVersion,
+
+    // Pyth account isn't the expected account type
+    #[error("Oracle account has the wrong account type")]
+    OracleWrongAccountType,
+
+    // Pyth product account doesn't reference the price account
+    #[error("Oracle product and price accounts don't match")]
+    OracleProductPriceMismatch,
+
+    // Finalizer isn't the creator or acceptor during the market's finalize exclusivity window
+    #[error("Finalize reserved for the bet's parties")]
+    FinalizeReservedForParties,
+
+    // Cancel amount is zero or more than the bet's unmatched size
+    #[error("Invalid cancel amount")]
+    InvalidCancelAmount,
+
+    // Price account is delisted by the market, new bets can't use it
+    #[error("Oracle delisted")]
+    OracleDelisted,
+
+    // Market's oracle denylist has no free slots
+    #[error("Oracle denylist full")]
+    OracleDenylistFull,
+
+    // Rollover interval must be positive
+    #[error("Invalid rollover interval")]
+    InvalidRolloverInterval,
+
+    // Bet wasn't created with rollover
+    #[error("Not a rollover bet")]
+    NotRolloverBet,
+
+    // Bet hasn't expired or still has accepts to finalize
+    #[error("Bet not settled")]
+    BetNotSettled,
+
+    // Rollover balance can't refill the bet and pay the keeper
+    #[error("Insufficient rollover balance")]
+    InsufficientRolloverBalance,
+
+    // Market settles on a single price read, it doesn't record observations
+    #[error("Settlement observations disabled")]
+    SettlementObservationsDisabled,
+
+    // Recording is before expiration, or after the settlement window with enough observations
+    #[error("Outside settlement window")]
+    OutsideSettlementWindow,
+
+    // Bet already has MAX_SETTLEMENT_OBSERVATIONS observations
+    #[error("Settlement observations full")]
+    SettlementObservationsFull,
+
+    // Price at this pyth publish slot is already recorded
+    #[error("Observation slot already recorded")]
+    DuplicateObservationSlot,
+
+    // Finalize before the settlement window closed and the observations are full
+    #[error("Settlement window open")]
+    SettlementWindowOpen,
+
+    // Fewer recorded observations than the market's minimum
+    #[error("Not enough settlement observations")]
+    NotEnoughObservations,
+
+    // Cancel condition prices are out of order or it leaves no time to accept
+    #[error("Invalid cancel condition")]
+    InvalidCancelCondition,
+
+    // Odds are below the market's minimum odds
+    #[error("Odds below market minimum")]
+    OddsBelowMinimum,
+
+    // Odds are above the market's maximum odds
+    #[error("Odds above market maximum")]
+    OddsAboveMaximum,
+
+    // Market only lists fixed-odds bets
+    #[error("Variable odds not allowed")]
+    VariableOddsNotAllowed,
+
+    // Escrow token account is frozen by the mint's freeze authority
+    #[error("Escrow frozen")]
+    EscrowFrozen,
+
+    // Instruction must be a top-level instruction of the transaction, not a CPI
+    #[error("CPI not allowed")]
+    CpiNotAllowed,
+
+    // Expiration time or slot is not after now, or too far ahead
+    #[error("Invalid expiration time")]
+    InvalidExpirationTime,
+
+    // Cancel condition time is not after now, or too far ahead
+    #[error("Invalid cancel time")]
+    InvalidCancelTime,
+
+    // Accept deadline is not after now, or too far ahead
+    #[error("Invalid deadline")]
+    InvalidDeadline,
+
+    // Pyth program isn't a known cluster's Pyth program and the market doesn't allow unverified ones
+    #[error("Unknown oracle program")]
+    UnknownOracleProgram,
+
+    // Bet's odds were updated within the market's quote cooldown and the acceptor didn't pass matching expected odds
+    #[error("Quote cooldown")]
+    QuoteCooldown,
+
+    // Accept is larger than the bet's remaining acceptable size
+    #[error("Accept exceeds remaining capacity")]
+    AcceptExceedsCapacity,
+
+    // Settlement price is negative, bets only settle on non-negative prices
+    #[error("Negative settlement price")]
+    NegativeSettlementPrice,
+
+    // SettleBet already paid the winner, the accepted bet finishes with DistributeFees
+    #[error("Bet already settled")]
+    BetSettled,
+
+    // DistributeFees needs SettleBet to have paid the winner first
+    #[error("SettleBet required")]
+    SettleBetRequired,
+
+    // SOL bets finalize in one phase with FinalizeBet
+    #[error("Two phase settlement is for token bets")]
+    TwoPhaseSettlementTokenOnly,
+
+    // Accept would pay its winner more than the market's max_position_payout
+    #[error("Position payout exceeds the market's cap")]
+    PositionPayoutExceedsCap,
+
+    // AcceptBestBet candidates are on another market or oracle
+    #[error("Candidate bets must share the market and oracle")]
+    CandidateMismatch,
+
+    // No AcceptBestBet candidate, or pair of candidates with a split, can take the size
+    #[error("No candidate bet can take the size")]
+    NoCandidateFits,
+
+    // PlaceAcceptOrder args are out of range
+    #[error("Invalid accept order")]
+    InvalidAcceptOrder,
+
+    // MatchOrder bet isn't on the order's feed and mint, or its direction, expiration or odds are outside the order's
+    #[error("Bet does not match the accept order")]
+    OrderDoesNotMatch,
+
+    // the price account no longer links back to the bet's product, or reports a different exponent
+    #[error("Oracle feed changed since the bet was created")]
+    OracleFeedChanged,
+
+    // two of the instruction's writable accounts are the same account, the log names the roles
+    #[error("Duplicate account")]
+    DuplicateAccount,
+
+    // a processor tried a bet or position status move the transition rules don't allow, the log names both statuses
+    #[error("Invalid status transition")]
+    InvalidStatusTransition,
+
+    // the oracle's price status isn't Trading (Unknown, Halted, Auction, or stale), retry once the feed trades again
+    #[error("Oracle price unavailable")]
+    OraclePriceUnavailable,
+
+    // CreatePool or JoinPool args are out of range
+    #[error("Invalid pool arguments")]
+    InvalidPool,
+
+    // JoinPool after the pool's accept cutoff or settlement
+    #[error("Pool is closed to new stakes")]
+    PoolClosed,
+
+    // a participant's stakes in a pool must all be on the side of their first one
+    #[error("Pool position is on the other side")]
+    PoolSideMismatch,
+
+    // SettlePool before the pool's expiration
+    #[error("Pool has not expired")]
+    PoolNotExpired,
+
+    // ExtendExpiry on a bet that hasn't expired, has nothing left to settle or is a rollover bet, the log says which
+    #[error("Bet expiry can't be extended")]
+    ExpiryNotExtendable,
+
+    // the bet has used the market's max_extensions, or the market allows none. RefundExpiredAcceptedBet refunds it once the feed's refund delay passes
+    #[error("Bet has no expiry extensions left")]
+    ExpiryExtensionsExhausted,
+
+    // the oracle published a valid price after the bet's expiration, finalize can settle on it
+    #[error("Oracle published a price after expiration")]
+    OraclePricePublished,
+
+    // a lamport debit would leave a program owned account open below its rent exempt minimum
+    #[error("Would break rent exemption")]
+    WouldBreakRentExemption,
+
+    // CreatePool on a market that settles on recorded observations, pools settle on a single oracle read
+    #[error("Pools can't settle on observations")]
+    PoolSettlesOnObservations,
+
+    // CreateBet's escrow already exists and is owned by someone other than the escrow authority of the creator's bet
+    #[error("Escrow not owned by the bet's escrow authority")]
+    EscrowNotOwnedByCreator,
+
+    // a SOL bet in a market with strict_sol_funding wasn't funded by system transfers earlier in its transaction
+    #[error("Escrow not funded in the transaction")]
+    EscrowNotFundedInTransaction,
+
+    // RefundExpiredAcceptedBet on a bet its oracle can still settle, or that settles on recorded observations
+    #[error("Bet can't be refunded")]
+    NotRefundable,
+
+    // InitBettingMarket with a mint another market already accepts, the log names the market
+    #[error("Mint accepted by another market")]
+    MintInAnotherMarket,
+
+    // Codes 1000 to 1099 are reserved for rejected instruction arguments. The code is the args struct's base
+    // plus the field's index: CreateBetArgs from 1000, AcceptBetArgs from 1050. Other errors are added above
+
+    // CreateBetArgs.bet_size is invalid
+    #[error("Invalid argument: CreateBetArgs.bet_size")]
+    InvalidCreateBetSize = 1000,
+
+    // CreateBetArgs.odds is invalid
+    #[error("Invalid argument: CreateBetArgs.odds")]
+    InvalidCreateOdds,
+
+    // CreateBetArgs.expiration_time is invalid
+    #[error("Invalid argument: CreateBetArgs.expiration_time")]
+    InvalidCreateExpirationTime,
+
+    // CreateBetArgs.bet_direction is invalid
+    #[error("Invalid argument: CreateBetArgs.bet_direction")]
+    InvalidCreateBetDirection,
+
+    // CreateBetArgs.bet_price is invalid
+    #[error("Invalid argument: CreateBetArgs.bet_price")]
+    InvalidCreateBetPrice,
+
+    // CreateBetArgs.cancel_condition is invalid
+    #[error("Invalid argument: CreateBetArgs.cancel_condition")]
+    InvalidCreateCancelCondition,
+
+    // CreateBetArgs.variable_odds is invalid
+    #[error("Invalid argument: CreateBetArgs.variable_odds")]
+    InvalidCreateVariableOdds,
+
+    // CreateBetArgs.min_fill is invalid
+    #[error("Invalid argument: CreateBetArgs.min_fill")]
+    InvalidCreateMinFill,
+
+    // CreateBetArgs.max_acceptors is invalid
+    #[error("Invalid argument: CreateBetArgs.max_acceptors")]
+    InvalidCreateMaxAcceptors,
+
+    // CreateBetArgs.tag is invalid
+    #[error("Invalid argument: CreateBetArgs.tag")]
+    InvalidCreateTag,
+
+    // CreateBetArgs.rollover_interval_secs is invalid
+    #[error("Invalid argument: CreateBetArgs.rollover_interval_secs")]
+    InvalidCreateRolloverIntervalSecs,
+
+    // CreateBetArgs.expiry_kind is invalid
+    #[error("Invalid argument: CreateBetArgs.expiry_kind")]
+    InvalidCreateExpiryKind,
+
+    // CreateBetArgs.max_odds_change is invalid
+    #[error("Invalid argument: CreateBetArgs.max_odds_change")]
+    InvalidCreateMaxOddsChange,
+
+    // CreateBetArgs.max_total_acceptable is invalid
+    #[error("Invalid argument: CreateBetArgs.max_total_acceptable")]
+    InvalidCreateMaxTotalAcceptable,
+
+    // CreateBetArgs.nonce is invalid
+    #[error("Invalid argument: CreateBetArgs.nonce")]
+    InvalidCreateNonce,
+
+    // CreateBetArgs.both_sides is invalid
+    #[error("Invalid argument: CreateBetArgs.both_sides")]
+    InvalidCreateBothSides,
+
+    // CreateBetArgs.payout_curve is invalid
+    #[error("Invalid argument: CreateBetArgs.payout_curve")]
+    InvalidCreatePayoutCurve,
+
+    // AcceptBetArgs.bet_size is invalid
+    #[error("Invalid argument: AcceptBetArgs.bet_size")]
+    InvalidAcceptBetSize = 1050,
+
+    // AcceptBetArgs.expected_start_price is invalid
+    #[error("Invalid argument: AcceptBetArgs.expected_start_price")]
+    InvalidAcceptExpectedStartPrice,
+
+    // AcceptBetArgs.deadline is invalid
+    #[error("Invalid argument: AcceptBetArgs.deadline")]
+    InvalidAcceptDeadline,
+
+    // AcceptBetArgs.expected_odds is invalid
+    #[error("Invalid argument: AcceptBetArgs.expected_odds")]
+    InvalidAcceptExpectedOdds,
+
+    // AcceptBetArgs.side is invalid
+    #[error("Invalid argument: AcceptBetArgs.side")]
+    InvalidAcceptSide,
+}
+
+/// Logs the rejected argument's field and value, and returns its error from the reserved argument range
+pub fn invalid_argument<T: std::fmt::Debug>(error: BetError, field: &str, value: T) -> ProgramError {
+    msg!("Invalid argument {}: {:?}", field, value);
+    error.into()
 }
 
 impl PrintProgramError for BetError {
@@ -0,0 +1,180 @@
+use borsh::{BorshSerialize, BorshDeserialize};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::CompiledInstruction,
+    log::sol_log_data,
+    msg,
+    program::invoke_signed,
+    pubkey::Pubkey
+};
+use crate::{
+    error::BetError,
+    instruction::{self, BetInstruction},
+    pdas::find_event_authority,
+    state::{Direction, BetStatus, PositionStatus},
+    utils::EVENT_AUTHORITY_SEED
+};
+
+// prefix the runtime puts in front of sol_log_data output
+pub const PROGRAM_DATA_LOG_PREFIX: &str = "Program data: ";
+
+/// Events emitted by the program with sol_log_data, borsh encoded
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub enum BetEvent {
+    BetCreated {
+        bet: Pubkey,
+        bet_escrow_account: Pubkey,
+        escrow_authority: Pubkey, // PDA with transfer authority for the escrow token accounts
+        odds: i64,
+        bet_size: u64,
+        start_price: i64,
+        remaining_capacity: u64,
+        tag: String, // padded to MAX_TAG_LEN with 0u8s
+        created_at: i64,
+        nonce: u64, // NO_NONCE if the bet state account isn't derived from a nonce
+        refund_destination: Pubkey, // the default pubkey if none
+    },
+    BetAccepted {
+        bet: Pubkey,
+        accepted_bet: Pubkey,
+        accepted_bet_escrow_account: Pubkey,
+        escrow_authority: Pubkey,
+        bet_size: u64,
+        acceptor_payment_amount: u64,
+        effective_odds: i64, // odds after the variable odds adjustment
+        remaining_capacity: u64,
+        updated_at: i64,
+        creator_direction: Direction, // the creator's side of the position, the acceptor took the opposite
+        status: BetStatus, // the bet's status after the fill
+    },
+    FinalizeSimulated {
+        bet: Pubkey,
+        accepted_bet: Pubkey,
+        settleable: bool, // false before the bet's expiration time, the outcome is at the current price
+        price: i64,
+        creator_wins: bool,
+        winner_payment_account: Pubkey,
+        winner_amount: u64,
+        loser_amount: u64, // the loser's stake the payout curve leaves them, 0 on binary bets
+        commission_amount: u64,
+        finalizer_amount: u64,
+        degraded: bool, // price published after the market's preferred settlement window, no finalizer reward
+        oracle_feed_changed: bool, // the feed changed since the bet was created, RefundExpiredAcceptedBet refunds both stakes
+        oracle_unavailable: bool, // the feed isn't trading, RefundExpiredAcceptedBet refunds both stakes
+    },
+    BetCancelled {
+        bet: Pubkey,
+        refunded_amount: u64, // escrow balance returned to the creator
+        remaining_capacity: u64, // unmatched size left, 0 once the bet is cancelled
+        refunded_bounty_lamports: u64, // unpaid keeper bounty returned to the creator
+        updated_at: i64,
+        status: BetStatus, // Cancelled after a full cancel
+    },
+    BetRolledOver {
+        bet: Pubkey,
+        expiration_time: i64,
+        start_price: i64,
+        bet_price: i64,
+        rollover_balance: u64, // left after refilling the bet and paying the keeper
+        keeper_reward: u64,
+        updated_at: i64,
+        status: BetStatus, // Open, the next period starts with no accepts
+    },
+    BetFinalized {
+        bet: Pubkey,
+        accepted_bet: Pubkey,
+        price: i64,
+        creator_wins: bool,
+        winner_payment_account: Pubkey,
+        winner_amount: u64,
+        loser_amount: u64, // the loser's stake the payout curve leaves them, 0 on binary bets
+        commission_amount: u64,
+        finalizer_amount: u64,
+        degraded: bool, // price published after the market's preferred settlement window, the finalizer reward went to the winner
+        fee_exempt: bool, // the market owner's bet, no commission was charged
+        keeper_bounty_amount: u64, // lamports of the bet's keeper bounty paid to the finalizer
+        oracle_feed_changed: bool, // the feed changed since the bet was created, each party got their stake back
+        oracle_unavailable: bool, // the feed wasn't trading ORACLE_UNAVAILABLE_REFUND_SECS after expiration, each party got their stake back
+        updated_at: i64,
+        status: PositionStatus, // Settled, or Refunded when the oracle feed changed or wasn't trading
+    },
+    CreateBetValidated {
+        creator: Pubkey,
+        betting_market: Pubkey,
+        pyth_oracle_price_account: Pubkey,
+        start_price: i64, // the price CreateBet would record now
+    },
+    ExpiryExtended {
+        bet: Pubkey,
+        expiration_time: i64, // the new expiration
+        extensions_used: u8,
+    },
+    PoolSettled {
+        pool: Pubkey,
+        settlement_price: i64,
+        winning_side: Direction,
+        refunded: bool, // a side had no stakes or the oracle feed changed, every stake is returned
+        side_totals: [u64; 2],
+        commission_amount: u64,
+    },
+}
+
+/// Logs the event as borsh encoded program data
+pub fn emit_event(event: &BetEvent) -> ProgramResult {
+    sol_log_data(&[&event.try_to_vec()?]);
+    Ok(())
+}
+
+/// Emits the event as the data of a self-CPI EmitEvent instruction signed by the event authority PDA,
+/// inner instruction data isn't truncated like the logs are
+pub fn emit_event_cpi<'a>(
+    program_id: &Pubkey,
+    event_authority_account_info: &AccountInfo<'a>,
+    program_account_info: &AccountInfo<'a>,
+    event: &BetEvent,
+) -> ProgramResult {
+    let (event_authority, bump_seed) = find_event_authority(program_id);
+    if event_authority != *event_authority_account_info.key {
+        msg!("Incorrect event authority: expected {}, got {}", event_authority, event_authority_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+    if program_account_info.key != program_id {
+        msg!("Incorrect program account: expected {}, got {}", program_id, program_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+    invoke_signed(
+        &instruction::emit_event(*program_id, event.clone()),
+        &[
+            event_authority_account_info.clone(),
+            program_account_info.clone()
+        ],
+        &[&[EVENT_AUTHORITY_SEED.as_bytes(), &[bump_seed]]]
+    )
+}
+
+/// Extracts all bet events from the inner instructions of a confirmed transaction,
+/// account_keys are the transaction's account keys the instructions index into
+pub fn parse_events_from_inner_instructions(
+    program_id: &Pubkey,
+    account_keys: &[Pubkey],
+    inner_instructions: &[CompiledInstruction],
+) -> Vec<BetEvent> {
+    inner_instructions.iter()
+        .filter(|ix| account_keys.get(ix.program_id_index as usize) == Some(program_id))
+        .filter_map(|ix| match BetInstruction::try_from_slice(&ix.data) {
+            Ok(BetInstruction::EmitEvent(event)) => Some(event),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Extracts all bet events from the log messages of a confirmed transaction
+pub fn parse_events_from_logs(logs: &[String]) -> Vec<BetEvent> {
+    logs.iter()
+        .filter_map(|log| log.strip_prefix(PROGRAM_DATA_LOG_PREFIX))
+        .filter_map(|data| base64::decode(data).ok())
+        .filter_map(|bytes| BetEvent::try_from_slice(&bytes).ok())
+        .collect()
+}
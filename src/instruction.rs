@@ -1,21 +1,97 @@
 use borsh::{BorshSerialize, BorshDeserialize};
 use solana_program::{
     instruction::{AccountMeta, Instruction},
+    msg,
+    program_error::ProgramError,
     pubkey::Pubkey,
     sysvar,
     system_program
 };
 
 use crate::{
-    state::{CancelCondition, Direction}
+    error::BetError,
+    state::{CancelCondition, Direction, ExpiryKind, PayoutCurve, TiePolicy},
+    events::BetEvent,
+    pdas::{find_escrow_authority, find_market, find_mint_registry, find_accepted_bet, find_accepted_escrow, get_escrow_address, find_event_authority, find_creator_exposure, find_user_stats, find_accept_order, find_feed_exposure, find_receipt, find_program_info, find_program_data, find_pool, find_pool_position},
+    token::get_associated_token_address,
+    utils::anchor_discriminator
 };
 
 #[repr(C)]
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
 /// Args for init betting market
 pub struct InitBettingMarketArgs {
-    pub sol_payment: bool, // true is paid with SOL, false is paid with a token
-    pub payment_mint: Option<Pubkey>
+    pub accepted_mints: Vec<Pubkey>, // up to MAX_ACCEPTED_MINTS settlement mints, the native mint for SOL
+    pub max_expiration_horizon_secs: i64, // max seconds between bet creation and expiration, 0 is unlimited
+    pub min_duration_secs: i64, // min seconds between bet creation and expiration, at most the horizon
+    pub allow_variable_odds: bool, // false rejects bets with variable odds
+    pub allow_unverified_oracle_program: bool // true accepts a pyth program outside constants, for local testing only
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+/// Args for update betting market, None leaves the field unchanged
+pub struct UpdateBettingMarketArgs {
+    pub max_expiration_horizon_secs: Option<i64>,
+    pub accept_cutoff_secs: Option<i64>,
+    pub min_duration_secs: Option<i64>,
+    pub min_seconds_between_bets: Option<i64>,
+    pub max_open_bets_per_creator: Option<u32>,
+    pub finalizer_reward: Option<FinalizerReward>,
+    pub finalize_exclusivity_secs: Option<i64>,
+    pub settlement_observations: Option<SettlementObservations>,
+    pub preferred_settlement_window_secs: Option<i64>,
+    pub odds_bounds: Option<OddsBounds>,
+    pub clock_tolerance_secs: Option<i64>, // at most MAX_CLOCK_TOLERANCE_SECS and half the accept cutoff
+    pub quote_cooldown_secs: Option<i64>,
+    pub max_position_payout: Option<u64>, // 0 removes the cap, applies to later accepts only
+    pub sol_commission_account: Option<Pubkey>, // the default pubkey pays SOL commission to fee_commission_account again
+    pub settlement_receipts: Option<bool>, // applies to later finalizes and settles
+    pub supplied_start_price: Option<SuppliedStartPriceBounds>, // max_age_slots 0 ignores supplied start prices
+    pub expiry_extensions: Option<ExpiryExtensions>, // max_extensions 0 disables ExtendExpiry
+    pub strict_sol_funding: Option<bool>, // applies to later SOL bets
+    pub tie_policy: Option<TiePolicy> // applies to later finalizes, settles and pool settlements
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+/// How close and how recent a creator's supplied start price must be for CreateBet to record it
+pub struct SuppliedStartPriceBounds {
+    pub tolerance_bps: u16, // at most MAX_START_PRICE_TOLERANCE_BPS from the oracle's price at the publish slot
+    pub max_age_slots: u64 // publish slots older than this are ignored
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+/// How many times and how far ExtendExpiry can move a bet's expiration when its feed published nothing after it
+pub struct ExpiryExtensions {
+    pub max_extensions: u8, // at most MAX_EXPIRY_EXTENSIONS
+    pub extension_secs: i64 // between 1 and MAX_EXTENSION_SECS when max_extensions is set
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+/// Odds bounds of new creates and accepts
+pub struct OddsBounds {
+    pub min_odds: i64, // at least 100
+    pub max_odds: i64 // at least min_odds
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+/// Windowed settlement, finalize settles on the median of the prices recorded after expiration
+pub struct SettlementObservations {
+    pub window_secs: i64, // 0 settles on a single price read at finalize
+    pub min_count: u8 // between 1 and MAX_SETTLEMENT_OBSERVATIONS when window_secs is set
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+/// Finalizer reward schedule, in bps of the matched bet size
+pub struct FinalizerReward {
+    pub base_bps: u16, // reward at expiration
+    pub growth_bps_per_hour: u16, // linear growth per hour since expiration
+    pub max_bps: u16 // cap, between base_bps and MAX_FINALIZER_REWARD_BPS
 }
 
 #[repr(C)]
@@ -24,11 +100,33 @@ pub struct InitBettingMarketArgs {
 pub struct CreateBetArgs {
     pub bet_size: u64,
     pub odds: i64, // the odds given for the bet, e.g. even odds = 2.00 = 200
-    pub expiration_time: i64, // the time at which the bet expires
+    pub expiration_time: i64, // the time or slot at which the bet expires, see expiry_kind
     pub bet_direction: Direction, // "above" / "below"
     pub bet_price: i64, // the price the asset must be above/below at expiration time
     pub cancel_condition: CancelCondition,
     pub variable_odds: Option<i64>, // the amount price must change for odds to increase by 1
+    pub min_fill: u64, // smallest bet_size an acceptor can take, unless it takes all the remaining capacity
+    pub max_acceptors: Option<u16>, // max number of acceptors, None is unlimited
+    pub tag: String, // free-form label, at most MAX_TAG_LEN bytes
+    pub rollover_interval_secs: Option<i64>, // if given, the bet can be rolled over to the next period every this many seconds (slots for slot expiries)
+    pub expiry_kind: ExpiryKind, // whether expiration_time is a unix timestamp or a slot
+    pub max_odds_change: Option<i64>, // caps the variable odds adjustment in either direction, only with variable_odds
+    pub max_total_acceptable: Option<u64>, // caps the total accepted size below bet_size, None makes all of bet_size acceptable
+    pub nonce: Option<u64>, // the creator's nonce the bet state account was created from, see pdas::derive_bet_addresses
+    pub both_sides: bool, // acceptors pick their side at the posted odds, half the size per side. Not with variable_odds
+    pub payout_curve: PayoutCurve, // Binary, or LinearPerPoint with units_per_point and cap both above 0
+    pub keeper_bounty_lamports: u64, // lamports from the creator shared among the finalizers of the bet's accepts
+    pub supplied_start_price: Option<SuppliedStartPrice>, // the creator's read of the oracle, recorded if the program can verify it
+    pub refund_destination: Option<Pubkey>, // where cancels return unmatched escrow instead of the payment account, fixed at creation
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+/// A start price read off the oracle by the creator. CreateBet records it if the oracle's current or previous aggregate
+/// was published at publish_slot within the market's tolerance of price, and publish_slot is recent, else the live price
+pub struct SuppliedStartPrice {
+    pub price: i64,
+    pub publish_slot: u64
 }
 
 #[repr(C)]
@@ -39,92 +137,596 @@ pub struct AcceptBetArgs {
     // E.g. original bet size 200, odds 1.50. Total payments = 200*1.50 = 300. (acceptors must pay 300 - 200 = 100 total)
     // bet_size = 100, so is accepting half the original bet, so this acceptor pays 50.
     pub bet_size: u64,
+    pub expected_start_price: Option<i64>, // if given, must match the bet's stored start price
+    pub deadline: Option<i64>, // if given, the accept fails when executed after this unix timestamp
+    pub expected_odds: Option<i64>, // if given, must match the bet's effective odds, required during the market's quote cooldown
+    pub side: Option<Direction>, // the acceptor's side, required on both_sides bets and None otherwise
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+/// Args for accept best bet, each fill is an AcceptBet with these args
+pub struct AcceptBestBetArgs {
+    pub bet_size: u64, // total size to accept across the chosen candidates
+    pub deadline: Option<i64>, // if given, the accept fails when executed after this unix timestamp
+    pub side: Option<Direction>, // the acceptor's side, for candidates that are both_sides bets
+    pub allow_split: bool, // true lets the size be split across two candidates when that costs less
+}
+
+// most candidate bets an AcceptBestBet can take
+pub const MAX_ACCEPT_BEST_CANDIDATES: usize = 4;
+// accounts of each AcceptBestBet candidate
+pub const ACCEPT_BEST_CANDIDATE_ACCOUNTS: usize = 5;
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+/// One bet of an AcceptMultiple, filled like an AcceptBet with these args
+pub struct AcceptMultipleFill {
+    pub bet_size: u64,
+    pub expected_odds: Option<i64>, // if given, must match the bet's effective odds, required during the market's quote cooldown
+    pub side: Option<Direction>, // the acceptor's side, required on both_sides bets and None otherwise
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+/// Args for accept multiple
+pub struct AcceptMultipleArgs {
+    pub fills: Vec<AcceptMultipleFill>, // one per bet group, in the groups' order
+    pub deadline: Option<i64>, // if given, the accepts fail when executed after this unix timestamp
+    pub skip_failed: bool, // true skips a bet failing AcceptBet's checks and logs why, false fails them all
+}
+
+// most bets an AcceptMultiple can fill, a token market fill takes about a quarter of the default compute budget
+pub const MAX_ACCEPT_MULTIPLE_GROUPS: usize = 4;
+// accounts of each AcceptMultiple bet group
+pub const ACCEPT_MULTIPLE_GROUP_ACCOUNTS: usize = 7;
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+/// Args for create pool
+pub struct CreatePoolArgs {
+    pub seed_amount: u64, // the creator's stake, on side
+    pub side: Direction,
+    pub bet_price: i64, // Above wins at or above it, Below wins below it
+    pub expiration_time: i64, // unix timestamp, after the market's accept cutoff from now
+    pub nonce: u64, // any value distinct from the creator's other pools in the market, derives the pool PDA
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+/// Args for join pool
+pub struct JoinPoolArgs {
+    pub stake: u64,
+    pub side: Direction, // must be the side of the participant's earlier stakes in the pool
+}
+
+// most accepted bets a CloseSettledAccounts can close, bounding its compute
+pub const MAX_CLOSE_SETTLED_ACCOUNTS: usize = 20;
+// accounts of each CloseSettledAccounts position
+pub const CLOSE_SETTLED_POSITION_ACCOUNTS: usize = 2;
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+/// Args for place accept order
+pub struct PlaceAcceptOrderArgs {
+    pub size: u64, // total bet size the order fills
+    pub direction: Direction, // the acceptor's side
+    pub min_odds: i64, // odds bounds of the fills, the escrow is funded at max_odds
+    pub max_odds: i64,
+    pub min_expiration_time: i64, // unix timestamps bounding the expiration of the bets it fills
+    pub max_expiration_time: i64,
+    pub matcher_fee_bps: u16, // paid to the matcher of each fill, at most MAX_MATCHER_FEE_BPS of its acceptor payment
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+/// Args for clone bet, the other bet parameters are copied from the source bet
+pub struct CloneBetArgs {
+    pub bet_size: u64,
+    pub expiration_time: i64, // in the source bet's expiry units, the cancel condition time keeps its distance from expiration
+    pub relative_strike: bool, // true keeps the bet and cancel prices' offsets from the spot price, false copies them as they are
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+/// Args for increase bet
+pub struct IncreaseBetArgs {
+    pub amount: u64, // added to the rollover balance
 }
 
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+/// Args for delist oracle
+pub struct DelistOracleArgs {
+    pub delisted: bool, // true delists the price account, false lists it again
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+/// Args for cancel bet
+pub struct CancelBetArgs {
+    pub amount: u64, // unmatched size to withdraw, CANCEL_ALL_REMAINING withdraws all of it and cancels the bet
+}
+
+// cancel amount that withdraws all the bet's unmatched size
+pub const CANCEL_ALL_REMAINING: u64 = u64::MAX;
+
 /// Instructions supported by the YoYo Bet program
 #[derive(BorshSerialize, BorshDeserialize, Clone)]
 pub enum BetInstruction {
-    // [signer] owner_account
-    // [writable] betting_market_account
-    // [] commission_fee_account
+    // [signer, writable] owner_account - pays the rent of the betting market account
+    // [writable] betting_market_account - PDA ["market", accepted_mints[0]], created by the instruction
+    // [] commission_fee_account - wallet, token bets pay commission to its ATA of the bet's mint
     // [] pyth_program
+    // [] rent_sysvar
+    // [] system_program
+    // [] instructions_sysvar - the instruction can't be invoked via CPI
+    // [] accepted mint accounts - one per accepted mint, in the same order, owned by spl-token or Token-2022
+    // [writable] mint registry accounts - PDA ["mint", mint] per accepted mint, in the same order, created by the instruction
     InitBettingMarket(InitBettingMarketArgs),
 
-    // [signer] creator_main_account
+    // [signer, writable] creator_main_account - pays the keeper bounty
     // [writable] creator_payment_account
-    // [writable] bet_state_account
-    // [writable] bet_escrow_account
+    // [writable] bet_state_account - holds the keeper bounty on top of its rent
+    // [writable] bet_escrow_account - the ATA of the escrow authority PDA, wrapped SOL for SOL bets
     // [] betting_market_account
     // [] pyth_oracle_product_account
     // [] pyth_oracle_price_account
     // [] rent_sysvar
+    // [] token_program - the program that owns the bet's mint
     // [] system_program
-    // [] token_program
+    // [] clock_sysvar
+    // [] payment_mint - one of the market's accepted mints, the native mint for SOL bets
+    // [] escrow authority PDA - [PREFIX, betting_market_account, bet_state_account]
+    // [] associated_token_program
+    // [] event authority PDA - ["__event_authority"], signs the EmitEvent self-CPI
+    // [] yoyo bet program
+    // [writable] creator_exposure_account - PDA ["creator", betting_market_account, creator_main_account], created on the creator's first bet
+    // [writable] feed_exposure_account - PDA ["feed", betting_market_account, pyth_oracle_price_account], created on the feed's first bet
+    // [] instructions_sysvar - read for the transaction's earlier transfers into the escrow of a SOL bet
+    // [] refund_destination_account - only with refund_destination, a token account of payment_mint for token bets
     CreateBet(CreateBetArgs),
 
-    // [signer] acceptor_main_account
+    // [signer, writable] acceptor_main_account - pays the rent of the accepted bet state and escrow accounts
     // [writable] acceptor_payment_account
     // [writable] bet_state_account
     // [writable] bet_escrow_account
-    // [writable] accepted_bet_state_account
-    // [writable] accepted_bet_escrow_account
+    // [writable] accepted_bet_state_account - PDA [PREFIX, bet_state_account, acceptor_main_account, accepted_count], created by the instruction
+    // [writable] accepted_bet_escrow_account - PDA ["escrow", accepted_bet_state_account], created by the instruction as
+    //     a token account owned by the accepted escrow authority [PREFIX, betting_market_account, accepted_bet_state_account]
+    //     and funded with both stakes
     // [] betting_market_account
     // [] pyth_oracle_price_account
-    // [] rent_sysvar
+    // [] rent_sysvar - read by the token program's InitializeAccount2
+    // [] token_program - the program that owns the bet's mint
     // [] system_program
-    // [] token_program
-    // [] clock_sysvar
-    // [] PDA - has transfer authority for escrow token accounts
+    // [] PDA - owns the bet escrow token account, [PREFIX, betting_market_account, bet_state_account]
+    // [] payment_mint - the bet's mint
+    // [writable] feed_exposure_account - PDA ["feed", betting_market_account, pyth_oracle_price_account]
+    // The clock is read with the sysvar syscall and the BetAccepted event is logged, so neither takes an account. SOL
+    // bets escrow wrapped SOL, so both bet types need the token accounts. A SOL accept can pass the acceptor_main_account
+    // as its acceptor_payment_account, 13 distinct accounts, a token accept passes 14
     AcceptBet(AcceptBetArgs),
 
-    // [signer] creator_main_account
-    // [writable] creator_payment_account
+    // [signer, writable] creator_main_account - refunded the unpaid keeper bounty on a full cancel with no accepts left to finalize
+    // [writable] creator_payment_account - the bet's refund_destination if it has one
     // [writable] bet_state_account
     // [writable] bet_escrow_account
     // [] betting_market_account
-    // [] system_program
-    // [] token_program
-    // [] PDA
-    CancelBet(),
+    // [] token_program - the program that owns the bet's mint
+    // [] PDA - owns the bet escrow token account
+    // [] clock_sysvar
+    // [] event authority PDA
+    // [] yoyo bet program
+    // [writable] creator_exposure_account
+    // [writable] feed_exposure_account
+    // partial withdrawals from SOL bets are paid as wrapped SOL, so creator_payment_account must be a native mint token account
+    CancelBet(CancelBetArgs),
 
-    // [signer] finalizer_main_account
+    // [signer, writable] finalizer_main_account - pays the rent of the winner's and commission ATAs if they have to be created, gets a share of the keeper bounty
     // [writable] finalizer_payment_account
-    // [writable] commission_fee_account
-    // [] bet_state_account
-    // [] accepted_bet_state_account
+    // [writable] commission_fee_account - the market's SOL commission account for SOL bets, fee_commission_account's ATA of the mint for token bets
+    // [writable] bet_state_account - finalized_count is incremented
+    // [writable] accepted_bet_state_account
     // [writable] accepted_bet_escrow_account
     // [writable] creator_payment_account
     // [writable] acceptor_payment_account
+    // [] betting_market_account
+    // [] pyth_price_account
+    // [] token_program - the program that owns the bet's mint
+    // [] system_program
+    // [writable] PDA - owns the accepted bet escrow token account, pays out unwrapped SOL for SOL bets
+    // [] clock_sysvar
+    // [] event authority PDA
+    // [] yoyo bet program
+    // [writable] feed_exposure_account
+    // [writable] creator_exposure_account - PDA ["creator", betting_market, creator_main_account], the bet leaves its open
+    // bets when its last accept is finalized
+    // token bets whose winner payment account was closed or isn't a token account of the mint only - the winner is paid into their ATA:
+    // [] payment_mint
+    // [] winner_main_account
+    // [writable] winner ATA - created if missing, the finalizer is reimbursed with the accepted escrow's rent
+    // [] associated_token_program
+    // token bets of a mint the commission ATA doesn't exist for yet only, unless the bet is fee exempt - the finalizer creates it:
+    // [] payment_mint
+    // [] fee_commission_account - the market's commission authority
+    // [] associated_token_program
+    // markets with settlement_receipts only - the finalizer pays the receipt's rent:
+    // [] rent_sysvar
+    // [writable] receipt PDA ["receipt", accepted_bet_state_account]
+    // optional, to update the creator's and acceptor's stats - created if missing, the finalizer pays the rent:
+    // [] rent_sysvar
+    // [writable] creator stats PDA ["stats", betting_market, creator_main_account]
+    // [writable] acceptor stats PDA ["stats", betting_market, acceptor_main_account]
+    FinalizeBet(),
+
+    // [signer] owner_account
+    // [writable] betting_market_account
+    // [] instructions_sysvar - the instruction can't be invoked via CPI
+    UpdateBettingMarket(UpdateBettingMarketArgs),
+
+    // [signer] owner_account
+    // [writable] betting_market_account
+    // [] pyth_oracle_price_account - must be owned by the market's pyth program
+    // [] instructions_sysvar - the instruction can't be invoked via CPI
+    AddAllowedOracle(),
+
+    // [signer] owner_account
+    // [writable] betting_market_account
+    // [] pyth_oracle_price_account
+    // [] instructions_sysvar - the instruction can't be invoked via CPI
+    RemoveAllowedOracle(),
+
+    // read-only, logs a FinalizeSimulated event with the would-be outcome
+    // [] bet_state_account
+    // [] accepted_bet_state_account
+    // [] betting_market_account
     // [] pyth_price_account
+    // [] clock_sysvar
+    SimulateFinalize(),
+
+    // no-op carrying a borsh encoded event in its data, only invoked by the program itself with CPI
+    // [signer] event authority PDA
+    EmitEvent(BetEvent),
+
+    // [signer] owner_account
+    // [writable] betting_market_account
+    // [] pyth_oracle_price_account
+    // [] instructions_sysvar - the instruction can't be invoked via CPI
+    DelistOracle(DelistOracleArgs),
+
+    // creates a bet with the direction, odds, cancel condition, variable odds, min fill, max acceptors and tag of a bet of the creator
+    // [] source_bet_state_account
+    // then the CreateBet accounts, with the source bet's betting market and pyth accounts
+    CloneBet(CloneBetArgs),
+
+    // tops up the rollover balance of a rollover bet
+    // [signer, writable] creator_main_account
+    // [writable] creator_payment_account
+    // [writable] bet_state_account
+    // [writable] bet_escrow_account
+    // [] token_program - the program that owns the bet's mint
+    // [] system_program
+    // [] clock_sysvar
+    IncreaseBet(IncreaseBetArgs),
+
+    // re-opens a settled rollover bet for its next period, refilled from the rollover balance
+    // [signer] keeper_main_account
+    // [writable] keeper_payment_account - receives the keeper reward in the bet's mint, wrapped SOL for SOL bets
+    // [writable] bet_state_account
+    // [writable] bet_escrow_account
+    // [] betting_market_account
+    // [] pyth_oracle_price_account
+    // [] token_program - the program that owns the bet's mint
+    // [] PDA - owns the bet escrow token account
+    // [] clock_sysvar
+    // [] event authority PDA
+    // [] yoyo bet program
+    RolloverBet(),
+
+    // records the pyth price into the bet's settlement observations, for markets with a settlement window
+    // [writable] bet_state_account
+    // [] betting_market_account
+    // [] pyth_oracle_price_account
+    // [] clock_sysvar
+    RecordSettlementPrice(),
+
+    // first phase of a two phase token bet finalize, for when FinalizeBet doesn't fit the compute budget. Takes the
+    // FinalizeBet accounts, pays the winner and records the commission and finalizer amounts in the accepted bet
+    SettleBet(),
+
+    // second phase, anyone can call it after SettleBet. Pays the recorded commission and finalizer amounts, sweeps
+    // anything left in the accepted escrow to the commission account and closes the escrow
+    // [writable] bet_state_account - finalized_count is incremented
+    // [writable] accepted_bet_state_account
+    // [writable] accepted_bet_escrow_account
+    // [writable] commission_fee_account
+    // [writable] finalizer_payment_account - recorded by SettleBet
+    // [writable] escrow_rent_recipient - recorded by SettleBet
     // [] betting_market_account
+    // [] token_program - the program that owns the bet's mint
+    // [] PDA - owns the accepted bet escrow token account
+    DistributeFees(),
+
+    // accepts the candidate bet with the cheapest acceptor payment for the size, or splits it across two candidates,
+    // and runs AcceptBet on each chosen bet. The candidates must share the market and oracle
+    // [signer, writable] acceptor_main_account
+    // [writable] acceptor_payment_account
+    // [] betting_market_account
+    // [] pyth_oracle_price_account
+    // [] rent_sysvar
+    // [] token_program
+    // [] system_program
+    // [] payment_mint
+    // [writable] feed_exposure_account - the candidates' feed
+    // then for each of 1 to MAX_ACCEPT_BEST_CANDIDATES candidates, the AcceptBet accounts of that bet:
+    // [writable] bet_state_account
+    // [writable] bet_escrow_account
+    // [] PDA - owns the bet escrow token account
+    // [writable] accepted_bet_state_account - PDA [PREFIX, bet_state_account, acceptor_main_account, accepted_count]
+    // [writable] accepted_bet_escrow_account - PDA ["escrow", accepted_bet_state_account]
+    AcceptBestBet(AcceptBestBetArgs),
+
+    // places the acceptor's standing accept order in the market, escrowing its budget. One order per acceptor and market
+    // [signer, writable] acceptor_main_account - pays the rent, and the budget of SOL orders
+    // [writable] acceptor_payment_account - pays the budget of token orders, receives the fills' payouts and the refund
+    // [writable] order_account - PDA ["order", betting_market_account, acceptor_main_account]
+    // [writable] order_escrow_account
+    // [] order escrow authority PDA - [PREFIX, betting_market_account, order_account]
+    // [] betting_market_account
+    // [] pyth_oracle_price_account
+    // [] payment_mint
+    // [] rent_sysvar
+    // [] token_program
     // [] system_program
+    // [] associated_token_program
+    PlaceAcceptOrder(PlaceAcceptOrderArgs),
+
+    // fills an accept order against a bet that matches it, anyone can call it. The fill takes as much of the bet as the
+    // order has left, at the bet's current odds, and pays the matcher fee from the order's escrow
+    // [signer, writable] matcher_main_account - pays the rent of the accepted bet accounts
+    // [writable] matcher_payment_account - receives the matcher fee, a token account of the mint, wrapped SOL for SOL orders
+    // [writable] order_account
+    // [writable] order_escrow_account
+    // [] order escrow authority PDA
+    // then the AcceptBet accounts, with the order's acceptor_main_account and acceptor_payment_account
+    MatchOrder(),
+
+    // cancels the acceptor's accept order, refunding the rest of its escrow and closing the order
+    // [signer, writable] acceptor_main_account - receives the rent
+    // [writable] acceptor_payment_account - receives the refund
+    // [writable] order_account
+    // [writable] order_escrow_account
+    // [] order escrow authority PDA
     // [] token_program
-    // [] PDA
+    CancelAcceptOrder(),
+
+    // closes finalized accepted bets of a bet, sending each one's rent to its acceptor, anyone can call it. Accepted bets
+    // that aren't finalized or don't belong to the bet are skipped. A bet with every accept finalized leaves its
+    // creator's open bets, if FinalizeBet didn't already take it out
+    // [writable] bet_state_account
+    // [writable] creator_exposure_account - PDA ["creator", betting_market, creator_main_account]
+    // then for each of 1 to MAX_CLOSE_SETTLED_ACCOUNTS positions:
+    // [writable] accepted_bet_state_account
+    // [writable] acceptor_main_account - the accepted bet's acceptor, receives its rent
+    CloseSettledAccounts(),
+
+    // read-only, runs the CreateBet checks on the args without creating accounts or moving funds. Logs a
+    // CreateBetValidated event, or fails with the error CreateBet would. The state account's nonce derivation, the
+    // payment account and the escrow are only checked by CreateBet
+    // [] creator_main_account
+    // [] betting_market_account
+    // [] pyth_oracle_product_account
+    // [] pyth_oracle_price_account
+    // [] payment_mint
     // [] clock_sysvar
-    FinalizeBet(),
+    // [] creator_exposure_account - PDA ["creator", betting_market_account, creator_main_account], may not exist yet
+    ValidateCreateBet(CreateBetArgs),
+
+    // accepts several bets of a market in one transaction, running AcceptBet on each bet group with its fill. With
+    // skip_failed, a bet failing any of AcceptBet's checks is skipped and logged, they all run before its first CPI. A
+    // failing CPI, such as a payment short of funds, fails the transaction
+    // [signer, writable] acceptor_main_account
+    // [writable] acceptor_payment_account
+    // [] betting_market_account
+    // [] rent_sysvar
+    // [] token_program
+    // [] system_program
+    // [] payment_mint
+    // then for each of 1 to MAX_ACCEPT_MULTIPLE_GROUPS bets, one per fill:
+    // [writable] bet_state_account
+    // [writable] bet_escrow_account
+    // [] PDA - owns the bet escrow token account
+    // [writable] accepted_bet_state_account - PDA [PREFIX, bet_state_account, acceptor_main_account, accepted_count]
+    // [writable] accepted_bet_escrow_account - PDA ["escrow", accepted_bet_state_account]
+    // [] pyth_oracle_price_account - the bet's oracle
+    // [writable] feed_exposure_account - PDA ["feed", betting_market_account, pyth_oracle_price_account]
+    AcceptMultiple(AcceptMultipleArgs),
+
+    // writes the running build's version and features to the ProgramInfo account, creating it on the first call.
+    // The upgrade authority calls it after each upgrade
+    // [signer, writable] upgrade_authority - pays the rent
+    // [] program_data - the program's ProgramData account under the upgradeable loader
+    // [writable] program_info_account - PDA ["program_info"]
+    // [] rent_sysvar
+    // [] system_program
+    // [] instructions_sysvar - the instruction can't be invoked via CPI
+    UpdateProgramInfo(),
+
+    // creates a pool on a market's allowed feed, escrowing the creator's seed stake on their side. SOL pools take the
+    // stake from the creator's lamports, token pools from the payment account
+    // [signer, writable] creator_main_account - pays the rent
+    // [writable] creator_payment_account - a token account of the mint, wrapped SOL for SOL pools, receives the payout
+    // [writable] pool_account - PDA ["pool", betting_market_account, creator_main_account, nonce]
+    // [writable] pool_escrow_account - the ATA of the escrow authority
+    // [] PDA [PREFIX, betting_market_account, pool_account] - owns the pool escrow token account
+    // [writable] pool_position_account - PDA ["pool_position", pool_account, creator_main_account]
+    // [] betting_market_account
+    // [] pyth_oracle_product_account
+    // [] pyth_oracle_price_account
+    // [] payment_mint
+    // [] rent_sysvar
+    // [] token_program
+    // [] system_program
+    // [] associated_token_program
+    CreatePool(CreatePoolArgs),
+
+    // adds a stake to a pool until the market's accept cutoff before its expiration, creating the participant's
+    // position on their first stake
+    // [signer, writable] participant_main_account - pays the position's rent
+    // [writable] participant_payment_account - a token account of the mint, wrapped SOL for SOL pools, receives the payout
+    // [writable] pool_account
+    // [writable] pool_escrow_account
+    // [writable] pool_position_account - PDA ["pool_position", pool_account, participant_main_account]
+    // [] betting_market_account
+    // [] rent_sysvar
+    // [] token_program
+    // [] system_program
+    JoinPool(JoinPoolArgs),
+
+    // after the pool's expiration, pays a position and closes it, anyone can call it. The first call reads the oracle
+    // once and settles the pool, paying the commission. Winners get their stake and their share of the losing side,
+    // losers nothing, and every participant their stake when the pool is refunded
+    // [writable] pool_account
+    // [writable] pool_escrow_account
+    // [] PDA [PREFIX, betting_market_account, pool_account] - owns the pool escrow token account
+    // [writable] pool_position_account
+    // [writable] participant_payment_account - the position's
+    // [writable] participant_main_account - the position's, receives its rent. It signs within the market's
+    //     finalize_exclusivity_secs after expiration, when only the participant can claim
+    // [writable] commission_fee_account - the market fee_commission_account's ATA of the mint
+    // [] betting_market_account
+    // [] pyth_oracle_price_account
+    // [] token_program
+    SettlePool(),
+
+    // moves an expired bet's expiration out by the market's extension_secs when its oracle published no valid price
+    // after expiration and has none now, up to the market's max_extensions. Anyone can call it. An extended bet takes
+    // no new accepts, and once the extensions are used RefundExpiredAcceptedBet refunds it after the oracle unavailable delay
+    // [writable] bet_state_account
+    // [] betting_market_account
+    // [] pyth_oracle_price_account
+    ExtendExpiry(),
+
+    // refunds each party's stake of an accepted bet its oracle can't settle, once ORACLE_FEED_CHANGED_REFUND_SECS past
+    // expiration for a feed that changed or ORACLE_UNAVAILABLE_REFUND_SECS for one that isn't trading. Takes the
+    // FinalizeBet accounts, and charges no commission or finalizer reward
+    RefundExpiredAcceptedBet(),
+}
+
+// Anchor instruction names, in BetInstruction variant order
+pub const ANCHOR_INSTRUCTION_NAMES: [&str; 30] = [
+    "init_betting_market",
+    "create_bet",
+    "accept_bet",
+    "cancel_bet",
+    "finalize_bet",
+    "update_betting_market",
+    "add_allowed_oracle",
+    "remove_allowed_oracle",
+    "simulate_finalize",
+    "emit_event",
+    "delist_oracle",
+    "clone_bet",
+    "increase_bet",
+    "rollover_bet",
+    "record_settlement_price",
+    "settle_bet",
+    "distribute_fees",
+    "accept_best_bet",
+    "place_accept_order",
+    "match_order",
+    "cancel_accept_order",
+    "close_settled_accounts",
+    "validate_create_bet",
+    "accept_multiple",
+    "update_program_info",
+    "create_pool",
+    "join_pool",
+    "settle_pool",
+    "extend_expiry",
+    "refund_expired_accepted_bet",
+];
+
+impl BetInstruction {
+    /// Unpacks native borsh instruction data, or with the anchor-compat feature also
+    /// Anchor framing: the sha256("global:<name>")[..8] discriminator followed by the borsh args.
+    /// Trailing bytes after the args are rejected, and any decode failure is InvalidInstruction
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if cfg!(feature = "anchor-compat") && input.len() >= 8 {
+            let (discriminator, args) = input.split_at(8);
+            if let Some(index) = ANCHOR_INSTRUCTION_NAMES.iter().position(|name| discriminator == anchor_discriminator("global", name)) {
+                let mut native_input = vec![index as u8];
+                native_input.extend_from_slice(args);
+                return Self::decode(&native_input);
+            }
+        }
+        Self::decode(input)
+    }
+
+    // decodes the variant tag byte and its args, logging the tag when the data doesn't decode.
+    // try_from_slice fails unless the whole input is consumed, so trailing bytes are an error
+    fn decode(input: &[u8]) -> Result<Self, ProgramError> {
+        let tag = match input.first() {
+            Some(tag) => *tag,
+            None => {
+                msg!("Empty instruction data");
+                return Err(BetError::InvalidInstruction.into());
+            }
+        };
+        Self::try_from_slice(input).map_err(|error| {
+            msg!("Invalid data for instruction tag {}: {}", tag, error);
+            BetError::InvalidInstruction.into()
+        })
+    }
+
+    /// Packs the instruction with Anchor framing, for Anchor client tooling
+    pub fn pack_anchor(&self) -> Vec<u8> {
+        let native_data = self.try_to_vec().unwrap();
+        let mut data = anchor_discriminator("global", ANCHOR_INSTRUCTION_NAMES[native_data[0] as usize]).to_vec();
+        data.extend_from_slice(&native_data[1..]);
+        data
+    }
 }
 
 /// Creates a InitBettingMarket Instruction
+#[allow(clippy::too_many_arguments)]
 pub fn init_betting_market(
     program_id: Pubkey,
     owner_account: Pubkey,
-    betting_market_account: Pubkey,
     commission_fee_account: Pubkey,
     pyth_program: Pubkey,
-    sol_payment: bool,
-    payment_mint: Option<Pubkey>
+    accepted_mints: Vec<Pubkey>,
+    max_expiration_horizon_secs: i64,
+    min_duration_secs: i64,
+    allow_variable_odds: bool,
+    allow_unverified_oracle_program: bool
 ) -> Instruction {
+    let (betting_market_account, _bump_seed) = find_market(&program_id, &accepted_mints[0]);
+    let mut accounts = vec![
+        AccountMeta::new(owner_account, true),
+        AccountMeta::new(betting_market_account, false),
+        AccountMeta::new_readonly(commission_fee_account, false),
+        AccountMeta::new_readonly(pyth_program, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::instructions::id(), false)
+    ];
+    for mint in accepted_mints.iter() {
+        accounts.push(AccountMeta::new_readonly(*mint, false));
+    }
+    for mint in accepted_mints.iter() {
+        accounts.push(AccountMeta::new(find_mint_registry(&program_id, mint).0, false));
+    }
     Instruction {
         program_id,
-        accounts: vec![
-            AccountMeta::new(owner_account, true),
-            AccountMeta::new(betting_market_account, false),
-            AccountMeta::new_readonly(commission_fee_account, false),
-            AccountMeta::new_readonly(pyth_program, false)
-        ],
+        accounts,
         data: BetInstruction::InitBettingMarket(InitBettingMarketArgs {
-            sol_payment: sol_payment,
-            payment_mint: payment_mint
+            accepted_mints,
+            max_expiration_horizon_secs,
+            min_duration_secs,
+            allow_variable_odds,
+            allow_unverified_oracle_program
         })
         .try_to_vec()
         .unwrap()
@@ -138,7 +740,8 @@ pub fn create_bet(
     creator_main_account: Pubkey,
     creator_payment_account: Pubkey,
     bet_state_account: Pubkey,
-    bet_escrow_account: Pubkey,
+    payment_mint: Pubkey, // the native mint for SOL bets
+    token_program: Pubkey, // the program that owns payment_mint
     betting_market_account: Pubkey,
     pyth_oracle_product_account: Pubkey,
     pyth_oracle_price_account: Pubkey,
@@ -149,21 +752,37 @@ pub fn create_bet(
     bet_price: i64,
     cancel_condition: CancelCondition,
     variable_odds: Option<i64>,
+    min_fill: u64,
+    max_acceptors: Option<u16>,
+    tag: String,
+    rollover_interval_secs: Option<i64>,
+    expiry_kind: ExpiryKind,
+    max_odds_change: Option<i64>,
+    max_total_acceptable: Option<u64>,
+    nonce: Option<u64>,
+    both_sides: bool,
+    payout_curve: PayoutCurve,
+    keeper_bounty_lamports: u64,
+    supplied_start_price: Option<SuppliedStartPrice>,
+    refund_destination: Option<Pubkey>,
 ) -> Instruction {
+    let mut accounts = create_bet_accounts(
+        program_id,
+        creator_main_account,
+        creator_payment_account,
+        bet_state_account,
+        payment_mint,
+        token_program,
+        betting_market_account,
+        pyth_oracle_product_account,
+        pyth_oracle_price_account
+    );
+    if let Some(refund_destination) = refund_destination {
+        accounts.push(AccountMeta::new_readonly(refund_destination, false));
+    }
     Instruction {
         program_id,
-        accounts: vec![
-            AccountMeta::new(creator_main_account, true),
-            AccountMeta::new(creator_payment_account, false),
-            AccountMeta::new(bet_state_account, false),
-            AccountMeta::new(bet_escrow_account, false),
-            AccountMeta::new_readonly(betting_market_account, false),
-            AccountMeta::new_readonly(pyth_oracle_product_account, false),
-            AccountMeta::new_readonly(pyth_oracle_price_account, false),
-            AccountMeta::new_readonly(sysvar::rent::id(), false),
-            AccountMeta::new_readonly(spl_token::ID, false),
-            AccountMeta::new_readonly(system_program::id(), false)
-        ],
+        accounts,
         data: BetInstruction::CreateBet(CreateBetArgs {
             bet_size,
             odds,
@@ -172,46 +791,1161 @@ pub fn create_bet(
             bet_price,
             cancel_condition,
             variable_odds,
+            min_fill,
+            max_acceptors,
+            tag,
+            rollover_interval_secs,
+            expiry_kind,
+            max_odds_change,
+            max_total_acceptable,
+            nonce,
+            both_sides,
+            payout_curve,
+            keeper_bounty_lamports,
+            supplied_start_price,
+            refund_destination,
         })
         .try_to_vec()
         .unwrap(),
     }
 }
 
-/// Creates a CreateBet Instruction
-#[allow(clippy::too_many_arguments)]
-pub fn accept_bet(
+/// Creates a ValidateCreateBet Instruction, a dry run of CreateBet with the same args
+pub fn validate_create_bet(
     program_id: Pubkey,
-    acceptor_main_account: Pubkey,
-    acceptor_payment_account: Pubkey,
-    bet_state_account: Pubkey,
-    bet_escrow_account: Pubkey,
-    accepted_bet_state_account: Pubkey,
-    accepted_bet_escrow_account: Pubkey,
+    creator_main_account: Pubkey,
+    payment_mint: Pubkey, // the native mint for SOL bets
     betting_market_account: Pubkey,
+    pyth_oracle_product_account: Pubkey,
     pyth_oracle_price_account: Pubkey,
-    bet_size: u64,
+    args: CreateBetArgs,
 ) -> Instruction {
+    let (creator_exposure_account, _bump_seed) = find_creator_exposure(&program_id, &betting_market_account, &creator_main_account);
     Instruction {
         program_id,
         accounts: vec![
-            AccountMeta::new(acceptor_main_account, true),
-            AccountMeta::new(acceptor_payment_account, false),
-            AccountMeta::new(bet_state_account, false),
-            AccountMeta::new(bet_escrow_account, false),
-            AccountMeta::new(accepted_bet_state_account, false),
-            AccountMeta::new(accepted_bet_escrow_account, false),
+            AccountMeta::new_readonly(creator_main_account, false),
             AccountMeta::new_readonly(betting_market_account, false),
+            AccountMeta::new_readonly(pyth_oracle_product_account, false),
             AccountMeta::new_readonly(pyth_oracle_price_account, false),
-            AccountMeta::new_readonly(sysvar::rent::id(), false),
-            AccountMeta::new_readonly(spl_token::ID, false),
-            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(payment_mint, false),
             AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(creator_exposure_account, false)
         ],
-        data: BetInstruction::AcceptBet(AcceptBetArgs {
-            bet_size,
-        })
-        .try_to_vec()
-        .unwrap(),
+        data: BetInstruction::ValidateCreateBet(args).try_to_vec().unwrap(),
     }
-}
\ No newline at end of file
+}
+
+/// Creates a UpdateProgramInfo Instruction
+pub fn update_program_info(
+    program_id: Pubkey,
+    upgrade_authority: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(upgrade_authority, true),
+            AccountMeta::new_readonly(find_program_data(&program_id).0, false),
+            AccountMeta::new(find_program_info(&program_id).0, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::instructions::id(), false)
+        ],
+        data: BetInstruction::UpdateProgramInfo().try_to_vec().unwrap(),
+    }
+}
+
+/// Creates a CloneBet Instruction
+#[allow(clippy::too_many_arguments)]
+pub fn clone_bet(
+    program_id: Pubkey,
+    source_bet_state_account: Pubkey,
+    creator_main_account: Pubkey,
+    creator_payment_account: Pubkey,
+    bet_state_account: Pubkey,
+    payment_mint: Pubkey, // the native mint for SOL bets
+    token_program: Pubkey, // the program that owns payment_mint
+    betting_market_account: Pubkey, // the source bet's
+    pyth_oracle_product_account: Pubkey, // the source bet's
+    pyth_oracle_price_account: Pubkey, // the source bet's
+    bet_size: u64,
+    expiration_time: i64,
+    relative_strike: bool,
+) -> Instruction {
+    let mut accounts = vec![AccountMeta::new_readonly(source_bet_state_account, false)];
+    accounts.extend(create_bet_accounts(
+        program_id,
+        creator_main_account,
+        creator_payment_account,
+        bet_state_account,
+        payment_mint,
+        token_program,
+        betting_market_account,
+        pyth_oracle_product_account,
+        pyth_oracle_price_account
+    ));
+    Instruction {
+        program_id,
+        accounts,
+        data: BetInstruction::CloneBet(CloneBetArgs {
+            bet_size,
+            expiration_time,
+            relative_strike,
+        })
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+// accounts of CreateBet, also taken by CloneBet after the source bet
+#[allow(clippy::too_many_arguments)]
+fn create_bet_accounts(
+    program_id: Pubkey,
+    creator_main_account: Pubkey,
+    creator_payment_account: Pubkey,
+    bet_state_account: Pubkey,
+    payment_mint: Pubkey,
+    token_program: Pubkey,
+    betting_market_account: Pubkey,
+    pyth_oracle_product_account: Pubkey,
+    pyth_oracle_price_account: Pubkey,
+) -> Vec<AccountMeta> {
+    let bet_escrow_account = get_escrow_address(&program_id, &betting_market_account, &bet_state_account, &payment_mint, &token_program);
+    let (escrow_authority, _bump_seed) = find_escrow_authority(&program_id, &betting_market_account, &bet_state_account);
+    let (event_authority, _bump_seed) = find_event_authority(&program_id);
+    let (creator_exposure_account, _bump_seed) = find_creator_exposure(&program_id, &betting_market_account, &creator_main_account);
+    let (feed_exposure_account, _bump_seed) = find_feed_exposure(&program_id, &betting_market_account, &pyth_oracle_price_account);
+    vec![
+        AccountMeta::new(creator_main_account, true),
+        AccountMeta::new(creator_payment_account, false),
+        AccountMeta::new(bet_state_account, false),
+        AccountMeta::new(bet_escrow_account, false),
+        AccountMeta::new_readonly(betting_market_account, false),
+        AccountMeta::new_readonly(pyth_oracle_product_account, false),
+        AccountMeta::new_readonly(pyth_oracle_price_account, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(token_program, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(payment_mint, false),
+        AccountMeta::new_readonly(escrow_authority, false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new_readonly(event_authority, false),
+        AccountMeta::new_readonly(program_id, false),
+        AccountMeta::new(creator_exposure_account, false),
+        AccountMeta::new(feed_exposure_account, false),
+        AccountMeta::new_readonly(sysvar::instructions::id(), false)
+    ]
+}
+
+/// Creates a AcceptBet Instruction
+#[allow(clippy::too_many_arguments)]
+pub fn accept_bet(
+    program_id: Pubkey,
+    acceptor_main_account: Pubkey,
+    acceptor_payment_account: Pubkey,
+    bet_state_account: Pubkey,
+    accepted_count: u32, // the bet's current accepted_count, used to derive the accepted bet state account
+    payment_mint: Pubkey, // the native mint for SOL bets
+    token_program: Pubkey, // the program that owns payment_mint
+    betting_market_account: Pubkey,
+    pyth_oracle_price_account: Pubkey,
+    accepted_bet_escrow_account: Option<Pubkey>, // None derives it from the accepted bet state account
+    bet_size: u64,
+    expected_start_price: Option<i64>,
+    deadline: Option<i64>,
+    expected_odds: Option<i64>,
+    side: Option<Direction>,
+) -> Instruction {
+    let (accepted_bet_state_account, _bump_seed) = find_accepted_bet(&program_id, &bet_state_account, &acceptor_main_account, accepted_count);
+    let bet_escrow_account = get_escrow_address(&program_id, &betting_market_account, &bet_state_account, &payment_mint, &token_program);
+    let accepted_bet_escrow_account = accepted_bet_escrow_account.unwrap_or_else(||
+        find_accepted_escrow(&program_id, &accepted_bet_state_account).0
+    );
+    let (escrow_authority, _bump_seed) = find_escrow_authority(&program_id, &betting_market_account, &bet_state_account);
+    let (feed_exposure_account, _bump_seed) = find_feed_exposure(&program_id, &betting_market_account, &pyth_oracle_price_account);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(acceptor_main_account, true),
+            AccountMeta::new(acceptor_payment_account, false),
+            AccountMeta::new(bet_state_account, false),
+            AccountMeta::new(bet_escrow_account, false),
+            AccountMeta::new(accepted_bet_state_account, false),
+            AccountMeta::new(accepted_bet_escrow_account, false),
+            AccountMeta::new_readonly(betting_market_account, false),
+            AccountMeta::new_readonly(pyth_oracle_price_account, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(escrow_authority, false),
+            AccountMeta::new_readonly(payment_mint, false),
+            AccountMeta::new(feed_exposure_account, false)
+        ],
+        data: BetInstruction::AcceptBet(AcceptBetArgs {
+            bet_size,
+            expected_start_price,
+            deadline,
+            expected_odds,
+            side,
+        })
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Creates a AcceptBestBet Instruction, candidates are (bet_state_account, its current accepted_count)
+#[allow(clippy::too_many_arguments)]
+pub fn accept_best_bet(
+    program_id: Pubkey,
+    acceptor_main_account: Pubkey,
+    acceptor_payment_account: Pubkey,
+    payment_mint: Pubkey, // the native mint for SOL bets
+    token_program: Pubkey, // the program that owns payment_mint
+    betting_market_account: Pubkey,
+    pyth_oracle_price_account: Pubkey,
+    candidates: &[(Pubkey, u32)],
+    bet_size: u64,
+    deadline: Option<i64>,
+    side: Option<Direction>,
+    allow_split: bool,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(acceptor_main_account, true),
+        AccountMeta::new(acceptor_payment_account, false),
+        AccountMeta::new_readonly(betting_market_account, false),
+        AccountMeta::new_readonly(pyth_oracle_price_account, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(token_program, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(payment_mint, false),
+        AccountMeta::new(find_feed_exposure(&program_id, &betting_market_account, &pyth_oracle_price_account).0, false)
+    ];
+    for (bet_state_account, accepted_count) in candidates {
+        let (accepted_bet_state_account, _bump_seed) = find_accepted_bet(&program_id, bet_state_account, &acceptor_main_account, *accepted_count);
+        accounts.push(AccountMeta::new(*bet_state_account, false));
+        accounts.push(AccountMeta::new(get_escrow_address(&program_id, &betting_market_account, bet_state_account, &payment_mint, &token_program), false));
+        accounts.push(AccountMeta::new_readonly(find_escrow_authority(&program_id, &betting_market_account, bet_state_account).0, false));
+        accounts.push(AccountMeta::new(accepted_bet_state_account, false));
+        accounts.push(AccountMeta::new(find_accepted_escrow(&program_id, &accepted_bet_state_account).0, false));
+    }
+    Instruction {
+        program_id,
+        accounts,
+        data: BetInstruction::AcceptBestBet(AcceptBestBetArgs {
+            bet_size,
+            deadline,
+            side,
+            allow_split,
+        })
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// A bet for the accept_multiple builder
+#[derive(Clone, Debug)]
+pub struct AcceptMultipleBet {
+    pub bet_state_account: Pubkey,
+    pub accepted_count: u32, // the bet's current accepted_count, used to derive the accepted bet state account
+    pub pyth_oracle_price_account: Pubkey,
+    pub fill: AcceptMultipleFill,
+}
+
+/// Creates a AcceptMultiple Instruction, filling each bet in order
+#[allow(clippy::too_many_arguments)]
+pub fn accept_multiple(
+    program_id: Pubkey,
+    acceptor_main_account: Pubkey,
+    acceptor_payment_account: Pubkey,
+    payment_mint: Pubkey, // the native mint for SOL bets
+    token_program: Pubkey, // the program that owns payment_mint
+    betting_market_account: Pubkey,
+    bets: Vec<AcceptMultipleBet>,
+    deadline: Option<i64>,
+    skip_failed: bool,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(acceptor_main_account, true),
+        AccountMeta::new(acceptor_payment_account, false),
+        AccountMeta::new_readonly(betting_market_account, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(token_program, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(payment_mint, false),
+    ];
+    let mut fills = Vec::with_capacity(bets.len());
+    for bet in bets {
+        let bet_state_account = &bet.bet_state_account;
+        let (accepted_bet_state_account, _bump_seed) = find_accepted_bet(&program_id, bet_state_account, &acceptor_main_account, bet.accepted_count);
+        accounts.push(AccountMeta::new(*bet_state_account, false));
+        accounts.push(AccountMeta::new(get_escrow_address(&program_id, &betting_market_account, bet_state_account, &payment_mint, &token_program), false));
+        accounts.push(AccountMeta::new_readonly(find_escrow_authority(&program_id, &betting_market_account, bet_state_account).0, false));
+        accounts.push(AccountMeta::new(accepted_bet_state_account, false));
+        accounts.push(AccountMeta::new(find_accepted_escrow(&program_id, &accepted_bet_state_account).0, false));
+        accounts.push(AccountMeta::new_readonly(bet.pyth_oracle_price_account, false));
+        accounts.push(AccountMeta::new(find_feed_exposure(&program_id, &betting_market_account, &bet.pyth_oracle_price_account).0, false));
+        fills.push(bet.fill);
+    }
+    Instruction {
+        program_id,
+        accounts,
+        data: BetInstruction::AcceptMultiple(AcceptMultipleArgs {
+            fills,
+            deadline,
+            skip_failed,
+        })
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Creates a PlaceAcceptOrder Instruction
+#[allow(clippy::too_many_arguments)]
+pub fn place_accept_order(
+    program_id: Pubkey,
+    acceptor_main_account: Pubkey,
+    acceptor_payment_account: Pubkey,
+    payment_mint: Pubkey, // the native mint for SOL orders
+    token_program: Pubkey, // the program that owns payment_mint
+    betting_market_account: Pubkey,
+    pyth_oracle_price_account: Pubkey,
+    size: u64,
+    direction: Direction,
+    min_odds: i64,
+    max_odds: i64,
+    min_expiration_time: i64,
+    max_expiration_time: i64,
+    matcher_fee_bps: u16,
+) -> Instruction {
+    let (order_account, _bump_seed) = find_accept_order(&program_id, &betting_market_account, &acceptor_main_account);
+    let (order_escrow_authority, _bump_seed) = find_escrow_authority(&program_id, &betting_market_account, &order_account);
+    let order_escrow_account = get_associated_token_address(&order_escrow_authority, &payment_mint, &token_program);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(acceptor_main_account, true),
+            AccountMeta::new(acceptor_payment_account, false),
+            AccountMeta::new(order_account, false),
+            AccountMeta::new(order_escrow_account, false),
+            AccountMeta::new_readonly(order_escrow_authority, false),
+            AccountMeta::new_readonly(betting_market_account, false),
+            AccountMeta::new_readonly(pyth_oracle_price_account, false),
+            AccountMeta::new_readonly(payment_mint, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false)
+        ],
+        data: BetInstruction::PlaceAcceptOrder(PlaceAcceptOrderArgs {
+            size,
+            direction,
+            min_odds,
+            max_odds,
+            min_expiration_time,
+            max_expiration_time,
+            matcher_fee_bps,
+        })
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Creates a MatchOrder Instruction filling the acceptor's order in the market against the bet
+#[allow(clippy::too_many_arguments)]
+pub fn match_order(
+    program_id: Pubkey,
+    matcher_main_account: Pubkey,
+    matcher_payment_account: Pubkey,
+    acceptor_main_account: Pubkey, // the order's acceptor
+    acceptor_payment_account: Pubkey, // the order's acceptor_payment_account
+    bet_state_account: Pubkey,
+    accepted_count: u32, // the bet's current accepted_count, used to derive the accepted bet state account
+    payment_mint: Pubkey, // the native mint for SOL orders
+    token_program: Pubkey, // the program that owns payment_mint
+    betting_market_account: Pubkey,
+    pyth_oracle_price_account: Pubkey,
+) -> Instruction {
+    let (order_account, _bump_seed) = find_accept_order(&program_id, &betting_market_account, &acceptor_main_account);
+    let (order_escrow_authority, _bump_seed) = find_escrow_authority(&program_id, &betting_market_account, &order_account);
+    let order_escrow_account = get_associated_token_address(&order_escrow_authority, &payment_mint, &token_program);
+    let mut accounts = vec![
+        AccountMeta::new(matcher_main_account, true),
+        AccountMeta::new(matcher_payment_account, false),
+        AccountMeta::new(order_account, false),
+        AccountMeta::new(order_escrow_account, false),
+        AccountMeta::new_readonly(order_escrow_authority, false)
+    ];
+    // the AcceptBet accounts, the acceptor doesn't sign
+    let mut accept_bet_accounts = accept_bet(
+        program_id,
+        acceptor_main_account,
+        acceptor_payment_account,
+        bet_state_account,
+        accepted_count,
+        payment_mint,
+        token_program,
+        betting_market_account,
+        pyth_oracle_price_account,
+        None,
+        0,
+        None,
+        None,
+        None,
+        None,
+    ).accounts;
+    accept_bet_accounts[0].is_signer = false;
+    accounts.append(&mut accept_bet_accounts);
+    Instruction {
+        program_id,
+        accounts,
+        data: BetInstruction::MatchOrder().try_to_vec().unwrap(),
+    }
+}
+
+/// Creates a CancelAcceptOrder Instruction
+pub fn cancel_accept_order(
+    program_id: Pubkey,
+    acceptor_main_account: Pubkey,
+    acceptor_payment_account: Pubkey,
+    payment_mint: Pubkey, // the native mint for SOL orders
+    token_program: Pubkey, // the program that owns payment_mint
+    betting_market_account: Pubkey,
+) -> Instruction {
+    let (order_account, _bump_seed) = find_accept_order(&program_id, &betting_market_account, &acceptor_main_account);
+    let (order_escrow_authority, _bump_seed) = find_escrow_authority(&program_id, &betting_market_account, &order_account);
+    let order_escrow_account = get_associated_token_address(&order_escrow_authority, &payment_mint, &token_program);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(acceptor_main_account, true),
+            AccountMeta::new(acceptor_payment_account, false),
+            AccountMeta::new(order_account, false),
+            AccountMeta::new(order_escrow_account, false),
+            AccountMeta::new_readonly(order_escrow_authority, false),
+            AccountMeta::new_readonly(token_program, false)
+        ],
+        data: BetInstruction::CancelAcceptOrder().try_to_vec().unwrap(),
+    }
+}
+
+/// Creates a CreatePool Instruction
+#[allow(clippy::too_many_arguments)]
+pub fn create_pool(
+    program_id: Pubkey,
+    creator_main_account: Pubkey,
+    creator_payment_account: Pubkey,
+    payment_mint: Pubkey, // the native mint for SOL pools
+    token_program: Pubkey, // the program that owns payment_mint
+    betting_market_account: Pubkey,
+    pyth_oracle_product_account: Pubkey,
+    pyth_oracle_price_account: Pubkey,
+    seed_amount: u64,
+    side: Direction,
+    bet_price: i64,
+    expiration_time: i64,
+    nonce: u64,
+) -> Instruction {
+    let (pool_account, _bump_seed) = find_pool(&program_id, &betting_market_account, &creator_main_account, nonce);
+    let (pool_escrow_authority, _bump_seed) = find_escrow_authority(&program_id, &betting_market_account, &pool_account);
+    let pool_escrow_account = get_associated_token_address(&pool_escrow_authority, &payment_mint, &token_program);
+    let (pool_position_account, _bump_seed) = find_pool_position(&program_id, &pool_account, &creator_main_account);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(creator_main_account, true),
+            AccountMeta::new(creator_payment_account, false),
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new(pool_escrow_account, false),
+            AccountMeta::new_readonly(pool_escrow_authority, false),
+            AccountMeta::new(pool_position_account, false),
+            AccountMeta::new_readonly(betting_market_account, false),
+            AccountMeta::new_readonly(pyth_oracle_product_account, false),
+            AccountMeta::new_readonly(pyth_oracle_price_account, false),
+            AccountMeta::new_readonly(payment_mint, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false)
+        ],
+        data: BetInstruction::CreatePool(CreatePoolArgs {
+            seed_amount,
+            side,
+            bet_price,
+            expiration_time,
+            nonce,
+        })
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Creates a JoinPool Instruction
+#[allow(clippy::too_many_arguments)]
+pub fn join_pool(
+    program_id: Pubkey,
+    participant_main_account: Pubkey,
+    participant_payment_account: Pubkey,
+    pool_account: Pubkey,
+    payment_mint: Pubkey, // the pool's payment_mint
+    token_program: Pubkey, // the program that owns payment_mint
+    betting_market_account: Pubkey,
+    stake: u64,
+    side: Direction,
+) -> Instruction {
+    let (pool_escrow_authority, _bump_seed) = find_escrow_authority(&program_id, &betting_market_account, &pool_account);
+    let pool_escrow_account = get_associated_token_address(&pool_escrow_authority, &payment_mint, &token_program);
+    let (pool_position_account, _bump_seed) = find_pool_position(&program_id, &pool_account, &participant_main_account);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(participant_main_account, true),
+            AccountMeta::new(participant_payment_account, false),
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new(pool_escrow_account, false),
+            AccountMeta::new(pool_position_account, false),
+            AccountMeta::new_readonly(betting_market_account, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(system_program::id(), false)
+        ],
+        data: BetInstruction::JoinPool(JoinPoolArgs { stake, side }).try_to_vec().unwrap(),
+    }
+}
+
+/// Creates a SettlePool Instruction paying the participant's position
+#[allow(clippy::too_many_arguments)]
+pub fn settle_pool(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    participant_main_account: Pubkey,
+    participant_payment_account: Pubkey, // the position's participant_payment_account
+    payment_mint: Pubkey, // the pool's payment_mint
+    token_program: Pubkey, // the program that owns payment_mint
+    betting_market_account: Pubkey,
+    fee_commission_account: Pubkey, // the market's fee_commission_account
+    pyth_oracle_price_account: Pubkey,
+    participant_signs: bool, // required within the market's finalize_exclusivity_secs after expiration
+) -> Instruction {
+    let (pool_escrow_authority, _bump_seed) = find_escrow_authority(&program_id, &betting_market_account, &pool_account);
+    let pool_escrow_account = get_associated_token_address(&pool_escrow_authority, &payment_mint, &token_program);
+    let (pool_position_account, _bump_seed) = find_pool_position(&program_id, &pool_account, &participant_main_account);
+    let commission_fee_account = get_associated_token_address(&fee_commission_account, &payment_mint, &token_program);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new(pool_escrow_account, false),
+            AccountMeta::new_readonly(pool_escrow_authority, false),
+            AccountMeta::new(pool_position_account, false),
+            AccountMeta::new(participant_payment_account, false),
+            AccountMeta::new(participant_main_account, participant_signs),
+            AccountMeta::new(commission_fee_account, false),
+            AccountMeta::new_readonly(betting_market_account, false),
+            AccountMeta::new_readonly(pyth_oracle_price_account, false),
+            AccountMeta::new_readonly(token_program, false)
+        ],
+        data: BetInstruction::SettlePool().try_to_vec().unwrap(),
+    }
+}
+
+/// Creates a ExtendExpiry Instruction
+pub fn extend_expiry(
+    program_id: Pubkey,
+    bet_state_account: Pubkey,
+    betting_market_account: Pubkey,
+    pyth_oracle_price_account: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(bet_state_account, false),
+            AccountMeta::new_readonly(betting_market_account, false),
+            AccountMeta::new_readonly(pyth_oracle_price_account, false)
+        ],
+        data: BetInstruction::ExtendExpiry().try_to_vec().unwrap(),
+    }
+}
+
+/// Creates a CloseSettledAccounts Instruction, closing each (accepted_bet_state_account, acceptor_main_account) position
+pub fn close_settled_accounts(
+    program_id: Pubkey,
+    betting_market_account: Pubkey,
+    creator_main_account: Pubkey,
+    bet_state_account: Pubkey,
+    positions: &[(Pubkey, Pubkey)],
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(bet_state_account, false),
+        AccountMeta::new(find_creator_exposure(&program_id, &betting_market_account, &creator_main_account).0, false),
+    ];
+    for (accepted_bet_state_account, acceptor_main_account) in positions {
+        accounts.push(AccountMeta::new(*accepted_bet_state_account, false));
+        accounts.push(AccountMeta::new(*acceptor_main_account, false));
+    }
+    Instruction {
+        program_id,
+        accounts,
+        data: BetInstruction::CloseSettledAccounts().try_to_vec().unwrap(),
+    }
+}
+
+/// Creates a CancelBet Instruction
+#[allow(clippy::too_many_arguments)]
+pub fn cancel_bet(
+    program_id: Pubkey,
+    creator_main_account: Pubkey,
+    creator_payment_account: Pubkey, // the bet's refund_destination if it has one
+    bet_state_account: Pubkey,
+    payment_mint: Pubkey, // the native mint for SOL bets
+    token_program: Pubkey, // the program that owns payment_mint
+    betting_market_account: Pubkey,
+    pyth_oracle_price_account: Pubkey, // the bet's feed, for its feed exposure account
+    amount: u64, // CANCEL_ALL_REMAINING cancels the bet
+) -> Instruction {
+    let bet_escrow_account = get_escrow_address(&program_id, &betting_market_account, &bet_state_account, &payment_mint, &token_program);
+    let (escrow_authority, _bump_seed) = find_escrow_authority(&program_id, &betting_market_account, &bet_state_account);
+    let (event_authority, _bump_seed) = find_event_authority(&program_id);
+    let (creator_exposure_account, _bump_seed) = find_creator_exposure(&program_id, &betting_market_account, &creator_main_account);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(creator_main_account, true),
+            AccountMeta::new(creator_payment_account, false),
+            AccountMeta::new(bet_state_account, false),
+            AccountMeta::new(bet_escrow_account, false),
+            AccountMeta::new_readonly(betting_market_account, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(escrow_authority, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(event_authority, false),
+            AccountMeta::new_readonly(program_id, false),
+            AccountMeta::new(creator_exposure_account, false),
+            AccountMeta::new(find_feed_exposure(&program_id, &betting_market_account, &pyth_oracle_price_account).0, false)
+        ],
+        data: BetInstruction::CancelBet(CancelBetArgs {
+            amount,
+        })
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Creates a FinalizeBet Instruction
+#[allow(clippy::too_many_arguments)]
+pub fn finalize_bet(
+    program_id: Pubkey,
+    finalizer_main_account: Pubkey,
+    finalizer_payment_account: Pubkey,
+    commission_fee_account: Pubkey,
+    bet_state_account: Pubkey,
+    accepted_bet_state_account: Pubkey,
+    accepted_bet_escrow_account: Pubkey,
+    creator_main_account: Pubkey,
+    creator_payment_account: Pubkey,
+    acceptor_payment_account: Pubkey,
+    betting_market_account: Pubkey,
+    pyth_oracle_price_account: Pubkey,
+    token_program: Pubkey, // the program that owns the bet's mint
+    closed_winner_payment: Option<(Pubkey, Pubkey)>, // (winner main account, payment mint) if the winner's payment account was closed or isn't a token account of the mint
+    missing_commission_ata: Option<(Pubkey, Pubkey)>, // (fee_commission_account, payment mint) if the commission ATA doesn't exist yet
+    settlement_receipts: bool, // the market has settlement_receipts, so the receipt PDA is written
+    user_stats: Option<(Pubkey, Pubkey)>, // (creator main account, acceptor main account) to update their stats
+) -> Instruction {
+    let (escrow_authority, _bump_seed) = find_escrow_authority(&program_id, &betting_market_account, &accepted_bet_state_account);
+    let (event_authority, _bump_seed) = find_event_authority(&program_id);
+    let mut accounts = vec![
+        AccountMeta::new(finalizer_main_account, true),
+        AccountMeta::new(finalizer_payment_account, false),
+        AccountMeta::new(commission_fee_account, false),
+        AccountMeta::new(bet_state_account, false),
+        AccountMeta::new(accepted_bet_state_account, false),
+        AccountMeta::new(accepted_bet_escrow_account, false),
+        AccountMeta::new(creator_payment_account, false),
+        AccountMeta::new(acceptor_payment_account, false),
+        AccountMeta::new_readonly(betting_market_account, false),
+        AccountMeta::new_readonly(pyth_oracle_price_account, false),
+        AccountMeta::new_readonly(token_program, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(escrow_authority, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(event_authority, false),
+        AccountMeta::new_readonly(program_id, false),
+        AccountMeta::new(find_feed_exposure(&program_id, &betting_market_account, &pyth_oracle_price_account).0, false),
+        AccountMeta::new(find_creator_exposure(&program_id, &betting_market_account, &creator_main_account).0, false),
+    ];
+    if let Some((winner_main_account, payment_mint)) = closed_winner_payment {
+        accounts.push(AccountMeta::new_readonly(payment_mint, false));
+        accounts.push(AccountMeta::new_readonly(winner_main_account, false));
+        accounts.push(AccountMeta::new(get_associated_token_address(&winner_main_account, &payment_mint, &token_program), false));
+        accounts.push(AccountMeta::new_readonly(spl_associated_token_account::id(), false));
+    }
+    if let Some((commission_authority, payment_mint)) = missing_commission_ata {
+        accounts.push(AccountMeta::new_readonly(payment_mint, false));
+        accounts.push(AccountMeta::new_readonly(commission_authority, false));
+        accounts.push(AccountMeta::new_readonly(spl_associated_token_account::id(), false));
+    }
+    if settlement_receipts {
+        accounts.push(AccountMeta::new_readonly(sysvar::rent::id(), false));
+        accounts.push(AccountMeta::new(find_receipt(&program_id, &accepted_bet_state_account).0, false));
+    }
+    if let Some((creator_main_account, acceptor_main_account)) = user_stats {
+        accounts.push(AccountMeta::new_readonly(sysvar::rent::id(), false));
+        accounts.push(AccountMeta::new(find_user_stats(&program_id, &betting_market_account, &creator_main_account).0, false));
+        accounts.push(AccountMeta::new(find_user_stats(&program_id, &betting_market_account, &acceptor_main_account).0, false));
+    }
+    Instruction {
+        program_id,
+        accounts,
+        data: BetInstruction::FinalizeBet()
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Creates a SettleBet Instruction, with the FinalizeBet accounts
+#[allow(clippy::too_many_arguments)]
+pub fn settle_bet(
+    program_id: Pubkey,
+    settler_main_account: Pubkey,
+    settler_payment_account: Pubkey, // gets the finalizer reward from DistributeFees
+    commission_fee_account: Pubkey,
+    bet_state_account: Pubkey,
+    accepted_bet_state_account: Pubkey,
+    accepted_bet_escrow_account: Pubkey,
+    creator_main_account: Pubkey,
+    creator_payment_account: Pubkey,
+    acceptor_payment_account: Pubkey,
+    betting_market_account: Pubkey,
+    pyth_oracle_price_account: Pubkey,
+    token_program: Pubkey, // the program that owns the bet's mint
+    closed_winner_payment: Option<(Pubkey, Pubkey)>, // (winner main account, payment mint) if the winner's payment account was closed or isn't a token account of the mint
+    missing_commission_ata: Option<(Pubkey, Pubkey)>, // (fee_commission_account, payment mint) if the commission ATA doesn't exist yet
+    settlement_receipts: bool, // the market has settlement_receipts, so the receipt PDA is written
+    user_stats: Option<(Pubkey, Pubkey)>, // (creator main account, acceptor main account) to update their stats
+) -> Instruction {
+    Instruction {
+        data: BetInstruction::SettleBet()
+        .try_to_vec()
+        .unwrap(),
+        ..finalize_bet(
+            program_id,
+            settler_main_account,
+            settler_payment_account,
+            commission_fee_account,
+            bet_state_account,
+            accepted_bet_state_account,
+            accepted_bet_escrow_account,
+            creator_main_account,
+            creator_payment_account,
+            acceptor_payment_account,
+            betting_market_account,
+            pyth_oracle_price_account,
+            token_program,
+            closed_winner_payment,
+            missing_commission_ata,
+            settlement_receipts,
+            user_stats
+        )
+    }
+}
+
+/// Creates a RefundExpiredAcceptedBet Instruction, with the FinalizeBet accounts
+#[allow(clippy::too_many_arguments)]
+pub fn refund_expired_accepted_bet(
+    program_id: Pubkey,
+    finalizer_main_account: Pubkey,
+    finalizer_payment_account: Pubkey,
+    commission_fee_account: Pubkey,
+    bet_state_account: Pubkey,
+    accepted_bet_state_account: Pubkey,
+    accepted_bet_escrow_account: Pubkey,
+    creator_main_account: Pubkey,
+    creator_payment_account: Pubkey,
+    acceptor_payment_account: Pubkey,
+    betting_market_account: Pubkey,
+    pyth_oracle_price_account: Pubkey,
+    token_program: Pubkey, // the program that owns the bet's mint
+    closed_creator_payment: Option<(Pubkey, Pubkey)>, // (creator main account, payment mint) if the creator's payment account was closed or isn't a token account of the mint
+    missing_commission_ata: Option<(Pubkey, Pubkey)>, // (fee_commission_account, payment mint) if the commission ATA doesn't exist yet
+    settlement_receipts: bool, // the market has settlement_receipts, so the receipt PDA is written
+    user_stats: Option<(Pubkey, Pubkey)>, // (creator main account, acceptor main account) to update their stats
+) -> Instruction {
+    Instruction {
+        data: BetInstruction::RefundExpiredAcceptedBet()
+        .try_to_vec()
+        .unwrap(),
+        ..finalize_bet(
+            program_id,
+            finalizer_main_account,
+            finalizer_payment_account,
+            commission_fee_account,
+            bet_state_account,
+            accepted_bet_state_account,
+            accepted_bet_escrow_account,
+            creator_main_account,
+            creator_payment_account,
+            acceptor_payment_account,
+            betting_market_account,
+            pyth_oracle_price_account,
+            token_program,
+            closed_creator_payment,
+            missing_commission_ata,
+            settlement_receipts,
+            user_stats
+        )
+    }
+}
+
+/// Creates a DistributeFees Instruction
+#[allow(clippy::too_many_arguments)]
+pub fn distribute_fees(
+    program_id: Pubkey,
+    bet_state_account: Pubkey,
+    accepted_bet_state_account: Pubkey,
+    accepted_bet_escrow_account: Pubkey,
+    commission_fee_account: Pubkey,
+    finalizer_payment_account: Pubkey, // AcceptedBet.finalizer_payment_account
+    escrow_rent_recipient: Pubkey, // AcceptedBet.escrow_rent_recipient
+    betting_market_account: Pubkey,
+    token_program: Pubkey, // the program that owns the bet's mint
+) -> Instruction {
+    let (escrow_authority, _bump_seed) = find_escrow_authority(&program_id, &betting_market_account, &accepted_bet_state_account);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(bet_state_account, false),
+            AccountMeta::new(accepted_bet_state_account, false),
+            AccountMeta::new(accepted_bet_escrow_account, false),
+            AccountMeta::new(commission_fee_account, false),
+            AccountMeta::new(finalizer_payment_account, false),
+            AccountMeta::new(escrow_rent_recipient, false),
+            AccountMeta::new_readonly(betting_market_account, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(escrow_authority, false),
+        ],
+        data: BetInstruction::DistributeFees()
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Creates a UpdateBettingMarket Instruction
+#[allow(clippy::too_many_arguments)]
+pub fn update_betting_market(
+    program_id: Pubkey,
+    owner_account: Pubkey,
+    betting_market_account: Pubkey,
+    max_expiration_horizon_secs: Option<i64>,
+    accept_cutoff_secs: Option<i64>,
+    min_duration_secs: Option<i64>,
+    min_seconds_between_bets: Option<i64>,
+    max_open_bets_per_creator: Option<u32>,
+    finalizer_reward: Option<FinalizerReward>,
+    finalize_exclusivity_secs: Option<i64>,
+    settlement_observations: Option<SettlementObservations>,
+    preferred_settlement_window_secs: Option<i64>,
+    odds_bounds: Option<OddsBounds>,
+    clock_tolerance_secs: Option<i64>,
+    quote_cooldown_secs: Option<i64>,
+    max_position_payout: Option<u64>,
+    sol_commission_account: Option<Pubkey>,
+    settlement_receipts: Option<bool>,
+    supplied_start_price: Option<SuppliedStartPriceBounds>,
+    expiry_extensions: Option<ExpiryExtensions>,
+    strict_sol_funding: Option<bool>,
+    tie_policy: Option<TiePolicy>
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(owner_account, true),
+            AccountMeta::new(betting_market_account, false),
+            AccountMeta::new_readonly(sysvar::instructions::id(), false)
+        ],
+        data: BetInstruction::UpdateBettingMarket(UpdateBettingMarketArgs {
+            max_expiration_horizon_secs,
+            accept_cutoff_secs,
+            min_duration_secs,
+            min_seconds_between_bets,
+            max_open_bets_per_creator,
+            finalizer_reward,
+            finalize_exclusivity_secs,
+            settlement_observations,
+            preferred_settlement_window_secs,
+            odds_bounds,
+            clock_tolerance_secs,
+            quote_cooldown_secs,
+            max_position_payout,
+            sol_commission_account,
+            settlement_receipts,
+            supplied_start_price,
+            expiry_extensions,
+            strict_sol_funding,
+            tie_policy
+        })
+        .try_to_vec()
+        .unwrap()
+    }
+}
+
+/// Creates a AddAllowedOracle Instruction
+pub fn add_allowed_oracle(
+    program_id: Pubkey,
+    owner_account: Pubkey,
+    betting_market_account: Pubkey,
+    pyth_oracle_price_account: Pubkey
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(owner_account, true),
+            AccountMeta::new(betting_market_account, false),
+            AccountMeta::new_readonly(pyth_oracle_price_account, false),
+            AccountMeta::new_readonly(sysvar::instructions::id(), false)
+        ],
+        data: BetInstruction::AddAllowedOracle()
+        .try_to_vec()
+        .unwrap()
+    }
+}
+
+/// Creates a RemoveAllowedOracle Instruction
+pub fn remove_allowed_oracle(
+    program_id: Pubkey,
+    owner_account: Pubkey,
+    betting_market_account: Pubkey,
+    pyth_oracle_price_account: Pubkey
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(owner_account, true),
+            AccountMeta::new(betting_market_account, false),
+            AccountMeta::new_readonly(pyth_oracle_price_account, false),
+            AccountMeta::new_readonly(sysvar::instructions::id(), false)
+        ],
+        data: BetInstruction::RemoveAllowedOracle()
+        .try_to_vec()
+        .unwrap()
+    }
+}
+
+/// Creates an IncreaseBet Instruction
+#[allow(clippy::too_many_arguments)]
+pub fn increase_bet(
+    program_id: Pubkey,
+    creator_main_account: Pubkey,
+    creator_payment_account: Pubkey,
+    bet_state_account: Pubkey,
+    payment_mint: Pubkey, // the native mint for SOL bets
+    token_program: Pubkey, // the program that owns payment_mint
+    betting_market_account: Pubkey,
+    amount: u64,
+) -> Instruction {
+    let bet_escrow_account = get_escrow_address(&program_id, &betting_market_account, &bet_state_account, &payment_mint, &token_program);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(creator_main_account, true),
+            AccountMeta::new(creator_payment_account, false),
+            AccountMeta::new(bet_state_account, false),
+            AccountMeta::new(bet_escrow_account, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false)
+        ],
+        data: BetInstruction::IncreaseBet(IncreaseBetArgs {
+            amount,
+        })
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Creates a RolloverBet Instruction
+#[allow(clippy::too_many_arguments)]
+pub fn rollover_bet(
+    program_id: Pubkey,
+    keeper_main_account: Pubkey,
+    keeper_payment_account: Pubkey,
+    bet_state_account: Pubkey,
+    payment_mint: Pubkey, // the native mint for SOL bets
+    token_program: Pubkey, // the program that owns payment_mint
+    betting_market_account: Pubkey,
+    pyth_oracle_price_account: Pubkey,
+) -> Instruction {
+    let bet_escrow_account = get_escrow_address(&program_id, &betting_market_account, &bet_state_account, &payment_mint, &token_program);
+    let (escrow_authority, _bump_seed) = find_escrow_authority(&program_id, &betting_market_account, &bet_state_account);
+    let (event_authority, _bump_seed) = find_event_authority(&program_id);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(keeper_main_account, true),
+            AccountMeta::new(keeper_payment_account, false),
+            AccountMeta::new(bet_state_account, false),
+            AccountMeta::new(bet_escrow_account, false),
+            AccountMeta::new_readonly(betting_market_account, false),
+            AccountMeta::new_readonly(pyth_oracle_price_account, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(escrow_authority, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(event_authority, false),
+            AccountMeta::new_readonly(program_id, false)
+        ],
+        data: BetInstruction::RolloverBet()
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Creates a DelistOracle Instruction
+pub fn delist_oracle(
+    program_id: Pubkey,
+    owner_account: Pubkey,
+    betting_market_account: Pubkey,
+    pyth_oracle_price_account: Pubkey,
+    delisted: bool
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(owner_account, true),
+            AccountMeta::new(betting_market_account, false),
+            AccountMeta::new_readonly(pyth_oracle_price_account, false),
+            AccountMeta::new_readonly(sysvar::instructions::id(), false)
+        ],
+        data: BetInstruction::DelistOracle(DelistOracleArgs {
+            delisted
+        })
+        .try_to_vec()
+        .unwrap()
+    }
+}
+
+/// Creates a RecordSettlementPrice Instruction
+pub fn record_settlement_price(
+    program_id: Pubkey,
+    bet_state_account: Pubkey,
+    betting_market_account: Pubkey,
+    pyth_oracle_price_account: Pubkey
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(bet_state_account, false),
+            AccountMeta::new_readonly(betting_market_account, false),
+            AccountMeta::new_readonly(pyth_oracle_price_account, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false)
+        ],
+        data: BetInstruction::RecordSettlementPrice()
+        .try_to_vec()
+        .unwrap()
+    }
+}
+
+/// Creates a SimulateFinalize Instruction
+pub fn simulate_finalize(
+    program_id: Pubkey,
+    bet_state_account: Pubkey,
+    accepted_bet_state_account: Pubkey,
+    betting_market_account: Pubkey,
+    pyth_oracle_price_account: Pubkey
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(bet_state_account, false),
+            AccountMeta::new_readonly(accepted_bet_state_account, false),
+            AccountMeta::new_readonly(betting_market_account, false),
+            AccountMeta::new_readonly(pyth_oracle_price_account, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false)
+        ],
+        data: BetInstruction::SimulateFinalize()
+        .try_to_vec()
+        .unwrap()
+    }
+}
+
+/// Creates an EmitEvent Instruction, for the program's self-CPI
+pub fn emit_event(
+    program_id: Pubkey,
+    event: BetEvent,
+) -> Instruction {
+    let (event_authority, _bump_seed) = find_event_authority(&program_id);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(event_authority, true),
+        ],
+        data: BetInstruction::EmitEvent(event)
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded_instructions() -> Vec<Vec<u8>> {
+        let key = Pubkey::new_unique;
+        vec![
+            init_betting_market(key(), key(), key(), key(), vec![key(), key()], 86400, 60, true, false).data,
+            cancel_bet(key(), key(), key(), key(), key(), key(), key(), key(), 1_000).data,
+        ]
+    }
+
+    fn invalid(input: &[u8]) {
+        assert_eq!(BetInstruction::unpack(input).err(), Some(BetError::InvalidInstruction.into()), "{:?}", input);
+    }
+
+    #[test]
+    fn unpack_round_trips() {
+        for data in encoded_instructions() {
+            assert_eq!(BetInstruction::unpack(&data).unwrap().try_to_vec().unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn unpack_rejects_truncated_payloads() {
+        invalid(&[]);
+        for data in encoded_instructions() {
+            for len in 1..data.len() {
+                invalid(&data[..len]);
+            }
+        }
+    }
+
+    #[test]
+    fn unpack_rejects_unknown_tags() {
+        for tag in ANCHOR_INSTRUCTION_NAMES.len() as u8..=u8::MAX {
+            invalid(&[tag]);
+            invalid(&[tag, 0, 0, 0, 0, 0, 0, 0, 0]);
+        }
+    }
+
+    #[test]
+    fn unpack_rejects_trailing_bytes() {
+        for data in encoded_instructions() {
+            invalid(&[data.as_slice(), &[0]].concat());
+            invalid(&[data.as_slice(), &[0; 32]].concat());
+        }
+    }
+
+    #[cfg(feature = "anchor-compat")]
+    #[test]
+    fn unpack_checks_anchor_framing() {
+        for data in encoded_instructions() {
+            let instruction = BetInstruction::unpack(&data).unwrap();
+            let framed = instruction.pack_anchor();
+            assert_eq!(BetInstruction::unpack(&framed).unwrap().try_to_vec().unwrap(), data);
+            invalid(&framed[..framed.len() - 1]);
+            invalid(&[framed.as_slice(), &[0]].concat());
+            // an unknown discriminator falls back to native decoding, where its first byte is an unknown tag
+            let mut unknown = framed.clone();
+            unknown[0] = u8::MAX;
+            invalid(&unknown);
+        }
+    }
+}
@@ -0,0 +1,438 @@
+//! Declarative borsh layouts of the instruction args and account state, for clients that generate their
+//! own (de)serializers. The export-layouts binary writes them to layouts.json
+use crate::{
+    state::{MAX_TAG_LEN, RESERVED_LEN, MAX_SETTLEMENT_OBSERVATIONS, MAX_ACCEPTED_MINTS, MAX_ALLOWED_PRICE_ACCOUNTS, MAX_DELISTED_PRICE_ACCOUNTS},
+    utils::DISCRIMINATOR_LEN
+};
+
+/// A borsh type, named types refer to a Layout in TYPES
+pub enum Ty {
+    Bool,
+    U8,
+    U16,
+    U32,
+    I32,
+    U64,
+    I64,
+    Pubkey,
+    String(usize), // at most this many bytes, after the u32 length
+    Option(&'static Ty),
+    Vec(&'static Ty, usize), // at most this many items, after the u32 length
+    Array(&'static Ty, usize),
+    Defined(&'static str)
+}
+
+pub struct Field {
+    pub name: &'static str,
+    pub ty: Ty
+}
+
+pub enum Body {
+    Struct(&'static [Field]),
+    Enum(&'static [&'static str]), // unit variants, encoded as a u8 index
+    DataEnum(&'static [(&'static str, &'static [Field])]) // variants with fields, a u8 index followed by the variant's fields
+}
+
+pub struct Layout {
+    pub name: &'static str,
+    pub body: Body
+}
+
+const fn field(name: &'static str, ty: Ty) -> Field {
+    Field { name, ty }
+}
+
+/// Types used by the instruction args and accounts
+pub const TYPES: &[Layout] = &[
+    Layout { name: "Direction", body: Body::Enum(&["Above", "Below"]) },
+    Layout { name: "ExpiryKind", body: Body::Enum(&["UnixTime", "Slot"]) },
+    Layout { name: "BetStatus", body: Body::Enum(&["Uninitialized", "Open", "PartiallyAccepted", "FullyAccepted", "Cancelled", "Expired"]) },
+    Layout { name: "PoolStatus", body: Body::Enum(&["Uninitialized", "Open", "Settled", "Refunded"]) },
+    Layout { name: "TiePolicy", body: Body::Enum(&["CreatorWins", "AcceptorWins", "Refund"]) },
+    Layout { name: "PositionStatus", body: Body::Enum(&["Uninitialized", "Open", "Settled", "Refunded", "Unwound"]) },
+    Layout { name: "PayoutCurve", body: Body::DataEnum(&[
+        ("Binary", &[]),
+        ("LinearPerPoint", &[
+            field("units_per_point", Ty::U64),
+            field("cap", Ty::U64),
+        ]),
+    ]) },
+    Layout { name: "CancelCondition", body: Body::Struct(&[
+        field("below_price", Ty::I64),
+        field("above_price", Ty::I64),
+        field("time", Ty::I64),
+    ]) },
+    Layout { name: "PriceObservation", body: Body::Struct(&[
+        field("slot", Ty::U64),
+        field("price", Ty::I64),
+    ]) },
+    Layout { name: "SuppliedStartPrice", body: Body::Struct(&[
+        field("price", Ty::I64),
+        field("publish_slot", Ty::U64),
+    ]) },
+];
+
+/// Instruction args, the instruction data is the BetInstruction variant's u8 index followed by these
+pub const INSTRUCTION_ARGS: &[Layout] = &[
+    Layout { name: "InitBettingMarketArgs", body: Body::Struct(&[
+        field("accepted_mints", Ty::Vec(&Ty::Pubkey, MAX_ACCEPTED_MINTS)),
+        field("max_expiration_horizon_secs", Ty::I64),
+        field("min_duration_secs", Ty::I64),
+        field("allow_variable_odds", Ty::Bool),
+        field("allow_unverified_oracle_program", Ty::Bool),
+    ]) },
+    Layout { name: "CreateBetArgs", body: Body::Struct(&[
+        field("bet_size", Ty::U64),
+        field("odds", Ty::I64),
+        field("expiration_time", Ty::I64),
+        field("bet_direction", Ty::Defined("Direction")),
+        field("bet_price", Ty::I64),
+        field("cancel_condition", Ty::Defined("CancelCondition")),
+        field("variable_odds", Ty::Option(&Ty::I64)),
+        field("min_fill", Ty::U64),
+        field("max_acceptors", Ty::Option(&Ty::U16)),
+        field("tag", Ty::String(MAX_TAG_LEN)),
+        field("rollover_interval_secs", Ty::Option(&Ty::I64)),
+        field("expiry_kind", Ty::Defined("ExpiryKind")),
+        field("max_odds_change", Ty::Option(&Ty::I64)),
+        field("max_total_acceptable", Ty::Option(&Ty::U64)),
+        field("nonce", Ty::Option(&Ty::U64)),
+        field("both_sides", Ty::Bool),
+        field("payout_curve", Ty::Defined("PayoutCurve")),
+        field("keeper_bounty_lamports", Ty::U64),
+        field("supplied_start_price", Ty::Option(&Ty::Defined("SuppliedStartPrice"))),
+        field("refund_destination", Ty::Option(&Ty::Pubkey)),
+    ]) },
+    Layout { name: "AcceptBetArgs", body: Body::Struct(&[
+        field("bet_size", Ty::U64),
+        field("expected_start_price", Ty::Option(&Ty::I64)),
+        field("deadline", Ty::Option(&Ty::I64)),
+        field("expected_odds", Ty::Option(&Ty::I64)),
+        field("side", Ty::Option(&Ty::Defined("Direction"))),
+    ]) },
+];
+
+/// Account state, prefixed by DISCRIMINATOR_LEN bytes in anchor-compat builds
+pub const ACCOUNTS: &[Layout] = &[
+    Layout { name: "Bet", body: Body::Struct(&[
+        field("status", Ty::Defined("BetStatus")),
+        field("betting_market", Ty::Pubkey),
+        field("creator_main_account", Ty::Pubkey),
+        field("creator_payment_account", Ty::Pubkey),
+        field("bet_escrow_account", Ty::Pubkey),
+        field("odds", Ty::I64),
+        field("bet_size", Ty::U64),
+        field("pyth_oracle_product_account", Ty::Pubkey),
+        field("pyth_oracle_price_account", Ty::Pubkey),
+        field("expiration_time", Ty::I64),
+        field("bet_direction", Ty::Defined("Direction")),
+        field("bet_price", Ty::I64),
+        field("start_price", Ty::I64),
+        field("cancel_condition", Ty::Defined("CancelCondition")),
+        field("last_interaction_slot", Ty::U64),
+        field("nonce", Ty::U64),
+        field("tag", Ty::String(MAX_TAG_LEN)),
+        field("variable_odds", Ty::Option(&Ty::I64)),
+        field("total_amount_accepted", Ty::U64),
+        field("legacy_cancelled", Ty::Bool),
+        field("payment_mint", Ty::Pubkey),
+        field("accepted_count", Ty::U32),
+        field("finalized_count", Ty::U32),
+        field("min_fill", Ty::U64),
+        field("max_acceptors", Ty::Option(&Ty::U16)),
+        field("created_at", Ty::I64),
+        field("updated_at", Ty::I64),
+        field("rollover", Ty::Bool),
+        field("rollover_interval_secs", Ty::I64),
+        field("rollover_balance", Ty::U64),
+        field("period_accepted_base", Ty::U32),
+        field("settlement_observations", Ty::Array(&Ty::Defined("PriceObservation"), MAX_SETTLEMENT_OBSERVATIONS)),
+        field("settlement_observations_count", Ty::U8),
+        field("expiry_kind", Ty::Defined("ExpiryKind")),
+        field("last_odds_update_time", Ty::I64),
+        field("max_odds_change", Ty::Option(&Ty::I64)),
+        field("max_total_acceptable", Ty::Option(&Ty::U64)),
+        field("escrow_seeds_version", Ty::U8),
+        field("escrow_bump_seed", Ty::U8),
+        field("both_sides", Ty::Bool),
+        field("side_amounts_accepted", Ty::Array(&Ty::U64, 2)),
+        field("payout_curve", Ty::Defined("PayoutCurve")),
+        field("fee_exempt", Ty::Bool),
+        field("keeper_bounty_lamports", Ty::U64),
+        field("keeper_bounty_paid_count", Ty::U32),
+        field("feed_exposure_tracked", Ty::Bool),
+        field("oracle_symbol_hash", Ty::Array(&Ty::U8, 32)),
+        field("oracle_expo", Ty::I32),
+        field("refund_destination", Ty::Pubkey),
+        field("extensions_used", Ty::U8),
+        field("open_bet_released", Ty::Bool),
+        field("reserved", Ty::Array(&Ty::U8, RESERVED_LEN - 59)),
+    ]) },
+    Layout { name: "BettingMarket", body: Body::Struct(&[
+        field("owner", Ty::Pubkey),
+        field("fee_commission_account", Ty::Pubkey),
+        field("accepted_mints", Ty::Array(&Ty::Pubkey, MAX_ACCEPTED_MINTS)),
+        field("accepted_mints_count", Ty::U8),
+        field("pyth_program_id", Ty::Pubkey),
+        field("max_expiration_horizon_secs", Ty::I64),
+        field("bump_seed", Ty::U8),
+        field("allowed_price_accounts", Ty::Array(&Ty::Pubkey, MAX_ALLOWED_PRICE_ACCOUNTS)),
+        field("allowed_price_accounts_count", Ty::U8),
+        field("accept_cutoff_secs", Ty::I64),
+        field("min_duration_secs", Ty::I64),
+        field("min_seconds_between_bets", Ty::I64),
+        field("max_open_bets_per_creator", Ty::U32),
+        field("finalizer_reward_base_bps", Ty::U16),
+        field("finalizer_reward_growth_bps_per_hour", Ty::U16),
+        field("finalizer_reward_max_bps", Ty::U16),
+        field("finalize_exclusivity_secs", Ty::I64),
+        field("delisted_price_accounts", Ty::Array(&Ty::Pubkey, MAX_DELISTED_PRICE_ACCOUNTS)),
+        field("delisted_price_accounts_count", Ty::U8),
+        field("settlement_window_secs", Ty::I64),
+        field("min_settlement_observations", Ty::U8),
+        field("preferred_settlement_window_secs", Ty::I64),
+        field("min_odds", Ty::I64),
+        field("max_odds", Ty::I64),
+        field("allow_variable_odds", Ty::Bool),
+        field("clock_tolerance_secs", Ty::I64),
+        field("allow_unverified_oracle_program", Ty::Bool),
+        field("quote_cooldown_secs", Ty::I64),
+        field("max_position_payout", Ty::U64),
+        field("sol_commission_account", Ty::Pubkey),
+        field("settlement_receipts", Ty::Bool),
+        field("start_price_tolerance_bps", Ty::U16),
+        field("max_start_price_age_slots", Ty::U64),
+        field("max_extensions", Ty::U8),
+        field("extension_secs", Ty::I64),
+        field("strict_sol_funding", Ty::Bool),
+        field("tie_policy", Ty::Defined("TiePolicy")),
+        field("reserved", Ty::Array(&Ty::U8, RESERVED_LEN - 54)),
+    ]) },
+    Layout { name: "MintRegistry", body: Body::Struct(&[
+        field("is_initialized", Ty::Bool),
+        field("mint", Ty::Pubkey),
+        field("betting_market", Ty::Pubkey),
+        field("bump_seed", Ty::U8),
+    ]) },
+    Layout { name: "AcceptedBet", body: Body::Struct(&[
+        field("status", Ty::Defined("PositionStatus")),
+        field("bet", Ty::Pubkey),
+        field("accepted_bet_escrow_account", Ty::Pubkey),
+        field("acceptor_main_account", Ty::Pubkey),
+        field("acceptor_payment_account", Ty::Pubkey),
+        field("odds", Ty::I64),
+        field("bet_size", Ty::U64),
+        field("legacy_finalized", Ty::Bool),
+        field("fees_pending", Ty::Bool),
+        field("commission_amount", Ty::U64),
+        field("finalizer_amount", Ty::U64),
+        field("finalizer_payment_account", Ty::Pubkey),
+        field("escrow_rent_recipient", Ty::Pubkey),
+        field("creator_direction", Ty::Defined("Direction")),
+        field("reserved", Ty::Array(&Ty::U8, RESERVED_LEN)),
+    ]) },
+    Layout { name: "CreatorExposure", body: Body::Struct(&[
+        field("is_initialized", Ty::Bool),
+        field("betting_market", Ty::Pubkey),
+        field("creator", Ty::Pubkey),
+        field("open_bets", Ty::U32),
+        field("last_created_at", Ty::I64),
+        field("bump_seed", Ty::U8),
+    ]) },
+    Layout { name: "UserStats", body: Body::Struct(&[
+        field("is_initialized", Ty::Bool),
+        field("betting_market", Ty::Pubkey),
+        field("user", Ty::Pubkey),
+        field("wins", Ty::U32),
+        field("losses", Ty::U32),
+        field("volume", Ty::U64),
+        field("net_pnl", Ty::I64),
+        field("bump_seed", Ty::U8),
+    ]) },
+    Layout { name: "FeedExposure", body: Body::Struct(&[
+        field("is_initialized", Ty::Bool),
+        field("betting_market", Ty::Pubkey),
+        field("pyth_oracle_price_account", Ty::Pubkey),
+        field("unmatched_amount", Ty::U64),
+        field("matched_notional", Ty::U64),
+        field("unmatched_saturated", Ty::Bool),
+        field("matched_saturated", Ty::Bool),
+        field("bump_seed", Ty::U8),
+    ]) },
+    Layout { name: "Receipt", body: Body::Struct(&[
+        field("is_initialized", Ty::Bool),
+        field("betting_market", Ty::Pubkey),
+        field("bet", Ty::Pubkey),
+        field("accepted_bet", Ty::Pubkey),
+        field("creator_main_account", Ty::Pubkey),
+        field("acceptor_main_account", Ty::Pubkey),
+        field("creator_direction", Ty::Defined("Direction")),
+        field("creator_stake", Ty::U64),
+        field("acceptor_stake", Ty::U64),
+        field("settlement_price", Ty::I64),
+        field("creator_wins", Ty::Bool),
+        field("refunded", Ty::Bool),
+        field("creator_payout", Ty::U64),
+        field("acceptor_payout", Ty::U64),
+        field("settled_at", Ty::I64),
+        field("bump_seed", Ty::U8),
+    ]) },
+    Layout { name: "AcceptOrder", body: Body::Struct(&[
+        field("is_initialized", Ty::Bool),
+        field("betting_market", Ty::Pubkey),
+        field("acceptor_main_account", Ty::Pubkey),
+        field("acceptor_payment_account", Ty::Pubkey),
+        field("payment_mint", Ty::Pubkey),
+        field("pyth_oracle_price_account", Ty::Pubkey),
+        field("order_escrow_account", Ty::Pubkey),
+        field("direction", Ty::Defined("Direction")),
+        field("min_odds", Ty::I64),
+        field("max_odds", Ty::I64),
+        field("remaining_size", Ty::U64),
+        field("min_expiration_time", Ty::I64),
+        field("max_expiration_time", Ty::I64),
+        field("matcher_fee_bps", Ty::U16),
+        field("bump_seed", Ty::U8),
+        field("escrow_bump_seed", Ty::U8),
+        field("reserved", Ty::Array(&Ty::U8, RESERVED_LEN)),
+    ]) },
+    Layout { name: "ProgramInfo", body: Body::Struct(&[
+        field("is_initialized", Ty::Bool),
+        field("version_major", Ty::U16),
+        field("version_minor", Ty::U16),
+        field("version_patch", Ty::U16),
+        field("features", Ty::U64),
+        field("updated_at", Ty::I64),
+        field("bump_seed", Ty::U8),
+        field("reserved", Ty::Array(&Ty::U8, RESERVED_LEN)),
+    ]) },
+    Layout { name: "Pool", body: Body::Struct(&[
+        field("status", Ty::Defined("PoolStatus")),
+        field("betting_market", Ty::Pubkey),
+        field("creator_main_account", Ty::Pubkey),
+        field("payment_mint", Ty::Pubkey),
+        field("pool_escrow_account", Ty::Pubkey),
+        field("pyth_oracle_product_account", Ty::Pubkey),
+        field("pyth_oracle_price_account", Ty::Pubkey),
+        field("bet_price", Ty::I64),
+        field("oracle_expo", Ty::I32),
+        field("expiration_time", Ty::I64),
+        field("side_totals", Ty::Array(&Ty::U64, 2)),
+        field("participant_count", Ty::U32),
+        field("settlement_price", Ty::I64),
+        field("winning_side", Ty::Defined("Direction")),
+        field("commission_amount", Ty::U64),
+        field("unclaimed_stake", Ty::U64),
+        field("nonce", Ty::U64),
+        field("bump_seed", Ty::U8),
+        field("escrow_bump_seed", Ty::U8),
+        field("reserved", Ty::Array(&Ty::U8, RESERVED_LEN)),
+    ]) },
+    Layout { name: "PoolPosition", body: Body::Struct(&[
+        field("is_initialized", Ty::Bool),
+        field("pool", Ty::Pubkey),
+        field("participant_main_account", Ty::Pubkey),
+        field("participant_payment_account", Ty::Pubkey),
+        field("side", Ty::Defined("Direction")),
+        field("stake", Ty::U64),
+        field("bump_seed", Ty::U8),
+        field("reserved", Ty::Array(&Ty::U8, RESERVED_LEN)),
+    ]) },
+];
+
+fn find_type(name: &str) -> &'static Layout {
+    TYPES.iter().find(|layout| layout.name == name).expect("undefined layout type")
+}
+
+impl Ty {
+    /// Largest borsh encoded size of the type
+    pub fn max_len(&self) -> usize {
+        match self {
+            Ty::Bool | Ty::U8 => 1,
+            Ty::U16 => 2,
+            Ty::U32 | Ty::I32 => 4,
+            Ty::U64 | Ty::I64 => 8,
+            Ty::Pubkey => 32,
+            Ty::String(max_bytes) => 4 + max_bytes,
+            Ty::Option(ty) => 1 + ty.max_len(),
+            Ty::Vec(ty, max_items) => 4 + ty.max_len() * max_items,
+            Ty::Array(ty, len) => ty.max_len() * len,
+            Ty::Defined(name) => find_type(name).max_len()
+        }
+    }
+
+    fn to_json(&self) -> String {
+        match self {
+            Ty::Bool => "\"bool\"".to_string(),
+            Ty::U8 => "\"u8\"".to_string(),
+            Ty::U16 => "\"u16\"".to_string(),
+            Ty::U32 => "\"u32\"".to_string(),
+            Ty::I32 => "\"i32\"".to_string(),
+            Ty::U64 => "\"u64\"".to_string(),
+            Ty::I64 => "\"i64\"".to_string(),
+            Ty::Pubkey => "\"pubkey\"".to_string(),
+            Ty::String(max_bytes) => format!("{{\"string\": {}}}", max_bytes),
+            Ty::Option(ty) => format!("{{\"option\": {}}}", ty.to_json()),
+            Ty::Vec(ty, max_items) => format!("{{\"vec\": [{}, {}]}}", ty.to_json(), max_items),
+            Ty::Array(ty, len) => format!("{{\"array\": [{}, {}]}}", ty.to_json(), len),
+            Ty::Defined(name) => format!("{{\"defined\": \"{}\"}}", name)
+        }
+    }
+}
+
+impl Layout {
+    /// Largest borsh encoded size, without the anchor-compat discriminator
+    pub fn max_len(&self) -> usize {
+        match &self.body {
+            Body::Struct(fields) => fields.iter().map(|field| field.ty.max_len()).sum(),
+            Body::Enum(_) => 1,
+            Body::DataEnum(variants) => 1 + variants.iter()
+                .map(|(_, fields)| fields.iter().map(|field| field.ty.max_len()).sum::<usize>())
+                .max()
+                .unwrap_or(0)
+        }
+    }
+
+    fn to_json(&self, indent: &str) -> String {
+        let body = match &self.body {
+            Body::Struct(fields) => {
+                let fields: Vec<String> = fields.iter()
+                    .map(|field| format!("{}    {{\"name\": \"{}\", \"type\": {}}}", indent, field.name, field.ty.to_json()))
+                    .collect();
+                format!("\"kind\": \"struct\", \"max_len\": {}, \"fields\": [\n{}\n{}  ]", self.max_len(), fields.join(",\n"), indent)
+            },
+            Body::Enum(variants) => {
+                let variants: Vec<String> = variants.iter().map(|variant| format!("\"{}\"", variant)).collect();
+                format!("\"kind\": \"enum\", \"variants\": [{}]", variants.join(", "))
+            },
+            Body::DataEnum(variants) => {
+                let variants: Vec<String> = variants.iter()
+                    .map(|(name, fields)| {
+                        let fields: Vec<String> = fields.iter()
+                            .map(|field| format!("{{\"name\": \"{}\", \"type\": {}}}", field.name, field.ty.to_json()))
+                            .collect();
+                        format!("{}    {{\"name\": \"{}\", \"fields\": [{}]}}", indent, name, fields.join(", "))
+                    })
+                    .collect();
+                format!("\"kind\": \"enum\", \"max_len\": {}, \"variants\": [\n{}\n{}  ]", self.max_len(), variants.join(",\n"), indent)
+            }
+        };
+        format!("{}{{\n{}  \"name\": \"{}\", {}\n{}}}", indent, indent, self.name, body, indent)
+    }
+}
+
+fn layouts_to_json(layouts: &[Layout]) -> String {
+    let layouts: Vec<String> = layouts.iter().map(|layout| layout.to_json("    ")).collect();
+    format!("[\n{}\n  ]", layouts.join(",\n"))
+}
+
+/// The layouts.json document: the types, instruction args and accounts, and the accounts' discriminator length
+pub fn layouts_json() -> String {
+    format!(
+        "{{\n  \"discriminator_len\": {},\n  \"types\": {},\n  \"instruction_args\": {},\n  \"accounts\": {}\n}}\n",
+        DISCRIMINATOR_LEN,
+        layouts_to_json(TYPES),
+        layouts_to_json(INSTRUCTION_ARGS),
+        layouts_to_json(ACCOUNTS)
+    )
+}
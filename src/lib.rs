@@ -4,4 +4,21 @@ pub mod error;
 pub mod processor;
 pub mod state;
 pub mod utils;
-pub mod pyth;
\ No newline at end of file
+pub mod pyth;
+pub mod events;
+pub mod pdas;
+pub mod offsets;
+pub mod token;
+pub mod math;
+pub mod constants;
+pub mod budgets;
+#[cfg(feature = "client")]
+pub mod tx;
+#[cfg(feature = "client")]
+pub mod layouts;
+#[cfg(feature = "client")]
+pub mod snapshot;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "test-utils")]
+pub mod pyth_fixtures;
\ No newline at end of file
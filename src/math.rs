@@ -0,0 +1,404 @@
+//! Pure settlement and quote math, shared by the processor and clients. Nothing here reads accounts or sysvars
+use solana_program::clock::DEFAULT_MS_PER_SLOT;
+use crate::{
+    error::BetError,
+    state::{Bet, BetStatus, BettingMarket, Direction, PayoutCurve, PriceObservation, TiePolicy, MAX_SETTLEMENT_OBSERVATIONS}
+};
+
+// the furthest ahead a supplied timestamp can be, 10 years
+pub const MAX_TIMESTAMP_HORIZON_SECS: i64 = 10 * 365 * 24 * 60 * 60;
+// the same horizon in slots, at DEFAULT_MS_PER_SLOT
+pub const MAX_SLOT_HORIZON: i64 = MAX_TIMESTAMP_HORIZON_SECS * 1000 / DEFAULT_MS_PER_SLOT as i64;
+
+// a supplied unix timestamp must be after now and within the horizon, which rejects
+// negative values and millisecond timestamps. error names the offending field
+pub fn validate_timestamp(value: i64, now: i64, error: BetError) -> Result<(), BetError> {
+    if value <= now || value > now.saturating_add(MAX_TIMESTAMP_HORIZON_SECS) {
+        return Err(error);
+    }
+    Ok(())
+}
+
+// a supplied slot must be after the current slot and within the horizon
+pub fn validate_slot(value: i64, slot: u64, error: BetError) -> Result<(), BetError> {
+    let slot = slot as i64;
+    if value <= slot || value > slot.saturating_add(MAX_SLOT_HORIZON) {
+        return Err(error);
+    }
+    Ok(())
+}
+
+// the window closes settlement_window_secs after expiration, or once the observations are full
+pub fn settlement_window_closed(bet: &Bet, market: &BettingMarket, secs_since_expiry: i64) -> bool {
+    secs_since_expiry >= market.settlement_window_secs
+        || bet.settlement_observations_count as usize == MAX_SETTLEMENT_OBSERVATIONS
+}
+
+// median of the observed prices, the mean of the middle two for an even count
+pub fn median_price(observations: &[PriceObservation]) -> i64 {
+    let mut prices = [0i64; MAX_SETTLEMENT_OBSERVATIONS];
+    let prices = &mut prices[..observations.len()];
+    for (price, observation) in prices.iter_mut().zip(observations) {
+        *price = observation.price;
+    }
+    prices.sort_unstable();
+    let mid = prices.len() / 2;
+    if prices.len() % 2 == 1 {
+        prices[mid]
+    } else {
+        prices[mid - 1] + (prices[mid] - prices[mid - 1]) / 2
+    }
+}
+
+pub fn latest_observation_slot(observations: &[PriceObservation]) -> u64 {
+    observations.iter().map(|observation| observation.slot).max().unwrap_or(0)
+}
+
+// which side a bet settles for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    CreatorWins,
+    AcceptorWins,
+    Refund, // a tie under TiePolicy::Refund
+}
+
+// the settlement decision, finalize and its simulation both use it. The creator wins an Above bet above the bet
+// price and a Below bet below it, a tie goes by the market's tie policy. Negative prices are rejected
+pub fn determine_winner(direction: Direction, settlement_price: i64, bet_price: i64, tie_policy: TiePolicy) -> Result<Outcome, BetError> {
+    if settlement_price < 0 {
+        return Err(BetError::NegativeSettlementPrice);
+    }
+    if settlement_price == bet_price {
+        return Ok(match tie_policy {
+            TiePolicy::CreatorWins => Outcome::CreatorWins,
+            TiePolicy::AcceptorWins => Outcome::AcceptorWins,
+            TiePolicy::Refund => Outcome::Refund,
+        });
+    }
+    let creator_wins = match direction {
+        Direction::Above => settlement_price > bet_price,
+        Direction::Below => settlement_price < bet_price,
+    };
+    Ok(if creator_wins { Outcome::CreatorWins } else { Outcome::AcceptorWins })
+}
+
+// finalizer reward in bps, grows linearly from the market's base at expiration up to its cap
+pub fn finalizer_reward_bps(market: &BettingMarket, secs_since_expiry: i64) -> u64 {
+    let growth = (market.finalizer_reward_growth_bps_per_hour as u64)
+        .saturating_mul(secs_since_expiry.max(0) as u64) / 3600;
+    (market.finalizer_reward_base_bps as u64)
+        .saturating_add(growth)
+        .min(market.finalizer_reward_max_bps as u64)
+}
+
+// true if value is within tolerance_bps of reference, computed in i128 so it can't overflow
+pub fn within_tolerance_bps(value: i64, reference: i64, tolerance_bps: u16) -> bool {
+    (value as i128 - reference as i128).unsigned_abs() * 10000 <= (reference as i128).unsigned_abs() * tolerance_bps as u128
+}
+
+// distance of the settlement price from the bet price in oracle price units, computed in i128 so it can't overflow
+pub fn price_distance(settlement_price: i64, bet_price: i64) -> u128 {
+    (settlement_price as i128 - bet_price as i128).unsigned_abs()
+}
+
+// the part of the loser's stake the winner takes: all of it on a binary bet, 1/cap of it per whole point of distance
+// from the bet price on a linear bet, up to all of it at cap points
+pub fn curve_winnings(payout_curve: PayoutCurve, loser_stake: u64, settlement_price: i64, bet_price: i64) -> Result<u64, BetError> {
+    match payout_curve {
+        PayoutCurve::Binary => Ok(loser_stake),
+        PayoutCurve::LinearPerPoint { units_per_point, cap } => {
+            if units_per_point == 0 || cap == 0 {
+                return Err(BetError::InvalidCreatePayoutCurve);
+            }
+            let points = (price_distance(settlement_price, bet_price) / units_per_point as u128).min(cap as u128);
+            // points <= cap, so the result is at most loser_stake
+            Ok((loser_stake as u128 * points / cap as u128) as u64)
+        }
+    }
+}
+
+// splits the accepted escrow into (commission, finalizer, winner, loser) amounts, fees are charged on the matched bet size
+// and taken off the top, fee exempt bets pay no commission. The loser gets back loser_refund, the part of their stake
+// the payout curve leaves them, unless the fees need it, and the winner gets the rest
+pub fn settlement_amounts(pot: u64, bet_size: u64, finalizer_reward_bps: u64, loser_refund: u64, fee_exempt: bool) -> Result<(u64, u64, u64, u64), BetError> {
+    let commission_amount = if fee_exempt { 0 } else { bet_size / 50 };
+    let finalizer_amount = bet_size.checked_mul(finalizer_reward_bps).ok_or(BetError::AmountOverflow)? / 10000;
+    let net_amount = pot
+        .checked_sub(commission_amount + finalizer_amount)
+        .ok_or(BetError::AmountUnderflow)?;
+    let loser_amount = loser_refund.min(net_amount);
+    Ok((commission_amount, finalizer_amount, net_amount - loser_amount, loser_amount))
+}
+
+// what the acceptor pays to take bet_size of the bet at the given odds
+pub fn acceptor_payment_amount(bet_size: u64, odds: i64) -> u64 {
+    bet_size * ((odds - 100) as u64) / 100
+}
+
+// the matcher's fee on an accept order fill, in bps of the fill's acceptor payment
+pub fn matcher_fee(acceptor_payment: u64, matcher_fee_bps: u16) -> Result<u64, BetError> {
+    Ok(acceptor_payment.checked_mul(matcher_fee_bps as u64).ok_or(BetError::AmountOverflow)? / 10000)
+}
+
+// a pool's commission, taken from the losing side's stakes at the 2% bets charge on the matched size
+pub fn pool_commission(losing_pool: u64) -> u64 {
+    losing_pool / 50
+}
+
+// a pool winner's share of the losing side's stakes after commission, stake * distributable / winning_pool in u128.
+// Rounds down, so the winners' shares never add up to more than distributable, the last claim takes the dust
+pub fn pool_winnings(stake: u64, winning_pool: u64, distributable: u64) -> Result<u64, BetError> {
+    if stake > winning_pool {
+        return Err(BetError::AmountOverflow);
+    }
+    if winning_pool == 0 {
+        return Ok(0);
+    }
+    // stake <= winning_pool, so the share is at most distributable
+    Ok((stake as u128 * distributable as u128 / winning_pool as u128) as u64)
+}
+
+// what an accept order escrows, the acceptor payment of its size at max_odds and the matcher fee on it. Both round
+// down, so the fills of the size at or below max_odds never take more than this in total
+pub fn accept_order_budget(size: u64, max_odds: i64, matcher_fee_bps: u16) -> Result<u64, BetError> {
+    let acceptor_payment = size.checked_mul((max_odds - 100) as u64).ok_or(BetError::AmountOverflow)? / 100;
+    acceptor_payment.checked_add(matcher_fee(acceptor_payment, matcher_fee_bps)?).ok_or(BetError::AmountOverflow)
+}
+
+// the most a position of bet_size at the odds pays its winner, the creator's stake plus the acceptor payment
+pub fn position_payout(bet_size: u64, odds: i64) -> Result<u64, BetError> {
+    bet_size.checked_add(acceptor_payment_amount(bet_size, odds)).ok_or(BetError::AmountOverflow)
+}
+
+// the largest bet_size whose position payout at the odds is within the cap
+pub fn max_size_for_payout(max_payout: u64, odds: i64) -> u64 {
+    (max_payout as u128 * 100 / odds.max(100) as u128) as u64
+}
+
+/// Quotes the acceptor payment for accepting bet_size of a bet on the given side (both_sides bets only) at the given
+/// price, time and slot, with the same checks AcceptBet makes on the bet
+pub fn quote_accept_bet(bet: &Bet, bet_size: u64, side: Option<Direction>, price: i64, now: i64, slot: u64) -> Result<u64, BetError> {
+    if bet.status == BetStatus::Cancelled {
+        return Err(BetError::BetCancelled);
+    }
+    if side.is_some() != bet.both_sides {
+        return Err(BetError::InvalidAcceptSide);
+    }
+    if let Some(max_acceptors) = bet.max_acceptors {
+        if bet.accepted_count.saturating_sub(bet.period_accepted_base) >= max_acceptors as u32 {
+            return Err(BetError::MaxAcceptorsReached);
+        }
+    }
+    let remaining_capacity = bet.remaining_acceptable_for(side);
+    if bet_size > remaining_capacity {
+        return Err(BetError::AcceptExceedsCapacity);
+    }
+    if bet_size < bet.min_fill && bet_size != remaining_capacity {
+        return Err(BetError::FillBelowMinimum);
+    }
+    if bet.cancel_condition_blocks(price, now) || bet.is_expired(now, slot) {
+        return Err(BetError::BetNoLongerValid);
+    }
+    let odds = bet.effective_odds(price)?;
+    Ok(acceptor_payment_amount(bet_size, odds))
+}
+
+/// Picks the fills of an AcceptBestBet as (candidate index, size): the cheapest candidate for the acceptor that takes the
+/// whole size, or with allow_split, one candidate's remaining capacity and another for the rest if that costs less.
+/// Candidates quote_accept_bet rejects at a size are skipped for it
+#[allow(clippy::too_many_arguments)]
+pub fn select_best_fills(candidates: &[Bet], bet_size: u64, side: Option<Direction>, price: i64, now: i64, slot: u64, allow_split: bool) -> Result<Vec<(usize, u64)>, BetError> {
+    let quote = |index: usize, size: u64| quote_accept_bet(&candidates[index], size, side, price, now, slot).ok();
+    let mut best: Option<(u64, Vec<(usize, u64)>)> = None;
+    for index in 0..candidates.len() {
+        if let Some(payment) = quote(index, bet_size) {
+            if !matches!(best, Some((best_payment, _)) if best_payment <= payment) {
+                best = Some((payment, vec![(index, bet_size)]));
+            }
+        }
+    }
+    if allow_split {
+        for first in 0..candidates.len() {
+            let first_size = candidates[first].remaining_acceptable_for(side).min(bet_size);
+            if first_size == 0 || first_size == bet_size {
+                continue;
+            }
+            let first_payment = match quote(first, first_size) {
+                Some(payment) => payment,
+                None => continue
+            };
+            for second in (0..candidates.len()).filter(|second| *second != first) {
+                if let Some(second_payment) = quote(second, bet_size - first_size) {
+                    let payment = first_payment.saturating_add(second_payment);
+                    if !matches!(best, Some((best_payment, _)) if best_payment <= payment) {
+                        best = Some((payment, vec![(first, first_size), (second, bet_size - first_size)]));
+                    }
+                }
+            }
+        }
+    }
+    best.map(|(_, fills)| fills).ok_or(BetError::NoCandidateFits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::MAX_BETTING_MARKET_DATA_LEN;
+
+    // a zeroed market, zeroed accounts read as freshly allocated ones
+    fn market() -> BettingMarket {
+        BettingMarket::from_account_data(&[0; MAX_BETTING_MARKET_DATA_LEN]).unwrap()
+    }
+
+    #[test]
+    fn determine_winner_truth_table() {
+        let bet_price = 10_000;
+        let cases = [
+            (Direction::Above, bet_price - 1, Outcome::AcceptorWins),
+            (Direction::Above, bet_price, Outcome::CreatorWins),
+            (Direction::Above, bet_price + 1, Outcome::CreatorWins),
+            (Direction::Below, bet_price - 1, Outcome::CreatorWins),
+            (Direction::Below, bet_price, Outcome::CreatorWins),
+            (Direction::Below, bet_price + 1, Outcome::AcceptorWins),
+        ];
+        for (direction, settlement_price, outcome) in cases {
+            assert_eq!(determine_winner(direction, settlement_price, bet_price, TiePolicy::CreatorWins), Ok(outcome), "{:?} settling at {}", direction, settlement_price);
+        }
+    }
+
+    #[test]
+    fn determine_winner_settles_ties_by_the_tie_policy() {
+        let policies = [
+            (TiePolicy::CreatorWins, Outcome::CreatorWins),
+            (TiePolicy::AcceptorWins, Outcome::AcceptorWins),
+            (TiePolicy::Refund, Outcome::Refund),
+        ];
+        for (tie_policy, outcome) in policies {
+            for direction in [Direction::Above, Direction::Below] {
+                assert_eq!(determine_winner(direction, 10_000, 10_000, tie_policy), Ok(outcome));
+                assert_eq!(determine_winner(direction, 0, 0, tie_policy), Ok(outcome));
+                // the policy only decides ties
+                assert_ne!(determine_winner(direction, 10_001, 10_000, tie_policy), Ok(Outcome::Refund));
+                assert_ne!(determine_winner(direction, 9_999, 10_000, tie_policy), Ok(Outcome::Refund));
+            }
+            assert_eq!(determine_winner(Direction::Above, 10_001, 10_000, tie_policy), Ok(Outcome::CreatorWins));
+            assert_eq!(determine_winner(Direction::Below, 10_001, 10_000, tie_policy), Ok(Outcome::AcceptorWins));
+        }
+    }
+
+    #[test]
+    fn determine_winner_rejects_negative_prices() {
+        for direction in [Direction::Above, Direction::Below] {
+            assert_eq!(determine_winner(direction, -1, 10_000, TiePolicy::CreatorWins), Err(BetError::NegativeSettlementPrice));
+            assert_eq!(determine_winner(direction, i64::MIN, 0, TiePolicy::CreatorWins), Err(BetError::NegativeSettlementPrice));
+            // even at a negative bet price
+            assert_eq!(determine_winner(direction, -1, -1, TiePolicy::Refund), Err(BetError::NegativeSettlementPrice));
+        }
+        // zero is a price, not a rejection
+        assert_eq!(determine_winner(Direction::Below, 0, 0, TiePolicy::CreatorWins), Ok(Outcome::CreatorWins));
+    }
+
+    fn reward_market(base_bps: u16, growth_bps_per_hour: u16, max_bps: u16) -> BettingMarket {
+        let mut market = market();
+        market.finalizer_reward_base_bps = base_bps;
+        market.finalizer_reward_growth_bps_per_hour = growth_bps_per_hour;
+        market.finalizer_reward_max_bps = max_bps;
+        market
+    }
+
+    #[test]
+    fn finalizer_reward_is_the_base_at_expiry() {
+        assert_eq!(finalizer_reward_bps(&reward_market(10, 5, 100), 0), 10);
+        // growth rounds down, 5 bps per hour adds nothing for the first 720 seconds
+        assert_eq!(finalizer_reward_bps(&reward_market(10, 5, 100), 719), 10);
+        assert_eq!(finalizer_reward_bps(&reward_market(10, 5, 100), 720), 11);
+    }
+
+    #[test]
+    fn finalizer_reward_grows_linearly() {
+        let market = reward_market(10, 6, 100);
+        assert_eq!(finalizer_reward_bps(&market, 600), 11);
+        assert_eq!(finalizer_reward_bps(&market, 3600), 16);
+        assert_eq!(finalizer_reward_bps(&market, 2 * 3600), 22);
+        assert_eq!(finalizer_reward_bps(&market, 5 * 3600 + 1800), 43);
+    }
+
+    #[test]
+    fn finalizer_reward_stops_at_the_cap() {
+        let market = reward_market(10, 6, 40);
+        assert_eq!(finalizer_reward_bps(&market, 5 * 3600), 40);
+        assert_eq!(finalizer_reward_bps(&market, 6 * 3600), 40);
+        assert_eq!(finalizer_reward_bps(&market, i64::MAX), 40);
+        assert_eq!(finalizer_reward_bps(&reward_market(u16::MAX, u16::MAX, 40), i64::MAX), 40);
+    }
+
+    #[test]
+    fn finalizer_reward_before_expiry_is_the_base() {
+        let market = reward_market(10, 6, 100);
+        assert_eq!(finalizer_reward_bps(&market, -1), 10);
+        assert_eq!(finalizer_reward_bps(&market, -3600), 10);
+        assert_eq!(finalizer_reward_bps(&market, i64::MIN), 10);
+    }
+
+    #[test]
+    fn validate_timestamp_bounds() {
+        let now = 1_700_000_000;
+        let error = BetError::InvalidExpirationTime;
+        assert_eq!(validate_timestamp(now, now, error.clone()), Err(error.clone()));
+        assert_eq!(validate_timestamp(now + 1, now, error.clone()), Ok(()));
+        assert_eq!(validate_timestamp(now + MAX_TIMESTAMP_HORIZON_SECS, now, error.clone()), Ok(()));
+        assert_eq!(validate_timestamp(now + MAX_TIMESTAMP_HORIZON_SECS + 1, now, error.clone()), Err(error.clone()));
+        assert_eq!(validate_timestamp(now - 1, now, error.clone()), Err(error.clone()));
+        assert_eq!(validate_timestamp(-1, now, error.clone()), Err(error.clone()));
+        assert_eq!(validate_timestamp(i64::MIN, now, error.clone()), Err(error.clone()));
+        // a millisecond timestamp of a moment inside the horizon is past it in seconds
+        assert_eq!(validate_timestamp((now + 60) * 1000, now, error.clone()), Err(error.clone()));
+        // the horizon saturates rather than overflowing
+        assert_eq!(validate_timestamp(i64::MAX, i64::MAX - 1, error), Ok(()));
+    }
+
+    #[test]
+    fn validate_slot_bounds() {
+        let slot = 250_000_000;
+        let error = BetError::InvalidExpirationTime;
+        assert_eq!(validate_slot(slot as i64, slot, error.clone()), Err(error.clone()));
+        assert_eq!(validate_slot(slot as i64 + 1, slot, error.clone()), Ok(()));
+        assert_eq!(validate_slot(slot as i64 + MAX_SLOT_HORIZON, slot, error.clone()), Ok(()));
+        assert_eq!(validate_slot(slot as i64 + MAX_SLOT_HORIZON + 1, slot, error.clone()), Err(error.clone()));
+        assert_eq!(validate_slot(slot as i64 - 1, slot, error.clone()), Err(error.clone()));
+        assert_eq!(validate_slot(-1, slot, error.clone()), Err(error.clone()));
+        assert_eq!(validate_slot(-1, 0, error.clone()), Err(error.clone()));
+        // a millisecond timestamp passed as a slot is past the horizon
+        assert_eq!(validate_slot(1_700_000_000_000, slot, error.clone()), Err(error));
+    }
+
+    #[test]
+    fn binary_curve_pays_the_whole_stake() {
+        assert_eq!(curve_winnings(PayoutCurve::Binary, 1_000, 10_000, 10_000), Ok(1_000));
+        assert_eq!(curve_winnings(PayoutCurve::Binary, 1_000, 50_000, 10_000), Ok(1_000));
+    }
+
+    #[test]
+    fn linear_curve_points() {
+        let curve = PayoutCurve::LinearPerPoint { units_per_point: 100, cap: 4 };
+        // no distance, no winnings
+        assert_eq!(curve_winnings(curve, 1_000, 10_000, 10_000), Ok(0));
+        // part of a point counts for nothing
+        assert_eq!(curve_winnings(curve, 1_000, 10_099, 10_000), Ok(0));
+        assert_eq!(curve_winnings(curve, 1_000, 10_100, 10_000), Ok(250));
+        assert_eq!(curve_winnings(curve, 1_000, 9_700, 10_000), Ok(750));
+        // the cap takes the whole stake, further points take nothing more
+        assert_eq!(curve_winnings(curve, 1_000, 10_400, 10_000), Ok(1_000));
+        assert_eq!(curve_winnings(curve, 1_000, i64::MAX, i64::MIN), Ok(1_000));
+        // rounds down
+        assert_eq!(curve_winnings(PayoutCurve::LinearPerPoint { units_per_point: 1, cap: 3 }, 1_000, 1, 0), Ok(333));
+        assert_eq!(curve_winnings(PayoutCurve::LinearPerPoint { units_per_point: 1, cap: 3 }, u64::MAX, 2, 0), Ok(u64::MAX / 3 * 2));
+    }
+
+    #[test]
+    fn linear_curve_rejects_zero_parameters() {
+        for curve in [PayoutCurve::LinearPerPoint { units_per_point: 0, cap: 4 }, PayoutCurve::LinearPerPoint { units_per_point: 100, cap: 0 }] {
+            assert_eq!(curve_winnings(curve, 1_000, 10_100, 10_000), Err(BetError::InvalidCreatePayoutCurve));
+        }
+    }
+}
@@ -0,0 +1,21 @@
+//! Byte offsets of the fixed position Bet fields, for memcmp filters in getProgramAccounts
+use crate::{
+    state::MAX_TAG_LEN,
+    utils::DISCRIMINATOR_LEN
+};
+
+// the BetStatus variant index
+pub const BET_STATUS_OFFSET: usize = DISCRIMINATOR_LEN;
+pub const BET_BETTING_MARKET_OFFSET: usize = BET_STATUS_OFFSET + 1;
+pub const BET_CREATOR_MAIN_ACCOUNT_OFFSET: usize = BET_BETTING_MARKET_OFFSET + 32;
+pub const BET_PYTH_ORACLE_PRICE_ACCOUNT_OFFSET: usize = BET_CREATOR_MAIN_ACCOUNT_OFFSET + 32 + 32 + 32 + 8 + 8 + 32;
+pub const BET_EXPIRATION_TIME_OFFSET: usize = BET_PYTH_ORACLE_PRICE_ACCOUNT_OFFSET + 32;
+// little endian u64, refreshed with updated_at, for finding bets nobody has touched since a slot
+pub const BET_LAST_INTERACTION_SLOT_OFFSET: usize = BET_EXPIRATION_TIME_OFFSET + 8 + 1 + 8 + 8 + 24;
+// little endian u64, the creator's nonce or NO_NONCE
+pub const BET_NONCE_OFFSET: usize = BET_LAST_INTERACTION_SLOT_OFFSET + 8;
+// the borsh u32 length prefix of the tag, always MAX_TAG_LEN since tags are padded
+pub const BET_TAG_LEN_OFFSET: usize = BET_NONCE_OFFSET + 8;
+pub const BET_TAG_OFFSET: usize = BET_TAG_LEN_OFFSET + 4;
+// fields after the tag start with the variable length variable_odds Option, so have no fixed offset
+pub const BET_FIXED_FIELDS_END: usize = BET_TAG_OFFSET + MAX_TAG_LEN;
@@ -0,0 +1,179 @@
+use solana_program::{pubkey::{Pubkey, PubkeyError}, bpf_loader_upgradeable};
+use crate::{
+    utils::{PREFIX, MARKET_SEED, MINT_SEED, CREATOR_SEED, STATS_SEED, ORDER_SEED, FEED_SEED, RECEIPT_SEED, PROGRAM_INFO_SEED, EVENT_AUTHORITY_SEED, POOL_SEED, POOL_POSITION_SEED, ESCROW_SEED},
+    token::get_associated_token_address
+};
+
+/// Finds the canonical betting market account of a primary settlement mint (the native mint for SOL), the first of
+/// the market's accepted mints
+pub fn find_market(program_id: &Pubkey, primary_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MARKET_SEED.as_bytes(), primary_mint.as_ref()], program_id)
+}
+
+/// Finds the registry account of a mint, naming the one betting market that accepts it
+pub fn find_mint_registry(program_id: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MINT_SEED.as_bytes(), mint.as_ref()], program_id)
+}
+
+/// Finds the exposure account of a creator in a betting market
+pub fn find_creator_exposure(program_id: &Pubkey, betting_market_account: &Pubkey, creator: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CREATOR_SEED.as_bytes(), betting_market_account.as_ref(), creator.as_ref()], program_id)
+}
+
+/// Finds the stats account of a user in a betting market
+pub fn find_user_stats(program_id: &Pubkey, betting_market_account: &Pubkey, user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[STATS_SEED.as_bytes(), betting_market_account.as_ref(), user.as_ref()], program_id)
+}
+
+/// Finds the exposure account of a price account in a betting market
+pub fn find_feed_exposure(program_id: &Pubkey, betting_market_account: &Pubkey, pyth_oracle_price_account: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FEED_SEED.as_bytes(), betting_market_account.as_ref(), pyth_oracle_price_account.as_ref()], program_id)
+}
+
+/// Finds the accept order account of an acceptor in a betting market
+pub fn find_accept_order(program_id: &Pubkey, betting_market_account: &Pubkey, acceptor: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ORDER_SEED.as_bytes(), betting_market_account.as_ref(), acceptor.as_ref()], program_id)
+}
+
+/// Finds the settlement receipt of an accepted bet
+pub fn find_receipt(program_id: &Pubkey, accepted_bet_state_account: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[RECEIPT_SEED.as_bytes(), accepted_bet_state_account.as_ref()], program_id)
+}
+
+/// Finds the nonce'th pool of a creator in a betting market
+pub fn find_pool(program_id: &Pubkey, betting_market_account: &Pubkey, creator: &Pubkey, nonce: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[POOL_SEED.as_bytes(), betting_market_account.as_ref(), creator.as_ref(), &nonce.to_le_bytes()],
+        program_id
+    )
+}
+
+/// Finds the position account of a participant in a pool
+pub fn find_pool_position(program_id: &Pubkey, pool: &Pubkey, participant: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[POOL_POSITION_SEED.as_bytes(), pool.as_ref(), participant.as_ref()], program_id)
+}
+
+/// Finds the program's ProgramInfo account, its version and features
+pub fn find_program_info(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PROGRAM_INFO_SEED.as_bytes()], program_id)
+}
+
+/// Finds the ProgramData account of a program deployed with the upgradeable loader
+pub fn find_program_data(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id())
+}
+
+/// Finds the accepted bet state account of the accepted_count'th accept of a bet by an acceptor
+pub fn find_accepted_bet(program_id: &Pubkey, bet_state_account: &Pubkey, acceptor: &Pubkey, accepted_count: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[PREFIX.as_bytes(), bet_state_account.as_ref(), acceptor.as_ref(), &accepted_count.to_le_bytes()],
+        program_id
+    )
+}
+
+/// Finds the escrow token account of an accepted bet, created by the fill and owned by the accepted escrow authority
+pub fn find_accepted_escrow(program_id: &Pubkey, accepted_bet_state_account: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ESCROW_SEED.as_bytes(), accepted_bet_state_account.as_ref()], program_id)
+}
+
+/// Escrow authority seeds [PREFIX, state_account] of bets created before the seeds were scoped to the market
+pub const ESCROW_SEEDS_LEGACY: u8 = 0;
+/// Escrow authority seeds [PREFIX, betting_market, state_account], used by new bets
+pub const ESCROW_SEEDS_MARKET: u8 = 1;
+
+/// Seeds of the escrow authority of a bet or accepted bet state account, without the bump. The version is the bet's
+/// escrow_seeds_version, its accepted bets use the same version
+pub fn escrow_authority_seeds<'a>(seeds_version: u8, betting_market_account: &'a Pubkey, state_account: &'a Pubkey) -> Vec<&'a [u8]> {
+    if seeds_version == ESCROW_SEEDS_LEGACY {
+        vec![PREFIX.as_bytes(), state_account.as_ref()]
+    } else {
+        vec![PREFIX.as_bytes(), betting_market_account.as_ref(), state_account.as_ref()]
+    }
+}
+
+/// Finds the PDA that owns the escrow token account of a bet or accepted bet state account in the market
+pub fn find_escrow_authority(program_id: &Pubkey, betting_market_account: &Pubkey, state_account: &Pubkey) -> (Pubkey, u8) {
+    find_escrow_authority_versioned(program_id, ESCROW_SEEDS_MARKET, betting_market_account, state_account)
+}
+
+/// Finds the escrow authority with the given seeds version
+pub fn find_escrow_authority_versioned(program_id: &Pubkey, seeds_version: u8, betting_market_account: &Pubkey, state_account: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&escrow_authority_seeds(seeds_version, betting_market_account, state_account), program_id)
+}
+
+/// The escrow authority for a known bump, and the signer seeds including the bump
+pub fn escrow_authority_signer<'a>(
+    program_id: &Pubkey,
+    seeds_version: u8,
+    betting_market_account: &'a Pubkey,
+    state_account: &'a Pubkey,
+    bump_seed: &'a [u8],
+) -> Result<(Pubkey, Vec<&'a [u8]>), PubkeyError> {
+    let mut seeds = escrow_authority_seeds(seeds_version, betting_market_account, state_account);
+    seeds.push(bump_seed);
+    let escrow_authority = Pubkey::create_program_address(&seeds, program_id)?;
+    Ok((escrow_authority, seeds))
+}
+
+/// Escrow token account of a bet state account in the market - the ATA of its escrow authority. Accepted bets created
+/// before find_accepted_escrow used the same derivation
+pub fn get_escrow_address(program_id: &Pubkey, betting_market_account: &Pubkey, state_account: &Pubkey, mint: &Pubkey, token_program_id: &Pubkey) -> Pubkey {
+    let (escrow_authority, _bump_seed) = find_escrow_authority(program_id, betting_market_account, state_account);
+    get_associated_token_address(&escrow_authority, mint, token_program_id)
+}
+
+/// Finds the PDA that signs the program's self-CPI EmitEvent instructions
+pub fn find_event_authority(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[EVENT_AUTHORITY_SEED.as_bytes()], program_id)
+}
+
+/// Seed of the bet state account created from a creator's nonce, the nonce in decimal
+pub fn bet_seed(nonce: u64) -> String {
+    nonce.to_string()
+}
+
+/// Every address of a bet created with create_account_with_seed(creator, bet_seed(nonce), program_id). The schema is
+/// stable: the bet state account is Pubkey::create_with_seed(creator, bet_seed(nonce), program_id) and the other
+/// addresses are the PDAs above, so clients can predict them before sending anything
+#[derive(Clone, Debug, PartialEq)]
+pub struct BetAddresses {
+    pub program_id: Pubkey,
+    pub betting_market_account: Pubkey,
+    pub bet_state_account: Pubkey,
+    pub escrow_authority: Pubkey,
+    pub creator_exposure_account: Pubkey,
+}
+
+impl BetAddresses {
+    /// The bet escrow, the escrow authority's ATA of the bet's mint
+    pub fn escrow_account(&self, mint: &Pubkey, token_program_id: &Pubkey) -> Pubkey {
+        get_associated_token_address(&self.escrow_authority, mint, token_program_id)
+    }
+
+    /// The (accepted bet state account, accepted escrow authority) of the accepted_count'th accept by an acceptor
+    pub fn accepted_bet(&self, acceptor: &Pubkey, accepted_count: u32) -> (Pubkey, Pubkey) {
+        let (accepted_bet_state_account, _bump_seed) = find_accepted_bet(&self.program_id, &self.bet_state_account, acceptor, accepted_count);
+        let (accepted_escrow_authority, _bump_seed) = find_escrow_authority(&self.program_id, &self.betting_market_account, &accepted_bet_state_account);
+        (accepted_bet_state_account, accepted_escrow_authority)
+    }
+
+    /// The escrow token account of the accepted_count'th accept by an acceptor
+    pub fn accepted_escrow_account(&self, acceptor: &Pubkey, accepted_count: u32) -> Pubkey {
+        let (accepted_bet_state_account, _bump_seed) = find_accepted_bet(&self.program_id, &self.bet_state_account, acceptor, accepted_count);
+        find_accepted_escrow(&self.program_id, &accepted_bet_state_account).0
+    }
+}
+
+/// Derives the addresses of a creator's bet in a market from their chosen nonce, the bet stores the nonce
+pub fn derive_bet_addresses(program_id: &Pubkey, betting_market_account: &Pubkey, creator: &Pubkey, nonce: u64) -> Result<BetAddresses, PubkeyError> {
+    let bet_state_account = Pubkey::create_with_seed(creator, &bet_seed(nonce), program_id)?;
+    let (escrow_authority, _bump_seed) = find_escrow_authority(program_id, betting_market_account, &bet_state_account);
+    let (creator_exposure_account, _bump_seed) = find_creator_exposure(program_id, betting_market_account, creator);
+    Ok(BetAddresses {
+        program_id: *program_id,
+        betting_market_account: *betting_market_account,
+        bet_state_account,
+        escrow_authority,
+        creator_exposure_account,
+    })
+}
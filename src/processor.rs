@@ -6,33 +6,35 @@ use solana_program::{
     program_pack::{Pack},
     sysvar::{rent::Rent, Sysvar},
     program::{invoke, invoke_signed},
-    clock::{Clock},
+    clock::{Clock, DEFAULT_MS_PER_SLOT},
     system_program::{check_id},
     system_instruction,
-    program_error::ProgramError
+    program_error::{PrintProgramError, ProgramError},
+    hash::hash
 };
 
 use spl_token::state::Account as TokenAccount;
 
 use crate::{
-    instruction::BetInstruction,
-    error::BetError,
-    utils::PREFIX,
-    utils::create_or_allocate_account_raw,
-    utils::puffed_out_string,
-    state::{BettingMarket, Bet, Direction, CancelCondition, AcceptedBet},
-    pyth
+    instruction::{BetInstruction, CreateBetArgs, FinalizerReward, SettlementObservations, OddsBounds, CANCEL_ALL_REMAINING, MAX_ACCEPT_BEST_CANDIDATES, ACCEPT_BEST_CANDIDATE_ACCOUNTS, MAX_CLOSE_SETTLED_ACCOUNTS, CLOSE_SETTLED_POSITION_ACCOUNTS, AcceptMultipleArgs, MAX_ACCEPT_MULTIPLE_GROUPS, ACCEPT_MULTIPLE_GROUP_ACCOUNTS, SuppliedStartPrice, SuppliedStartPriceBounds, CreatePoolArgs, JoinPoolArgs, ExpiryExtensions},
+    error::{BetError, invalid_argument},
+    utils::{PREFIX, MARKET_SEED, MINT_SEED, CREATOR_SEED, STATS_SEED, ORDER_SEED, FEED_SEED, RECEIPT_SEED, create_or_allocate_account_raw, debit_lamports, PROGRAM_INFO_SEED, POOL_SEED, POOL_POSITION_SEED, ESCROW_SEED, puffed_out_string, assert_not_cpi, assert_distinct_accounts, assert_upgrade_authority, lamports_transferred_earlier},
+    state::{BettingMarket, MAX_BETTING_MARKET_DATA_LEN, MintRegistry, MAX_MINT_REGISTRY_DATA_LEN, DEFAULT_ACCEPT_CUTOFF_SECS, MAX_ACCEPTED_MINTS, MAX_ALLOWED_PRICE_ACCOUNTS, MAX_ACCEPTED_BET_DATA_LEN, Bet, BetStatus, PositionStatus, Direction, ExpiryKind, PayoutCurve, TiePolicy, AcceptedBet, MAX_TAG_LEN, CreatorExposure, MAX_CREATOR_EXPOSURE_DATA_LEN, DEFAULT_FINALIZER_REWARD_BASE_BPS, DEFAULT_FINALIZER_REWARD_GROWTH_BPS_PER_HOUR, DEFAULT_FINALIZER_REWARD_MAX_BPS, MAX_FINALIZER_REWARD_BPS, MAX_DELISTED_PRICE_ACCOUNTS, PriceObservation, MAX_SETTLEMENT_OBSERVATIONS, DEFAULT_MIN_ODDS, DEFAULT_MAX_ODDS, MAX_CLOCK_TOLERANCE_SECS, MAX_START_PRICE_TOLERANCE_BPS, MAX_EXPIRY_EXTENSIONS, MAX_EXTENSION_SECS, UserStats, MAX_USER_STATS_DATA_LEN, NO_NONCE, ORACLE_FEED_CHANGED_REFUND_SECS, ORACLE_UNAVAILABLE_REFUND_SECS, AcceptOrder, MAX_ACCEPT_ORDER_DATA_LEN, MAX_MATCHER_FEE_BPS, FeedExposure, MAX_FEED_EXPOSURE_DATA_LEN, Receipt, MAX_RECEIPT_DATA_LEN, ProgramInfo, MAX_PROGRAM_INFO_DATA_LEN, PROGRAM_FEATURES, Pool, PoolStatus, MAX_POOL_DATA_LEN, PoolPosition, MAX_POOL_POSITION_DATA_LEN},
+    events::{BetEvent, emit_event, emit_event_cpi},
+    pdas::{find_escrow_authority, find_escrow_authority_versioned, escrow_authority_signer, ESCROW_SEEDS_MARKET, bet_seed, find_market, find_mint_registry, find_accepted_bet, find_accepted_escrow, find_event_authority, find_creator_exposure, find_user_stats, find_accept_order, find_feed_exposure, find_receipt, find_program_info, find_pool, find_pool_position},
+    token::{self, check_token_program, check_mint_extensions, get_associated_token_address, create_associated_token_account},
+    pyth,
+    constants::is_known_pyth_program,
+    budgets::compute_checkpoint,
+    math::{MAX_TIMESTAMP_HORIZON_SECS, validate_timestamp, validate_slot, determine_winner, Outcome, curve_winnings, finalizer_reward_bps, settlement_amounts, settlement_window_closed, median_price, latest_observation_slot, acceptor_payment_amount, position_payout, max_size_for_payout, select_best_fills, matcher_fee, accept_order_budget, within_tolerance_bps, pool_commission, pool_winnings}
 };
 
 use std::convert::TryInto;
-use borsh::{BorshSerialize, BorshDeserialize};
 
 use pyth_client::{
-    Product,
     Price,
     PriceConf,
-    load_price,
-    load_product
+    load_price
 };
 
 pub fn process_instruction<'a>(
@@ -40,15 +42,19 @@ pub fn process_instruction<'a>(
     accounts: &'a [AccountInfo<'a>],
     input: &[u8],
 ) -> ProgramResult {
-    let instruction = BetInstruction::try_from_slice(input)?;
-    match instruction {
+    let instruction = BetInstruction::unpack(input)?;
+    compute_checkpoint("start");
+    let result = match instruction {
         BetInstruction::InitBettingMarket(args) => {
             msg!("Instruction: Init Betting Market");
             process_init_betting_market(
                 program_id, 
                 accounts, 
-                args.sol_payment, 
-                args.payment_mint
+                args.accepted_mints,
+                args.max_expiration_horizon_secs,
+                args.min_duration_secs,
+                args.allow_variable_odds,
+                args.allow_unverified_oracle_program
             )
         },
         BetInstruction::CreateBet(args) => {
@@ -56,13 +62,7 @@ pub fn process_instruction<'a>(
             process_create_bet(
                 program_id,
                 accounts,
-                args.bet_size,
-                args.odds,
-                args.expiration_time,
-                args.bet_direction,
-                args.bet_price,
-                args.cancel_condition,
-                args.variable_odds,
+                args
             )
         },
         BetInstruction::AcceptBet(args) => {
@@ -70,804 +70,5158 @@ pub fn process_instruction<'a>(
             process_accept_bet(
                 program_id,
                 accounts,
-                args.bet_size
+                args.bet_size,
+                args.expected_start_price,
+                args.deadline,
+                args.expected_odds,
+                args.side
             )
         },
-        BetInstruction::CancelBet() => {
+        BetInstruction::CancelBet(args) => {
             msg!("Instruction: Cancel Bet");
             process_cancel_bet(
                 program_id,
                 accounts,
+                args.amount,
             )
         },
         BetInstruction::FinalizeBet() => {
             msg!("Instruction: Finalize Bet");
             process_finalize_bet(
+                program_id,
+                accounts,
+                false,
+                false
+            )
+        },
+        BetInstruction::UpdateBettingMarket(args) => {
+            msg!("Instruction: Update Betting Market");
+            process_update_betting_market(
+                program_id,
+                accounts,
+                args.max_expiration_horizon_secs,
+                args.accept_cutoff_secs,
+                args.min_duration_secs,
+                args.min_seconds_between_bets,
+                args.max_open_bets_per_creator,
+                args.finalizer_reward,
+                args.finalize_exclusivity_secs,
+                args.settlement_observations,
+                args.preferred_settlement_window_secs,
+                args.odds_bounds,
+                args.clock_tolerance_secs,
+                args.quote_cooldown_secs,
+                args.max_position_payout,
+                args.sol_commission_account,
+                args.settlement_receipts,
+                args.supplied_start_price,
+                args.expiry_extensions,
+                args.strict_sol_funding,
+                args.tie_policy
+            )
+        },
+        BetInstruction::SimulateFinalize() => {
+            msg!("Instruction: Simulate Finalize");
+            process_simulate_finalize(
                 program_id,
                 accounts
             )
         },
-    }
+        BetInstruction::AddAllowedOracle() => {
+            msg!("Instruction: Add Allowed Oracle");
+            process_add_allowed_oracle(
+                program_id,
+                accounts
+            )
+        },
+        BetInstruction::RemoveAllowedOracle() => {
+            msg!("Instruction: Remove Allowed Oracle");
+            process_remove_allowed_oracle(
+                program_id,
+                accounts
+            )
+        },
+        BetInstruction::DelistOracle(args) => {
+            msg!("Instruction: Delist Oracle");
+            process_delist_oracle(
+                program_id,
+                accounts,
+                args.delisted
+            )
+        },
+        BetInstruction::CloneBet(args) => {
+            msg!("Instruction: Clone Bet");
+            process_clone_bet(
+                program_id,
+                accounts,
+                args.bet_size,
+                args.expiration_time,
+                args.relative_strike
+            )
+        },
+        BetInstruction::IncreaseBet(args) => {
+            msg!("Instruction: Increase Bet");
+            process_increase_bet(
+                program_id,
+                accounts,
+                args.amount
+            )
+        },
+        BetInstruction::RolloverBet() => {
+            msg!("Instruction: Rollover Bet");
+            process_rollover_bet(
+                program_id,
+                accounts
+            )
+        },
+        BetInstruction::RecordSettlementPrice() => {
+            msg!("Instruction: Record Settlement Price");
+            process_record_settlement_price(
+                program_id,
+                accounts
+            )
+        },
+        BetInstruction::SettleBet() => {
+            msg!("Instruction: Settle Bet");
+            process_finalize_bet(
+                program_id,
+                accounts,
+                true,
+                false
+            )
+        },
+        BetInstruction::DistributeFees() => {
+            msg!("Instruction: Distribute Fees");
+            process_distribute_fees(
+                program_id,
+                accounts
+            )
+        },
+        BetInstruction::AcceptBestBet(args) => {
+            msg!("Instruction: Accept Best Bet");
+            process_accept_best_bet(
+                program_id,
+                accounts,
+                args.bet_size,
+                args.deadline,
+                args.side,
+                args.allow_split
+            )
+        },
+        BetInstruction::PlaceAcceptOrder(args) => {
+            msg!("Instruction: Place Accept Order");
+            process_place_accept_order(
+                program_id,
+                accounts,
+                args.size,
+                args.direction,
+                args.min_odds,
+                args.max_odds,
+                args.min_expiration_time,
+                args.max_expiration_time,
+                args.matcher_fee_bps
+            )
+        },
+        BetInstruction::MatchOrder() => {
+            msg!("Instruction: Match Order");
+            process_match_order(
+                program_id,
+                accounts
+            )
+        },
+        BetInstruction::CancelAcceptOrder() => {
+            msg!("Instruction: Cancel Accept Order");
+            process_cancel_accept_order(
+                program_id,
+                accounts
+            )
+        },
+        BetInstruction::ValidateCreateBet(args) => {
+            msg!("Instruction: Validate Create Bet");
+            process_validate_create_bet(
+                program_id,
+                accounts,
+                args
+            )
+        },
+        BetInstruction::AcceptMultiple(args) => {
+            msg!("Instruction: Accept Multiple");
+            process_accept_multiple(
+                program_id,
+                accounts,
+                args
+            )
+        },
+        BetInstruction::UpdateProgramInfo() => {
+            msg!("Instruction: Update Program Info");
+            process_update_program_info(
+                program_id,
+                accounts
+            )
+        },
+        BetInstruction::CreatePool(args) => {
+            msg!("Instruction: Create Pool");
+            process_create_pool(
+                program_id,
+                accounts,
+                args
+            )
+        },
+        BetInstruction::JoinPool(args) => {
+            msg!("Instruction: Join Pool");
+            process_join_pool(
+                program_id,
+                accounts,
+                args
+            )
+        },
+        BetInstruction::SettlePool() => {
+            msg!("Instruction: Settle Pool");
+            process_settle_pool(
+                program_id,
+                accounts
+            )
+        },
+        BetInstruction::ExtendExpiry() => {
+            msg!("Instruction: Extend Expiry");
+            process_extend_expiry(
+                program_id,
+                accounts
+            )
+        },
+        BetInstruction::RefundExpiredAcceptedBet() => {
+            msg!("Instruction: Refund Expired Accepted Bet");
+            process_finalize_bet(
+                program_id,
+                accounts,
+                false,
+                true
+            )
+        },
+        BetInstruction::CloseSettledAccounts() => {
+            msg!("Instruction: Close Settled Accounts");
+            process_close_settled_accounts(
+                program_id,
+                accounts
+            )
+        },
+        BetInstruction::EmitEvent(_event) => {
+            // no log, the event is read from the inner instruction data
+            process_emit_event(
+                program_id,
+                accounts
+            )
+        },
+    };
+    compute_checkpoint("end");
+    result
 }
 
 pub fn process_init_betting_market<'a>(
     program_id: &'a Pubkey,
     accounts: &'a [AccountInfo<'a>],
-    sol_payment: bool,
-    payment_mint: Option<Pubkey>
+    accepted_mints: Vec<Pubkey>,
+    max_expiration_horizon_secs: i64,
+    min_duration_secs: i64,
+    allow_variable_odds: bool,
+    allow_unverified_oracle_program: bool
 ) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
     let owner_account_info = next_account_info(account_info_iter)?;
     let betting_market_account_info = next_account_info(account_info_iter)?;
     let commission_fee_account_info = next_account_info(account_info_iter)?;
     let pyth_program = next_account_info(account_info_iter)?;
+    let rent_sysvar_account_info = next_account_info(account_info_iter)?;
+    let system_program_account_info = next_account_info(account_info_iter)?;
+    if !check_id(system_program_account_info.key) {
+        return Err(BetError::InvalidSystemProgram.into());
+    }
+    let instructions_sysvar_account_info = next_account_info(account_info_iter)?;
 
     // check owner signed tx
     if !owner_account_info.is_signer {
         return Err(BetError::IncorrectOwner.into());
     }
 
-    // check program is owner of the bet_state_account_info
-    if betting_market_account_info.owner != program_id {
-        return Err(BetError::IncorrectOwner.into());
+    // market admin instructions must be signed for directly, not through another program
+    assert_not_cpi(program_id, instructions_sysvar_account_info)?;
+
+    // check the pyth program is a known cluster's, unless the market is explicitly for local testing
+    if !allow_unverified_oracle_program && !is_known_pyth_program(pyth_program.key) {
+        msg!("Pyth program {} isn't the mainnet-beta, devnet or testnet Pyth program", pyth_program.key);
+        return Err(BetError::UnknownOracleProgram.into());
     }
 
-    let mut betting_market_account = BettingMarket::from_account_info(betting_market_account_info)?;
+    // check expiration horizon isn't negative
+    if max_expiration_horizon_secs < 0 {
+        return Err(BetError::InvalidInstruction.into());
+    }
 
-    if sol_payment == false {
-        if let Some(mint) = payment_mint {
-            betting_market_account.payment_mint = Some(mint);
-        } else {
-            return Err(BetError::NoPaymentMintGiven.into());
+    // check the minimum duration isn't negative and leaves room below the horizon
+    check_duration_bounds(min_duration_secs, max_expiration_horizon_secs)?;
+
+    // check the accepted mints fit in the market and aren't repeated
+    if accepted_mints.is_empty() {
+        return Err(BetError::NoPaymentMintGiven.into());
+    }
+    if accepted_mints.len() > MAX_ACCEPTED_MINTS {
+        msg!("At most {} accepted mints, got {}", MAX_ACCEPTED_MINTS, accepted_mints.len());
+        return Err(BetError::InvalidAcceptedMints.into());
+    }
+    for (i, mint) in accepted_mints.iter().enumerate() {
+        if accepted_mints[..i].contains(mint) {
+            msg!("Accepted mint {} given twice", mint);
+            return Err(BetError::InvalidAcceptedMints.into());
+        }
+    }
+
+    // each accepted mint is passed as an account, owned by spl-token or Token-2022
+    for mint in accepted_mints.iter() {
+        let payment_mint_account_info = next_account_info(account_info_iter)?;
+        if *mint != *payment_mint_account_info.key {
+            msg!("Incorrect mint account: expected {}, got {}", mint, payment_mint_account_info.key);
+            return Err(BetError::InvalidMint.into());
+        }
+        check_token_program(payment_mint_account_info.owner)?;
+        // wrapped SOL lives in spl-token
+        if *mint == spl_token::native_mint::id() && *payment_mint_account_info.owner != spl_token::id() {
+            return Err(BetError::InvalidTokenProgram.into());
+        }
+        check_mint_extensions(payment_mint_account_info)?;
+    }
+
+    // the market is the PDA of its first accepted mint, so each settlement asset has one canonical market
+    let (market_address, bump_seed) = find_market(program_id, &accepted_mints[0]);
+    if market_address != *betting_market_account_info.key {
+        msg!("Incorrect betting market account: expected {}, got {}", market_address, betting_market_account_info.key);
+        return Err(BetError::WrongBettingMarket.into());
+    }
+    if betting_market_account_info.owner == program_id {
+        return Err(BetError::AccountAlreadyInitialized.into());
+    }
+
+    // each accepted mint's registry follows, a mint another market already accepts can't be added to this one
+    let mut mint_registry_account_infos = Vec::with_capacity(accepted_mints.len());
+    for mint in accepted_mints.iter() {
+        let mint_registry_account_info = next_account_info(account_info_iter)?;
+        let (mint_registry_address, mint_registry_bump_seed) = find_mint_registry(program_id, mint);
+        if mint_registry_address != *mint_registry_account_info.key {
+            msg!("Incorrect mint registry account: expected {}, got {}", mint_registry_address, mint_registry_account_info.key);
+            return Err(BetError::InvalidAccounts.into());
         }
+        if mint_registry_account_info.owner == program_id {
+            let mint_registry = MintRegistry::from_account_info(mint_registry_account_info)?;
+            msg!("Mint {} is accepted by betting market {}", mint, mint_registry.betting_market);
+            return Err(BetError::MintInAnotherMarket.into());
+        }
+        mint_registry_account_infos.push((mint_registry_account_info, mint_registry_bump_seed));
     }
+
+    // create the betting market account, owner pays the rent
+    create_or_allocate_account_raw(
+        *program_id,
+        betting_market_account_info,
+        rent_sysvar_account_info,
+        system_program_account_info,
+        owner_account_info,
+        MAX_BETTING_MARKET_DATA_LEN,
+        &[
+            MARKET_SEED.as_bytes(),
+            accepted_mints[0].as_ref(),
+            &[bump_seed]
+        ]
+    )?;
+
+    let mut betting_market_account = BettingMarket::from_account_info(betting_market_account_info)?;
+    betting_market_account.accepted_mints[..accepted_mints.len()].copy_from_slice(&accepted_mints);
+    betting_market_account.accepted_mints_count = accepted_mints.len() as u8;
+    betting_market_account.bump_seed = bump_seed;
     betting_market_account.owner = *owner_account_info.key;
-    betting_market_account.sol_payment = sol_payment;
     betting_market_account.fee_commission_account = *commission_fee_account_info.key;
     betting_market_account.pyth_program_id = *pyth_program.key;
+    betting_market_account.max_expiration_horizon_secs = max_expiration_horizon_secs;
+    betting_market_account.accept_cutoff_secs = DEFAULT_ACCEPT_CUTOFF_SECS;
+    betting_market_account.min_duration_secs = min_duration_secs;
+    betting_market_account.finalizer_reward_base_bps = DEFAULT_FINALIZER_REWARD_BASE_BPS;
+    betting_market_account.finalizer_reward_growth_bps_per_hour = DEFAULT_FINALIZER_REWARD_GROWTH_BPS_PER_HOUR;
+    betting_market_account.finalizer_reward_max_bps = DEFAULT_FINALIZER_REWARD_MAX_BPS;
+    betting_market_account.min_odds = DEFAULT_MIN_ODDS;
+    betting_market_account.max_odds = DEFAULT_MAX_ODDS;
+    betting_market_account.allow_variable_odds = allow_variable_odds;
+    betting_market_account.allow_unverified_oracle_program = allow_unverified_oracle_program;
+
+    // pack the betting_market_account
+    betting_market_account.save(betting_market_account_info)?;
+
+    // register the market as the one accepting each of its mints, owner pays the rent
+    for (mint, (mint_registry_account_info, mint_registry_bump_seed)) in accepted_mints.iter().zip(mint_registry_account_infos) {
+        create_or_allocate_account_raw(
+            *program_id,
+            mint_registry_account_info,
+            rent_sysvar_account_info,
+            system_program_account_info,
+            owner_account_info,
+            MAX_MINT_REGISTRY_DATA_LEN,
+            &[
+                MINT_SEED.as_bytes(),
+                mint.as_ref(),
+                &[mint_registry_bump_seed]
+            ]
+        )?;
+        let mut mint_registry = MintRegistry::from_account_info(mint_registry_account_info)?;
+        mint_registry.is_initialized = true;
+        mint_registry.mint = *mint;
+        mint_registry.betting_market = *betting_market_account_info.key;
+        mint_registry.bump_seed = mint_registry_bump_seed;
+        mint_registry.save(mint_registry_account_info)?;
+    }
 
     Ok(())
 }
 
-pub fn process_create_bet<'a>(
+#[allow(clippy::too_many_arguments)]
+pub fn process_update_betting_market<'a>(
     program_id: &'a Pubkey,
     accounts: &'a [AccountInfo<'a>],
-    bet_size: u64,
-    odds: i64,
-    expiration_time: i64,
-    bet_direction: Direction,
-    bet_price: i64,
-    cancel_condition: CancelCondition,
-    variable_odds: Option<i64>,
+    max_expiration_horizon_secs: Option<i64>,
+    accept_cutoff_secs: Option<i64>,
+    min_duration_secs: Option<i64>,
+    min_seconds_between_bets: Option<i64>,
+    max_open_bets_per_creator: Option<u32>,
+    finalizer_reward: Option<FinalizerReward>,
+    finalize_exclusivity_secs: Option<i64>,
+    settlement_observations: Option<SettlementObservations>,
+    preferred_settlement_window_secs: Option<i64>,
+    odds_bounds: Option<OddsBounds>,
+    clock_tolerance_secs: Option<i64>,
+    quote_cooldown_secs: Option<i64>,
+    max_position_payout: Option<u64>,
+    sol_commission_account: Option<Pubkey>,
+    settlement_receipts: Option<bool>,
+    supplied_start_price: Option<SuppliedStartPriceBounds>,
+    expiry_extensions: Option<ExpiryExtensions>,
+    strict_sol_funding: Option<bool>,
+    tie_policy: Option<TiePolicy>
 ) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
-    let creator_main_account_info = next_account_info(account_info_iter)?;
-    let creator_payment_account_info = next_account_info(account_info_iter)?;
-    let bet_state_account_info = next_account_info(account_info_iter)?;
-    let bet_escrow_account_info = next_account_info(account_info_iter)?;
+    let owner_account_info = next_account_info(account_info_iter)?;
+    let betting_market_account_info = next_account_info(account_info_iter)?;
+    let instructions_sysvar_account_info = next_account_info(account_info_iter)?;
+
+    // check owner signed tx
+    if !owner_account_info.is_signer {
+        return Err(BetError::IncorrectSigner.into());
+    }
+
+    // market admin instructions must be signed for directly, not through another program
+    assert_not_cpi(program_id, instructions_sysvar_account_info)?;
+
+    // check program is owner of the betting_market_account_info
+    if betting_market_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+
+    let mut betting_market_account = BettingMarket::from_account_info(betting_market_account_info)?;
+
+    // check signer is the betting market owner
+    if betting_market_account.owner != *owner_account_info.key {
+        msg!("Signer is not the betting market owner");
+        return Err(BetError::UnauthorizedAccount.into());
+    }
+
+    if let Some(horizon) = max_expiration_horizon_secs {
+        if horizon < 0 {
+            return Err(BetError::InvalidInstruction.into());
+        }
+        betting_market_account.max_expiration_horizon_secs = horizon;
+    }
+    if let Some(cutoff) = accept_cutoff_secs {
+        if cutoff < 0 {
+            return Err(BetError::InvalidInstruction.into());
+        }
+        betting_market_account.accept_cutoff_secs = cutoff;
+    }
+    if let Some(min_duration) = min_duration_secs {
+        betting_market_account.min_duration_secs = min_duration;
+    }
+    check_duration_bounds(betting_market_account.min_duration_secs, betting_market_account.max_expiration_horizon_secs)?;
+    if let Some(min_seconds) = min_seconds_between_bets {
+        if min_seconds < 0 {
+            return Err(BetError::InvalidInstruction.into());
+        }
+        betting_market_account.min_seconds_between_bets = min_seconds;
+    }
+    if let Some(max_open_bets) = max_open_bets_per_creator {
+        betting_market_account.max_open_bets_per_creator = max_open_bets;
+    }
+    if let Some(reward) = finalizer_reward {
+        if reward.base_bps > reward.max_bps || reward.max_bps > MAX_FINALIZER_REWARD_BPS {
+            msg!("Finalizer reward must satisfy base {} <= max {} <= {}", reward.base_bps, reward.max_bps, MAX_FINALIZER_REWARD_BPS);
+            return Err(BetError::InvalidInstruction.into());
+        }
+        betting_market_account.finalizer_reward_base_bps = reward.base_bps;
+        betting_market_account.finalizer_reward_growth_bps_per_hour = reward.growth_bps_per_hour;
+        betting_market_account.finalizer_reward_max_bps = reward.max_bps;
+    }
+    if let Some(exclusivity) = finalize_exclusivity_secs {
+        if exclusivity < 0 {
+            return Err(BetError::InvalidInstruction.into());
+        }
+        betting_market_account.finalize_exclusivity_secs = exclusivity;
+    }
+    if let Some(observations) = settlement_observations {
+        if observations.window_secs < 0
+            || (observations.window_secs > 0 && (observations.min_count == 0 || observations.min_count as usize > MAX_SETTLEMENT_OBSERVATIONS)) {
+            msg!("Settlement observations need a window >= 0 and a min count between 1 and {}", MAX_SETTLEMENT_OBSERVATIONS);
+            return Err(BetError::InvalidInstruction.into());
+        }
+        betting_market_account.settlement_window_secs = observations.window_secs;
+        betting_market_account.min_settlement_observations = observations.min_count;
+    }
+    if let Some(window) = preferred_settlement_window_secs {
+        if window < 0 {
+            return Err(BetError::InvalidInstruction.into());
+        }
+        betting_market_account.preferred_settlement_window_secs = window;
+    }
+    if let Some(bounds) = odds_bounds {
+        if bounds.min_odds < 100 || bounds.min_odds > bounds.max_odds {
+            msg!("Odds bounds must satisfy 100 <= min {} <= max {}", bounds.min_odds, bounds.max_odds);
+            return Err(BetError::InvalidInstruction.into());
+        }
+        betting_market_account.min_odds = bounds.min_odds;
+        betting_market_account.max_odds = bounds.max_odds;
+    }
+    if let Some(tolerance) = clock_tolerance_secs {
+        betting_market_account.clock_tolerance_secs = tolerance;
+    }
+    check_clock_tolerance(betting_market_account.clock_tolerance_secs, betting_market_account.accept_cutoff_secs)?;
+    if let Some(cooldown) = quote_cooldown_secs {
+        if cooldown < 0 {
+            return Err(BetError::InvalidInstruction.into());
+        }
+        betting_market_account.quote_cooldown_secs = cooldown;
+    }
+    if let Some(max_payout) = max_position_payout {
+        betting_market_account.max_position_payout = max_payout;
+    }
+    if let Some(sol_commission) = sol_commission_account {
+        betting_market_account.sol_commission_account = sol_commission;
+    }
+    if let Some(receipts) = settlement_receipts {
+        betting_market_account.settlement_receipts = receipts;
+    }
+    if let Some(bounds) = supplied_start_price {
+        if bounds.tolerance_bps > MAX_START_PRICE_TOLERANCE_BPS {
+            msg!("Start price tolerance {} bps is above the max {}", bounds.tolerance_bps, MAX_START_PRICE_TOLERANCE_BPS);
+            return Err(BetError::InvalidInstruction.into());
+        }
+        betting_market_account.start_price_tolerance_bps = bounds.tolerance_bps;
+        betting_market_account.max_start_price_age_slots = bounds.max_age_slots;
+    }
+    if let Some(extensions) = expiry_extensions {
+        if extensions.max_extensions > MAX_EXPIRY_EXTENSIONS
+            || (extensions.max_extensions > 0 && !(1..=MAX_EXTENSION_SECS).contains(&extensions.extension_secs)) {
+            msg!("Expiry extensions need at most {} extensions of 1 to {} seconds", MAX_EXPIRY_EXTENSIONS, MAX_EXTENSION_SECS);
+            return Err(BetError::InvalidInstruction.into());
+        }
+        betting_market_account.max_extensions = extensions.max_extensions;
+        betting_market_account.extension_secs = extensions.extension_secs;
+    }
+    if let Some(strict) = strict_sol_funding {
+        betting_market_account.strict_sol_funding = strict;
+    }
+    if let Some(tie_policy) = tie_policy {
+        betting_market_account.tie_policy = tie_policy;
+    }
+
+    // pack the betting_market_account
+    betting_market_account.save(betting_market_account_info)?;
+
+    Ok(())
+}
+
+pub fn process_add_allowed_oracle<'a>(
+    program_id: &'a Pubkey,
+    accounts: &'a [AccountInfo<'a>]
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let owner_account_info = next_account_info(account_info_iter)?;
     let betting_market_account_info = next_account_info(account_info_iter)?;
-    let pyth_oracle_product_account_info = next_account_info(account_info_iter)?;
     let pyth_oracle_price_account_info = next_account_info(account_info_iter)?;
-    let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
-    let token_program_account_info = next_account_info(account_info_iter)?;
-    spl_token::check_program_account(token_program_account_info.key)?;
-    let system_program_account_info = next_account_info(account_info_iter)?;
-    if check_id(system_program_account_info.key) == false {
-        return Err(BetError::InvalidSystemProgram.into());
+    let instructions_sysvar_account_info = next_account_info(account_info_iter)?;
+
+    // check owner signed tx
+    if !owner_account_info.is_signer {
+        return Err(BetError::IncorrectSigner.into());
     }
 
-    // check creator_account_info is the tx signer
-    if !creator_main_account_info.is_signer {
+    // market admin instructions must be signed for directly, not through another program
+    assert_not_cpi(program_id, instructions_sysvar_account_info)?;
+
+    // check program is owner of the betting_market_account_info
+    if betting_market_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+
+    let mut betting_market_account = BettingMarket::from_account_info(betting_market_account_info)?;
+
+    // check signer is the betting market owner
+    if betting_market_account.owner != *owner_account_info.key {
+        msg!("Signer is not the betting market owner");
+        return Err(BetError::UnauthorizedAccount.into());
+    }
+
+    // check the price account belongs to the market's pyth program
+    if betting_market_account.pyth_program_id != *pyth_oracle_price_account_info.owner {
+        msg!("Pyth price account provided is not owned by the Pyth oracle program");
+        return Err(BetError::OracleWrongOwner.into());
+    }
+
+    if betting_market_account.allowed_price_accounts().contains(pyth_oracle_price_account_info.key) {
+        return Err(BetError::OracleAlreadyAllowed.into());
+    }
+    let count = betting_market_account.allowed_price_accounts_count as usize;
+    if count >= MAX_ALLOWED_PRICE_ACCOUNTS {
+        return Err(BetError::OracleAllowlistFull.into());
+    }
+    betting_market_account.allowed_price_accounts[count] = *pyth_oracle_price_account_info.key;
+    betting_market_account.allowed_price_accounts_count += 1;
+
+    // pack the betting_market_account
+    betting_market_account.save(betting_market_account_info)?;
+
+    Ok(())
+}
+
+pub fn process_remove_allowed_oracle<'a>(
+    program_id: &'a Pubkey,
+    accounts: &'a [AccountInfo<'a>]
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let owner_account_info = next_account_info(account_info_iter)?;
+    let betting_market_account_info = next_account_info(account_info_iter)?;
+    let pyth_oracle_price_account_info = next_account_info(account_info_iter)?;
+    let instructions_sysvar_account_info = next_account_info(account_info_iter)?;
+
+    // check owner signed tx
+    if !owner_account_info.is_signer {
         return Err(BetError::IncorrectSigner.into());
     }
 
-    // check program is owner of the bet_state_account_info
-    if bet_state_account_info.owner != program_id {
+    // market admin instructions must be signed for directly, not through another program
+    assert_not_cpi(program_id, instructions_sysvar_account_info)?;
+
+    // check program is owner of the betting_market_account_info
+    if betting_market_account_info.owner != program_id {
         return Err(BetError::IncorrectOwner.into());
     }
 
-    // check bet_state_account_info has enough lamports to be rent exempt
-    if !rent.is_exempt(bet_state_account_info.lamports(), bet_state_account_info.data_len()) {
-        return Err(BetError::NotRentExempt.into());
+    let mut betting_market_account = BettingMarket::from_account_info(betting_market_account_info)?;
+
+    // check signer is the betting market owner
+    if betting_market_account.owner != *owner_account_info.key {
+        msg!("Signer is not the betting market owner");
+        return Err(BetError::UnauthorizedAccount.into());
     }
 
-    // unpack the bet_state_account_info
-    let mut bet_state_account = Bet::from_account_info(&bet_state_account_info)?;
-    // unpack the betting_market_account_info
-    let betting_market_account = BettingMarket::from_account_info(&betting_market_account_info)?;
+    // swap the last entry into the removed slot and clear the last slot
+    let index = betting_market_account.allowed_price_accounts()
+        .iter()
+        .position(|price_account| price_account == pyth_oracle_price_account_info.key)
+        .ok_or(BetError::OracleNotAllowed)?;
+    let last = betting_market_account.allowed_price_accounts_count as usize - 1;
+    betting_market_account.allowed_price_accounts.swap(index, last);
+    betting_market_account.allowed_price_accounts[last] = Pubkey::default();
+    betting_market_account.allowed_price_accounts_count -= 1;
+
+    // pack the betting_market_account
+    betting_market_account.save(betting_market_account_info)?;
+
+    Ok(())
+}
+
+pub fn process_delist_oracle<'a>(
+    program_id: &'a Pubkey,
+    accounts: &'a [AccountInfo<'a>],
+    delisted: bool
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let owner_account_info = next_account_info(account_info_iter)?;
+    let betting_market_account_info = next_account_info(account_info_iter)?;
+    let pyth_oracle_price_account_info = next_account_info(account_info_iter)?;
+    let instructions_sysvar_account_info = next_account_info(account_info_iter)?;
+
+    // check owner signed tx
+    if !owner_account_info.is_signer {
+        return Err(BetError::IncorrectSigner.into());
+    }
+
+    // market admin instructions must be signed for directly, not through another program
+    assert_not_cpi(program_id, instructions_sysvar_account_info)?;
+
+    // check program is owner of the betting_market_account_info
+    if betting_market_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+
+    let mut betting_market_account = BettingMarket::from_account_info(betting_market_account_info)?;
+
+    // check signer is the betting market owner
+    if betting_market_account.owner != *owner_account_info.key {
+        msg!("Signer is not the betting market owner");
+        return Err(BetError::UnauthorizedAccount.into());
+    }
+
+    // setting the current state again is a no-op
+    let index = betting_market_account.delisted_price_accounts()
+        .iter()
+        .position(|price_account| price_account == pyth_oracle_price_account_info.key);
+    match (delisted, index) {
+        (true, None) => {
+            let count = betting_market_account.delisted_price_accounts_count as usize;
+            if count >= MAX_DELISTED_PRICE_ACCOUNTS {
+                return Err(BetError::OracleDenylistFull.into());
+            }
+            betting_market_account.delisted_price_accounts[count] = *pyth_oracle_price_account_info.key;
+            betting_market_account.delisted_price_accounts_count += 1;
+        },
+        (false, Some(index)) => {
+            // swap the last entry into the relisted slot and clear the last slot
+            let last = betting_market_account.delisted_price_accounts_count as usize - 1;
+            betting_market_account.delisted_price_accounts.swap(index, last);
+            betting_market_account.delisted_price_accounts[last] = Pubkey::default();
+            betting_market_account.delisted_price_accounts_count -= 1;
+        },
+        _ => {}
+    }
+
+    // pack the betting_market_account
+    betting_market_account.save(betting_market_account_info)?;
+
+    Ok(())
+}
+
+pub fn process_update_program_info<'a>(
+    program_id: &'a Pubkey,
+    accounts: &'a [AccountInfo<'a>],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let upgrade_authority_account_info = next_account_info(account_info_iter)?;
+    let program_data_account_info = next_account_info(account_info_iter)?;
+    let program_info_account_info = next_account_info(account_info_iter)?;
+    let rent_sysvar_account_info = next_account_info(account_info_iter)?;
+    let system_program_account_info = next_account_info(account_info_iter)?;
+    if !check_id(system_program_account_info.key) {
+        return Err(BetError::InvalidSystemProgram.into());
+    }
+    let instructions_sysvar_account_info = next_account_info(account_info_iter)?;
+
+    // only the program's upgrade authority records what the deployment supports, signing for it directly
+    assert_upgrade_authority(program_id, program_data_account_info, upgrade_authority_account_info)?;
+    assert_not_cpi(program_id, instructions_sysvar_account_info)?;
+
+    // check it is the program's ProgramInfo PDA, created on the first call
+    let (program_info_address, bump_seed) = find_program_info(program_id);
+    if program_info_address != *program_info_account_info.key {
+        msg!("Incorrect program info account: expected {}, got {}", program_info_address, program_info_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+    if program_info_account_info.owner != program_id {
+        create_or_allocate_account_raw(
+            *program_id,
+            program_info_account_info,
+            rent_sysvar_account_info,
+            system_program_account_info,
+            upgrade_authority_account_info,
+            MAX_PROGRAM_INFO_DATA_LEN,
+            &[
+                PROGRAM_INFO_SEED.as_bytes(),
+                &[bump_seed]
+            ]
+        )?;
+    }
+
+    // record the running build's version and features
+    let mut program_info_account = ProgramInfo::from_account_info(program_info_account_info)?;
+    let (major, minor, patch) = ProgramInfo::build_version();
+    program_info_account.is_initialized = true;
+    program_info_account.version_major = major;
+    program_info_account.version_minor = minor;
+    program_info_account.version_patch = patch;
+    program_info_account.features = PROGRAM_FEATURES;
+    program_info_account.updated_at = Clock::get()?.unix_timestamp;
+    program_info_account.bump_seed = bump_seed;
+    program_info_account.save(program_info_account_info)?;
+    msg!("Program info: version {}, features {:#x}", format!("{}.{}.{}", major, minor, patch), PROGRAM_FEATURES);
+
+    Ok(())
+}
+
+pub fn process_create_bet<'a>(
+    program_id: &'a Pubkey,
+    accounts: &'a [AccountInfo<'a>],
+    args: CreateBetArgs,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let creator_main_account_info = next_account_info(account_info_iter)?;
+    let creator_payment_account_info = next_account_info(account_info_iter)?;
+    let bet_state_account_info = next_account_info(account_info_iter)?;
+    let bet_escrow_account_info = next_account_info(account_info_iter)?;
+    let betting_market_account_info = next_account_info(account_info_iter)?;
+    let pyth_oracle_product_account_info = next_account_info(account_info_iter)?;
+    let pyth_oracle_price_account_info = next_account_info(account_info_iter)?;
+    let rent_sysvar_account_info = next_account_info(account_info_iter)?;
+    let rent = &Rent::from_account_info(rent_sysvar_account_info)?;
+    let token_program_account_info = next_account_info(account_info_iter)?;
+    check_token_program(token_program_account_info.key)?;
+    let system_program_account_info = next_account_info(account_info_iter)?;
+    if !check_id(system_program_account_info.key) {
+        return Err(BetError::InvalidSystemProgram.into());
+    }
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let payment_mint_account_info = next_account_info(account_info_iter)?;
+    let escrow_authority_account_info = next_account_info(account_info_iter)?;
+    let associated_token_program_account_info = next_account_info(account_info_iter)?;
+    let event_authority_account_info = next_account_info(account_info_iter)?;
+    let program_account_info = next_account_info(account_info_iter)?;
+    let creator_exposure_account_info = next_account_info(account_info_iter)?;
+    let feed_exposure_account_info = next_account_info(account_info_iter)?;
+    let instructions_sysvar_account_info = next_account_info(account_info_iter)?;
+    if *associated_token_program_account_info.key != spl_associated_token_account::id() {
+        return Err(BetError::InvalidAccounts.into());
+    }
+    assert_distinct_accounts(&[
+        ("creator_payment", creator_payment_account_info),
+        ("bet_state", bet_state_account_info),
+        ("bet_escrow", bet_escrow_account_info),
+        ("betting_market", betting_market_account_info),
+    ])?;
+
+    // check creator_account_info is the tx signer
+    if !creator_main_account_info.is_signer {
+        return Err(BetError::IncorrectSigner.into());
+    }
+
+    // check program is owner of the bet_state_account_info
+    if bet_state_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+
+    // check bet_state_account_info has enough lamports to be rent exempt
+    if !rent.is_exempt(bet_state_account_info.lamports(), bet_state_account_info.data_len()) {
+        return Err(BetError::NotRentExempt.into());
+    }
+
+    // check program is owner of the betting_market_account_info
+    if betting_market_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+
+    // unpack the bet_state_account_info, a bet already written is never overwritten, checked before any CPI
+    let mut bet_state_account = Bet::from_account_info(bet_state_account_info)?;
+    if bet_state_account.is_initialized() {
+        return Err(BetError::AccountAlreadyInitialized.into())
+    }
+    // unpack the betting_market_account_info
+    let betting_market_account = BettingMarket::from_account_info(betting_market_account_info)?;
+
+    // check the token program is the one that owns the mint
+    if payment_mint_account_info.owner != token_program_account_info.key {
+        msg!("Incorrect token program: expected {}, got {}", payment_mint_account_info.owner, token_program_account_info.key);
+        return Err(BetError::InvalidTokenProgram.into());
+    }
+
+    // SOL bets pay out lamports to a system account, token bets pay out to a token account of the mint
+    let sol_payment = *payment_mint_account_info.key == spl_token::native_mint::id();
+    if !sol_payment {
+        if creator_payment_account_info.owner != token_program_account_info.key {
+            return Err(BetError::IsNotTokenAccount.into());
+        }
+        let creator_payment_account = TokenAccount::unpack_from_slice(&creator_payment_account_info.data.borrow())?;
+        if creator_payment_account.mint != *payment_mint_account_info.key {
+            return Err(BetError::InvalidMint.into());
+        }
+    }
+
+    // the refund destination is passed after the instructions sysvar, token bets refund to a token account of the mint
+    if let Some(refund_destination) = args.refund_destination {
+        let refund_destination_account_info = next_account_info(account_info_iter)?;
+        if *refund_destination_account_info.key != refund_destination || refund_destination == Pubkey::default() {
+            msg!("Incorrect refund destination account: expected {}, got {}", refund_destination, refund_destination_account_info.key);
+            return Err(BetError::InvalidAccounts.into());
+        }
+        token::validate_payout_destinations(
+            &[("refund_destination", refund_destination_account_info, args.bet_size)],
+            payment_mint_account_info.key,
+            token_program_account_info.key
+        )?;
+    }
+
+    // the creator's exposure account is the PDA of the market and creator, created on their first bet
+    let (creator_exposure_address, exposure_bump_seed) = find_creator_exposure(program_id, betting_market_account_info.key, creator_main_account_info.key);
+    if creator_exposure_address != *creator_exposure_account_info.key {
+        msg!("Incorrect creator exposure account: expected {}, got {}", creator_exposure_address, creator_exposure_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+
+    // the checks ValidateCreateBet runs, before any funds move
+    let (oracle_symbol_hash, price_account) = validate_create(
+        &betting_market_account,
+        payment_mint_account_info.key,
+        pyth_oracle_product_account_info,
+        pyth_oracle_price_account_info,
+        load_creator_exposure(program_id, creator_exposure_account_info)?.as_ref(),
+        &args,
+        clock
+    )?;
+    let CreateBetArgs {
+        bet_size,
+        odds,
+        expiration_time,
+        bet_direction,
+        bet_price,
+        cancel_condition,
+        variable_odds,
+        min_fill,
+        max_acceptors,
+        tag,
+        rollover_interval_secs,
+        expiry_kind,
+        max_odds_change,
+        max_total_acceptable,
+        nonce,
+        both_sides,
+        payout_curve,
+        keeper_bounty_lamports,
+        supplied_start_price,
+        refund_destination,
+    } = args;
+
+    // the escrow is the ATA of the PDA derived from the betting market and bet state account
+    let (escrow_authority, escrow_bump_seed) = find_escrow_authority(program_id, betting_market_account_info.key, bet_state_account_info.key);
+    if escrow_authority != *escrow_authority_account_info.key {
+        msg!("Incorrect escrow authority: expected {}, got {}", escrow_authority, escrow_authority_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+    let escrow_address = get_associated_token_address(&escrow_authority, payment_mint_account_info.key, token_program_account_info.key);
+    if escrow_address != *bet_escrow_account_info.key {
+        msg!("Incorrect escrow account: expected {}, got {}", escrow_address, bet_escrow_account_info.key);
+        return Err(BetError::WrongEscrowAccount.into());
+    }
+
+    // anyone can create the escrow ahead of the bet, it is used as it is if the escrow authority owns it
+    let escrow_exists = bet_escrow_account_info.owner == token_program_account_info.key;
+    if escrow_exists {
+        let bet_escrow_account = TokenAccount::unpack(&bet_escrow_account_info.data.borrow())?;
+        if bet_escrow_account.owner != escrow_authority {
+            msg!("Escrow isn't owned by the escrow authority: expected {}, got {}", escrow_authority, bet_escrow_account.owner);
+            return Err(BetError::EscrowNotOwnedByCreator.into());
+        }
+    }
+
+    // a market with strict_sol_funding takes a SOL bet only when system transfers earlier in its transaction paid the
+    // bet size into the escrow, so the funding can't be seen on chain before the bet
+    if sol_payment && betting_market_account.strict_sol_funding {
+        let transferred = lamports_transferred_earlier(instructions_sysvar_account_info, bet_escrow_account_info.key)?;
+        if transferred < bet_size {
+            msg!("Earlier instructions transferred {} lamports to the escrow, less than the bet size {}", transferred, bet_size);
+            return Err(BetError::EscrowNotFundedInTransaction.into());
+        }
+    }
+
+    // create the escrow token account, creator pays the rent
+    if !escrow_exists {
+        msg!("Calling the associated token program to create the escrow account...");
+        invoke(
+            &create_associated_token_account(
+                creator_main_account_info.key,
+                &escrow_authority,
+                payment_mint_account_info.key,
+                token_program_account_info.key
+            ),
+            &[
+                creator_main_account_info.clone(),
+                bet_escrow_account_info.clone(),
+                escrow_authority_account_info.clone(),
+                payment_mint_account_info.clone(),
+                system_program_account_info.clone(),
+                token_program_account_info.clone(),
+                rent_sysvar_account_info.clone(),
+                associated_token_program_account_info.clone(),
+            ],
+        )?;
+    }
+
+    if sol_payment {
+        // lamports already sent to the escrow above its rent exempt reserve count towards the bet size, the creator's
+        // wallet pays the rest. Then wrap all of it
+        let escrow_reserve = TokenAccount::unpack(&bet_escrow_account_info.data.borrow())?.is_native.unwrap_or(0);
+        let shortfall = bet_size.saturating_sub(bet_escrow_account_info.lamports().saturating_sub(escrow_reserve));
+        if shortfall > 0 {
+            msg!("Calling the system program to transfer the rest of the bet size to the escrow...");
+            invoke(
+                &system_instruction::transfer(creator_main_account_info.key, bet_escrow_account_info.key, shortfall),
+                &[
+                    creator_main_account_info.clone(),
+                    bet_escrow_account_info.clone(),
+                    system_program_account_info.clone(),
+                ],
+            )?;
+        }
+        invoke(
+            &token::sync_native(token_program_account_info.key, bet_escrow_account_info.key)?,
+            &[
+                bet_escrow_account_info.clone(),
+                token_program_account_info.clone(),
+            ],
+        )?;
+    } else {
+        // transfer the bet size from the creator into the escrow
+        let transfer_tokens_to_escrow_ix = token::transfer(
+            token_program_account_info.key,
+            creator_payment_account_info.key,
+            bet_escrow_account_info.key,
+            creator_main_account_info.key,
+            bet_size
+        )?;
+        msg!("Calling the token program to transfer the bet size to the escrow...");
+        invoke(
+            &transfer_tokens_to_escrow_ix,
+            &[
+                token_program_account_info.clone(),
+                creator_payment_account_info.clone(),
+                bet_escrow_account_info.clone(),
+                creator_main_account_info.clone(),
+            ],
+        )?;
+    }
+
+    // a bet created from a nonce must have the state account derived from it, so the stored nonce re-derives it
+    if let Some(nonce) = nonce {
+        let bet_address = Pubkey::create_with_seed(creator_main_account_info.key, &bet_seed(nonce), program_id)?;
+        if bet_address != *bet_state_account_info.key {
+            msg!("Bet state account isn't derived from nonce {}: expected {}, got {}", nonce, bet_address, bet_state_account_info.key);
+            return Err(BetError::InvalidAccounts.into());
+        }
+    }
+
+    // the creator's exposure account is created on their first bet
+    if creator_exposure_account_info.owner != program_id {
+        create_or_allocate_account_raw(
+            *program_id,
+            creator_exposure_account_info,
+            rent_sysvar_account_info,
+            system_program_account_info,
+            creator_main_account_info,
+            MAX_CREATOR_EXPOSURE_DATA_LEN,
+            &[
+                CREATOR_SEED.as_bytes(),
+                betting_market_account_info.key.as_ref(),
+                creator_main_account_info.key.as_ref(),
+                &[exposure_bump_seed]
+            ]
+        )?;
+    }
+    let mut creator_exposure_account = CreatorExposure::from_account_info(creator_exposure_account_info)?;
+    if !creator_exposure_account.is_initialized {
+        creator_exposure_account.is_initialized = true;
+        creator_exposure_account.betting_market = *betting_market_account_info.key;
+        creator_exposure_account.creator = *creator_main_account_info.key;
+        creator_exposure_account.bump_seed = exposure_bump_seed;
+    }
+    creator_exposure_account.open_bets = creator_exposure_account.open_bets.checked_add(1).ok_or(BetError::AmountOverflow)?;
+    creator_exposure_account.last_created_at = clock.unix_timestamp;
+    creator_exposure_account.save(creator_exposure_account_info)?;
+
+    // the feed's exposure account is the PDA of the market and price account, created by the feed's first bet.
+    // Rollover bets refill themselves each period and aren't counted
+    let (feed_exposure_address, feed_exposure_bump_seed) = find_feed_exposure(program_id, betting_market_account_info.key, pyth_oracle_price_account_info.key);
+    if feed_exposure_address != *feed_exposure_account_info.key {
+        msg!("Incorrect feed exposure account: expected {}, got {}", feed_exposure_address, feed_exposure_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+    if feed_exposure_account_info.owner != program_id {
+        create_or_allocate_account_raw(
+            *program_id,
+            feed_exposure_account_info,
+            rent_sysvar_account_info,
+            system_program_account_info,
+            creator_main_account_info,
+            MAX_FEED_EXPOSURE_DATA_LEN,
+            &[
+                FEED_SEED.as_bytes(),
+                betting_market_account_info.key.as_ref(),
+                pyth_oracle_price_account_info.key.as_ref(),
+                &[feed_exposure_bump_seed]
+            ]
+        )?;
+    }
+    let mut feed_exposure_account = FeedExposure::from_account_info(feed_exposure_account_info)?;
+    if !feed_exposure_account.is_initialized {
+        feed_exposure_account.is_initialized = true;
+        feed_exposure_account.betting_market = *betting_market_account_info.key;
+        feed_exposure_account.pyth_oracle_price_account = *pyth_oracle_price_account_info.key;
+        feed_exposure_account.bump_seed = feed_exposure_bump_seed;
+    }
+    let feed_exposure_tracked = rollover_interval_secs.is_none();
+    if feed_exposure_tracked {
+        feed_exposure_account.add_unmatched(bet_size);
+    }
+    feed_exposure_account.save(feed_exposure_account_info)?;
+
+    // write the data to state
+    bet_state_account.set_status(BetStatus::Open)?;
+    bet_state_account.betting_market = *betting_market_account_info.key;
+    bet_state_account.payment_mint = *payment_mint_account_info.key;
+    bet_state_account.creator_main_account = *creator_main_account_info.key;
+    bet_state_account.creator_payment_account = *creator_payment_account_info.key;
+    bet_state_account.bet_escrow_account = *bet_escrow_account_info.key;
+    bet_state_account.odds = odds;
+    bet_state_account.bet_size = bet_size;
+    bet_state_account.pyth_oracle_product_account = *pyth_oracle_product_account_info.key;
+    bet_state_account.pyth_oracle_price_account = *pyth_oracle_price_account_info.key;
+    bet_state_account.expiration_time = expiration_time;
+    bet_state_account.bet_direction = bet_direction;
+    bet_state_account.bet_price = bet_price;
+    bet_state_account.start_price = start_price(&betting_market_account, &price_account, supplied_start_price.as_ref(), clock)?;
+    bet_state_account.cancel_condition = cancel_condition;
+    bet_state_account.nonce = nonce.unwrap_or(NO_NONCE);
+    bet_state_account.tag = puffed_out_string(&tag, MAX_TAG_LEN);
+    bet_state_account.variable_odds = variable_odds;
+    bet_state_account.min_fill = min_fill;
+    bet_state_account.max_acceptors = max_acceptors;
+    bet_state_account.total_amount_accepted = 0;
+    bet_state_account.accepted_count = 0;
+    bet_state_account.finalized_count = 0;
+    bet_state_account.legacy_cancelled = false;
+    bet_state_account.created_at = clock.unix_timestamp;
+    bet_state_account.updated_at = clock.unix_timestamp;
+    bet_state_account.last_interaction_slot = clock.slot;
+    bet_state_account.rollover = rollover_interval_secs.is_some();
+    bet_state_account.rollover_interval_secs = rollover_interval_secs.unwrap_or(0);
+    bet_state_account.rollover_balance = 0;
+    bet_state_account.period_accepted_base = 0;
+    bet_state_account.settlement_observations_count = 0;
+    bet_state_account.expiry_kind = expiry_kind;
+    bet_state_account.last_odds_update_time = clock.unix_timestamp;
+    bet_state_account.max_odds_change = max_odds_change;
+    bet_state_account.max_total_acceptable = max_total_acceptable;
+    bet_state_account.escrow_seeds_version = ESCROW_SEEDS_MARKET;
+    bet_state_account.escrow_bump_seed = escrow_bump_seed;
+    bet_state_account.both_sides = both_sides;
+    bet_state_account.side_amounts_accepted = [0; 2];
+    bet_state_account.payout_curve = payout_curve;
+    // the market owner's own liquidity doesn't pay commission to itself
+    bet_state_account.fee_exempt = *creator_main_account_info.key == betting_market_account.owner;
+    bet_state_account.keeper_bounty_lamports = keeper_bounty_lamports;
+    bet_state_account.refund_destination = refund_destination.unwrap_or_default();
+    bet_state_account.keeper_bounty_paid_count = 0;
+    bet_state_account.feed_exposure_tracked = feed_exposure_tracked;
+    bet_state_account.oracle_symbol_hash = oracle_symbol_hash;
+    bet_state_account.oracle_expo = price_account.expo;
+
+    // pack the bet_state_account
+    bet_state_account.save(bet_state_account_info)?;
+
+    // escrow the keeper bounty in the bet state account, on top of the rent it already holds
+    if keeper_bounty_lamports > 0 {
+        msg!("Calling system program to transfer the keeper bounty to the bet state account");
+        invoke(
+            &system_instruction::transfer(creator_main_account_info.key, bet_state_account_info.key, keeper_bounty_lamports),
+            &[
+                creator_main_account_info.clone(),
+                bet_state_account_info.clone(),
+                system_program_account_info.clone()
+            ]
+        )?;
+    }
+
+    // emit the derived escrow authority and amounts for clients
+    emit_event_cpi(program_id, event_authority_account_info, program_account_info, &BetEvent::BetCreated {
+        bet: *bet_state_account_info.key,
+        bet_escrow_account: *bet_escrow_account_info.key,
+        escrow_authority,
+        odds,
+        bet_size,
+        start_price: bet_state_account.start_price,
+        remaining_capacity: bet_size,
+        tag: bet_state_account.tag.clone(),
+        created_at: bet_state_account.created_at,
+        nonce: bet_state_account.nonce,
+        refund_destination: refund_destination.unwrap_or_default(),
+    })?;
+   
+    Ok(())
+}
+
+// the CreateBet checks that don't need the bet's accounts, shared with ValidateCreateBet so its dry run makes the same
+// checks in the same order. Returns the product's symbol hash and the price account
+fn validate_create(
+    betting_market_account: &BettingMarket,
+    payment_mint: &Pubkey,
+    pyth_oracle_product_account_info: &AccountInfo,
+    pyth_oracle_price_account_info: &AccountInfo,
+    creator_exposure_account: Option<&CreatorExposure>, // None before the creator's first bet in the market
+    args: &CreateBetArgs,
+    clock: &Clock,
+) -> Result<([u8; 32], Price), ProgramError> {
+    let CreateBetArgs {
+        bet_size,
+        odds,
+        expiration_time,
+        ref cancel_condition,
+        variable_odds,
+        min_fill,
+        max_acceptors,
+        ref tag,
+        rollover_interval_secs,
+        expiry_kind,
+        max_odds_change,
+        max_total_acceptable,
+        nonce,
+        both_sides,
+        payout_curve,
+        ..
+    } = *args;
+
+    // check the mint is one of the betting market's accepted mints
+    if !betting_market_account.accepts_mint(payment_mint) {
+        msg!("Mint {} is not accepted by the betting market", payment_mint);
+        return Err(BetError::InvalidMint.into());
+    }
+
+    // check valid pyth keys, the product's symbol is locked into the bet with the price exponent
+    let oracle_symbol_hash = validate_pyth_keys(
+        &betting_market_account.pyth_program_id,
+        pyth_oracle_product_account_info,
+        pyth_oracle_price_account_info
+    )?;
+
+    // check the price account is on the market's allowlist (empty allowlist = any feed)
+    if !betting_market_account.is_price_account_allowed(pyth_oracle_price_account_info.key) {
+        msg!("Price account {} is not on the betting market's allowlist", pyth_oracle_price_account_info.key);
+        return Err(BetError::OracleNotAllowed.into());
+    }
+
+    // check the price account isn't delisted for new bets
+    if betting_market_account.is_price_account_delisted(pyth_oracle_price_account_info.key) {
+        msg!("Price account {} is delisted by the betting market", pyth_oracle_price_account_info.key);
+        return Err(BetError::OracleDelisted.into());
+    }
+
+    // the nonce's state account is checked by CreateBet, NO_NONCE marks bets without one
+    if nonce == Some(NO_NONCE) {
+        return Err(invalid_argument(BetError::InvalidCreateNonce, "nonce", nonce));
+    }
+
+    // check the bet has a size
+    if bet_size == 0 {
+        return Err(invalid_argument(BetError::InvalidCreateBetSize, "bet_size", bet_size));
+    }
+
+    // assert odds aren't less than 100
+    if odds < 100 {
+        return Err(invalid_argument(BetError::InvalidCreateOdds, "odds", odds));
+    }
+
+    // check the odds are within the market's bounds
+    betting_market_account.check_odds(odds)?;
+
+    // check variable odds move the odds by one per a positive price change
+    if let Some(variable_odds) = variable_odds {
+        if variable_odds <= 0 {
+            return Err(invalid_argument(BetError::InvalidCreateVariableOdds, "variable_odds", variable_odds));
+        }
+    }
+
+    // check the variable odds cap is positive and only set with variable odds
+    if let Some(max_odds_change) = max_odds_change {
+        if max_odds_change <= 0 || variable_odds.is_none() {
+            return Err(invalid_argument(BetError::InvalidCreateMaxOddsChange, "max_odds_change", max_odds_change));
+        }
+    }
+
+    // check a both sides bet has fixed odds, the variable odds adjustment depends on the creator's direction
+    if both_sides && variable_odds.is_some() {
+        return Err(invalid_argument(BetError::InvalidCreateBothSides, "both_sides", both_sides));
+    }
+
+    // check a linear payout curve pays something per point and reaches the whole stake
+    if let PayoutCurve::LinearPerPoint { units_per_point, cap } = payout_curve {
+        if units_per_point == 0 || cap == 0 {
+            return Err(invalid_argument(BetError::InvalidCreatePayoutCurve, "payout_curve", format!("{:?}", payout_curve)));
+        }
+    }
+
+    // check the market lists variable odds bets
+    if variable_odds.is_some() && !betting_market_account.allow_variable_odds {
+        return Err(BetError::VariableOddsNotAllowed.into());
+    }
+
+    // check the expiration is in the future and within the horizon, a slot expiry against the current slot
+    let expiration_check = match expiry_kind {
+        ExpiryKind::UnixTime => validate_timestamp(expiration_time, clock.unix_timestamp, BetError::InvalidCreateExpirationTime),
+        ExpiryKind::Slot => validate_slot(expiration_time, clock.slot, BetError::InvalidCreateExpirationTime)
+    };
+    if expiration_check.is_err() {
+        return Err(invalid_argument(BetError::InvalidCreateExpirationTime, "expiration_time", expiration_time));
+    }
+
+    // check expiration time is between the market's minimum duration and maximum horizon (0 = unlimited)
+    let duration = expiry_kind.to_secs(expiration_time.saturating_sub(expiry_kind.now(clock.unix_timestamp, clock.slot)));
+    if duration < betting_market_account.min_duration_secs {
+        msg!("Bet lasts {} seconds, the betting market's minimum is {}", duration, betting_market_account.min_duration_secs);
+        return Err(BetError::DurationTooShort.into());
+    }
+    if betting_market_account.max_expiration_horizon_secs > 0
+        && duration > betting_market_account.max_expiration_horizon_secs {
+        msg!("Expiration time is beyond the betting market's maximum horizon");
+        return Err(BetError::ExpirationTooFar.into());
+    }
+
+    // check the cancel condition leaves the bet acceptable, and its time is in the future and within the horizon
+    // unless it is CancelCondition::none()'s unbounded time. Its time is always unix time
+    let unix_expiration_time = match expiry_kind {
+        ExpiryKind::UnixTime => expiration_time,
+        ExpiryKind::Slot => i64::MAX
+    };
+    if (cancel_condition.time != i64::MAX
+        && validate_timestamp(cancel_condition.time, clock.unix_timestamp, BetError::InvalidCreateCancelCondition).is_err())
+        || cancel_condition.validate(clock.unix_timestamp, unix_expiration_time).is_err() {
+        return Err(invalid_argument(BetError::InvalidCreateCancelCondition, "cancel_condition", cancel_condition));
+    }
+
+    // check the minimum fill can be met
+    if min_fill > bet_size {
+        msg!("Minimum fill {} is larger than the bet size {}", min_fill, bet_size);
+        return Err(invalid_argument(BetError::InvalidCreateMinFill, "min_fill", min_fill));
+    }
+
+    // check the matchable cap is within the escrowed size and allows the minimum fill (None = all of bet_size)
+    if let Some(max_total_acceptable) = max_total_acceptable {
+        if max_total_acceptable == 0 || max_total_acceptable > bet_size || max_total_acceptable < min_fill {
+            msg!("Maximum total acceptable must be between the minimum fill {} and the bet size {}", min_fill, bet_size);
+            return Err(invalid_argument(BetError::InvalidCreateMaxTotalAcceptable, "max_total_acceptable", max_total_acceptable));
+        }
+    }
+
+    // check the acceptor cap allows at least one acceptor (None = unlimited)
+    if max_acceptors == Some(0) {
+        return Err(invalid_argument(BetError::InvalidCreateMaxAcceptors, "max_acceptors", max_acceptors));
+    }
+
+    // check the rollover interval moves expiration forward
+    if let Some(interval) = rollover_interval_secs {
+        if interval <= 0 {
+            return Err(invalid_argument(BetError::InvalidCreateRolloverIntervalSecs, "rollover_interval_secs", interval));
+        }
+    }
+
+    // check the tag fits its fixed size slot
+    if tag.len() > MAX_TAG_LEN {
+        msg!("Tag is {} bytes, at most {} allowed", tag.len(), MAX_TAG_LEN);
+        return Err(invalid_argument(BetError::InvalidCreateTag, "tag", tag));
+    }
+
+    // check the market's rate limits (0 = no limit)
+    if let Some(creator_exposure_account) = creator_exposure_account {
+        if creator_exposure_account.is_initialized
+            && betting_market_account.min_seconds_between_bets > 0
+            && clock.unix_timestamp < creator_exposure_account.last_created_at.saturating_add(betting_market_account.min_seconds_between_bets) {
+            msg!("Last bet created at {}, the betting market requires {} seconds between bets", creator_exposure_account.last_created_at, betting_market_account.min_seconds_between_bets);
+            return Err(BetError::CreatingTooFast.into());
+        }
+        if betting_market_account.max_open_bets_per_creator > 0
+            && creator_exposure_account.open_bets >= betting_market_account.max_open_bets_per_creator {
+            msg!("Creator has {} open bets, the betting market allows {}", creator_exposure_account.open_bets, betting_market_account.max_open_bets_per_creator);
+            return Err(BetError::TooManyOpenBets.into());
+        }
+    }
+
+    // the price account, its price is the bet's start price (used for variable odds)
+    let pyth_price_data = pyth_oracle_price_account_info.try_borrow_data()?;
+    let price_account: Price = *load_price(&pyth_price_data).map_err(|_| BetError::InvalidAccountInput)?;
+    Ok((oracle_symbol_hash, price_account))
+}
+
+// the creator's exposure account if it was created, by the creator's first bet in the market
+fn load_creator_exposure(program_id: &Pubkey, creator_exposure_account_info: &AccountInfo) -> Result<Option<CreatorExposure>, ProgramError> {
+    if creator_exposure_account_info.owner != program_id {
+        return Ok(None);
+    }
+    CreatorExposure::from_account_info(creator_exposure_account_info).map(Some)
+}
+
+pub fn process_validate_create_bet(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    args: CreateBetArgs,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let creator_main_account_info = next_account_info(account_info_iter)?;
+    let betting_market_account_info = next_account_info(account_info_iter)?;
+    let pyth_oracle_product_account_info = next_account_info(account_info_iter)?;
+    let pyth_oracle_price_account_info = next_account_info(account_info_iter)?;
+    let payment_mint_account_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let creator_exposure_account_info = next_account_info(account_info_iter)?;
+
+    // same account checks as create
+    if betting_market_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+    let betting_market_account = BettingMarket::from_account_info(betting_market_account_info)?;
+    let (creator_exposure_address, _bump_seed) = find_creator_exposure(program_id, betting_market_account_info.key, creator_main_account_info.key);
+    if creator_exposure_address != *creator_exposure_account_info.key {
+        msg!("Incorrect creator exposure account: expected {}, got {}", creator_exposure_address, creator_exposure_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+
+    let (_oracle_symbol_hash, price_account) = validate_create(
+        &betting_market_account,
+        payment_mint_account_info.key,
+        pyth_oracle_product_account_info,
+        pyth_oracle_price_account_info,
+        load_creator_exposure(program_id, creator_exposure_account_info)?.as_ref(),
+        &args,
+        clock
+    )?;
+
+    emit_event(&BetEvent::CreateBetValidated {
+        creator: *creator_main_account_info.key,
+        betting_market: *betting_market_account_info.key,
+        pyth_oracle_price_account: *pyth_oracle_price_account_info.key,
+        start_price: start_price(&betting_market_account, &price_account, args.supplied_start_price.as_ref(), clock)?,
+    })?;
+
+    Ok(())
+}
+
+pub fn process_clone_bet<'a>(
+    program_id: &'a Pubkey,
+    accounts: &'a [AccountInfo<'a>],
+    bet_size: u64,
+    expiration_time: i64,
+    relative_strike: bool,
+) -> ProgramResult {
+    // the source bet, followed by the CreateBet accounts
+    let (source_bet_state_account_info, create_bet_accounts) = accounts.split_first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let account_info_iter = &mut create_bet_accounts.iter();
+    let creator_main_account_info = next_account_info(account_info_iter)?;
+    let _creator_payment_account_info = next_account_info(account_info_iter)?;
+    let _bet_state_account_info = next_account_info(account_info_iter)?;
+    let _bet_escrow_account_info = next_account_info(account_info_iter)?;
+    let betting_market_account_info = next_account_info(account_info_iter)?;
+    let pyth_oracle_product_account_info = next_account_info(account_info_iter)?;
+    let pyth_oracle_price_account_info = next_account_info(account_info_iter)?;
+
+    // check program is owner of the source bet
+    if source_bet_state_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+    let source_bet_state_account = Bet::from_account_info(source_bet_state_account_info)?;
+    if !source_bet_state_account.is_initialized() {
+        return Err(BetError::InvalidBetAccount.into());
+    }
+
+    // check the creator created the source bet (CreateBet checks they signed)
+    if source_bet_state_account.creator_main_account != *creator_main_account_info.key {
+        msg!("Signer did not create the source bet: expected {}, got {}", source_bet_state_account.creator_main_account, creator_main_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+
+    // the clone uses the source bet's market and oracle
+    if source_bet_state_account.betting_market != *betting_market_account_info.key {
+        msg!("Incorrect betting market account: expected {}, got {}", source_bet_state_account.betting_market, betting_market_account_info.key);
+        return Err(BetError::WrongBettingMarket.into());
+    }
+    if source_bet_state_account.pyth_oracle_product_account != *pyth_oracle_product_account_info.key
+        || source_bet_state_account.pyth_oracle_price_account != *pyth_oracle_price_account_info.key {
+        msg!("Pyth accounts don't match the source bet's");
+        return Err(BetError::WrongOracleAccount.into());
+    }
+
+    // shift the prices by the spot price move since the source bet was created
+    let price_shift = if relative_strike {
+        let pyth_price_data = pyth_oracle_price_account_info.try_borrow_data()?;
+        let price_account: Price = *load_price(&pyth_price_data).map_err(|_| BetError::InvalidAccountInput)?;
+        let price: PriceConf = current_price(&price_account)?;
+        price.price.checked_sub(source_bet_state_account.start_price).ok_or(BetError::AmountOverflow)?
+    } else {
+        0
+    };
+
+    // the cancel time keeps its distance from expiration, the clone's expiration is in the source's expiry units
+    let cancel_condition = source_bet_state_account.cancel_condition.shifted(
+        price_shift,
+        source_bet_state_account.expiry_kind.to_secs(expiration_time.saturating_sub(source_bet_state_account.expiration_time))
+    );
+
+    process_create_bet(
+        program_id,
+        create_bet_accounts,
+        CreateBetArgs {
+            bet_size,
+            odds: source_bet_state_account.odds,
+            expiration_time,
+            bet_direction: source_bet_state_account.bet_direction,
+            bet_price: source_bet_state_account.bet_price.checked_add(price_shift).ok_or(BetError::AmountOverflow)?,
+            cancel_condition,
+            variable_odds: source_bet_state_account.variable_odds,
+            min_fill: source_bet_state_account.min_fill,
+            max_acceptors: source_bet_state_account.max_acceptors,
+            tag: source_bet_state_account.tag.trim_end_matches('\0').to_string(),
+            rollover_interval_secs: if source_bet_state_account.rollover { Some(source_bet_state_account.rollover_interval_secs) } else { None },
+            expiry_kind: source_bet_state_account.expiry_kind,
+            max_odds_change: source_bet_state_account.max_odds_change,
+            max_total_acceptable: None,
+            nonce: None,
+            both_sides: source_bet_state_account.both_sides,
+            payout_curve: source_bet_state_account.payout_curve,
+            keeper_bounty_lamports: 0,
+            supplied_start_price: None,
+            refund_destination: None,
+        }
+    )
+}
+
+pub fn process_accept_bet<'a>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'a>],
+    bet_size: u64,
+    expected_start_price: Option<i64>,
+    deadline: Option<i64>,
+    expected_odds: Option<i64>,
+    side: Option<Direction>,
+) -> ProgramResult {
+    fill_bet(program_id, accounts, bet_size, expected_start_price, deadline, expected_odds, side, None)
+}
+
+// where a fill's rent and acceptor payment come from when the acceptor doesn't sign it. MatchOrder fills with the
+// matcher paying the rent and the payment taken from the order's escrow, signed by its escrow authority
+struct FillFunding<'b, 'a> {
+    payer_account_info: &'b AccountInfo<'a>,
+    source_account_info: &'b AccountInfo<'a>,
+    source_authority_account_info: &'b AccountInfo<'a>,
+    source_authority_seeds: &'b [&'b [u8]],
+}
+
+// the accounts of the AcceptBet layout
+struct FillAccounts<'b, 'a> {
+    acceptor_main_account_info: &'b AccountInfo<'a>,
+    acceptor_payment_account_info: &'b AccountInfo<'a>,
+    bet_state_account_info: &'b AccountInfo<'a>,
+    bet_escrow_account_info: &'b AccountInfo<'a>,
+    accepted_bet_state_account_info: &'b AccountInfo<'a>,
+    accepted_bet_escrow_account_info: &'b AccountInfo<'a>,
+    betting_market_account_info: &'b AccountInfo<'a>,
+    pyth_oracle_price_account_info: &'b AccountInfo<'a>,
+    rent_sysvar_account_info: &'b AccountInfo<'a>,
+    token_program_account_info: &'b AccountInfo<'a>,
+    system_program_account_info: &'b AccountInfo<'a>,
+    pda_account_info: &'b AccountInfo<'a>,
+    payment_mint_account_info: &'b AccountInfo<'a>,
+    feed_exposure_account_info: &'b AccountInfo<'a>,
+}
+
+impl<'b, 'a> FillAccounts<'b, 'a> {
+    fn new(accounts: &'b [AccountInfo<'a>]) -> Result<Self, ProgramError> {
+        let account_info_iter = &mut accounts.iter();
+        Ok(FillAccounts {
+            acceptor_main_account_info: next_account_info(account_info_iter)?,
+            acceptor_payment_account_info: next_account_info(account_info_iter)?,
+            bet_state_account_info: next_account_info(account_info_iter)?,
+            bet_escrow_account_info: next_account_info(account_info_iter)?,
+            accepted_bet_state_account_info: next_account_info(account_info_iter)?,
+            accepted_bet_escrow_account_info: next_account_info(account_info_iter)?,
+            betting_market_account_info: next_account_info(account_info_iter)?,
+            pyth_oracle_price_account_info: next_account_info(account_info_iter)?,
+            rent_sysvar_account_info: next_account_info(account_info_iter)?,
+            token_program_account_info: next_account_info(account_info_iter)?,
+            system_program_account_info: next_account_info(account_info_iter)?,
+            pda_account_info: next_account_info(account_info_iter)?,
+            payment_mint_account_info: next_account_info(account_info_iter)?,
+            feed_exposure_account_info: next_account_info(account_info_iter)?,
+        })
+    }
+}
+
+// what check_fill reads and computes for execute_fill
+struct CheckedFill {
+    clock: Clock,
+    bet_state_account: Bet,
+    accepted_bet_bump_seed: u8,
+    bet_odds: i64,
+    acceptor_payment_amount: u64,
+    accepted_escrow_authority: Pubkey,
+    accepted_escrow_bump_seed: u8,
+    feed_exposure_account: Option<FeedExposure>,
+}
+
+// AcceptBet on the accounts in its layout, funded by the signing acceptor or by the given funding
+#[allow(clippy::too_many_arguments)]
+fn fill_bet<'a>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'a>],
+    bet_size: u64,
+    expected_start_price: Option<i64>,
+    deadline: Option<i64>,
+    expected_odds: Option<i64>,
+    side: Option<Direction>,
+    funding: Option<&FillFunding<'_, 'a>>,
+) -> ProgramResult {
+    let fill_accounts = FillAccounts::new(accounts)?;
+    let checked = check_fill(program_id, &fill_accounts, bet_size, expected_start_price, deadline, expected_odds, side, funding.is_some())?;
+    execute_fill(program_id, &fill_accounts, checked, bet_size, side, funding)
+}
+
+// every AcceptBet check, made before the fill changes any account so a failed fill can be skipped
+#[allow(clippy::too_many_arguments)]
+fn check_fill(
+    program_id: &Pubkey,
+    accounts: &FillAccounts,
+    bet_size: u64,
+    expected_start_price: Option<i64>,
+    deadline: Option<i64>,
+    expected_odds: Option<i64>,
+    side: Option<Direction>,
+    funded: bool,
+) -> Result<CheckedFill, ProgramError> {
+    let FillAccounts {
+        acceptor_main_account_info,
+        acceptor_payment_account_info,
+        bet_state_account_info,
+        bet_escrow_account_info,
+        accepted_bet_state_account_info,
+        accepted_bet_escrow_account_info,
+        betting_market_account_info,
+        pyth_oracle_price_account_info,
+        token_program_account_info,
+        system_program_account_info,
+        pda_account_info,
+        payment_mint_account_info,
+        feed_exposure_account_info,
+        ..
+    } = *accounts;
+    check_token_program(token_program_account_info.key)?;
+    if !check_id(system_program_account_info.key) {
+        return Err(BetError::InvalidSystemProgram.into());
+    }
+    let clock = Clock::get()?;
+    assert_distinct_accounts(&[
+        ("acceptor_payment", acceptor_payment_account_info),
+        ("bet_state", bet_state_account_info),
+        ("bet_escrow", bet_escrow_account_info),
+        ("accepted_bet_state", accepted_bet_state_account_info),
+        ("accepted_bet_escrow", accepted_bet_escrow_account_info),
+        ("betting_market", betting_market_account_info),
+    ])?;
+
+    // check acceptor_main_account_info is the tx signer, unless the fill is funded for them
+    if !funded && !acceptor_main_account_info.is_signer {
+        return Err(BetError::IncorrectSigner.into());
+    }
+
+    // check program is owner of the bet_state_account_info
+    if bet_state_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+
+    // unpack the bet account
+    let bet_state_account = Bet::from_account_info(bet_state_account_info)?;
+
+    // the accepted bet is the PDA of the bet, acceptor and the bet's accepted count, so each accept gets a fresh account
+    let (accepted_bet_address, accepted_bet_bump_seed) = find_accepted_bet(
+        program_id,
+        bet_state_account_info.key,
+        acceptor_main_account_info.key,
+        bet_state_account.accepted_count
+    );
+    if accepted_bet_address != *accepted_bet_state_account_info.key {
+        msg!("Incorrect accepted bet account: expected {}, got {}", accepted_bet_address, accepted_bet_state_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+
+    // check accepted bet state account hasn't already been initialized
+    if accepted_bet_state_account_info.owner == program_id {
+        return Err(BetError::AccountAlreadyInitialized.into())
+    }
+
+    // check it is correct betting market account
+    if bet_state_account.betting_market != *betting_market_account_info.key {
+        msg!("Incorrect betting market account: expected {}, got {}", bet_state_account.betting_market, betting_market_account_info.key);
+        return Err(BetError::WrongBettingMarket.into());
+    }
+    if betting_market_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+    let betting_market_account = BettingMarket::from_account_info(betting_market_account_info)?;
+
+    // check it is correct escrow account
+    if bet_state_account.bet_escrow_account != *bet_escrow_account_info.key {
+        msg!("Incorrect escrow account: expected {}, got {}", bet_state_account.bet_escrow_account, bet_escrow_account_info.key);
+        return Err(BetError::WrongEscrowAccount.into());
+    }
+
+    // check the token program is the one that owns the escrow
+    if bet_escrow_account_info.owner != token_program_account_info.key {
+        msg!("Incorrect token program: expected {}, got {}", bet_escrow_account_info.owner, token_program_account_info.key);
+        return Err(BetError::InvalidTokenProgram.into());
+    }
+    token::check_not_frozen(bet_escrow_account_info)?;
+
+    // check bet hasn't been cancelled
+    if bet_state_account.status == BetStatus::Cancelled {
+        return Err(BetError::BetCancelled.into());
+    }
+
+    // check the acceptor's transaction deadline hasn't passed
+    if let Some(deadline) = deadline {
+        if clock.unix_timestamp > deadline {
+            msg!("Accept deadline {} passed at {}", deadline, clock.unix_timestamp);
+            return Err(BetError::AcceptDeadlineExceeded.into());
+        }
+        // a deadline of now is still open, only one past the horizon is rejected
+        if deadline > clock.unix_timestamp.saturating_add(MAX_TIMESTAMP_HORIZON_SECS) {
+            return Err(invalid_argument(BetError::InvalidAcceptDeadline, "deadline", deadline));
+        }
+    }
+
+    // check the acceptor quoted against the same start price that is stored
+    if let Some(expected_start_price) = expected_start_price {
+        if expected_start_price != bet_state_account.start_price {
+            msg!("Expected start price {} but bet start price is {}", expected_start_price, bet_state_account.start_price);
+            return Err(BetError::StateMismatch.into());
+        }
+    }
+
+    // check the bet hasn't reached its acceptor cap
+    // the cap applies per period, rollover bets start counting again each period
+    let period_accepted_count = bet_state_account.accepted_count.saturating_sub(bet_state_account.period_accepted_base);
+    if let Some(max_acceptors) = bet_state_account.max_acceptors {
+        if period_accepted_count >= max_acceptors as u32 {
+            msg!("Bet already has {} acceptors", period_accepted_count);
+            return Err(BetError::MaxAcceptorsReached.into());
+        }
+    }
+
+    // check the accept takes some of the bet
+    if bet_size == 0 {
+        return Err(invalid_argument(BetError::InvalidAcceptBetSize, "bet_size", bet_size));
+    }
+
+    // check the expected odds are odds a bet can have
+    if let Some(expected_odds) = expected_odds {
+        if expected_odds < 100 {
+            return Err(invalid_argument(BetError::InvalidAcceptExpectedOdds, "expected_odds", expected_odds));
+        }
+    }
+
+    // check the acceptor picks a side exactly when the bet is both sided
+    if side.is_some() != bet_state_account.both_sides {
+        return Err(invalid_argument(BetError::InvalidAcceptSide, "side", side));
+    }
+
+    // check the fill fits the remaining capacity, which max_total_acceptable can hold below the escrowed size
+    // and both_sides bets split between the sides
+    let remaining_capacity = bet_state_account.remaining_acceptable_for(side);
+    if bet_size > remaining_capacity {
+        msg!("Accept of {} exceeds the remaining capacity {}", bet_size, remaining_capacity);
+        return Err(BetError::AcceptExceedsCapacity.into());
+    }
+
+    // check the fill meets the creator's minimum, unless it takes the whole remaining capacity
+    if bet_size < bet_state_account.min_fill && bet_size != remaining_capacity {
+        msg!("Fill {} is below the minimum fill {}", bet_size, bet_state_account.min_fill);
+        return Err(BetError::FillBelowMinimum.into());
+    }
+
+    // check it is correct oracle account
+    if *pyth_oracle_price_account_info.key != bet_state_account.pyth_oracle_price_account {
+        msg!("Invalid oracle account provided: expected {}, got {}", bet_state_account.pyth_oracle_price_account, pyth_oracle_price_account_info.key);
+        return Err(BetError::WrongOracleAccount.into());
+    }
+    // get the current price of the asset, from the feed the bet was created on
+    let pyth_price_data = pyth_oracle_price_account_info.try_borrow_data()?;
+    let price_account: Price = *load_price(&pyth_price_data).map_err(|_| BetError::InvalidAccountInput)?;
+    check_oracle_feed(&bet_state_account, &price_account)?;
+    let price: PriceConf = current_price(&price_account)?;
+
+    // check the current price and time are valid for bet to be accepted
+    if bet_state_account.cancel_condition_blocks(price.price, clock.unix_timestamp) || bet_state_account.is_expired(clock.unix_timestamp, clock.slot) {
+        msg!("Price moved beyond cancel condition prices or time too late to accept bet.");
+        return Err(BetError::BetNoLongerValid.into());
+    }
+
+    // check the accept isn't within the market's cutoff before expiration, allowing for the market's clock tolerance
+    if bet_state_account.secs_since_expiry(clock.unix_timestamp, clock.slot)
+        > betting_market_account.clock_tolerance_secs.saturating_sub(betting_market_account.accept_cutoff_secs) {
+        msg!("Bets can't be accepted within {} seconds of expiration", betting_market_account.accept_cutoff_secs);
+        return Err(BetError::TooCloseToExpiry.into());
+    }
+
+    // calculate the odds given the current price and variable odds condition, at least 100
+    let bet_odds = bet_state_account.effective_odds(price.price)?;
+
+    // check the odds are within the market's current bounds
+    betting_market_account.check_odds(bet_odds)?;
+
+    // check the acceptor's expected odds, which are required while the odds are within the market's quote cooldown
+    match expected_odds {
+        Some(expected_odds) if expected_odds != bet_odds => {
+            msg!("Expected odds {} but bet odds are {}", expected_odds, bet_odds);
+            return Err(BetError::StateMismatch.into());
+        },
+        None if clock.unix_timestamp.saturating_sub(bet_state_account.last_odds_update_time) < betting_market_account.quote_cooldown_secs => {
+            msg!("Odds were updated at {}, accepts within {} seconds must pass expected odds", bet_state_account.last_odds_update_time, betting_market_account.quote_cooldown_secs);
+            return Err(BetError::QuoteCooldown.into());
+        },
+        _ => {}
+    }
+
+    // given the odds, calculate how much the acceptor must pay
+    let acceptor_payment_amount: u64 = acceptor_payment_amount(bet_size, bet_odds);
+
+    // check the position's winner payout is within the market's cap
+    if betting_market_account.max_position_payout > 0
+        && position_payout(bet_size, bet_odds)? > betting_market_account.max_position_payout {
+        msg!(
+            "Position would pay out more than the market's cap of {}, accept at most {}",
+            betting_market_account.max_position_payout,
+            max_size_for_payout(betting_market_account.max_position_payout, bet_odds)
+        );
+        return Err(BetError::PositionPayoutExceedsCap.into());
+    }
+
+    // check the mint is the one the bet escrows
+    if bet_state_account.payment_mint != *payment_mint_account_info.key {
+        msg!("Incorrect mint: expected {}, got {}", bet_state_account.payment_mint, payment_mint_account_info.key);
+        return Err(BetError::InvalidMint.into());
+    }
+
+    // token bets take the acceptor payment from a token account of the bet's mint
+    if !bet_state_account.is_sol_payment() {
+        if acceptor_payment_account_info.owner != token_program_account_info.key {
+            return Err(BetError::IsNotTokenAccount.into());
+        }
+        let acceptor_payment_account = TokenAccount::unpack_from_slice(&acceptor_payment_account_info.data.borrow())?;
+        if acceptor_payment_account.mint != bet_state_account.payment_mint {
+            return Err(BetError::InvalidMint.into());
+        }
+    }
+
+    // check the bet escrow authority is the one from the seeds version and bump stored in the bet
+    let (bet_escrow_account_pda, _) = escrow_authority_signer(
+        program_id,
+        bet_state_account.escrow_seeds_version,
+        betting_market_account_info.key,
+        bet_state_account_info.key,
+        &[bet_state_account.escrow_bump_seed]
+    )?;
+    if bet_escrow_account_pda != *pda_account_info.key {
+        msg!("Incorrect escrow authority: expected {}, got {}", bet_escrow_account_pda, pda_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+
+    // check the bet escrow still holds enough tokens
+    let bet_escrow_account = TokenAccount::unpack_from_slice(&bet_escrow_account_info.data.borrow())?;
+    if bet_escrow_account.amount < bet_size {
+        msg!("Bet escrow holds {} tokens, {} needed", bet_escrow_account.amount, bet_size);
+        return Err(BetError::EscrowNotFunded.into());
+    }
+
+    // the accepted escrow is the PDA of the accepted bet state account, owned by the escrow authority derived from the
+    // accepted bet state account in the bet's seeds version
+    let (accepted_escrow_authority, _accepted_bump_seed) = find_escrow_authority_versioned(
+        program_id,
+        bet_state_account.escrow_seeds_version,
+        betting_market_account_info.key,
+        accepted_bet_state_account_info.key
+    );
+    let (accepted_escrow_address, accepted_escrow_bump_seed) = find_accepted_escrow(program_id, accepted_bet_state_account_info.key);
+    if accepted_escrow_address != *accepted_bet_escrow_account_info.key {
+        msg!("Incorrect accepted escrow account: expected {}, got {}", accepted_escrow_address, accepted_bet_escrow_account_info.key);
+        return Err(BetError::WrongEscrowAccount.into());
+    }
+
+    // check the feed exposure account of tracked bets, the fill is recorded in it
+    let feed_exposure_account = if bet_state_account.feed_exposure_tracked {
+        Some(load_feed_exposure(program_id, feed_exposure_account_info, &bet_state_account)?)
+    } else {
+        None
+    };
+
+    Ok(CheckedFill {
+        clock,
+        bet_state_account,
+        accepted_bet_bump_seed,
+        bet_odds,
+        acceptor_payment_amount,
+        accepted_escrow_authority,
+        accepted_escrow_bump_seed,
+        feed_exposure_account,
+    })
+}
+
+// the fill's CPIs and writes, on a fill check_fill passed
+fn execute_fill<'a>(
+    program_id: &Pubkey,
+    accounts: &FillAccounts<'_, 'a>,
+    checked: CheckedFill,
+    bet_size: u64,
+    side: Option<Direction>,
+    funding: Option<&FillFunding<'_, 'a>>,
+) -> ProgramResult {
+    let FillAccounts {
+        acceptor_main_account_info,
+        acceptor_payment_account_info,
+        bet_state_account_info,
+        bet_escrow_account_info,
+        accepted_bet_state_account_info,
+        accepted_bet_escrow_account_info,
+        betting_market_account_info,
+        rent_sysvar_account_info,
+        token_program_account_info,
+        system_program_account_info,
+        pda_account_info,
+        payment_mint_account_info,
+        feed_exposure_account_info,
+        ..
+    } = *accounts;
+    let CheckedFill {
+        clock,
+        mut bet_state_account,
+        accepted_bet_bump_seed,
+        bet_odds,
+        acceptor_payment_amount,
+        accepted_escrow_authority,
+        accepted_escrow_bump_seed,
+        feed_exposure_account,
+    } = checked;
+    let payer_account_info = funding.map(|funding| funding.payer_account_info).unwrap_or(acceptor_main_account_info);
+
+    // create the accepted bet state account, acceptor pays the rent
+    create_or_allocate_account_raw(
+        *program_id,
+        accepted_bet_state_account_info,
+        rent_sysvar_account_info,
+        system_program_account_info,
+        payer_account_info,
+        MAX_ACCEPTED_BET_DATA_LEN,
+        &[
+            PREFIX.as_bytes(),
+            bet_state_account_info.key.as_ref(),
+            acceptor_main_account_info.key.as_ref(),
+            &bet_state_account.accepted_count.to_le_bytes(),
+            &[accepted_bet_bump_seed]
+        ]
+    )?;
+    let mut accepted_bet_state_account = AcceptedBet::from_account_info(accepted_bet_state_account_info)?;
+
+    // create the accepted escrow token account, acceptor pays the rent. Mints with extensions the program accepts don't
+    // require account extensions, so the base account length fits both token programs
+    msg!("Calling the token program to create the accepted escrow account...");
+    create_or_allocate_account_raw(
+        *token_program_account_info.key,
+        accepted_bet_escrow_account_info,
+        rent_sysvar_account_info,
+        system_program_account_info,
+        payer_account_info,
+        TokenAccount::LEN,
+        &[
+            ESCROW_SEED.as_bytes(),
+            accepted_bet_state_account_info.key.as_ref(),
+            &[accepted_escrow_bump_seed]
+        ]
+    )?;
+    invoke(
+        &token::initialize_account2(
+            token_program_account_info.key,
+            accepted_bet_escrow_account_info.key,
+            payment_mint_account_info.key,
+            &accepted_escrow_authority
+        )?,
+        &[
+            accepted_bet_escrow_account_info.clone(),
+            payment_mint_account_info.clone(),
+            rent_sysvar_account_info.clone(),
+            token_program_account_info.clone(),
+        ],
+    )?;
+
+    // mints with a frozen default account state create the accepted escrow frozen
+    token::check_not_frozen(accepted_bet_escrow_account_info)?;
+
+    // get the bet escrow authority's signer seeds
+    let bet_escrow_bump_seed = [bet_state_account.escrow_bump_seed];
+    let (bet_escrow_account_pda, bet_escrow_account_transfer_seeds) = escrow_authority_signer(
+        program_id,
+        bet_state_account.escrow_seeds_version,
+        betting_market_account_info.key,
+        bet_state_account_info.key,
+        &bet_escrow_bump_seed
+    )?;
+
+    // transfer tokens from bet_escrow_account
+    let transfer_tokens_from_escrow_ix = token::transfer(
+        token_program_account_info.key, 
+        bet_escrow_account_info.key, 
+        accepted_bet_escrow_account_info.key,
+        &bet_escrow_account_pda, 
+        bet_size
+    )?;
+    invoke_signed(
+        &transfer_tokens_from_escrow_ix, 
+        &[
+            token_program_account_info.clone(),
+            bet_escrow_account_info.clone(),
+            accepted_bet_escrow_account_info.clone(),
+            pda_account_info.clone()
+        ],
+        &[&bet_escrow_account_transfer_seeds[..]]
+    )?;
+
+    if let Some(funding) = funding {
+        // transfer the acceptor payment from the funding source, wrapped SOL for SOL bets
+        msg!("Calling the token program to transfer the acceptor payment from the funding source...");
+        invoke_signed(
+            &token::transfer(
+                token_program_account_info.key,
+                funding.source_account_info.key,
+                accepted_bet_escrow_account_info.key,
+                funding.source_authority_account_info.key,
+                acceptor_payment_amount
+            )?,
+            &[
+                token_program_account_info.clone(),
+                funding.source_account_info.clone(),
+                accepted_bet_escrow_account_info.clone(),
+                funding.source_authority_account_info.clone()
+            ],
+            &[funding.source_authority_seeds]
+        )?;
+    } else if bet_state_account.is_sol_payment() {
+        // wrap the acceptor's lamports into the accepted escrow
+        msg!("Calling the system program to transfer the acceptor payment to the accepted escrow...");
+        invoke(
+            &system_instruction::transfer(acceptor_main_account_info.key, accepted_bet_escrow_account_info.key, acceptor_payment_amount),
+            &[
+                acceptor_main_account_info.clone(),
+                accepted_bet_escrow_account_info.clone(),
+                system_program_account_info.clone(),
+            ],
+        )?;
+        invoke(
+            &token::sync_native(token_program_account_info.key, accepted_bet_escrow_account_info.key)?,
+            &[
+                accepted_bet_escrow_account_info.clone(),
+                token_program_account_info.clone(),
+            ],
+        )?;
+    } else {
+        // transfer tokens from acceptor_payment_account_info
+        let transfer_tokens_from_acceptor_ix = token::transfer(
+            token_program_account_info.key, 
+            acceptor_payment_account_info.key,
+            accepted_bet_escrow_account_info.key, 
+            acceptor_main_account_info.key, 
+            acceptor_payment_amount
+        )?;
+        invoke(
+            &transfer_tokens_from_acceptor_ix,
+            &[
+                token_program_account_info.clone(),
+                acceptor_payment_account_info.clone(),
+                accepted_bet_escrow_account_info.clone(),
+                acceptor_main_account_info.clone()
+            ]
+        )?;
+    }
+
+    // write data to accepted bet state account
+    accepted_bet_state_account.set_status(PositionStatus::Open)?;
+    accepted_bet_state_account.bet = *bet_state_account_info.key;
+    accepted_bet_state_account.accepted_bet_escrow_account = *accepted_bet_escrow_account_info.key;
+    accepted_bet_state_account.acceptor_main_account = *acceptor_main_account_info.key;
+    accepted_bet_state_account.acceptor_payment_account = *acceptor_payment_account_info.key;
+    accepted_bet_state_account.bet_size = bet_size;
+    accepted_bet_state_account.odds = bet_odds;
+    accepted_bet_state_account.legacy_finalized = false;
+    accepted_bet_state_account.fees_pending = false;
+    accepted_bet_state_account.creator_direction = bet_state_account.creator_direction(side);
+
+    // save the accepted bet
+    accepted_bet_state_account.save(accepted_bet_state_account_info)?;
+
+    // record the fill on the bet
+    bet_state_account.total_amount_accepted = bet_state_account.total_amount_accepted.checked_add(bet_size).ok_or(BetError::AmountOverflow)?;
+    if let Some(side) = side {
+        bet_state_account.side_amounts_accepted[side as usize] = bet_state_account.side_amounts_accepted[side as usize]
+            .checked_add(bet_size).ok_or(BetError::AmountOverflow)?;
+    }
+    bet_state_account.accepted_count = bet_state_account.accepted_count.checked_add(1).ok_or(BetError::AmountOverflow)?;
+    let status = bet_state_account.capacity_status();
+    bet_state_account.set_status(status)?;
+    bet_state_account.updated_at = clock.unix_timestamp;
+    bet_state_account.last_interaction_slot = clock.slot;
+
+    // pack the bet_state_account
+    bet_state_account.save(bet_state_account_info)?;
+
+    // the fill moves from the feed's unmatched amount to its matched notional, both stakes of the position
+    if let Some(mut feed_exposure_account) = feed_exposure_account {
+        feed_exposure_account.match_unmatched(bet_size, position_payout(bet_size, bet_odds)?);
+        feed_exposure_account.save(feed_exposure_account_info)?;
+    }
+
+    // log the derived escrow authority and computed amounts for clients
+    emit_event(&BetEvent::BetAccepted {
+        bet: *bet_state_account_info.key,
+        accepted_bet: *accepted_bet_state_account_info.key,
+        accepted_bet_escrow_account: *accepted_bet_escrow_account_info.key,
+        escrow_authority: accepted_escrow_authority,
+        bet_size,
+        acceptor_payment_amount,
+        effective_odds: bet_odds,
+        remaining_capacity: bet_state_account.remaining_acceptable(),
+        updated_at: bet_state_account.updated_at,
+        creator_direction: accepted_bet_state_account.creator_direction,
+        status: bet_state_account.status,
+    })?;
+
+    Ok(())
+}
+
+pub fn process_accept_best_bet<'a>(
+    program_id: &'a Pubkey,
+    accounts: &'a [AccountInfo<'a>],
+    bet_size: u64,
+    deadline: Option<i64>,
+    side: Option<Direction>,
+    allow_split: bool,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let acceptor_main_account_info = next_account_info(account_info_iter)?;
+    let acceptor_payment_account_info = next_account_info(account_info_iter)?;
+    let betting_market_account_info = next_account_info(account_info_iter)?;
+    let pyth_oracle_price_account_info = next_account_info(account_info_iter)?;
+    let rent_sysvar_account_info = next_account_info(account_info_iter)?;
+    let token_program_account_info = next_account_info(account_info_iter)?;
+    let system_program_account_info = next_account_info(account_info_iter)?;
+    let payment_mint_account_info = next_account_info(account_info_iter)?;
+    let feed_exposure_account_info = next_account_info(account_info_iter)?;
+
+    // the rest are the candidates' accounts, a fixed number per candidate
+    let candidate_accounts = account_info_iter.as_slice();
+    let candidate_count = candidate_accounts.len() / ACCEPT_BEST_CANDIDATE_ACCOUNTS;
+    if candidate_count == 0 || candidate_count > MAX_ACCEPT_BEST_CANDIDATES || candidate_count * ACCEPT_BEST_CANDIDATE_ACCOUNTS != candidate_accounts.len() {
+        msg!("Expected 1 to {} candidates of {} accounts each", MAX_ACCEPT_BEST_CANDIDATES, ACCEPT_BEST_CANDIDATE_ACCOUNTS);
+        return Err(BetError::InvalidAccounts.into());
+    }
+
+    // check program is owner of the betting_market_account_info
+    if betting_market_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+    let betting_market_account = BettingMarket::from_account_info(betting_market_account_info)?;
+
+    // unpack the candidates, which must all be on the market and the passed oracle
+    let mut candidate_bets = vec![];
+    for candidate in candidate_accounts.chunks(ACCEPT_BEST_CANDIDATE_ACCOUNTS) {
+        let bet_state_account_info = &candidate[0];
+        if bet_state_account_info.owner != program_id {
+            return Err(BetError::IncorrectOwner.into());
+        }
+        let bet_state_account = Bet::from_account_info(bet_state_account_info)?;
+        if bet_state_account.betting_market != *betting_market_account_info.key
+            || bet_state_account.pyth_oracle_price_account != *pyth_oracle_price_account_info.key {
+            msg!("Candidate {} isn't on the betting market {} and oracle {}", bet_state_account_info.key, betting_market_account_info.key, pyth_oracle_price_account_info.key);
+            return Err(BetError::CandidateMismatch.into());
+        }
+        candidate_bets.push(bet_state_account);
+    }
+
+    // get the current price of the asset from the candidates' shared oracle
+    let price = {
+        let pyth_price_data = pyth_oracle_price_account_info.try_borrow_data()?;
+        let price_account: Price = *load_price(&pyth_price_data).map_err(|_| BetError::InvalidAccountInput)?;
+        current_price(&price_account)?.price
+    };
+    let clock = &Clock::get()?;
+
+    // the candidates the market's cutoff, odds bounds or quote cooldown would reject are left out. AcceptBestBet
+    // doesn't quote the odds, so a bet in its quote cooldown can't be filled by it
+    let mut candidate_indexes = vec![];
+    let mut candidates = vec![];
+    for (index, bet_state_account) in candidate_bets.into_iter().enumerate() {
+        let acceptable = bet_state_account.secs_since_expiry(clock.unix_timestamp, clock.slot)
+                <= betting_market_account.clock_tolerance_secs.saturating_sub(betting_market_account.accept_cutoff_secs)
+            && matches!(bet_state_account.effective_odds(price), Ok(odds) if betting_market_account.check_odds(odds).is_ok())
+            && clock.unix_timestamp.saturating_sub(bet_state_account.last_odds_update_time) >= betting_market_account.quote_cooldown_secs;
+        if acceptable {
+            candidate_indexes.push(index);
+            candidates.push(bet_state_account);
+        }
+    }
+
+    // pick the fills and run AcceptBet on each, with the shared accounts in the AcceptBet layout
+    let fills = select_best_fills(&candidates, bet_size, side, price, clock.unix_timestamp, clock.slot, allow_split)?;
+    for (index, fill_size) in fills {
+        let candidate = &candidate_accounts[candidate_indexes[index] * ACCEPT_BEST_CANDIDATE_ACCOUNTS..][..ACCEPT_BEST_CANDIDATE_ACCOUNTS];
+        msg!("Accepting {} of bet {}", fill_size, candidate[0].key);
+        let accept_bet_accounts = [
+            acceptor_main_account_info.clone(),
+            acceptor_payment_account_info.clone(),
+            candidate[0].clone(), // bet_state_account
+            candidate[1].clone(), // bet_escrow_account
+            candidate[3].clone(), // accepted_bet_state_account
+            candidate[4].clone(), // accepted_bet_escrow_account
+            betting_market_account_info.clone(),
+            pyth_oracle_price_account_info.clone(),
+            rent_sysvar_account_info.clone(),
+            token_program_account_info.clone(),
+            system_program_account_info.clone(),
+            candidate[2].clone(), // bet escrow authority
+            payment_mint_account_info.clone(),
+            feed_exposure_account_info.clone(),
+        ];
+        process_accept_bet(program_id, &accept_bet_accounts, fill_size, None, deadline, None, side)?;
+    }
+
+    Ok(())
+}
+
+pub fn process_accept_multiple<'a>(
+    program_id: &'a Pubkey,
+    accounts: &'a [AccountInfo<'a>],
+    args: AcceptMultipleArgs,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let acceptor_main_account_info = next_account_info(account_info_iter)?;
+    let acceptor_payment_account_info = next_account_info(account_info_iter)?;
+    let betting_market_account_info = next_account_info(account_info_iter)?;
+    let rent_sysvar_account_info = next_account_info(account_info_iter)?;
+    let token_program_account_info = next_account_info(account_info_iter)?;
+    let system_program_account_info = next_account_info(account_info_iter)?;
+    let payment_mint_account_info = next_account_info(account_info_iter)?;
+
+    // the rest are the bet groups, a fixed number of accounts per bet and one bet per fill
+    let group_accounts = account_info_iter.as_slice();
+    let group_count = group_accounts.len() / ACCEPT_MULTIPLE_GROUP_ACCOUNTS;
+    if group_count == 0 || group_count > MAX_ACCEPT_MULTIPLE_GROUPS || group_count * ACCEPT_MULTIPLE_GROUP_ACCOUNTS != group_accounts.len()
+        || group_count != args.fills.len() {
+        msg!("Expected 1 to {} bets of {} accounts each, one per fill", MAX_ACCEPT_MULTIPLE_GROUPS, ACCEPT_MULTIPLE_GROUP_ACCOUNTS);
+        return Err(BetError::InvalidAccounts.into());
+    }
+
+    // run AcceptBet on each bet, with the shared accounts in the AcceptBet layout
+    let mut accepted = 0;
+    for (group, fill) in group_accounts.chunks(ACCEPT_MULTIPLE_GROUP_ACCOUNTS).zip(&args.fills) {
+        let accept_bet_accounts = [
+            acceptor_main_account_info.clone(),
+            acceptor_payment_account_info.clone(),
+            group[0].clone(), // bet_state_account
+            group[1].clone(), // bet_escrow_account
+            group[3].clone(), // accepted_bet_state_account
+            group[4].clone(), // accepted_bet_escrow_account
+            betting_market_account_info.clone(),
+            group[5].clone(), // pyth_oracle_price_account
+            rent_sysvar_account_info.clone(),
+            token_program_account_info.clone(),
+            system_program_account_info.clone(),
+            group[2].clone(), // bet escrow authority
+            payment_mint_account_info.clone(),
+            group[6].clone(), // feed_exposure_account
+        ];
+        let fill_accounts = FillAccounts::new(&accept_bet_accounts)?;
+
+        // every check runs before the fill's first CPI, so a bet failing one is skipped with nothing changed
+        let checked = match check_fill(program_id, &fill_accounts, fill.bet_size, None, args.deadline, fill.expected_odds, fill.side, false) {
+            Ok(checked) => checked,
+            Err(error) if args.skip_failed => {
+                msg!("{}: skipped", group[0].key);
+                error.print::<BetError>();
+                continue;
+            },
+            Err(error) => return Err(error),
+        };
+        msg!("Accepting {} of bet {}", fill.bet_size, group[0].key);
+        execute_fill(program_id, &fill_accounts, checked, fill.bet_size, fill.side, None)?;
+        accepted += 1;
+    }
+    msg!("Accepted {} of {} bets", accepted, args.fills.len());
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn process_place_accept_order<'a>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'a>],
+    size: u64,
+    direction: Direction,
+    min_odds: i64,
+    max_odds: i64,
+    min_expiration_time: i64,
+    max_expiration_time: i64,
+    matcher_fee_bps: u16,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let acceptor_main_account_info = next_account_info(account_info_iter)?;
+    let acceptor_payment_account_info = next_account_info(account_info_iter)?;
+    let order_account_info = next_account_info(account_info_iter)?;
+    let order_escrow_account_info = next_account_info(account_info_iter)?;
+    let order_escrow_authority_account_info = next_account_info(account_info_iter)?;
+    let betting_market_account_info = next_account_info(account_info_iter)?;
+    let pyth_oracle_price_account_info = next_account_info(account_info_iter)?;
+    let payment_mint_account_info = next_account_info(account_info_iter)?;
+    let rent_sysvar_account_info = next_account_info(account_info_iter)?;
+    let token_program_account_info = next_account_info(account_info_iter)?;
+    check_token_program(token_program_account_info.key)?;
+    let system_program_account_info = next_account_info(account_info_iter)?;
+    if !check_id(system_program_account_info.key) {
+        return Err(BetError::InvalidSystemProgram.into());
+    }
+    let associated_token_program_account_info = next_account_info(account_info_iter)?;
+    if *associated_token_program_account_info.key != spl_associated_token_account::id() {
+        return Err(BetError::InvalidAccounts.into());
+    }
+    let clock = &Clock::get()?;
+
+    // check acceptor_main_account_info is the tx signer
+    if !acceptor_main_account_info.is_signer {
+        return Err(BetError::IncorrectSigner.into());
+    }
+
+    // check the order's bounds, an order must be fillable before it expires
+    if size == 0 {
+        return Err(invalid_argument(BetError::InvalidAcceptOrder, "size", size));
+    }
+    if min_odds < 100 || max_odds < min_odds {
+        return Err(invalid_argument(BetError::InvalidAcceptOrder, "odds", (min_odds, max_odds)));
+    }
+    if max_expiration_time < min_expiration_time || max_expiration_time <= clock.unix_timestamp {
+        return Err(invalid_argument(BetError::InvalidAcceptOrder, "expiration_time", (min_expiration_time, max_expiration_time)));
+    }
+    if matcher_fee_bps > MAX_MATCHER_FEE_BPS {
+        return Err(invalid_argument(BetError::InvalidAcceptOrder, "matcher_fee_bps", matcher_fee_bps));
+    }
+    let budget = accept_order_budget(size, max_odds, matcher_fee_bps)?;
+
+    // check program is owner of the betting_market_account_info
+    if betting_market_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+    let betting_market_account = BettingMarket::from_account_info(betting_market_account_info)?;
+
+    // check the mint is one of the betting market's accepted mints
+    if !betting_market_account.accepts_mint(payment_mint_account_info.key) {
+        msg!("Mint {} is not accepted by the betting market", payment_mint_account_info.key);
+        return Err(BetError::InvalidMint.into());
+    }
+
+    // check the token program is the one that owns the mint
+    if payment_mint_account_info.owner != token_program_account_info.key {
+        msg!("Incorrect token program: expected {}, got {}", payment_mint_account_info.owner, token_program_account_info.key);
+        return Err(BetError::InvalidTokenProgram.into());
+    }
+
+    // check the price account is one new bets can use, so the order can be filled
+    if !betting_market_account.is_price_account_allowed(pyth_oracle_price_account_info.key)
+        || betting_market_account.is_price_account_delisted(pyth_oracle_price_account_info.key) {
+        msg!("Price account {} is not allowed by the betting market", pyth_oracle_price_account_info.key);
+        return Err(BetError::OracleNotAllowed.into());
+    }
+
+    // SOL orders pay out lamports to a system account, token orders pay out to a token account of the mint
+    let sol_payment = *payment_mint_account_info.key == spl_token::native_mint::id();
+    if !sol_payment {
+        if acceptor_payment_account_info.owner != token_program_account_info.key {
+            return Err(BetError::IsNotTokenAccount.into());
+        }
+        let acceptor_payment_account = TokenAccount::unpack_from_slice(&acceptor_payment_account_info.data.borrow())?;
+        if acceptor_payment_account.mint != *payment_mint_account_info.key {
+            return Err(BetError::InvalidMint.into());
+        }
+    }
+
+    // the order is the PDA of the market and acceptor, it is closed at cancel so the acceptor can place another
+    let (order_address, order_bump_seed) = find_accept_order(program_id, betting_market_account_info.key, acceptor_main_account_info.key);
+    if order_address != *order_account_info.key {
+        msg!("Incorrect accept order account: expected {}, got {}", order_address, order_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+    if order_account_info.owner == program_id {
+        return Err(BetError::AccountAlreadyInitialized.into());
+    }
+    create_or_allocate_account_raw(
+        *program_id,
+        order_account_info,
+        rent_sysvar_account_info,
+        system_program_account_info,
+        acceptor_main_account_info,
+        MAX_ACCEPT_ORDER_DATA_LEN,
+        &[
+            ORDER_SEED.as_bytes(),
+            betting_market_account_info.key.as_ref(),
+            acceptor_main_account_info.key.as_ref(),
+            &[order_bump_seed]
+        ]
+    )?;
+    let mut order_account = AcceptOrder::from_account_info(order_account_info)?;
+
+    // the escrow is the ATA of the PDA derived from the betting market and order account
+    let (order_escrow_authority, order_escrow_bump_seed) = find_escrow_authority(program_id, betting_market_account_info.key, order_account_info.key);
+    if order_escrow_authority != *order_escrow_authority_account_info.key {
+        msg!("Incorrect escrow authority: expected {}, got {}", order_escrow_authority, order_escrow_authority_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+    let order_escrow_address = get_associated_token_address(&order_escrow_authority, payment_mint_account_info.key, token_program_account_info.key);
+    if order_escrow_address != *order_escrow_account_info.key {
+        msg!("Incorrect escrow account: expected {}, got {}", order_escrow_address, order_escrow_account_info.key);
+        return Err(BetError::WrongEscrowAccount.into());
+    }
+
+    // create the escrow token account, acceptor pays the rent
+    msg!("Calling the associated token program to create the order escrow account...");
+    invoke(
+        &create_associated_token_account(
+            acceptor_main_account_info.key,
+            &order_escrow_authority,
+            payment_mint_account_info.key,
+            token_program_account_info.key
+        ),
+        &[
+            acceptor_main_account_info.clone(),
+            order_escrow_account_info.clone(),
+            order_escrow_authority_account_info.clone(),
+            payment_mint_account_info.clone(),
+            system_program_account_info.clone(),
+            token_program_account_info.clone(),
+            rent_sysvar_account_info.clone(),
+            associated_token_program_account_info.clone(),
+        ],
+    )?;
+
+    // mints with a frozen default account state create the escrow frozen
+    token::check_not_frozen(order_escrow_account_info)?;
+
+    if sol_payment {
+        // wrap the budget of the acceptor's lamports into the escrow
+        msg!("Calling the system program to transfer the order budget to the escrow...");
+        invoke(
+            &system_instruction::transfer(acceptor_main_account_info.key, order_escrow_account_info.key, budget),
+            &[
+                acceptor_main_account_info.clone(),
+                order_escrow_account_info.clone(),
+                system_program_account_info.clone(),
+            ],
+        )?;
+        invoke(
+            &token::sync_native(token_program_account_info.key, order_escrow_account_info.key)?,
+            &[
+                order_escrow_account_info.clone(),
+                token_program_account_info.clone(),
+            ],
+        )?;
+    } else {
+        // transfer the budget from the acceptor into the escrow
+        msg!("Calling the token program to transfer the order budget to the escrow...");
+        invoke(
+            &token::transfer(
+                token_program_account_info.key,
+                acceptor_payment_account_info.key,
+                order_escrow_account_info.key,
+                acceptor_main_account_info.key,
+                budget
+            )?,
+            &[
+                token_program_account_info.clone(),
+                acceptor_payment_account_info.clone(),
+                order_escrow_account_info.clone(),
+                acceptor_main_account_info.clone(),
+            ],
+        )?;
+    }
+
+    // write data to the order account
+    order_account.is_initialized = true;
+    order_account.betting_market = *betting_market_account_info.key;
+    order_account.acceptor_main_account = *acceptor_main_account_info.key;
+    order_account.acceptor_payment_account = *acceptor_payment_account_info.key;
+    order_account.payment_mint = *payment_mint_account_info.key;
+    order_account.pyth_oracle_price_account = *pyth_oracle_price_account_info.key;
+    order_account.order_escrow_account = *order_escrow_account_info.key;
+    order_account.direction = direction;
+    order_account.min_odds = min_odds;
+    order_account.max_odds = max_odds;
+    order_account.remaining_size = size;
+    order_account.min_expiration_time = min_expiration_time;
+    order_account.max_expiration_time = max_expiration_time;
+    order_account.matcher_fee_bps = matcher_fee_bps;
+    order_account.bump_seed = order_bump_seed;
+    order_account.escrow_bump_seed = order_escrow_bump_seed;
+
+    order_account.save(order_account_info)
+}
+
+pub fn process_match_order<'a>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'a>],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let matcher_main_account_info = next_account_info(account_info_iter)?;
+    let matcher_payment_account_info = next_account_info(account_info_iter)?;
+    let order_account_info = next_account_info(account_info_iter)?;
+    let order_escrow_account_info = next_account_info(account_info_iter)?;
+    let order_escrow_authority_account_info = next_account_info(account_info_iter)?;
+
+    // the rest are the AcceptBet accounts of the fill
+    let accept_bet_accounts = account_info_iter.as_slice();
+    if accept_bet_accounts.len() < 14 {
+        msg!("Expected the AcceptBet accounts after the order accounts");
+        return Err(BetError::InvalidAccounts.into());
+    }
+    let acceptor_main_account_info = &accept_bet_accounts[0];
+    let acceptor_payment_account_info = &accept_bet_accounts[1];
+    let bet_state_account_info = &accept_bet_accounts[2];
+    let pyth_oracle_price_account_info = &accept_bet_accounts[7];
+    let token_program_account_info = &accept_bet_accounts[9];
+
+    // check matcher_main_account_info is the tx signer, they pay the rent of the fill
+    if !matcher_main_account_info.is_signer {
+        return Err(BetError::IncorrectSigner.into());
+    }
+
+    // check program is owner of the order and bet accounts
+    if order_account_info.owner != program_id || bet_state_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+    let mut order_account = AcceptOrder::from_account_info(order_account_info)?;
+    if !order_account.is_initialized {
+        return Err(BetError::InvalidAccounts.into());
+    }
+    let bet_state_account = Bet::from_account_info(bet_state_account_info)?;
+
+    // check the fill is for the order's acceptor, who gets its payout
+    if order_account.acceptor_main_account != *acceptor_main_account_info.key
+        || order_account.acceptor_payment_account != *acceptor_payment_account_info.key {
+        msg!("The fill's acceptor accounts aren't the order's {} and {}", order_account.acceptor_main_account, order_account.acceptor_payment_account);
+        return Err(BetError::InvalidAccounts.into());
+    }
+
+    // check it is correct escrow account
+    if order_account.order_escrow_account != *order_escrow_account_info.key {
+        msg!("Incorrect escrow account: expected {}, got {}", order_account.order_escrow_account, order_escrow_account_info.key);
+        return Err(BetError::WrongEscrowAccount.into());
+    }
+
+    // get the order escrow authority and its signer seeds from the bump stored in the order
+    let order_escrow_bump_seed = [order_account.escrow_bump_seed];
+    let (order_escrow_authority, order_escrow_transfer_seeds) = escrow_authority_signer(
+        program_id,
+        ESCROW_SEEDS_MARKET,
+        &order_account.betting_market,
+        order_account_info.key,
+        &order_escrow_bump_seed
+    )?;
+    if order_escrow_authority != *order_escrow_authority_account_info.key {
+        msg!("Incorrect escrow authority: expected {}, got {}", order_escrow_authority, order_escrow_authority_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+
+    // check the bet matches the order's market, feed, mint, direction and expiration window
+    let side = match order_account.side_for(&bet_state_account) {
+        Ok(side) => side,
+        Err(error) => {
+            msg!("Bet {} doesn't match the order's feed, mint, direction or expiration window", bet_state_account_info.key);
+            return Err(error.into());
+        }
+    };
+
+    // check the bet's current odds are within the order's bounds, AcceptBet checks the oracle is the bet's
+    if *pyth_oracle_price_account_info.key != bet_state_account.pyth_oracle_price_account {
+        return Err(BetError::WrongOracleAccount.into());
+    }
+    let price = {
+        let pyth_price_data = pyth_oracle_price_account_info.try_borrow_data()?;
+        let price_account: Price = *load_price(&pyth_price_data).map_err(|_| BetError::InvalidAccountInput)?;
+        current_price(&price_account)?.price
+    };
+    let odds = bet_state_account.effective_odds(price)?;
+    if let Err(error) = order_account.check_odds(odds) {
+        msg!("Bet odds {} are outside the order's {} to {}", odds, order_account.min_odds, order_account.max_odds);
+        return Err(error.into());
+    }
+
+    // fill as much of the bet as the order has left, the fill quotes the odds checked above
+    let fill_size = order_account.remaining_size.min(bet_state_account.remaining_acceptable_for(side));
+    if fill_size == 0 {
+        msg!("Nothing to fill: the order has {} left and the bet {}", order_account.remaining_size, bet_state_account.remaining_acceptable_for(side));
+        return Err(BetError::OrderDoesNotMatch.into());
+    }
+    msg!("Filling {} of bet {} for the order", fill_size, bet_state_account_info.key);
+    fill_bet(program_id, accept_bet_accounts, fill_size, None, None, Some(odds), side, Some(&FillFunding {
+        payer_account_info: matcher_main_account_info,
+        source_account_info: order_escrow_account_info,
+        source_authority_account_info: order_escrow_authority_account_info,
+        source_authority_seeds: &order_escrow_transfer_seeds,
+    }))?;
+
+    // pay the matcher fee on the fill's acceptor payment from the escrow
+    let fee = matcher_fee(acceptor_payment_amount(fill_size, odds), order_account.matcher_fee_bps)?;
+    if fee > 0 {
+        msg!("Calling the token program to pay the matcher fee of {}...", fee);
+        invoke_signed(
+            &token::transfer(
+                token_program_account_info.key,
+                order_escrow_account_info.key,
+                matcher_payment_account_info.key,
+                &order_escrow_authority,
+                fee
+            )?,
+            &[
+                token_program_account_info.clone(),
+                order_escrow_account_info.clone(),
+                matcher_payment_account_info.clone(),
+                order_escrow_authority_account_info.clone()
+            ],
+            &[&order_escrow_transfer_seeds[..]]
+        )?;
+    }
+
+    order_account.remaining_size -= fill_size;
+    order_account.save(order_account_info)
+}
+
+pub fn process_cancel_accept_order<'a>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'a>],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let acceptor_main_account_info = next_account_info(account_info_iter)?;
+    let acceptor_payment_account_info = next_account_info(account_info_iter)?;
+    let order_account_info = next_account_info(account_info_iter)?;
+    let order_escrow_account_info = next_account_info(account_info_iter)?;
+    let order_escrow_authority_account_info = next_account_info(account_info_iter)?;
+    let token_program_account_info = next_account_info(account_info_iter)?;
+    check_token_program(token_program_account_info.key)?;
+
+    // check acceptor_main_account_info is the tx signer
+    if !acceptor_main_account_info.is_signer {
+        return Err(BetError::IncorrectSigner.into());
+    }
+
+    // check program is owner of the order_account_info
+    if order_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+    let order_account = AcceptOrder::from_account_info(order_account_info)?;
+
+    // check the signer placed the order and the refund goes to its payment account
+    if order_account.acceptor_main_account != *acceptor_main_account_info.key
+        || order_account.acceptor_payment_account != *acceptor_payment_account_info.key {
+        msg!("Signer did not place the order: expected {}, got {}", order_account.acceptor_main_account, acceptor_main_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+
+    // check it is correct escrow account
+    if order_account.order_escrow_account != *order_escrow_account_info.key {
+        msg!("Incorrect escrow account: expected {}, got {}", order_account.order_escrow_account, order_escrow_account_info.key);
+        return Err(BetError::WrongEscrowAccount.into());
+    }
+
+    // get the order escrow authority and its signer seeds from the bump stored in the order
+    let order_escrow_bump_seed = [order_account.escrow_bump_seed];
+    let (order_escrow_authority, order_escrow_transfer_seeds) = escrow_authority_signer(
+        program_id,
+        ESCROW_SEEDS_MARKET,
+        &order_account.betting_market,
+        order_account_info.key,
+        &order_escrow_bump_seed
+    )?;
+    if order_escrow_authority != *order_escrow_authority_account_info.key {
+        msg!("Incorrect escrow authority: expected {}, got {}", order_escrow_authority, order_escrow_authority_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+
+    // refund the rest of the escrow, SOL orders unwrap it by closing the escrow to the payment account
+    let refund_destination_account_info = if order_account.payment_mint == spl_token::native_mint::id() {
+        acceptor_payment_account_info
+    } else {
+        let refunded_amount = TokenAccount::unpack_from_slice(&order_escrow_account_info.data.borrow())?.amount;
+        msg!("Calling token program to refund {} to the acceptor", refunded_amount);
+        invoke_signed(
+            &token::transfer(
+                token_program_account_info.key,
+                order_escrow_account_info.key,
+                acceptor_payment_account_info.key,
+                &order_escrow_authority,
+                refunded_amount
+            )?,
+            &[
+                token_program_account_info.clone(),
+                order_escrow_account_info.clone(),
+                acceptor_payment_account_info.clone(),
+                order_escrow_authority_account_info.clone()
+            ],
+            &[&order_escrow_transfer_seeds[..]]
+        )?;
+        acceptor_main_account_info
+    };
+    msg!("Calling token program to close the order escrow");
+    invoke_signed(
+        &token::close_account(
+            token_program_account_info.key,
+            order_escrow_account_info.key,
+            refund_destination_account_info.key,
+            &order_escrow_authority
+        )?,
+        &[
+            token_program_account_info.clone(),
+            order_escrow_account_info.clone(),
+            refund_destination_account_info.clone(),
+            order_escrow_authority_account_info.clone()
+        ],
+        &[&order_escrow_transfer_seeds[..]]
+    )?;
+
+    // close the order account, its rent goes back to the acceptor
+    let order_lamports = order_account_info.lamports();
+    debit_lamports(order_account_info, acceptor_main_account_info, order_lamports)?;
+    order_account_info.data.borrow_mut().fill(0);
+
+    Ok(())
+}
+
+pub fn process_create_pool<'a>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'a>],
+    args: CreatePoolArgs,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let creator_main_account_info = next_account_info(account_info_iter)?;
+    let creator_payment_account_info = next_account_info(account_info_iter)?;
+    let pool_account_info = next_account_info(account_info_iter)?;
+    let pool_escrow_account_info = next_account_info(account_info_iter)?;
+    let pool_escrow_authority_account_info = next_account_info(account_info_iter)?;
+    let pool_position_account_info = next_account_info(account_info_iter)?;
+    let betting_market_account_info = next_account_info(account_info_iter)?;
+    let pyth_oracle_product_account_info = next_account_info(account_info_iter)?;
+    let pyth_oracle_price_account_info = next_account_info(account_info_iter)?;
+    let payment_mint_account_info = next_account_info(account_info_iter)?;
+    let rent_sysvar_account_info = next_account_info(account_info_iter)?;
+    let token_program_account_info = next_account_info(account_info_iter)?;
+    check_token_program(token_program_account_info.key)?;
+    let system_program_account_info = next_account_info(account_info_iter)?;
+    if !check_id(system_program_account_info.key) {
+        return Err(BetError::InvalidSystemProgram.into());
+    }
+    let associated_token_program_account_info = next_account_info(account_info_iter)?;
+    if *associated_token_program_account_info.key != spl_associated_token_account::id() {
+        return Err(BetError::InvalidAccounts.into());
+    }
+    let clock = &Clock::get()?;
+
+    // check creator_main_account_info is the tx signer
+    if !creator_main_account_info.is_signer {
+        return Err(BetError::IncorrectSigner.into());
+    }
+
+    // check program is owner of the betting_market_account_info
+    if betting_market_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+    let betting_market_account = BettingMarket::from_account_info(betting_market_account_info)?;
+
+    // a pool settles on a single oracle read, there is no RecordSettlementPrice for pools
+    if betting_market_account.settles_on_observations() {
+        msg!("Betting market settles on {} second observation windows", betting_market_account.settlement_window_secs);
+        return Err(BetError::PoolSettlesOnObservations.into());
+    }
+
+    // check the pool's price and expiration, it must stay joinable for a while after creation
+    if args.bet_price <= 0 {
+        return Err(invalid_argument(BetError::InvalidPool, "bet_price", args.bet_price));
+    }
+    if validate_timestamp(args.expiration_time, clock.unix_timestamp.saturating_add(betting_market_account.accept_cutoff_secs), BetError::InvalidPool).is_err() {
+        return Err(invalid_argument(BetError::InvalidPool, "expiration_time", args.expiration_time));
+    }
+    let duration = args.expiration_time.saturating_sub(clock.unix_timestamp);
+    if duration < betting_market_account.min_duration_secs {
+        msg!("Pool lasts {} seconds, the betting market's minimum is {}", duration, betting_market_account.min_duration_secs);
+        return Err(BetError::DurationTooShort.into());
+    }
+    if betting_market_account.max_expiration_horizon_secs > 0
+        && duration > betting_market_account.max_expiration_horizon_secs {
+        msg!("Expiration time is beyond the betting market's maximum horizon");
+        return Err(BetError::ExpirationTooFar.into());
+    }
+
+    // check the mint is one of the betting market's accepted mints
+    if !betting_market_account.accepts_mint(payment_mint_account_info.key) {
+        msg!("Mint {} is not accepted by the betting market", payment_mint_account_info.key);
+        return Err(BetError::InvalidMint.into());
+    }
+
+    // check the token program is the one that owns the mint
+    if payment_mint_account_info.owner != token_program_account_info.key {
+        msg!("Incorrect token program: expected {}, got {}", payment_mint_account_info.owner, token_program_account_info.key);
+        return Err(BetError::InvalidTokenProgram.into());
+    }
+
+    // check valid pyth keys on a feed new bets can use, trading now so the pool can settle on it
+    validate_pyth_keys(
+        &betting_market_account.pyth_program_id,
+        pyth_oracle_product_account_info,
+        pyth_oracle_price_account_info
+    )?;
+    if !betting_market_account.is_price_account_allowed(pyth_oracle_price_account_info.key) {
+        msg!("Price account {} is not on the betting market's allowlist", pyth_oracle_price_account_info.key);
+        return Err(BetError::OracleNotAllowed.into());
+    }
+    if betting_market_account.is_price_account_delisted(pyth_oracle_price_account_info.key) {
+        msg!("Price account {} is delisted by the betting market", pyth_oracle_price_account_info.key);
+        return Err(BetError::OracleDelisted.into());
+    }
+    let oracle_expo = {
+        let pyth_price_data = pyth_oracle_price_account_info.try_borrow_data()?;
+        let price_account: Price = *load_price(&pyth_price_data).map_err(|_| BetError::InvalidAccountInput)?;
+        current_price(&price_account)?;
+        price_account.expo
+    };
+
+    // the pool is the PDA of the market, creator and nonce
+    let (pool_address, pool_bump_seed) = find_pool(program_id, betting_market_account_info.key, creator_main_account_info.key, args.nonce);
+    if pool_address != *pool_account_info.key {
+        msg!("Incorrect pool account: expected {}, got {}", pool_address, pool_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+    if pool_account_info.owner == program_id {
+        return Err(BetError::AccountAlreadyInitialized.into());
+    }
+    create_or_allocate_account_raw(
+        *program_id,
+        pool_account_info,
+        rent_sysvar_account_info,
+        system_program_account_info,
+        creator_main_account_info,
+        MAX_POOL_DATA_LEN,
+        &[
+            POOL_SEED.as_bytes(),
+            betting_market_account_info.key.as_ref(),
+            creator_main_account_info.key.as_ref(),
+            &args.nonce.to_le_bytes(),
+            &[pool_bump_seed]
+        ]
+    )?;
+    let mut pool_account = Pool::from_account_info(pool_account_info)?;
+
+    // the escrow is the ATA of the PDA derived from the betting market and pool account
+    let (pool_escrow_authority, pool_escrow_bump_seed) = find_escrow_authority(program_id, betting_market_account_info.key, pool_account_info.key);
+    if pool_escrow_authority != *pool_escrow_authority_account_info.key {
+        msg!("Incorrect escrow authority: expected {}, got {}", pool_escrow_authority, pool_escrow_authority_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+    let pool_escrow_address = get_associated_token_address(&pool_escrow_authority, payment_mint_account_info.key, token_program_account_info.key);
+    if pool_escrow_address != *pool_escrow_account_info.key {
+        msg!("Incorrect escrow account: expected {}, got {}", pool_escrow_address, pool_escrow_account_info.key);
+        return Err(BetError::WrongEscrowAccount.into());
+    }
+
+    // create the escrow token account, creator pays the rent
+    msg!("Calling the associated token program to create the pool escrow account...");
+    invoke(
+        &create_associated_token_account(
+            creator_main_account_info.key,
+            &pool_escrow_authority,
+            payment_mint_account_info.key,
+            token_program_account_info.key
+        ),
+        &[
+            creator_main_account_info.clone(),
+            pool_escrow_account_info.clone(),
+            pool_escrow_authority_account_info.clone(),
+            payment_mint_account_info.clone(),
+            system_program_account_info.clone(),
+            token_program_account_info.clone(),
+            rent_sysvar_account_info.clone(),
+            associated_token_program_account_info.clone(),
+        ],
+    )?;
+
+    // mints with a frozen default account state create the escrow frozen
+    token::check_not_frozen(pool_escrow_account_info)?;
+
+    // write data to the pool account, then add the creator's seed stake like a join
+    pool_account.status = PoolStatus::Open;
+    pool_account.betting_market = *betting_market_account_info.key;
+    pool_account.creator_main_account = *creator_main_account_info.key;
+    pool_account.payment_mint = *payment_mint_account_info.key;
+    pool_account.pool_escrow_account = *pool_escrow_account_info.key;
+    pool_account.pyth_oracle_product_account = *pyth_oracle_product_account_info.key;
+    pool_account.pyth_oracle_price_account = *pyth_oracle_price_account_info.key;
+    pool_account.bet_price = args.bet_price;
+    pool_account.oracle_expo = oracle_expo;
+    pool_account.expiration_time = args.expiration_time;
+    pool_account.nonce = args.nonce;
+    pool_account.bump_seed = pool_bump_seed;
+    pool_account.escrow_bump_seed = pool_escrow_bump_seed;
+    add_pool_stake(
+        program_id,
+        &mut pool_account,
+        pool_account_info,
+        pool_position_account_info,
+        creator_main_account_info,
+        creator_payment_account_info,
+        pool_escrow_account_info,
+        rent_sysvar_account_info,
+        system_program_account_info,
+        token_program_account_info,
+        args.side,
+        args.seed_amount
+    )?;
+
+    pool_account.save(pool_account_info)
+}
+
+pub fn process_join_pool<'a>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'a>],
+    args: JoinPoolArgs,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let participant_main_account_info = next_account_info(account_info_iter)?;
+    let participant_payment_account_info = next_account_info(account_info_iter)?;
+    let pool_account_info = next_account_info(account_info_iter)?;
+    let pool_escrow_account_info = next_account_info(account_info_iter)?;
+    let pool_position_account_info = next_account_info(account_info_iter)?;
+    let betting_market_account_info = next_account_info(account_info_iter)?;
+    let rent_sysvar_account_info = next_account_info(account_info_iter)?;
+    let token_program_account_info = next_account_info(account_info_iter)?;
+    check_token_program(token_program_account_info.key)?;
+    let system_program_account_info = next_account_info(account_info_iter)?;
+    if !check_id(system_program_account_info.key) {
+        return Err(BetError::InvalidSystemProgram.into());
+    }
+    let clock = &Clock::get()?;
+
+    // check participant_main_account_info is the tx signer
+    if !participant_main_account_info.is_signer {
+        return Err(BetError::IncorrectSigner.into());
+    }
+
+    // check program is owner of the pool and betting market accounts
+    if pool_account_info.owner != program_id || betting_market_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+    let mut pool_account = Pool::from_account_info(pool_account_info)?;
+    let betting_market_account = BettingMarket::from_account_info(betting_market_account_info)?;
+    if pool_account.betting_market != *betting_market_account_info.key {
+        msg!("Incorrect betting market: expected {}, got {}", pool_account.betting_market, betting_market_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+
+    // check it is correct escrow account, held by the token program that owns the pool's mint
+    if pool_account.pool_escrow_account != *pool_escrow_account_info.key {
+        msg!("Incorrect escrow account: expected {}, got {}", pool_account.pool_escrow_account, pool_escrow_account_info.key);
+        return Err(BetError::WrongEscrowAccount.into());
+    }
+    if pool_escrow_account_info.owner != token_program_account_info.key {
+        msg!("Incorrect token program: expected {}, got {}", pool_escrow_account_info.owner, token_program_account_info.key);
+        return Err(BetError::InvalidTokenProgram.into());
+    }
+
+    // stakes are taken until the market's accept cutoff before expiration, as AcceptBet checks it
+    if pool_account.status != PoolStatus::Open
+        || clock.unix_timestamp.saturating_sub(pool_account.expiration_time)
+            > betting_market_account.clock_tolerance_secs.saturating_sub(betting_market_account.accept_cutoff_secs) {
+        msg!("Pools can't be joined within {} seconds of expiration", betting_market_account.accept_cutoff_secs);
+        return Err(BetError::PoolClosed.into());
+    }
+
+    add_pool_stake(
+        program_id,
+        &mut pool_account,
+        pool_account_info,
+        pool_position_account_info,
+        participant_main_account_info,
+        participant_payment_account_info,
+        pool_escrow_account_info,
+        rent_sysvar_account_info,
+        system_program_account_info,
+        token_program_account_info,
+        args.side,
+        args.stake
+    )?;
+
+    pool_account.save(pool_account_info)
+}
+
+// adds the participant's stake on the side to the pool and their position, creating the position on their first stake.
+// SOL pools wrap the participant's lamports into the escrow, token pools transfer from the payment account, which
+// receives the payout either way
+#[allow(clippy::too_many_arguments)]
+fn add_pool_stake<'a>(
+    program_id: &Pubkey,
+    pool_account: &mut Pool,
+    pool_account_info: &AccountInfo<'a>,
+    pool_position_account_info: &AccountInfo<'a>,
+    participant_main_account_info: &AccountInfo<'a>,
+    participant_payment_account_info: &AccountInfo<'a>,
+    pool_escrow_account_info: &AccountInfo<'a>,
+    rent_sysvar_account_info: &AccountInfo<'a>,
+    system_program_account_info: &AccountInfo<'a>,
+    token_program_account_info: &AccountInfo<'a>,
+    side: Direction,
+    stake: u64,
+) -> ProgramResult {
+    if stake == 0 {
+        return Err(invalid_argument(BetError::InvalidPool, "stake", stake));
+    }
+    if !token::is_payout_account(participant_payment_account_info, token_program_account_info.key, &pool_account.payment_mint) {
+        msg!("Payment account {} is not a token account of the pool's mint", participant_payment_account_info.key);
+        return Err(BetError::WrongPaymentAccount.into());
+    }
+
+    // the position is the PDA of the pool and participant, later stakes must be on the same side
+    let (position_address, position_bump_seed) = find_pool_position(program_id, pool_account_info.key, participant_main_account_info.key);
+    if position_address != *pool_position_account_info.key {
+        msg!("Incorrect pool position account: expected {}, got {}", position_address, pool_position_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+    let mut position = if pool_position_account_info.owner == program_id {
+        let position = PoolPosition::from_account_info(pool_position_account_info)?;
+        if position.side != side {
+            msg!("Position is on {:?}, got {:?}", position.side, side);
+            return Err(BetError::PoolSideMismatch.into());
+        }
+        if position.participant_payment_account != *participant_payment_account_info.key {
+            msg!("Incorrect payment account: expected {}, got {}", position.participant_payment_account, participant_payment_account_info.key);
+            return Err(BetError::WrongPaymentAccount.into());
+        }
+        position
+    } else {
+        create_or_allocate_account_raw(
+            *program_id,
+            pool_position_account_info,
+            rent_sysvar_account_info,
+            system_program_account_info,
+            participant_main_account_info,
+            MAX_POOL_POSITION_DATA_LEN,
+            &[
+                POOL_POSITION_SEED.as_bytes(),
+                pool_account_info.key.as_ref(),
+                participant_main_account_info.key.as_ref(),
+                &[position_bump_seed]
+            ]
+        )?;
+        let mut position = PoolPosition::from_account_info(pool_position_account_info)?;
+        position.is_initialized = true;
+        position.pool = *pool_account_info.key;
+        position.participant_main_account = *participant_main_account_info.key;
+        position.participant_payment_account = *participant_payment_account_info.key;
+        position.side = side;
+        position.bump_seed = position_bump_seed;
+        pool_account.participant_count = pool_account.participant_count.checked_add(1).ok_or(BetError::AmountOverflow)?;
+        position
+    };
+    position.stake = position.stake.checked_add(stake).ok_or(BetError::AmountOverflow)?;
+    pool_account.side_totals[side as usize] = pool_account.side_total(side).checked_add(stake).ok_or(BetError::AmountOverflow)?;
+
+    if pool_account.payment_mint == spl_token::native_mint::id() {
+        // wrap the stake of the participant's lamports into the escrow
+        msg!("Calling the system program to transfer the stake to the pool escrow...");
+        invoke(
+            &system_instruction::transfer(participant_main_account_info.key, pool_escrow_account_info.key, stake),
+            &[
+                participant_main_account_info.clone(),
+                pool_escrow_account_info.clone(),
+                system_program_account_info.clone(),
+            ],
+        )?;
+        invoke(
+            &token::sync_native(token_program_account_info.key, pool_escrow_account_info.key)?,
+            &[
+                pool_escrow_account_info.clone(),
+                token_program_account_info.clone(),
+            ],
+        )?;
+    } else {
+        // transfer the stake from the participant into the escrow
+        msg!("Calling the token program to transfer the stake to the pool escrow...");
+        invoke(
+            &token::transfer(
+                token_program_account_info.key,
+                participant_payment_account_info.key,
+                pool_escrow_account_info.key,
+                participant_main_account_info.key,
+                stake
+            )?,
+            &[
+                token_program_account_info.clone(),
+                participant_payment_account_info.clone(),
+                pool_escrow_account_info.clone(),
+                participant_main_account_info.clone(),
+            ],
+        )?;
+    }
+
+    position.save(pool_position_account_info)
+}
+
+pub fn process_settle_pool<'a>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'a>],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pool_account_info = next_account_info(account_info_iter)?;
+    let pool_escrow_account_info = next_account_info(account_info_iter)?;
+    let pool_escrow_authority_account_info = next_account_info(account_info_iter)?;
+    let pool_position_account_info = next_account_info(account_info_iter)?;
+    let participant_payment_account_info = next_account_info(account_info_iter)?;
+    let participant_main_account_info = next_account_info(account_info_iter)?;
+    let commission_fee_account_info = next_account_info(account_info_iter)?;
+    let betting_market_account_info = next_account_info(account_info_iter)?;
+    let pyth_oracle_price_account_info = next_account_info(account_info_iter)?;
+    let token_program_account_info = next_account_info(account_info_iter)?;
+    check_token_program(token_program_account_info.key)?;
+    let clock = &Clock::get()?;
+
+    // check program is owner of the pool, position and betting market accounts
+    if pool_account_info.owner != program_id
+        || pool_position_account_info.owner != program_id
+        || betting_market_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+    let mut pool_account = Pool::from_account_info(pool_account_info)?;
+    let position = PoolPosition::from_account_info(pool_position_account_info)?;
+    let betting_market_account = BettingMarket::from_account_info(betting_market_account_info)?;
+
+    // check the accounts belong to the pool and the position
+    if pool_account.betting_market != *betting_market_account_info.key
+        || pool_account.pyth_oracle_price_account != *pyth_oracle_price_account_info.key {
+        msg!("Betting market or price account is not the pool's");
+        return Err(BetError::InvalidAccounts.into());
+    }
+    if pool_account.pool_escrow_account != *pool_escrow_account_info.key {
+        msg!("Incorrect escrow account: expected {}, got {}", pool_account.pool_escrow_account, pool_escrow_account_info.key);
+        return Err(BetError::WrongEscrowAccount.into());
+    }
+    if !position.is_initialized
+        || position.pool != *pool_account_info.key
+        || position.participant_main_account != *participant_main_account_info.key {
+        msg!("Position {} is not the participant's position in the pool", pool_position_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+    if position.participant_payment_account != *participant_payment_account_info.key {
+        msg!("Incorrect payment account: expected {}, got {}", position.participant_payment_account, participant_payment_account_info.key);
+        return Err(BetError::WrongPaymentAccount.into());
+    }
+
+    // get the pool escrow authority and its signer seeds from the bump stored in the pool
+    let pool_escrow_bump_seed = [pool_account.escrow_bump_seed];
+    let (pool_escrow_authority, pool_escrow_transfer_seeds) = escrow_authority_signer(
+        program_id,
+        ESCROW_SEEDS_MARKET,
+        &pool_account.betting_market,
+        pool_account_info.key,
+        &pool_escrow_bump_seed
+    )?;
+    if pool_escrow_authority != *pool_escrow_authority_account_info.key {
+        msg!("Incorrect escrow authority: expected {}, got {}", pool_escrow_authority, pool_escrow_authority_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+
+    let secs_since_expiry = clock.unix_timestamp.saturating_sub(pool_account.expiration_time);
+    if secs_since_expiry < 0 {
+        msg!("Pool expires at {}", pool_account.expiration_time);
+        return Err(BetError::PoolNotExpired.into());
+    }
+
+    // check only the participant claims their position during the market's exclusivity window after expiration
+    if secs_since_expiry < betting_market_account.finalize_exclusivity_secs && !participant_main_account_info.is_signer {
+        msg!("Only the participant can claim for {} seconds after expiration", betting_market_account.finalize_exclusivity_secs);
+        return Err(BetError::FinalizeReservedForParties.into());
+    }
+
+    // the first claim after expiration settles the pool, reading the oracle once. Markets that settle on
+    // observations can't create pools, one switched to them later still settles its pools on the single read
+    if pool_account.status == PoolStatus::Open {
+
+        // a feed that changed since creation or isn't trading can't settle the pool, after the bets' refund delays
+        // every stake is returned instead. A pool with an empty side has nothing to win and is refunded too
+        let pyth_price_data = pyth_oracle_price_account_info.try_borrow_data()?;
+        let price_account: Price = *load_price(&pyth_price_data).map_err(|_| BetError::InvalidAccountInput)?;
+        let oracle_feed_changed = price_account.prod.val != pool_account.pyth_oracle_product_account.to_bytes()
+            || price_account.expo != pool_account.oracle_expo;
+        if oracle_feed_changed && secs_since_expiry < ORACLE_FEED_CHANGED_REFUND_SECS {
+            msg!("Price account {} no longer belongs to product {}", pool_account.pyth_oracle_price_account, pool_account.pyth_oracle_product_account);
+            return Err(BetError::OracleFeedChanged.into());
+        }
+        let settlement_price = match current_price(&price_account) {
+            Ok(price) => Some(price.price),
+            Err(error) if secs_since_expiry < ORACLE_UNAVAILABLE_REFUND_SECS => return Err(error),
+            Err(_) => None
+        };
+        let empty_side = pool_account.side_totals.contains(&0);
+
+        // Above takes the creator's side of the market's tie policy, a tie the policy refunds has no winning side
+        let winning_side = match settlement_price {
+            Some(settlement_price) if !oracle_feed_changed && !empty_side => {
+                match determine_winner(Direction::Above, settlement_price, pool_account.bet_price, betting_market_account.tie_policy)? {
+                    Outcome::CreatorWins => Some(Direction::Above),
+                    Outcome::AcceptorWins => Some(Direction::Below),
+                    Outcome::Refund => None
+                }
+            },
+            _ => None
+        };
+
+        match settlement_price.zip(winning_side) {
+            Some((settlement_price, winning_side)) => {
+                let commission_amount = pool_commission(pool_account.side_total(winning_side.opposite()));
+
+                // the commission goes to the fee wallet's ATA of the mint, for SOL pools too
+                let commission_fee_account = get_associated_token_address(&betting_market_account.fee_commission_account, &pool_account.payment_mint, token_program_account_info.key);
+                if commission_fee_account != *commission_fee_account_info.key {
+                    msg!("Wrong commission fee account: expected {}, got {}", commission_fee_account, commission_fee_account_info.key);
+                    return Err(BetError::InvalidAccounts.into());
+                }
+                if commission_amount > 0 {
+                    if !token::is_payout_account(commission_fee_account_info, token_program_account_info.key, &pool_account.payment_mint) {
+                        msg!("Commission account {} doesn't exist yet", commission_fee_account_info.key);
+                        return Err(BetError::WrongPaymentAccount.into());
+                    }
+                    msg!("Calling token program to transfer {} commission", commission_amount);
+                    invoke_signed(
+                        &token::transfer(
+                            token_program_account_info.key,
+                            pool_escrow_account_info.key,
+                            commission_fee_account_info.key,
+                            &pool_escrow_authority,
+                            commission_amount
+                        )?,
+                        &[
+                            token_program_account_info.clone(),
+                            pool_escrow_account_info.clone(),
+                            commission_fee_account_info.clone(),
+                            pool_escrow_authority_account_info.clone()
+                        ],
+                        &[&pool_escrow_transfer_seeds[..]]
+                    )?;
+                }
+
+                pool_account.status = PoolStatus::Settled;
+                pool_account.settlement_price = settlement_price;
+                pool_account.winning_side = winning_side;
+                pool_account.commission_amount = commission_amount;
+                pool_account.unclaimed_stake = pool_account.side_total(winning_side);
+            },
+            _ => {
+                msg!("Pool can't be settled, refunding every stake");
+                pool_account.status = PoolStatus::Refunded;
+                pool_account.settlement_price = settlement_price.unwrap_or(0);
+                pool_account.unclaimed_stake = pool_account.side_totals[0].checked_add(pool_account.side_totals[1]).ok_or(BetError::AmountOverflow)?;
+            }
+        }
+        emit_event(&BetEvent::PoolSettled {
+            pool: *pool_account_info.key,
+            settlement_price: pool_account.settlement_price,
+            winning_side: pool_account.winning_side,
+            refunded: pool_account.status == PoolStatus::Refunded,
+            side_totals: pool_account.side_totals,
+            commission_amount: pool_account.commission_amount,
+        })?;
+    }
+
+    // the position's payout: its stake when refunded, its stake and pro rata share of the losing side after
+    // commission when on the winning side, nothing otherwise
+    let (claimed_stake, mut payout) = match pool_account.status {
+        PoolStatus::Refunded => (position.stake, position.stake),
+        PoolStatus::Settled if position.side == pool_account.winning_side => {
+            let losing_pool = pool_account.side_total(pool_account.winning_side.opposite());
+            let distributable = losing_pool.checked_sub(pool_account.commission_amount).ok_or(BetError::AmountOverflow)?;
+            let winnings = pool_winnings(position.stake, pool_account.side_total(pool_account.winning_side), distributable)?;
+            (position.stake, position.stake.checked_add(winnings).ok_or(BetError::AmountOverflow)?)
+        },
+        PoolStatus::Settled => (0, 0),
+        _ => return Err(BetError::InvalidAccounts.into())
+    };
+    pool_account.unclaimed_stake = pool_account.unclaimed_stake.checked_sub(claimed_stake).ok_or(BetError::AmountOverflow)?;
+
+    // the last claim takes what's left in the escrow, the rounding dust of the shares
+    if claimed_stake > 0 && pool_account.unclaimed_stake == 0 {
+        payout = TokenAccount::unpack_from_slice(&pool_escrow_account_info.data.borrow())?.amount;
+    }
+    if payout > 0 {
+        msg!("Calling token program to pay {} to the participant", payout);
+        invoke_signed(
+            &token::transfer(
+                token_program_account_info.key,
+                pool_escrow_account_info.key,
+                participant_payment_account_info.key,
+                &pool_escrow_authority,
+                payout
+            )?,
+            &[
+                token_program_account_info.clone(),
+                pool_escrow_account_info.clone(),
+                participant_payment_account_info.clone(),
+                pool_escrow_authority_account_info.clone()
+            ],
+            &[&pool_escrow_transfer_seeds[..]]
+        )?;
+    }
+    pool_account.save(pool_account_info)?;
+
+    // close the position account, its rent goes back to the participant
+    let position_lamports = pool_position_account_info.lamports();
+    debit_lamports(pool_position_account_info, participant_main_account_info, position_lamports)?;
+    pool_position_account_info.data.borrow_mut().fill(0);
+
+    Ok(())
+}
+
+pub fn process_close_settled_accounts(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let (bet_state_account_info, accounts) = accounts.split_first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let (creator_exposure_account_info, position_accounts) = accounts.split_first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    // check program is owner of the bet
+    if bet_state_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+    let mut bet_state_account = Bet::from_account_info(bet_state_account_info)?;
+
+    let position_count = position_accounts.len() / CLOSE_SETTLED_POSITION_ACCOUNTS;
+    if position_count == 0 || position_count > MAX_CLOSE_SETTLED_ACCOUNTS || position_accounts.len() % CLOSE_SETTLED_POSITION_ACCOUNTS != 0 {
+        msg!("Expected 1 to {} positions of {} accounts each", MAX_CLOSE_SETTLED_ACCOUNTS, CLOSE_SETTLED_POSITION_ACCOUNTS);
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    // a position that doesn't qualify is skipped, so a crank's batch isn't failed by one already closed or unfinished
+    let mut closed_count = 0;
+    for position in position_accounts.chunks(CLOSE_SETTLED_POSITION_ACCOUNTS) {
+        let (accepted_bet_state_account_info, acceptor_main_account_info) = (&position[0], &position[1]);
+        if accepted_bet_state_account_info.owner != program_id {
+            msg!("{}: skipped, not owned by the program", accepted_bet_state_account_info.key);
+            continue;
+        }
+        let accepted_bet_state_account = match AcceptedBet::from_account_info(accepted_bet_state_account_info) {
+            Ok(accepted_bet_state_account) if accepted_bet_state_account.is_initialized() => accepted_bet_state_account,
+            _ => {
+                msg!("{}: skipped, not an accepted bet", accepted_bet_state_account_info.key);
+                continue;
+            }
+        };
+        if accepted_bet_state_account.bet != *bet_state_account_info.key {
+            msg!("{}: skipped, belongs to bet {}", accepted_bet_state_account_info.key, accepted_bet_state_account.bet);
+            continue;
+        }
+        if !accepted_bet_state_account.is_finalized() {
+            msg!("{}: skipped, not finalized", accepted_bet_state_account_info.key);
+            continue;
+        }
+        // the rent only ever goes to the acceptor, so anyone can close the account
+        if accepted_bet_state_account.acceptor_main_account != *acceptor_main_account_info.key {
+            msg!("{}: skipped, wrong acceptor: expected {}, got {}", accepted_bet_state_account_info.key, accepted_bet_state_account.acceptor_main_account, acceptor_main_account_info.key);
+            continue;
+        }
+
+        let accepted_bet_lamports = accepted_bet_state_account_info.lamports();
+        debit_lamports(accepted_bet_state_account_info, acceptor_main_account_info, accepted_bet_lamports)?;
+        accepted_bet_state_account_info.data.borrow_mut().fill(0);
+        msg!("{}: closed, {} lamports to {}", accepted_bet_state_account_info.key, accepted_bet_lamports, acceptor_main_account_info.key);
+        closed_count += 1;
+    }
+    msg!("Closed {} of {} accepted bets", closed_count, position_count);
+
+    // release a bet whose accepts are all finalized from its creator's open bets, if finalize didn't. DistributeFees
+    // finalizes the last accept of a two phase settlement without the creator's exposure account
+    if bet_state_account.counts_as_open()
+        && !bet_state_account.rollover
+        && bet_state_account.accepted_count > 0
+        && bet_state_account.finalized_count == bet_state_account.accepted_count {
+        release_open_bet(program_id, &mut bet_state_account, creator_exposure_account_info)?;
+        bet_state_account.save(bet_state_account_info)?;
+    }
+
+    Ok(())
+}
+
+// takes the bet out of its creator's open bets, at most once: on a full cancel, or once its last accept is finalized.
+// The caller saves the bet
+fn release_open_bet(program_id: &Pubkey, bet_state_account: &mut Bet, creator_exposure_account_info: &AccountInfo) -> ProgramResult {
+    let (creator_exposure_address, _bump_seed) = find_creator_exposure(program_id, &bet_state_account.betting_market, &bet_state_account.creator_main_account);
+    if creator_exposure_address != *creator_exposure_account_info.key {
+        msg!("Incorrect creator exposure account: expected {}, got {}", creator_exposure_address, creator_exposure_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+    if !bet_state_account.counts_as_open() {
+        return Ok(());
+    }
+    if creator_exposure_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+    let mut creator_exposure_account = CreatorExposure::from_account_info(creator_exposure_account_info)?;
+    creator_exposure_account.open_bets = creator_exposure_account.open_bets.saturating_sub(1);
+    creator_exposure_account.save(creator_exposure_account_info)?;
+    bet_state_account.open_bet_released = true;
+    Ok(())
+}
+
+pub fn process_cancel_bet<'a>(
+    program_id: &'a Pubkey,
+    accounts: &'a [AccountInfo<'a>],
+    amount: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let creator_main_account_info = next_account_info(account_info_iter)?;
+    let creator_payment_account_info = next_account_info(account_info_iter)?;
+    let bet_state_account_info = next_account_info(account_info_iter)?;
+    let bet_escrow_account_info = next_account_info(account_info_iter)?;
+    let betting_market_account_info = next_account_info(account_info_iter)?;
+    let token_program_account_info = next_account_info(account_info_iter)?;
+    check_token_program(token_program_account_info.key)?;
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let event_authority_account_info = next_account_info(account_info_iter)?;
+    let program_account_info = next_account_info(account_info_iter)?;
+    let creator_exposure_account_info = next_account_info(account_info_iter)?;
+    let feed_exposure_account_info = next_account_info(account_info_iter)?;
+
+    // check creator main account is signer
+    if !creator_main_account_info.is_signer {
+        return Err(BetError::IncorrectSigner.into());
+    }
+
+    // check program is owner of the bet_state_account_info
+    if bet_state_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+
+    // unpack state account data
+    let mut bet_state_account = Bet::from_account_info(bet_state_account_info)?;
+
+    // check creator main account created the bet
+    if bet_state_account.creator_main_account != *creator_main_account_info.key {
+        msg!("Signer did not create the bet: expected {}, got {}", bet_state_account.creator_main_account, creator_main_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+
+    // check bet hasn't already been cancelled, so the open bets count is only decremented once
+    if bet_state_account.status == BetStatus::Cancelled {
+        return Err(BetError::BetCancelled.into());
+    }
+
+    // check it is correct betting market account
+    if bet_state_account.betting_market != *betting_market_account_info.key {
+        msg!("Incorrect betting market account: expected {}, got {}", bet_state_account.betting_market, betting_market_account_info.key);
+        return Err(BetError::WrongBettingMarket.into());
+    }
+
+    // a bet with a refund destination only returns its escrow there, even to a signer holding the creator's key
+    if !bet_state_account.refunds_to(creator_payment_account_info.key) {
+        msg!("Incorrect refund destination: expected {}, got {}", bet_state_account.refund_destination, creator_payment_account_info.key);
+        return Err(BetError::WrongPaymentAccount.into());
+    }
+
+    // check it is correct escrow account
+    if bet_state_account.bet_escrow_account != *bet_escrow_account_info.key {
+        msg!("Incorrect escrow account: expected {}, got {}", bet_state_account.bet_escrow_account, bet_escrow_account_info.key);
+        return Err(BetError::WrongEscrowAccount.into());
+    }
+
+    // check the token program is the one that owns the escrow
+    if bet_escrow_account_info.owner != token_program_account_info.key {
+        msg!("Incorrect token program: expected {}, got {}", bet_escrow_account_info.owner, token_program_account_info.key);
+        return Err(BetError::InvalidTokenProgram.into());
+    }
+
+    // get the escrow authority and its signer seeds from the seeds version and bump stored in the bet
+    let bet_escrow_bump_seed = [bet_state_account.escrow_bump_seed];
+    let (bet_escrow_account_pda, bet_escrow_transfer_seeds) = escrow_authority_signer(
+        program_id,
+        bet_state_account.escrow_seeds_version,
+        betting_market_account_info.key,
+        bet_state_account_info.key,
+        &bet_escrow_bump_seed
+    )?;
+    if bet_escrow_account_pda != *pda_account_info.key {
+        msg!("Incorrect escrow authority: expected {}, got {}", bet_escrow_account_pda, pda_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+
+    // withdraw at most the unmatched size, CANCEL_ALL_REMAINING withdraws all of it
+    let remaining_capacity = bet_state_account.bet_size.saturating_sub(bet_state_account.total_amount_accepted);
+    let amount = if amount == CANCEL_ALL_REMAINING { remaining_capacity } else { amount };
+    if amount == 0 || amount > remaining_capacity {
+        msg!("Cancel amount {} must be between 1 and the unmatched size {}", amount, remaining_capacity);
+        return Err(BetError::InvalidCancelAmount.into());
+    }
+    let full_cancel = amount == remaining_capacity;
+
+    // send lamports / tokens from escrow account to creator payment account, a full cancel empties the escrow
+    let refunded_amount = if full_cancel {
+        TokenAccount::unpack_from_slice(&bet_escrow_account_info.data.borrow())?.amount
+    } else {
+        amount
+    };
+    if bet_state_account.is_sol_payment() && full_cancel {
+        // unwrap by closing the escrow, all its lamports go to the creator
+        msg!("Calling token program to close the escrow to the bet creator");
+        let close_escrow_ix = token::close_account(
+            token_program_account_info.key,
+            bet_escrow_account_info.key,
+            creator_payment_account_info.key,
+            &bet_escrow_account_pda
+        )?;
+        invoke_signed(
+            &close_escrow_ix,
+            &[
+                token_program_account_info.clone(),
+                bet_escrow_account_info.clone(),
+                creator_payment_account_info.clone(),
+                pda_account_info.clone()
+            ],
+            &[&bet_escrow_transfer_seeds[..]]
+        )?;
+    } else {
+        msg!("Calling token program to transfer tokens to bet creator");
+        let transfer_tokens_from_escrow_ix = token::transfer(
+            token_program_account_info.key, 
+            bet_escrow_account_info.key, 
+            creator_payment_account_info.key, 
+            &bet_escrow_account_pda, 
+            refunded_amount
+        )?;
+        invoke_signed(
+            &transfer_tokens_from_escrow_ix, 
+            &[
+                token_program_account_info.clone(),
+                bet_escrow_account_info.clone(),
+                creator_payment_account_info.clone(),
+                pda_account_info.clone()
+            ], 
+            &[&bet_escrow_transfer_seeds[..]]
+        )?;
+    }
+
+    // a full cancel refunds the unpaid keeper bounty, unless accepts are still waiting for a finalizer to take it
+    let refunded_bounty_lamports = if full_cancel && bet_state_account.keeper_bounty_paid_count == bet_state_account.accepted_count {
+        bet_state_account.keeper_bounty_lamports
+    } else {
+        0
+    };
+    if refunded_bounty_lamports > 0 {
+        msg!("Refunding the keeper bounty of {} lamports to the bet creator", refunded_bounty_lamports);
+        debit_lamports(bet_state_account_info, creator_main_account_info, refunded_bounty_lamports)?;
+        bet_state_account.keeper_bounty_lamports = 0;
+    }
+
+    // shrink the bet by the withdrawn amount, once nothing is unmatched cancel it so noone in future can try to accept it.
+    // A cancelled bet no longer counts towards the creator's open bets
+    bet_state_account.bet_size -= amount;
+    if full_cancel {
+        release_open_bet(program_id, &mut bet_state_account, creator_exposure_account_info)?;
+        bet_state_account.set_status(BetStatus::Cancelled)?;
+        // the full cancel refunded the rollover balance with the rest of the escrow
+        bet_state_account.rollover_balance = 0;
+    }
+    bet_state_account.updated_at = clock.unix_timestamp;
+    bet_state_account.last_interaction_slot = clock.slot;
+
+    // pack the bet_state_account
+    bet_state_account.save(bet_state_account_info)?;
+
+    // the withdrawn size leaves the feed's unmatched amount
+    if bet_state_account.feed_exposure_tracked {
+        let mut feed_exposure_account = load_feed_exposure(program_id, feed_exposure_account_info, &bet_state_account)?;
+        feed_exposure_account.remove_unmatched(amount);
+        feed_exposure_account.save(feed_exposure_account_info)?;
+    }
+
+    emit_event_cpi(program_id, event_authority_account_info, program_account_info, &BetEvent::BetCancelled {
+        bet: *bet_state_account_info.key,
+        refunded_amount,
+        remaining_capacity: remaining_capacity - amount,
+        refunded_bounty_lamports,
+        updated_at: bet_state_account.updated_at,
+        status: bet_state_account.status,
+    })?;
+
+    Ok(())
+}
+
+pub fn process_increase_bet<'a>(
+    program_id: &'a Pubkey,
+    accounts: &'a [AccountInfo<'a>],
+    amount: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let creator_main_account_info = next_account_info(account_info_iter)?;
+    let creator_payment_account_info = next_account_info(account_info_iter)?;
+    let bet_state_account_info = next_account_info(account_info_iter)?;
+    let bet_escrow_account_info = next_account_info(account_info_iter)?;
+    let token_program_account_info = next_account_info(account_info_iter)?;
+    check_token_program(token_program_account_info.key)?;
+    let system_program_account_info = next_account_info(account_info_iter)?;
+    if !check_id(system_program_account_info.key) {
+        return Err(BetError::InvalidSystemProgram.into());
+    }
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+    // check creator main account is signer
+    if !creator_main_account_info.is_signer {
+        return Err(BetError::IncorrectSigner.into());
+    }
+
+    // check program is owner of the bet_state_account_info
+    if bet_state_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+
+    // unpack state account data
+    let mut bet_state_account = Bet::from_account_info(bet_state_account_info)?;
+
+    // check creator main account created the bet
+    if bet_state_account.creator_main_account != *creator_main_account_info.key {
+        msg!("Signer did not create the bet: expected {}, got {}", bet_state_account.creator_main_account, creator_main_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+
+    // only rollover bets keep a standing balance
+    if !bet_state_account.rollover {
+        return Err(BetError::NotRolloverBet.into());
+    }
+    if bet_state_account.status == BetStatus::Cancelled {
+        return Err(BetError::BetCancelled.into());
+    }
+
+    // check it is correct escrow account
+    if bet_state_account.bet_escrow_account != *bet_escrow_account_info.key {
+        msg!("Incorrect escrow account: expected {}, got {}", bet_state_account.bet_escrow_account, bet_escrow_account_info.key);
+        return Err(BetError::WrongEscrowAccount.into());
+    }
+
+    // check the token program is the one that owns the escrow
+    if bet_escrow_account_info.owner != token_program_account_info.key {
+        msg!("Incorrect token program: expected {}, got {}", bet_escrow_account_info.owner, token_program_account_info.key);
+        return Err(BetError::InvalidTokenProgram.into());
+    }
+
+    if bet_state_account.is_sol_payment() {
+        // wrap the creator's lamports into the escrow
+        msg!("Calling the system program to transfer the top-up to the escrow...");
+        invoke(
+            &system_instruction::transfer(creator_main_account_info.key, bet_escrow_account_info.key, amount),
+            &[
+                creator_main_account_info.clone(),
+                bet_escrow_account_info.clone(),
+                system_program_account_info.clone(),
+            ],
+        )?;
+        invoke(
+            &token::sync_native(token_program_account_info.key, bet_escrow_account_info.key)?,
+            &[
+                bet_escrow_account_info.clone(),
+                token_program_account_info.clone(),
+            ],
+        )?;
+    } else {
+        // transfer the top-up from the creator into the escrow
+        let transfer_tokens_to_escrow_ix = token::transfer(
+            token_program_account_info.key,
+            creator_payment_account_info.key,
+            bet_escrow_account_info.key,
+            creator_main_account_info.key,
+            amount
+        )?;
+        msg!("Calling the token program to transfer the top-up to the escrow...");
+        invoke(
+            &transfer_tokens_to_escrow_ix,
+            &[
+                token_program_account_info.clone(),
+                creator_payment_account_info.clone(),
+                bet_escrow_account_info.clone(),
+                creator_main_account_info.clone(),
+            ],
+        )?;
+    }
+
+    bet_state_account.rollover_balance = bet_state_account.rollover_balance.checked_add(amount).ok_or(BetError::AmountOverflow)?;
+    bet_state_account.updated_at = clock.unix_timestamp;
+    bet_state_account.last_interaction_slot = clock.slot;
+
+    // pack the bet_state_account
+    bet_state_account.save(bet_state_account_info)?;
+
+    Ok(())
+}
+
+pub fn process_rollover_bet<'a>(
+    program_id: &'a Pubkey,
+    accounts: &'a [AccountInfo<'a>],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let keeper_main_account_info = next_account_info(account_info_iter)?;
+    let keeper_payment_account_info = next_account_info(account_info_iter)?;
+    let bet_state_account_info = next_account_info(account_info_iter)?;
+    let bet_escrow_account_info = next_account_info(account_info_iter)?;
+    let betting_market_account_info = next_account_info(account_info_iter)?;
+    let pyth_oracle_price_account_info = next_account_info(account_info_iter)?;
+    let token_program_account_info = next_account_info(account_info_iter)?;
+    check_token_program(token_program_account_info.key)?;
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let event_authority_account_info = next_account_info(account_info_iter)?;
+    let program_account_info = next_account_info(account_info_iter)?;
+
+    // check keeper main account is signer
+    if !keeper_main_account_info.is_signer {
+        return Err(BetError::IncorrectSigner.into());
+    }
+
+    // check program is owner of the bet_state_account_info and betting_market_account_info
+    if bet_state_account_info.owner != program_id || betting_market_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+
+    // unpack state account data
+    let mut bet_state_account = Bet::from_account_info(bet_state_account_info)?;
+    let betting_market_account = BettingMarket::from_account_info(betting_market_account_info)?;
+
+    if !bet_state_account.rollover {
+        return Err(BetError::NotRolloverBet.into());
+    }
+    if bet_state_account.status == BetStatus::Cancelled {
+        return Err(BetError::BetCancelled.into());
+    }
+
+    // the current period must be over and all its accepts settled
+    if bet_state_account.secs_since_expiry(clock.unix_timestamp, clock.slot) < 0
+        || bet_state_account.finalized_count != bet_state_account.accepted_count {
+        msg!("Bet has {} of {} accepts finalized", bet_state_account.finalized_count, bet_state_account.accepted_count);
+        return Err(BetError::BetNotSettled.into());
+    }
+
+    // check it is correct betting market account
+    if bet_state_account.betting_market != *betting_market_account_info.key {
+        msg!("Incorrect betting market account: expected {}, got {}", bet_state_account.betting_market, betting_market_account_info.key);
+        return Err(BetError::WrongBettingMarket.into());
+    }
+
+    // check it is correct oracle account
+    if *pyth_oracle_price_account_info.key != bet_state_account.pyth_oracle_price_account {
+        msg!("Invalid oracle account provided: expected {}, got {}", bet_state_account.pyth_oracle_price_account, pyth_oracle_price_account_info.key);
+        return Err(BetError::WrongOracleAccount.into());
+    }
+
+    // check it is correct escrow account
+    if bet_state_account.bet_escrow_account != *bet_escrow_account_info.key {
+        msg!("Incorrect escrow account: expected {}, got {}", bet_state_account.bet_escrow_account, bet_escrow_account_info.key);
+        return Err(BetError::WrongEscrowAccount.into());
+    }
+
+    // check the token program is the one that owns the escrow
+    if bet_escrow_account_info.owner != token_program_account_info.key {
+        msg!("Incorrect token program: expected {}, got {}", bet_escrow_account_info.owner, token_program_account_info.key);
+        return Err(BetError::InvalidTokenProgram.into());
+    }
+
+    // the keeper is paid in the bet's mint, wrapped SOL for SOL bets
+    if keeper_payment_account_info.owner != token_program_account_info.key {
+        return Err(BetError::IsNotTokenAccount.into());
+    }
+    let keeper_payment_account = TokenAccount::unpack_from_slice(&keeper_payment_account_info.data.borrow())?;
+    if keeper_payment_account.mint != bet_state_account.payment_mint {
+        return Err(BetError::InvalidMint.into());
+    }
+
+    // get the escrow authority and its signer seeds from the seeds version and bump stored in the bet
+    let bet_escrow_bump_seed = [bet_state_account.escrow_bump_seed];
+    let (bet_escrow_account_pda, bet_escrow_transfer_seeds) = escrow_authority_signer(
+        program_id,
+        bet_state_account.escrow_seeds_version,
+        betting_market_account_info.key,
+        bet_state_account_info.key,
+        &bet_escrow_bump_seed
+    )?;
+    if bet_escrow_account_pda != *pda_account_info.key {
+        msg!("Incorrect escrow authority: expected {}, got {}", bet_escrow_account_pda, pda_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+
+    // the keeper reward follows the market's finalizer reward, on the full bet size
+    let keeper_reward = bet_state_account.bet_size
+        .checked_mul(finalizer_reward_bps(&betting_market_account, bet_state_account.secs_since_expiry(clock.unix_timestamp, clock.slot)))
+        .ok_or(BetError::AmountOverflow)? / 10000;
+
+    // the matched part of the bet left the escrow, the rollover balance refills it and pays the keeper
+    let required_balance = bet_state_account.total_amount_accepted.checked_add(keeper_reward).ok_or(BetError::AmountOverflow)?;
+    if bet_state_account.rollover_balance < required_balance {
+        msg!("Rollover balance {} can't cover the refill of {} and keeper reward of {}", bet_state_account.rollover_balance, bet_state_account.total_amount_accepted, keeper_reward);
+        return Err(BetError::InsufficientRolloverBalance.into());
+    }
+
+    if keeper_reward > 0 {
+        msg!("Calling token program to transfer the keeper reward");
+        let transfer_tokens_from_escrow_ix = token::transfer(
+            token_program_account_info.key,
+            bet_escrow_account_info.key,
+            keeper_payment_account_info.key,
+            &bet_escrow_account_pda,
+            keeper_reward
+        )?;
+        invoke_signed(
+            &transfer_tokens_from_escrow_ix,
+            &[
+                token_program_account_info.clone(),
+                bet_escrow_account_info.clone(),
+                keeper_payment_account_info.clone(),
+                pda_account_info.clone()
+            ],
+            &[&bet_escrow_transfer_seeds[..]]
+        )?;
+    }
+
+    // move expiration forward by whole intervals until it is in the future, in the bet's expiry units
+    let interval = bet_state_account.rollover_interval_secs;
+    let now = bet_state_account.expiry_kind.now(clock.unix_timestamp, clock.slot);
+    let periods = now.saturating_sub(bet_state_account.expiration_time) / interval + 1;
+    let time_shift = periods.checked_mul(interval).ok_or(BetError::AmountOverflow)?;
+
+    // prices move with the spot price since the last period started, like a relative strike clone
+    let pyth_price_data = pyth_oracle_price_account_info.try_borrow_data()?;
+    let price_account: Price = *load_price(&pyth_price_data).map_err(|_| BetError::InvalidAccountInput)?;
+    check_oracle_feed(&bet_state_account, &price_account)?;
+    let price: PriceConf = current_price(&price_account)?;
+    let price_shift = price.price.checked_sub(bet_state_account.start_price).ok_or(BetError::AmountOverflow)?;
+
+    bet_state_account.expiration_time = bet_state_account.expiration_time.checked_add(time_shift).ok_or(BetError::AmountOverflow)?;
+    bet_state_account.bet_price = bet_state_account.bet_price.checked_add(price_shift).ok_or(BetError::AmountOverflow)?;
+    bet_state_account.start_price = price.price;
+    bet_state_account.cancel_condition = bet_state_account.cancel_condition.shifted(price_shift, bet_state_account.expiry_kind.to_secs(time_shift));
+    bet_state_account.rollover_balance -= required_balance;
+    bet_state_account.total_amount_accepted = 0;
+    bet_state_account.side_amounts_accepted = [0; 2];
+    bet_state_account.period_accepted_base = bet_state_account.accepted_count;
+    bet_state_account.set_status(BetStatus::Open)?;
+    bet_state_account.settlement_observations_count = 0;
+    bet_state_account.last_odds_update_time = clock.unix_timestamp;
+    bet_state_account.updated_at = clock.unix_timestamp;
+    bet_state_account.last_interaction_slot = clock.slot;
+
+    // pack the bet_state_account
+    bet_state_account.save(bet_state_account_info)?;
+
+    emit_event_cpi(program_id, event_authority_account_info, program_account_info, &BetEvent::BetRolledOver {
+        bet: *bet_state_account_info.key,
+        expiration_time: bet_state_account.expiration_time,
+        start_price: bet_state_account.start_price,
+        bet_price: bet_state_account.bet_price,
+        rollover_balance: bet_state_account.rollover_balance,
+        keeper_reward,
+        updated_at: bet_state_account.updated_at,
+        status: bet_state_account.status,
+    })?;
+
+    Ok(())
+}
+
+pub fn process_record_settlement_price<'a>(
+    program_id: &'a Pubkey,
+    accounts: &'a [AccountInfo<'a>],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let bet_state_account_info = next_account_info(account_info_iter)?;
+    let betting_market_account_info = next_account_info(account_info_iter)?;
+    let pyth_oracle_price_account_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+    // check program is owner of the bet_state_account_info and betting_market_account_info
+    if bet_state_account_info.owner != program_id || betting_market_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+
+    // unpack state account data
+    let mut bet_state_account = Bet::from_account_info(bet_state_account_info)?;
+    let betting_market_account = BettingMarket::from_account_info(betting_market_account_info)?;
+
+    // check it is correct betting market account
+    if bet_state_account.betting_market != *betting_market_account_info.key {
+        msg!("Incorrect betting market account: expected {}, got {}", bet_state_account.betting_market, betting_market_account_info.key);
+        return Err(BetError::WrongBettingMarket.into());
+    }
+
+    // check it is correct oracle account
+    if *pyth_oracle_price_account_info.key != bet_state_account.pyth_oracle_price_account {
+        msg!("Invalid oracle account provided: expected {}, got {}", bet_state_account.pyth_oracle_price_account, pyth_oracle_price_account_info.key);
+        return Err(BetError::WrongOracleAccount.into());
+    }
+
+    if !betting_market_account.settles_on_observations() {
+        return Err(BetError::SettlementObservationsDisabled.into());
+    }
+
+    // record from expiration until the window closes, past it only while the bet lacks the minimum to settle
+    let secs_since_expiry = bet_state_account.secs_since_expiry(clock.unix_timestamp, clock.slot);
+    if secs_since_expiry < 0
+        || (secs_since_expiry >= betting_market_account.settlement_window_secs
+            && bet_state_account.settlement_observations_count >= betting_market_account.min_settlement_observations) {
+        msg!("Settlement window is the {} seconds after expiration at {}", betting_market_account.settlement_window_secs, bet_state_account.expiration_time);
+        return Err(BetError::OutsideSettlementWindow.into());
+    }
+    let count = bet_state_account.settlement_observations_count as usize;
+    if count == MAX_SETTLEMENT_OBSERVATIONS {
+        return Err(BetError::SettlementObservationsFull.into());
+    }
+
+    // get price from pyth oracle, one observation per pyth publish slot. Finalize trusts the observations, so the
+    // feed is checked as they're recorded
+    let pyth_price_data = pyth_oracle_price_account_info.try_borrow_data()?;
+    let price_account: Price = *load_price(&pyth_price_data).map_err(|_| BetError::InvalidAccountInput)?;
+    check_oracle_feed(&bet_state_account, &price_account)?;
+    let price: PriceConf = current_price(&price_account)?;
+    let slot = price_account.agg.pub_slot;
+    if bet_state_account.settlement_observations().iter().any(|observation| observation.slot == slot) {
+        msg!("Price at slot {} is already recorded", slot);
+        return Err(BetError::DuplicateObservationSlot.into());
+    }
+
+    bet_state_account.settlement_observations[count] = PriceObservation {
+        slot,
+        price: price.price
+    };
+    bet_state_account.settlement_observations_count += 1;
 
-    // check if bet payment is SOL or a token
-    if betting_market_account.sol_payment == true {
-        // if yes, check that the program id is owner of the bet escrow account
-        if bet_escrow_account_info.owner != program_id {
-            return Err(BetError::IncorrectOwner.into());
-        }
+    // pack the bet_state_account
+    bet_state_account.save(bet_state_account_info)?;
 
-        // check escrow account has enough lamports for the bet
-        if bet_escrow_account_info.lamports() < bet_size {
-            return Err(BetError::AmountUnderflow.into());
-        }
-    } else {
-        // if no, check bet escrow account and creator payment account are token accounts 
-        if *bet_escrow_account_info.owner != spl_token::ID || *creator_payment_account_info.owner != spl_token::ID {
-            return Err(BetError::IsNotTokenAccount.into());
-        }
-        // unpack the token account data
-        let bet_escrow_account = TokenAccount::unpack_from_slice(&bet_escrow_account_info.data.borrow())?;
-        let creator_payment_account = TokenAccount::unpack_from_slice(&creator_payment_account_info.data.borrow())?;
+    Ok(())
+}
 
-        // check the escrow and payment accounts have the same mint and that it is same mint as in the betting market account
-        if let Some(payment_mint) = betting_market_account.payment_mint {
-            if bet_escrow_account.mint != creator_payment_account.mint || bet_escrow_account.mint != payment_mint {
-                return Err(BetError::InvalidMint.into());
-            }
-        }
+pub fn process_extend_expiry<'a>(
+    program_id: &'a Pubkey,
+    accounts: &'a [AccountInfo<'a>],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let bet_state_account_info = next_account_info(account_info_iter)?;
+    let betting_market_account_info = next_account_info(account_info_iter)?;
+    let pyth_oracle_price_account_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::get()?;
 
-        // check escrow account has enough tokens for the bet
-        if bet_escrow_account.amount < bet_size {
-            return Err(BetError::AmountUnderflow.into());
-        }
+    // check program is owner of the bet_state_account_info and betting_market_account_info
+    if bet_state_account_info.owner != program_id || betting_market_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
 
-        // get the PDA account Pubkey (derived from the bet_escrow_account_info Pubkey and prefix "yoyobet")
-        let bet_escrow_account_seeds = &[
-            PREFIX.as_bytes(),
-            bet_escrow_account_info.key.as_ref(),
-        ];
-        let (bet_escrow_account_pda, _bump_seed) = Pubkey::find_program_address(bet_escrow_account_seeds, program_id);
+    // unpack state account data
+    let mut bet_state_account = Bet::from_account_info(bet_state_account_info)?;
+    let betting_market_account = BettingMarket::from_account_info(betting_market_account_info)?;
 
-        
-        // call token program to transfer ownership of bet escrow account to PDA
-        let transfer_authority_change_ix = spl_token::instruction::set_authority(
-            token_program_account_info.key,
-            bet_escrow_account_info.key,
-            Some(&bet_escrow_account_pda),
-            spl_token::instruction::AuthorityType::AccountOwner,
-            creator_main_account_info.key,
-            &[&creator_main_account_info.key],
-        )?;
-        msg!("Calling the token program to transfer ownership authority to PDA...");
-        invoke(
-            &transfer_authority_change_ix,
-            &[
-                bet_escrow_account_info.clone(),
-                creator_main_account_info.clone(),
-                token_program_account_info.clone(),
-            ],
-        )?;
+    // check it is correct betting market account
+    if bet_state_account.betting_market != *betting_market_account_info.key {
+        msg!("Incorrect betting market account: expected {}, got {}", bet_state_account.betting_market, betting_market_account_info.key);
+        return Err(BetError::WrongBettingMarket.into());
     }
 
-    // check valid pyth keys
-    validate_pyth_keys(
-        &betting_market_account.pyth_program_id,
-        pyth_oracle_product_account_info, 
-        pyth_oracle_price_account_info
-    )?;
+    // check it is correct oracle account
+    if *pyth_oracle_price_account_info.key != bet_state_account.pyth_oracle_price_account {
+        msg!("Invalid oracle account provided: expected {}, got {}", bet_state_account.pyth_oracle_price_account, pyth_oracle_price_account_info.key);
+        return Err(BetError::WrongOracleAccount.into());
+    }
 
-    // check tournament state account hasn't already been initialized
-    if bet_state_account.is_initialized == true {
-        return Err(BetError::AccountAlreadyInitialized.into())
+    // only an expired bet with accepts and none settled yet is extended. Rollover bets move their expiration each period
+    let secs_since_expiry = bet_state_account.secs_since_expiry(clock.unix_timestamp, clock.slot);
+    if secs_since_expiry < 0 {
+        msg!("Bet expires at {}", bet_state_account.expiration_time);
+        return Err(BetError::ExpiryNotExtendable.into());
+    }
+    if bet_state_account.accepted_count == 0 || bet_state_account.finalized_count > 0 {
+        msg!("Bet has {} accepted bets, {} finalized", bet_state_account.accepted_count, bet_state_account.finalized_count);
+        return Err(BetError::ExpiryNotExtendable.into());
+    }
+    if bet_state_account.rollover {
+        msg!("Rollover bets aren't extended");
+        return Err(BetError::ExpiryNotExtendable.into());
+    }
+    if bet_state_account.extensions_used >= betting_market_account.max_extensions {
+        msg!("Bet used {} of the betting market's {} expiry extensions", bet_state_account.extensions_used, betting_market_account.max_extensions);
+        return Err(BetError::ExpiryExtensionsExhausted.into());
     }
 
-    // get the price from oracle (used for variable odds)
+    // check the oracle published no valid price since expiration and has none now, so finalize has nothing to settle
+    // on. The last valid aggregate's publish time is estimated from its slot, and recorded observations are prices too
     let pyth_price_data = pyth_oracle_price_account_info.try_borrow_data()?;
-    let price_account: Price = *load_price( &pyth_price_data ).unwrap();
-    let price: PriceConf = price_account.get_current_price().unwrap();
-
-    // assert odds aren't less than 100
-    if odds < 100 {
-        return Err(BetError::InvalidOdds.into());
+    let price_account: Price = *load_price(&pyth_price_data).map_err(|_| BetError::InvalidAccountInput)?;
+    let last_publish_slot = price_account.last_slot;
+    if current_price(&price_account).is_ok()
+        || bet_state_account.settlement_observations_count > 0
+        || bet_state_account.secs_since_expiry(estimated_publish_time(clock, last_publish_slot), last_publish_slot) >= 0 {
+        msg!("Oracle published a valid price at slot {}, after the bet's expiration", last_publish_slot);
+        return Err(BetError::OraclePricePublished.into());
     }
 
-    // write the data to state
-    bet_state_account.is_initialized = true;
-    bet_state_account.creator_main_account = *creator_main_account_info.key;
-    bet_state_account.creator_payment_account = *creator_payment_account_info.key;
-    bet_state_account.bet_escrow_account = *bet_escrow_account_info.key;
-    bet_state_account.odds = odds;
-    bet_state_account.bet_size = bet_size;
-    bet_state_account.pyth_oracle_product_account = *pyth_oracle_product_account_info.key;
-    bet_state_account.pyth_oracle_price_account = *pyth_oracle_price_account_info.key;
-    bet_state_account.expiration_time = expiration_time;
-    bet_state_account.bet_direction = bet_direction;
-    bet_state_account.bet_price = bet_price;
-    bet_state_account.start_price = price.price;
-    bet_state_account.cancel_condition = cancel_condition;
-    bet_state_account.variable_odds = variable_odds;
-    bet_state_account.total_amount_accepted = 0;
-    bet_state_account.cancelled = false;
+    // push the expiration out, in the bet's expiry units
+    bet_state_account.expiration_time = bet_state_account.expiration_time
+        .checked_add(bet_state_account.expiry_kind.from_secs(betting_market_account.extension_secs))
+        .ok_or(BetError::AmountOverflow)?;
+    bet_state_account.extensions_used += 1;
+    bet_state_account.updated_at = clock.unix_timestamp;
+    msg!("Expiration extended to {}, extension {} of {}", bet_state_account.expiration_time, bet_state_account.extensions_used, betting_market_account.max_extensions);
+    emit_event(&BetEvent::ExpiryExtended {
+        bet: *bet_state_account_info.key,
+        expiration_time: bet_state_account.expiration_time,
+        extensions_used: bet_state_account.extensions_used,
+    })?;
 
-    // pack the bet_state_account
-    bet_state_account.serialize(&mut &mut bet_state_account_info.data.borrow_mut()[..])?;
-   
-    Ok(())
+    bet_state_account.save(bet_state_account_info)
 }
 
-pub fn process_accept_bet<'a>(
+// FinalizeBet, or with settle_only SettleBet: the first phase of a two phase token bet finalize. It pays only the
+// winner and records the commission and finalizer amounts for DistributeFees. With refund_only it is
+// RefundExpiredAcceptedBet, which only refunds a bet its oracle can't settle, the other two only settle
+pub fn process_finalize_bet<'a>(
     program_id: &'a Pubkey,
     accounts: &'a [AccountInfo<'a>],
-    bet_size: u64,
+    settle_only: bool,
+    refund_only: bool,
 ) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
-    let acceptor_main_account_info = next_account_info(account_info_iter)?;
-    let acceptor_payment_account_info = next_account_info(account_info_iter)?;
+    let finalizer_main_account_info = next_account_info(account_info_iter)?;
+    let finalizer_payment_account_info = next_account_info(account_info_iter)?;
+    let commission_fee_account_info = next_account_info(account_info_iter)?;
     let bet_state_account_info = next_account_info(account_info_iter)?;
-    let bet_escrow_account_info = next_account_info(account_info_iter)?;
     let accepted_bet_state_account_info = next_account_info(account_info_iter)?;
     let accepted_bet_escrow_account_info = next_account_info(account_info_iter)?;
+    let creator_payment_account_info = next_account_info(account_info_iter)?;
+    let acceptor_payment_account_info = next_account_info(account_info_iter)?;
     let betting_market_account_info = next_account_info(account_info_iter)?;
     let pyth_oracle_price_account_info = next_account_info(account_info_iter)?;
-    let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
     let token_program_account_info = next_account_info(account_info_iter)?;
-    spl_token::check_program_account(token_program_account_info.key)?;
+    check_token_program(token_program_account_info.key)?;
     let system_program_account_info = next_account_info(account_info_iter)?;
-    if check_id(system_program_account_info.key) == false {
+    if !check_id(system_program_account_info.key) {
         return Err(BetError::InvalidSystemProgram.into());
     }
-    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
     let pda_account_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let event_authority_account_info = next_account_info(account_info_iter)?;
+    let program_account_info = next_account_info(account_info_iter)?;
+    let feed_exposure_account_info = next_account_info(account_info_iter)?;
+    let creator_exposure_account_info = next_account_info(account_info_iter)?;
 
-    // check acceptor_main_account_info is the tx signer
-    if !acceptor_main_account_info.is_signer {
+    if !finalizer_main_account_info.is_signer {
         return Err(BetError::IncorrectSigner.into());
     }
 
-    // check program is owner of the accepted_bet_state_account_info
-    if accepted_bet_state_account_info.owner != program_id {
+    // check program is owner of the state accounts before reading them, finalize writes the bet's counts back
+    if bet_state_account_info.owner != program_id
+        || accepted_bet_state_account_info.owner != program_id
+        || betting_market_account_info.owner != program_id {
         return Err(BetError::IncorrectOwner.into());
     }
 
-    // check accepted_bet_state_account_info has enough lamports to be rent exempt
-    if !rent.is_exempt(accepted_bet_state_account_info.lamports(), accepted_bet_state_account_info.data_len()) {
-        return Err(BetError::NotRentExempt.into());
-    }
-
-    // unpack the bet and betting market accounts
-    let bet_state_account = Bet::from_account_info(bet_state_account_info)?;
+    // unpack the state accounts
+    let mut bet_state_account = Bet::from_account_info(bet_state_account_info)?;
+    let mut accepted_bet_state_account = AcceptedBet::from_account_info(accepted_bet_state_account_info)?;
     let betting_market_account = BettingMarket::from_account_info(betting_market_account_info)?;
 
+    // check bet hasn't already been finalized or settled
+    if accepted_bet_state_account.is_finalized() {
+        msg!("Bet already finalized");
+        return Err(BetError::BetFinalized.into());
+    }
+    if accepted_bet_state_account.fees_pending {
+        msg!("Bet already settled, DistributeFees finishes it");
+        return Err(BetError::BetSettled.into());
+    }
+    // SOL bets unwrap the whole escrow at once, so only token bets settle in two phases
+    if settle_only && bet_state_account.is_sol_payment() {
+        return Err(BetError::TwoPhaseSettlementTokenOnly.into());
+    }
+    // check the accepted bet belongs to the bet
+    if accepted_bet_state_account.bet != *bet_state_account_info.key {
+        msg!("Wrong bet account: expected {}, got {}", accepted_bet_state_account.bet, bet_state_account_info.key);
+        return Err(BetError::WrongBetAccount.into());
+    }
     // check it is correct betting market account
     if bet_state_account.betting_market != *betting_market_account_info.key {
-        msg!("Incorrect betting market account");
-        return Err(BetError::InvalidAccounts.into());
+        msg!("Wrong betting market account: expected {}, got {}", bet_state_account.betting_market, betting_market_account_info.key);
+        return Err(BetError::WrongBettingMarket.into());
     }
-
-    // check it is correct escrow account
-    if bet_state_account.bet_escrow_account != *bet_escrow_account_info.key {
-        msg!("Incorrect escrow account");
+    // check it is correct pyth oracle account
+    if bet_state_account.pyth_oracle_price_account != *pyth_oracle_price_account_info.key {
+        msg!("Wrong pyth price account: expected {}, got {}", bet_state_account.pyth_oracle_price_account, pyth_oracle_price_account_info.key);
+        return Err(BetError::WrongOracleAccount.into());
+    }
+    // check it is correct commission fee account, SOL bets pay the market's SOL commission account and token bets pay
+    // the fee wallet's ATA of the bet's mint
+    let commission_fee_account = if bet_state_account.is_sol_payment() {
+        betting_market_account.sol_commission_destination()
+    } else {
+        get_associated_token_address(&betting_market_account.fee_commission_account, &bet_state_account.payment_mint, token_program_account_info.key)
+    };
+    if commission_fee_account != *commission_fee_account_info.key {
+        msg!("Wrong commission fee account: expected {}, got {}", commission_fee_account, commission_fee_account_info.key);
         return Err(BetError::InvalidAccounts.into());
     }
-
-    // check bet hasn't been cancelled
-    if bet_state_account.cancelled == true {
-        return Err(BetError::BetCancelled.into());
+    // check it is correct creator account
+    if bet_state_account.creator_payment_account != *creator_payment_account_info.key {
+        msg!("Wrong bet creator payment account: expected {}, got {}", bet_state_account.creator_payment_account, creator_payment_account_info.key);
+        return Err(BetError::WrongPaymentAccount.into());
     }
-
-    // check it is correct oracle account
-    if *pyth_oracle_price_account_info.key != bet_state_account.pyth_oracle_price_account {
-        msg!("Invalid oracle account provided.");
-        return Err(BetError::InvalidAccounts.into());
+    // check it is correct acceptor payment account
+    if accepted_bet_state_account.acceptor_payment_account != *acceptor_payment_account_info.key {
+        msg!("Wrong bet acceptor payment account: expected {}, got {}", accepted_bet_state_account.acceptor_payment_account, acceptor_payment_account_info.key);
+        return Err(BetError::WrongPaymentAccount.into());
     }
-    // get the current price of the asset
-    let pyth_price_data = pyth_oracle_price_account_info.try_borrow_data()?;
-    let price_account: Price = *load_price( &pyth_price_data ).unwrap();
-    let price: PriceConf = price_account.get_current_price().unwrap();
+    // check it is correct escrow account
+    if accepted_bet_state_account.accepted_bet_escrow_account != *accepted_bet_escrow_account_info.key {
+        msg!("Wrong escrow account: expected {}, got {}", accepted_bet_state_account.accepted_bet_escrow_account, accepted_bet_escrow_account_info.key);
+        return Err(BetError::WrongEscrowAccount.into());
+    }
+    // check the token program is the one that owns the escrow
+    if accepted_bet_escrow_account_info.owner != token_program_account_info.key {
+        msg!("Incorrect token program: expected {}, got {}", accepted_bet_escrow_account_info.owner, token_program_account_info.key);
+        return Err(BetError::InvalidTokenProgram.into());
+    }
+    token::check_not_frozen(accepted_bet_escrow_account_info)?;
 
-    // check current price is valid for bet to be accepted
-    if price.price > bet_state_account.cancel_condition.above_price || price.price < bet_state_account.cancel_condition.below_price {
-        msg!("Price moved beyond cancel condition prices.");
-        return Err(BetError::BetNoLongerValid.into());
+    // check time is after bet expiration time, allowing for the market's clock tolerance
+    let secs_since_expiry = bet_state_account.secs_since_expiry(clock.unix_timestamp, clock.slot);
+    if secs_since_expiry < betting_market_account.clock_tolerance_secs.saturating_neg() {
+        msg!("Time is before bet expiration time");
+        return Err(BetError::BeforeExpiryTime.into());
     }
 
-    // check time isn't too late
-    if clock.unix_timestamp > bet_state_account.cancel_condition.time || clock.unix_timestamp > bet_state_account.expiration_time {
-        msg!("Time too late to accept bet.");
-        return Err(BetError::BetNoLongerValid.into());
+    // check only the bet's parties finalize during the market's exclusivity window after expiration
+    if secs_since_expiry < betting_market_account.finalize_exclusivity_secs
+        && *finalizer_main_account_info.key != bet_state_account.creator_main_account
+        && *finalizer_main_account_info.key != accepted_bet_state_account.acceptor_main_account {
+        msg!("Only the creator or acceptor can finalize for {} seconds after expiration", betting_market_account.finalize_exclusivity_secs);
+        return Err(BetError::FinalizeReservedForParties.into());
     }
 
-    // calculate the odds given the current price and variable odds condition
-    let bet_odds: i64;
-    if let Some(variable_odds) = bet_state_account.variable_odds {
-        let odds_change: i64;
-        let price_change: i64;
-        if bet_state_account.bet_price > bet_state_account.start_price {
-            // price starts below bet price, so when price increases, odds decrease
-            price_change = price.price - bet_state_account.start_price;
-            odds_change = 0 - (price_change / variable_odds);
-        } else {
-            // price starts above bet price, so when price increases the odds increase
-            price_change = price.price - bet_state_account.start_price;
-            odds_change = price_change / variable_odds;
+    // get the settlement price, the median of the recorded observations or a single read of the pyth oracle. A feed
+    // that changed since the bet was created can't settle it, after ORACLE_FEED_CHANGED_REFUND_SECS past expiration
+    // RefundExpiredAcceptedBet gives each party their stake back instead. Nor can a feed that isn't trading, finalize
+    // can be retried until ORACLE_UNAVAILABLE_REFUND_SECS past expiration and the bet is refunded after
+    let (settlement_price, publish_slot, oracle_feed_changed, oracle_unavailable) = if betting_market_account.settles_on_observations() {
+        if refund_only {
+            msg!("Bet settles on recorded observations, finalize it instead");
+            return Err(BetError::NotRefundable.into());
+        }
+        if !settlement_window_closed(&bet_state_account, &betting_market_account, secs_since_expiry) {
+            msg!("Settlement window is open for {} seconds after expiration", betting_market_account.settlement_window_secs);
+            return Err(BetError::SettlementWindowOpen.into());
         }
-        bet_odds = bet_state_account.odds + odds_change;
+        if bet_state_account.settlement_observations_count < betting_market_account.min_settlement_observations {
+            msg!("Bet has {} settlement observations, the betting market requires {}", bet_state_account.settlement_observations_count, betting_market_account.min_settlement_observations);
+            return Err(BetError::NotEnoughObservations.into());
+        }
+        (median_price(bet_state_account.settlement_observations()), latest_observation_slot(bet_state_account.settlement_observations()), false, false)
     } else {
-        bet_odds = bet_state_account.odds; // no variable odds so bet odds are unchanged
-    }
+        let pyth_price_data = pyth_oracle_price_account_info.try_borrow_data()?;
+        let price_account: Price = *load_price(&pyth_price_data).map_err(|_| BetError::InvalidAccountInput)?;
+        let oracle_feed_changed = match check_oracle_feed(&bet_state_account, &price_account) {
+            Ok(()) => false,
+            Err(error) if !refund_only || secs_since_expiry < ORACLE_FEED_CHANGED_REFUND_SECS => {
+                if secs_since_expiry >= ORACLE_FEED_CHANGED_REFUND_SECS {
+                    msg!("Oracle feed changed, RefundExpiredAcceptedBet refunds the bet");
+                }
+                return Err(error);
+            },
+            Err(_) => {
+                msg!("Oracle feed changed, refunding both parties' stakes");
+                true
+            }
+        };
+        let oracle_unavailable = match current_price(&price_account) {
+            Ok(_) => false,
+            Err(error) if !refund_only || secs_since_expiry < ORACLE_UNAVAILABLE_REFUND_SECS => {
+                if secs_since_expiry >= ORACLE_UNAVAILABLE_REFUND_SECS {
+                    msg!("Oracle price unavailable, RefundExpiredAcceptedBet refunds the bet");
+                }
+                return Err(error);
+            },
+            Err(_) => {
+                msg!("Oracle price unavailable, refunding both parties' stakes");
+                true
+            }
+        };
+        if refund_only && !oracle_feed_changed && !oracle_unavailable {
+            msg!("Oracle can settle the bet, finalize it instead");
+            return Err(BetError::NotRefundable.into());
+        }
+        (price_account.agg.price, price_account.agg.pub_slot, oracle_feed_changed, oracle_unavailable)
+    };
+    let refund = oracle_feed_changed || oracle_unavailable;
 
-    // assert odds > 100
-    if bet_odds < 100 {
-        return Err(BetError::InvalidOdds.into());
+    // a price from after the preferred window still settles, but the finalizer's reward goes to the winner
+    let degraded = degraded_settlement(&bet_state_account, &betting_market_account, clock, publish_slot);
+    if degraded {
+        msg!("Degraded settlement: price published after the preferred settlement window, no finalizer reward");
     }
 
-    // given the odds, calculate how much the acceptor must pay
-    let acceptor_payment_amount: u64 = bet_size * ((bet_odds - 100) as u64) / 100;
-
-    // send payment from both escrow account and acceptor payment account
-    if betting_market_account.sol_payment {
+    // determine the bet winner, a refund pays the creator's stake as the winner amount and the acceptor's as the loser's.
+    // A tie is refunded too if the market's tie policy says so
+    let outcome = if refund {
+        Outcome::Refund
+    } else {
+        determine_winner(accepted_bet_state_account.creator_direction, settlement_price, bet_state_account.bet_price, betting_market_account.tie_policy)?
+    };
+    if outcome == Outcome::Refund && !refund {
+        msg!("Settled at the bet price, refunding both parties' stakes");
+    }
+    let refund = outcome == Outcome::Refund;
+    let creator_is_winner = outcome != Outcome::AcceptorWins;
+    // a refund charges no commission
+    let fee_exempt = bet_state_account.fee_exempt || refund;
+    let (mut bet_winner_account_info, bet_loser_account_info) = if creator_is_winner {
+        (creator_payment_account_info, acceptor_payment_account_info)
+    } else {
+        (acceptor_payment_account_info, creator_payment_account_info)
+    };
 
-        // check program is owner of the accepted bet escrow account
-        if accepted_bet_escrow_account_info.key != program_id {
-            msg!("Program is not owner of the bet escrow account");
-            return Err(BetError::IncorrectOwner.into());
+    // if the winner's payment account was closed, or is no longer a token account of the mint, pay into their ATA
+    // instead (created by the finalizer if missing)
+    let mut created_winner_ata = false;
+    if !bet_state_account.is_sol_payment()
+        && !token::is_payout_account(bet_winner_account_info, token_program_account_info.key, &bet_state_account.payment_mint) {
+        msg!("Winner payment account {} isn't a token account of the mint, paying their ATA", bet_winner_account_info.key);
+        let payment_mint_account_info = next_account_info(account_info_iter)?;
+        let winner_main_account_info = next_account_info(account_info_iter)?;
+        let winner_ata_account_info = next_account_info(account_info_iter)?;
+        let associated_token_program_account_info = next_account_info(account_info_iter)?;
+        if *associated_token_program_account_info.key != spl_associated_token_account::id() {
+            return Err(BetError::InvalidAccounts.into());
+        }
+        if bet_state_account.payment_mint != *payment_mint_account_info.key {
+            msg!("Incorrect mint: expected {}, got {}", bet_state_account.payment_mint, payment_mint_account_info.key);
+            return Err(BetError::InvalidMint.into());
+        }
+        let winner_main_account = if creator_is_winner {
+            bet_state_account.creator_main_account
+        } else {
+            accepted_bet_state_account.acceptor_main_account
+        };
+        if winner_main_account != *winner_main_account_info.key {
+            msg!("Wrong winner main account: expected {}, got {}", winner_main_account, winner_main_account_info.key);
+            return Err(BetError::WrongPaymentAccount.into());
+        }
+        let winner_ata = get_associated_token_address(&winner_main_account, payment_mint_account_info.key, token_program_account_info.key);
+        if winner_ata != *winner_ata_account_info.key {
+            msg!("Wrong winner token account: expected {}, got {}", winner_ata, winner_ata_account_info.key);
+            return Err(BetError::WrongPaymentAccount.into());
         }
 
-        // add lamports from escrow account program owns to accepted escrow account
-        **bet_escrow_account_info.lamports.borrow_mut() = bet_escrow_account_info.lamports().checked_sub(bet_size).ok_or(BetError::AmountUnderflow)?;
-        **accepted_bet_escrow_account_info.lamports.borrow_mut() = accepted_bet_escrow_account_info.lamports().checked_add(bet_size).ok_or(BetError::AmountOverflow)?;
+        // create the ATA if it doesn't exist yet, the finalizer pays the rent
+        if winner_ata_account_info.lamports() == 0 {
+            msg!("Calling the associated token program to create the winner's token account...");
+            invoke(
+                &create_associated_token_account(
+                    finalizer_main_account_info.key,
+                    &winner_main_account,
+                    payment_mint_account_info.key,
+                    token_program_account_info.key
+                ),
+                &[
+                    finalizer_main_account_info.clone(),
+                    winner_ata_account_info.clone(),
+                    winner_main_account_info.clone(),
+                    payment_mint_account_info.clone(),
+                    system_program_account_info.clone(),
+                    token_program_account_info.clone(),
+                    associated_token_program_account_info.clone(),
+                ],
+            )?;
+            created_winner_ata = true;
+        }
+        bet_winner_account_info = winner_ata_account_info;
+    }
 
-        // system program to transfer lamports from acceptor_payment_account_info
-        let transfer_lamports_from_acceptor_ix = system_instruction::transfer(
-            &acceptor_payment_account_info.key,
-            &accepted_bet_escrow_account_info.key,
-            acceptor_payment_amount
-        );
+    // the first token bet of a mint to pay commission creates the fee wallet's ATA of the mint, the finalizer pays
+    // the rent
+    let mut created_commission_ata = false;
+    if !bet_state_account.is_sol_payment() && !fee_exempt && commission_fee_account_info.lamports() == 0 {
+        msg!("Commission account {} doesn't exist yet, creating it", commission_fee_account_info.key);
+        let payment_mint_account_info = next_account_info(account_info_iter)?;
+        let commission_authority_account_info = next_account_info(account_info_iter)?;
+        let associated_token_program_account_info = next_account_info(account_info_iter)?;
+        if *associated_token_program_account_info.key != spl_associated_token_account::id() {
+            return Err(BetError::InvalidAccounts.into());
+        }
+        if bet_state_account.payment_mint != *payment_mint_account_info.key {
+            msg!("Incorrect mint: expected {}, got {}", bet_state_account.payment_mint, payment_mint_account_info.key);
+            return Err(BetError::InvalidMint.into());
+        }
+        if betting_market_account.fee_commission_account != *commission_authority_account_info.key {
+            msg!("Wrong commission authority: expected {}, got {}", betting_market_account.fee_commission_account, commission_authority_account_info.key);
+            return Err(BetError::InvalidAccounts.into());
+        }
+        msg!("Calling the associated token program to create the commission token account...");
         invoke(
-            &transfer_lamports_from_acceptor_ix,
+            &create_associated_token_account(
+                finalizer_main_account_info.key,
+                commission_authority_account_info.key,
+                payment_mint_account_info.key,
+                token_program_account_info.key
+            ),
             &[
+                finalizer_main_account_info.clone(),
+                commission_fee_account_info.clone(),
+                commission_authority_account_info.clone(),
+                payment_mint_account_info.clone(),
                 system_program_account_info.clone(),
-                acceptor_payment_account_info.clone(),
-                accepted_bet_state_account_info.clone()
-            ]
+                token_program_account_info.clone(),
+                associated_token_program_account_info.clone(),
+            ],
         )?;
+        created_commission_ata = true;
+    }
+    // the accepted escrow's rent reimburses a finalizer that created an ATA, it covers one token account
+    let reimburse_finalizer = created_winner_ata || created_commission_ata;
+
+    // get pda address, bump seed and seeds (the accepted escrow authority)
+    let (_, bump_seed) = find_escrow_authority_versioned(
+        program_id,
+        bet_state_account.escrow_seeds_version,
+        betting_market_account_info.key,
+        accepted_bet_state_account_info.key
+    );
+    let bet_escrow_bump_seed = [bump_seed];
+    let (bet_escrow_account_pda, bet_escrow_transfer_seeds) = escrow_authority_signer(
+        program_id,
+        bet_state_account.escrow_seeds_version,
+        betting_market_account_info.key,
+        accepted_bet_state_account_info.key,
+        &bet_escrow_bump_seed
+    )?;
+    if bet_escrow_account_pda != *pda_account_info.key {
+        msg!("Incorrect escrow authority: expected {}, got {}", bet_escrow_account_pda, pda_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+
+    // calculate commission amount, the loser gets back what the payout curve leaves of their stake and the winner
+    // gets the rest of the escrow (creator stake + acceptor payment)
+    let accepted_bet_escrow_account = TokenAccount::unpack_from_slice(&accepted_bet_escrow_account_info.data.borrow())?;
+    let loser_stake = if creator_is_winner {
+        accepted_bet_escrow_account.amount.saturating_sub(accepted_bet_state_account.bet_size)
     } else {
-        // get the pda address and bump seed (derived from the bet_escrow_account_info Pubkey and prefix "yoyobet")
-        let bet_escrow_account_seeds = &[
-            PREFIX.as_bytes(),
-            bet_escrow_account_info.key.as_ref(),
-        ];
-        let (bet_escrow_account_pda, bump_seed) = Pubkey::find_program_address(bet_escrow_account_seeds, program_id);
+        accepted_bet_state_account.bet_size
+    };
+    let loser_refund = if refund {
+        loser_stake
+    } else {
+        loser_stake - curve_winnings(bet_state_account.payout_curve, loser_stake, settlement_price, bet_state_account.bet_price)?
+    };
+    let (commission_amount, finalizer_amount, winner_amount, loser_amount) =
+        settlement_amounts(
+            accepted_bet_escrow_account.amount,
+            accepted_bet_state_account.bet_size,
+            if degraded || refund {
+                0
+            } else {
+                finalizer_reward_bps(&betting_market_account, bet_state_account.secs_since_expiry(clock.unix_timestamp, clock.slot))
+            },
+            loser_refund,
+            fee_exempt
+        )?;
 
-        // set transfer authority of the accepted bet escrow to PDA
-        let transfer_authority_change_ix = spl_token::instruction::set_authority(
+    // the winner's ATA fallback and the commission ATA are in place, check every destination can take its payout.
+    // SOL bets also return the escrow rent to the acceptor, whether they won or lost
+    token::validate_payout_destinations(
+        &[
+            ("commission_fee", commission_fee_account_info, if settle_only { 0 } else { commission_amount }),
+            ("finalizer_payment", finalizer_payment_account_info, if settle_only { 0 } else { finalizer_amount }),
+            ("winner_payment", bet_winner_account_info, winner_amount),
+            ("loser_payment", bet_loser_account_info, loser_amount),
+            ("acceptor_payment", acceptor_payment_account_info, if bet_state_account.is_sol_payment() {
+                accepted_bet_escrow_account_info.lamports().saturating_sub(accepted_bet_escrow_account.amount)
+            } else {
+                0
+            }),
+        ],
+        &bet_state_account.payment_mint,
+        token_program_account_info.key
+    )?;
+
+    // send payments to commission, winner and finalizer
+    if bet_state_account.is_sol_payment() {
+        // unwrap by closing the accepted escrow to its authority PDA
+        msg!("Calling token program to close the accepted escrow");
+        let close_escrow_ix = token::close_account(
             token_program_account_info.key,
             accepted_bet_escrow_account_info.key,
-            Some(&bet_escrow_account_pda),
-            spl_token::instruction::AuthorityType::AccountOwner,
-            acceptor_main_account_info.key,
-            &[&acceptor_main_account_info.key],
+            &bet_escrow_account_pda,
+            &bet_escrow_account_pda
         )?;
-        msg!("Calling the token program to transfer ownership authority to PDA...");
-        invoke(
-            &transfer_authority_change_ix,
+        invoke_signed(
+            &close_escrow_ix,
             &[
-                accepted_bet_escrow_account_info.clone(),
-                acceptor_main_account_info.clone(),
                 token_program_account_info.clone(),
+                accepted_bet_escrow_account_info.clone(),
+                pda_account_info.clone()
             ],
+            &[&bet_escrow_transfer_seeds[..]]
         )?;
 
-        // need the bump seed for the signer seeds for invoke signed
-        let bet_escrow_account_transfer_seeds = &[
-            PREFIX.as_bytes(),
-            bet_escrow_account_info.key.as_ref(),
-            &[bump_seed]
+        // pay out the lamports from the PDA, the leftover is the escrow rent the acceptor paid
+        msg!("Calling system program to transfer lamports to commission, finalizer, winner and loser accounts");
+        let payouts = [
+            (commission_fee_account_info, commission_amount),
+            (finalizer_payment_account_info, finalizer_amount),
+            (bet_winner_account_info, winner_amount),
+            (bet_loser_account_info, loser_amount),
+            (acceptor_payment_account_info, pda_account_info.lamports() - accepted_bet_escrow_account.amount),
         ];
+        for (destination_account_info, amount) in payouts.iter() {
+            invoke_signed(
+                &system_instruction::transfer(&bet_escrow_account_pda, destination_account_info.key, *amount),
+                &[
+                    system_program_account_info.clone(),
+                    pda_account_info.clone(),
+                    (*destination_account_info).clone()
+                ],
+                &[&bet_escrow_transfer_seeds[..]]
+            )?;
+        }
+    } else if settle_only {
+        // transfer tokens to winner payment account, the fees stay in the escrow for DistributeFees
+        msg!("Calling token program to transfer tokens to winner account");
+        invoke_signed(
+            &token::transfer(
+                token_program_account_info.key,
+                accepted_bet_escrow_account_info.key,
+                bet_winner_account_info.key,
+                &bet_escrow_account_pda,
+                winner_amount
+            )?,
+            &[
+                token_program_account_info.clone(),
+                accepted_bet_escrow_account_info.clone(),
+                bet_winner_account_info.clone(),
+                pda_account_info.clone()
+            ],
+            &[&bet_escrow_transfer_seeds[..]]
+        )?;
+
+        // transfer the linear payout curve's refund to the loser payment account
+        if loser_amount > 0 {
+            msg!("Calling token program to transfer tokens to loser account");
+            invoke_signed(
+                &token::transfer(
+                    token_program_account_info.key,
+                    accepted_bet_escrow_account_info.key,
+                    bet_loser_account_info.key,
+                    &bet_escrow_account_pda,
+                    loser_amount
+                )?,
+                &[
+                    token_program_account_info.clone(),
+                    accepted_bet_escrow_account_info.clone(),
+                    bet_loser_account_info.clone(),
+                    pda_account_info.clone()
+                ],
+                &[&bet_escrow_transfer_seeds[..]]
+            )?;
+        }
+    } else {
+        // transfer tokens to commission account, fee exempt bets have none
+        if commission_amount > 0 {
+            msg!("Calling token program to transfer tokens to commission account");
+            let transfer_tokens_from_escrow_to_commission_ix = token::transfer(
+                token_program_account_info.key, 
+                accepted_bet_escrow_account_info.key, 
+                commission_fee_account_info.key, 
+                &bet_escrow_account_pda, 
+                commission_amount
+            )?;
+            invoke_signed(
+                &transfer_tokens_from_escrow_to_commission_ix, 
+                &[
+                    token_program_account_info.clone(),
+                    accepted_bet_escrow_account_info.clone(),
+                    commission_fee_account_info.clone(),
+                    pda_account_info.clone()
+                ], 
+                &[&bet_escrow_transfer_seeds[..]]
+            )?;
+        }
 
-        // transfer tokens from bet_escrow_account
-        let transfer_tokens_from_escrow_ix = spl_token::instruction::transfer(
+        // transfer tokens to winner payment account
+        msg!("Calling token program to transfer tokens to winner account");
+        let transfer_tokens_from_escrow_to_winner_ix = token::transfer(
             token_program_account_info.key, 
-            bet_escrow_account_info.key, 
-            accepted_bet_escrow_account_info.key,
+            accepted_bet_escrow_account_info.key, 
+            bet_winner_account_info.key, 
             &bet_escrow_account_pda, 
-            &[&bet_escrow_account_pda], 
-            bet_size
+            winner_amount
         )?;
         invoke_signed(
-            &transfer_tokens_from_escrow_ix, 
+            &transfer_tokens_from_escrow_to_winner_ix, 
             &[
                 token_program_account_info.clone(),
-                bet_escrow_account_info.clone(),
                 accepted_bet_escrow_account_info.clone(),
+                bet_winner_account_info.clone(),
                 pda_account_info.clone()
-            ],
-            &[bet_escrow_account_transfer_seeds]
+            ], 
+            &[&bet_escrow_transfer_seeds[..]]
         )?;
 
-        // transfer tokens from acceptor_payment_account_info
-        let transfer_tokens_from_acceptor_ix = spl_token::instruction::transfer(
-            &token_program_account_info.key, 
-            &acceptor_payment_account_info.key,
-            &accepted_bet_escrow_account_info.key, 
-            &acceptor_main_account_info.key, 
-            &[&acceptor_main_account_info.key], 
-            acceptor_payment_amount
+        // transfer the linear payout curve's refund to the loser payment account
+        if loser_amount > 0 {
+            msg!("Calling token program to transfer tokens to loser account");
+            invoke_signed(
+                &token::transfer(
+                    token_program_account_info.key,
+                    accepted_bet_escrow_account_info.key,
+                    bet_loser_account_info.key,
+                    &bet_escrow_account_pda,
+                    loser_amount
+                )?,
+                &[
+                    token_program_account_info.clone(),
+                    accepted_bet_escrow_account_info.clone(),
+                    bet_loser_account_info.clone(),
+                    pda_account_info.clone()
+                ],
+                &[&bet_escrow_transfer_seeds[..]]
+            )?;
+        }
+
+        // transfer tokens to finalizer payment account
+        msg!("Calling token program to transfer tokens to finalizer account");
+        let transfer_tokens_from_escrow_to_finalizer_ix = token::transfer(
+            token_program_account_info.key, 
+            accepted_bet_escrow_account_info.key, 
+            finalizer_payment_account_info.key, 
+            &bet_escrow_account_pda, 
+            finalizer_amount
         )?;
-        invoke(
-            &transfer_tokens_from_acceptor_ix,
+        invoke_signed(
+            &transfer_tokens_from_escrow_to_finalizer_ix, 
             &[
                 token_program_account_info.clone(),
-                acceptor_payment_account_info.clone(),
                 accepted_bet_escrow_account_info.clone(),
-                acceptor_main_account_info.clone()
-            ]
+                finalizer_payment_account_info.clone(),
+                pda_account_info.clone()
+            ], 
+            &[&bet_escrow_transfer_seeds[..]]
         )?;
+
+        // reimburse the finalizer for the ATA rent with the rent of the now empty accepted escrow
+        if reimburse_finalizer {
+            msg!("Calling token program to close the accepted escrow to the finalizer");
+            let close_escrow_ix = token::close_account(
+                token_program_account_info.key,
+                accepted_bet_escrow_account_info.key,
+                finalizer_main_account_info.key,
+                &bet_escrow_account_pda
+            )?;
+            invoke_signed(
+                &close_escrow_ix,
+                &[
+                    token_program_account_info.clone(),
+                    accepted_bet_escrow_account_info.clone(),
+                    finalizer_main_account_info.clone(),
+                    pda_account_info.clone()
+                ],
+                &[&bet_escrow_transfer_seeds[..]]
+            )?;
+        }
     }
 
-    // write data to accepted bet state account
-    let mut accepted_bet_state_account = AcceptedBet::from_account_info(&accepted_bet_state_account_info)?;
-    accepted_bet_state_account.bet = *bet_state_account_info.key;
-    accepted_bet_state_account.accepted_bet_escrow_account = *accepted_bet_escrow_account_info.key;
-    accepted_bet_state_account.acceptor_main_account = *acceptor_main_account_info.key;
-    accepted_bet_state_account.acceptor_payment_account = *acceptor_payment_account_info.key;
-    accepted_bet_state_account.bet_size = bet_size;
-    accepted_bet_state_account.odds = bet_odds;
-    accepted_bet_state_account.finalized = false;
+    // update accepted bet state, close the position, and record what DistributeFees pays after a settle.
+    // An ATA created by the settler is reimbursed with the escrow's rent, otherwise it goes back to the acceptor
+    accepted_bet_state_account.set_status(if refund { PositionStatus::Refunded } else { PositionStatus::Settled })?;
+    if settle_only {
+        accepted_bet_state_account.fees_pending = true;
+        accepted_bet_state_account.commission_amount = commission_amount;
+        accepted_bet_state_account.finalizer_amount = finalizer_amount;
+        accepted_bet_state_account.finalizer_payment_account = *finalizer_payment_account_info.key;
+        accepted_bet_state_account.escrow_rent_recipient = if reimburse_finalizer {
+            *finalizer_main_account_info.key
+        } else {
+            accepted_bet_state_account.acceptor_main_account
+        };
+    }
+
+    // pack state account
+    accepted_bet_state_account.save(accepted_bet_state_account_info)?;
+
+    // the settled position leaves the feed's matched notional
+    if bet_state_account.feed_exposure_tracked {
+        let mut feed_exposure_account = load_feed_exposure(program_id, feed_exposure_account_info, &bet_state_account)?;
+        feed_exposure_account.remove_matched(position_payout(accepted_bet_state_account.bet_size, accepted_bet_state_account.odds)?);
+        feed_exposure_account.save(feed_exposure_account_info)?;
+    }
+
+    let creator_stake = accepted_bet_state_account.bet_size;
+    let acceptor_stake = accepted_bet_escrow_account.amount.saturating_sub(creator_stake);
+    let (creator_payout, acceptor_payout) = if creator_is_winner { (winner_amount, loser_amount) } else { (loser_amount, winner_amount) };
+
+    // record the outcome for both parties in markets with settlement receipts, the finalizer pays the rent
+    if betting_market_account.settlement_receipts {
+        let rent_sysvar_account_info = next_account_info(account_info_iter)?;
+        let receipt_account_info = next_account_info(account_info_iter)?;
+        write_receipt(
+            program_id,
+            receipt_account_info,
+            rent_sysvar_account_info,
+            system_program_account_info,
+            finalizer_main_account_info,
+            Receipt {
+                is_initialized: true,
+                betting_market: *betting_market_account_info.key,
+                bet: *bet_state_account_info.key,
+                accepted_bet: *accepted_bet_state_account_info.key,
+                creator_main_account: bet_state_account.creator_main_account,
+                acceptor_main_account: accepted_bet_state_account.acceptor_main_account,
+                creator_direction: accepted_bet_state_account.creator_direction,
+                creator_stake,
+                acceptor_stake,
+                settlement_price,
+                creator_wins: creator_is_winner,
+                refunded: refund,
+                creator_payout,
+                acceptor_payout,
+                settled_at: clock.unix_timestamp,
+                bump_seed: 0,
+            }
+        )?;
+    }
+
+    // update the parties' stats if the finalizer passed their accounts, a refund isn't a win or a loss
+    let stats_accounts = if refund { None } else { next_account_info(account_info_iter).ok() };
+    if let Some(rent_sysvar_account_info) = stats_accounts {
+        let creator_stats_account_info = next_account_info(account_info_iter)?;
+        let acceptor_stats_account_info = next_account_info(account_info_iter)?;
+        let parties = [
+            (creator_stats_account_info, bet_state_account.creator_main_account, creator_is_winner, creator_stake, creator_payout),
+            (acceptor_stats_account_info, accepted_bet_state_account.acceptor_main_account, !creator_is_winner, acceptor_stake, acceptor_payout),
+        ];
+        for (stats_account_info, user, won, stake, payout) in parties.iter() {
+            update_user_stats(
+                program_id,
+                stats_account_info,
+                rent_sysvar_account_info,
+                system_program_account_info,
+                finalizer_main_account_info,
+                betting_market_account_info.key,
+                user,
+                *won,
+                *stake,
+                *payout
+            )?;
+        }
+    }
+
+    // pay the finalizer an even share of the keeper bounty among the accepts that haven't taken theirs, from the
+    // lamports the bet state account holds above its rent
+    let unpaid_accepts = bet_state_account.accepted_count.saturating_sub(bet_state_account.keeper_bounty_paid_count).max(1);
+    let keeper_bounty_amount = bet_state_account.keeper_bounty_lamports / unpaid_accepts as u64;
+    if keeper_bounty_amount > 0 {
+        msg!("Paying the finalizer {} lamports of the keeper bounty", keeper_bounty_amount);
+        debit_lamports(bet_state_account_info, finalizer_main_account_info, keeper_bounty_amount)?;
+        bet_state_account.keeper_bounty_lamports -= keeper_bounty_amount;
+    }
+    bet_state_account.keeper_bounty_paid_count = bet_state_account.keeper_bounty_paid_count.saturating_add(1);
+
+    // count the finalized position on the bet, a settled one counts once DistributeFees finishes it
+    if !settle_only {
+        bet_state_account.finalized_count = bet_state_account.finalized_count.checked_add(1).ok_or(BetError::AmountOverflow)?;
+    }
+    // with its last accept finalized the bet no longer counts towards the creator's open bets. Rollover bets re-open for
+    // the next period, only cancelling releases them
+    if !bet_state_account.rollover && bet_state_account.finalized_count == bet_state_account.accepted_count {
+        release_open_bet(program_id, &mut bet_state_account, creator_exposure_account_info)?;
+    }
+    bet_state_account.mark_expired()?;
+    bet_state_account.updated_at = clock.unix_timestamp;
+    bet_state_account.last_interaction_slot = clock.slot;
+    bet_state_account.save(bet_state_account_info)?;
 
-    // pack the tournament_state_account
-    accepted_bet_state_account.serialize(&mut &mut accepted_bet_state_account_info.data.borrow_mut()[..])?;
+    emit_event_cpi(program_id, event_authority_account_info, program_account_info, &BetEvent::BetFinalized {
+        bet: *bet_state_account_info.key,
+        accepted_bet: *accepted_bet_state_account_info.key,
+        price: settlement_price,
+        creator_wins: creator_is_winner,
+        winner_payment_account: *bet_winner_account_info.key,
+        winner_amount,
+        loser_amount,
+        commission_amount,
+        finalizer_amount,
+        degraded,
+        fee_exempt: bet_state_account.fee_exempt,
+        keeper_bounty_amount,
+        oracle_feed_changed,
+        oracle_unavailable,
+        updated_at: bet_state_account.updated_at,
+        status: accepted_bet_state_account.status,
+    })?;
 
     Ok(())
 }
 
-pub fn process_cancel_bet<'a>(
+pub fn process_distribute_fees<'a>(
     program_id: &'a Pubkey,
     accounts: &'a [AccountInfo<'a>],
 ) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
-    let creator_main_account_info = next_account_info(account_info_iter)?;
-    let creator_payment_account_info = next_account_info(account_info_iter)?;
     let bet_state_account_info = next_account_info(account_info_iter)?;
-    let bet_escrow_account_info = next_account_info(account_info_iter)?;
+    let accepted_bet_state_account_info = next_account_info(account_info_iter)?;
+    let accepted_bet_escrow_account_info = next_account_info(account_info_iter)?;
+    let commission_fee_account_info = next_account_info(account_info_iter)?;
+    let finalizer_payment_account_info = next_account_info(account_info_iter)?;
+    let escrow_rent_recipient_account_info = next_account_info(account_info_iter)?;
     let betting_market_account_info = next_account_info(account_info_iter)?;
     let token_program_account_info = next_account_info(account_info_iter)?;
-    spl_token::check_program_account(token_program_account_info.key)?;
-    let system_program_account_info = next_account_info(account_info_iter)?;
-    if check_id(system_program_account_info.key) == false {
-        return Err(BetError::InvalidSystemProgram.into());
-    }
+    check_token_program(token_program_account_info.key)?;
     let pda_account_info = next_account_info(account_info_iter)?;
+    let clock = Clock::get()?;
 
-    // check creator main account is signer
-    if !creator_main_account_info.is_signer {
-        return Err(BetError::IncorrectSigner.into());
+    // check program is owner of the state accounts before reading them, distribute writes the bet and accepted bet back
+    if bet_state_account_info.owner != program_id
+        || accepted_bet_state_account_info.owner != program_id
+        || betting_market_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
     }
 
-    // unpack state account data
+    // unpack the state accounts
     let mut bet_state_account = Bet::from_account_info(bet_state_account_info)?;
+    let mut accepted_bet_state_account = AcceptedBet::from_account_info(accepted_bet_state_account_info)?;
     let betting_market_account = BettingMarket::from_account_info(betting_market_account_info)?;
 
-    // check creator main account created the bet
-    if bet_state_account.creator_main_account != *creator_main_account_info.key {
-        msg!("Signer did not create the bet!");
-        return Err(BetError::InvalidAccounts.into());
+    // check SettleBet paid the winner and the fees haven't been distributed yet
+    if accepted_bet_state_account.is_finalized() {
+        msg!("Bet already finalized");
+        return Err(BetError::BetFinalized.into());
+    }
+    if !accepted_bet_state_account.fees_pending {
+        msg!("Bet must be settled with SettleBet first");
+        return Err(BetError::SettleBetRequired.into());
+    }
+    // check the accepted bet belongs to the bet
+    if accepted_bet_state_account.bet != *bet_state_account_info.key {
+        msg!("Wrong bet account: expected {}, got {}", accepted_bet_state_account.bet, bet_state_account_info.key);
+        return Err(BetError::WrongBetAccount.into());
     }
-
     // check it is correct betting market account
     if bet_state_account.betting_market != *betting_market_account_info.key {
-        msg!("Incorrect betting market account");
+        msg!("Wrong betting market account: expected {}, got {}", bet_state_account.betting_market, betting_market_account_info.key);
+        return Err(BetError::WrongBettingMarket.into());
+    }
+    // check it is correct commission fee account, token bets pay the fee wallet's ATA of the bet's mint
+    let commission_fee_account = get_associated_token_address(&betting_market_account.fee_commission_account, &bet_state_account.payment_mint, token_program_account_info.key);
+    if commission_fee_account != *commission_fee_account_info.key {
+        msg!("Wrong commission fee account: expected {}, got {}", commission_fee_account, commission_fee_account_info.key);
         return Err(BetError::InvalidAccounts.into());
     }
-
-    // check it is correct escrow account
-    if bet_state_account.bet_escrow_account != *bet_escrow_account_info.key {
-        msg!("Incorrect escrow account");
+    // check the finalizer and rent recipient are the ones SettleBet recorded
+    if accepted_bet_state_account.finalizer_payment_account != *finalizer_payment_account_info.key {
+        msg!("Wrong finalizer payment account: expected {}, got {}", accepted_bet_state_account.finalizer_payment_account, finalizer_payment_account_info.key);
+        return Err(BetError::WrongPaymentAccount.into());
+    }
+    if accepted_bet_state_account.escrow_rent_recipient != *escrow_rent_recipient_account_info.key {
+        msg!("Wrong escrow rent recipient: expected {}, got {}", accepted_bet_state_account.escrow_rent_recipient, escrow_rent_recipient_account_info.key);
         return Err(BetError::InvalidAccounts.into());
     }
+    // check it is correct escrow account
+    if accepted_bet_state_account.accepted_bet_escrow_account != *accepted_bet_escrow_account_info.key {
+        msg!("Wrong escrow account: expected {}, got {}", accepted_bet_state_account.accepted_bet_escrow_account, accepted_bet_escrow_account_info.key);
+        return Err(BetError::WrongEscrowAccount.into());
+    }
+    // check the token program is the one that owns the escrow
+    if accepted_bet_escrow_account_info.owner != token_program_account_info.key {
+        msg!("Incorrect token program: expected {}, got {}", accepted_bet_escrow_account_info.owner, token_program_account_info.key);
+        return Err(BetError::InvalidTokenProgram.into());
+    }
+    token::check_not_frozen(accepted_bet_escrow_account_info)?;
 
-    // send lamports / tokens from escrow account to creator payment account
-    if betting_market_account.sol_payment == true {
-        msg!("Calling system program to transfer tokens to bet creator");
-        let transfer_lamports_from_escrow_ix = system_instruction::transfer(
-            &bet_escrow_account_info.key,
-            &creator_payment_account_info.key,
-            bet_escrow_account_info.lamports()
-        );
-        invoke(
-            &transfer_lamports_from_escrow_ix,
-            &[
-                system_program_account_info.clone(),
-                bet_escrow_account_info.clone(),
-                creator_payment_account_info.clone()
-            ]
-        )?;
-    } else {
-        // get pda address, bump seed and seeds
-        let bet_escrow_account_seeds = &[
-            PREFIX.as_bytes(),
-            bet_escrow_account_info.key.as_ref(),
-        ];
-        let (bet_escrow_account_pda, bump_seed) = Pubkey::find_program_address(bet_escrow_account_seeds, program_id);
-        let bet_escrow_transfer_seeds = &[
-            PREFIX.as_bytes(),
-            bet_escrow_account_info.key.as_ref(),
-            &[bump_seed]
-        ];
-
-        // unpack token account to get amount in there
-        let bet_escrow_account = TokenAccount::unpack_from_slice(&bet_escrow_account_info.data.borrow())?;
+    // get pda address, bump seed and seeds (the accepted escrow authority)
+    let (_, bump_seed) = find_escrow_authority_versioned(
+        program_id,
+        bet_state_account.escrow_seeds_version,
+        betting_market_account_info.key,
+        accepted_bet_state_account_info.key
+    );
+    let bet_escrow_bump_seed = [bump_seed];
+    let (bet_escrow_account_pda, bet_escrow_transfer_seeds) = escrow_authority_signer(
+        program_id,
+        bet_state_account.escrow_seeds_version,
+        betting_market_account_info.key,
+        accepted_bet_state_account_info.key,
+        &bet_escrow_bump_seed
+    )?;
+    if bet_escrow_account_pda != *pda_account_info.key {
+        msg!("Incorrect escrow authority: expected {}, got {}", bet_escrow_account_pda, pda_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
 
-        msg!("Calling token program to transfer tokens to bet creator");
-        let transfer_tokens_from_escrow_ix = spl_token::instruction::transfer(
-            token_program_account_info.key, 
-            bet_escrow_account_info.key, 
-            creator_payment_account_info.key, 
-            &bet_escrow_account_pda, 
-            &[&bet_escrow_account_pda], 
-            bet_escrow_account.amount
-        )?;
+    // pay the finalizer its recorded reward, the commission account gets the rest of the escrow including any dust
+    let accepted_bet_escrow_account = TokenAccount::unpack_from_slice(&accepted_bet_escrow_account_info.data.borrow())?;
+    let finalizer_amount = accepted_bet_state_account.finalizer_amount.min(accepted_bet_escrow_account.amount);
+    let commission_amount = accepted_bet_escrow_account.amount - finalizer_amount;
+    let payouts = [
+        (finalizer_payment_account_info, finalizer_amount),
+        (commission_fee_account_info, commission_amount),
+    ];
+    token::validate_payout_destinations(
+        &[
+            ("finalizer_payment", finalizer_payment_account_info, finalizer_amount),
+            ("commission_fee", commission_fee_account_info, commission_amount),
+        ],
+        &bet_state_account.payment_mint,
+        token_program_account_info.key
+    )?;
+    msg!("Calling token program to transfer tokens to finalizer and commission accounts");
+    for (destination_account_info, amount) in payouts.iter() {
+        if *amount == 0 {
+            continue;
+        }
         invoke_signed(
-            &transfer_tokens_from_escrow_ix, 
+            &token::transfer(
+                token_program_account_info.key,
+                accepted_bet_escrow_account_info.key,
+                destination_account_info.key,
+                &bet_escrow_account_pda,
+                *amount
+            )?,
             &[
                 token_program_account_info.clone(),
-                bet_escrow_account_info.clone(),
-                creator_payment_account_info.clone(),
+                accepted_bet_escrow_account_info.clone(),
+                (*destination_account_info).clone(),
                 pda_account_info.clone()
-            ], 
-            &[bet_escrow_transfer_seeds]
+            ],
+            &[&bet_escrow_transfer_seeds[..]]
         )?;
     }
 
-    // cancel the bet so noone in future can try to accept it
-    bet_state_account.cancelled = true;
+    // close the now empty accepted escrow to the recorded rent recipient
+    msg!("Calling token program to close the accepted escrow");
+    invoke_signed(
+        &token::close_account(
+            token_program_account_info.key,
+            accepted_bet_escrow_account_info.key,
+            escrow_rent_recipient_account_info.key,
+            &bet_escrow_account_pda
+        )?,
+        &[
+            token_program_account_info.clone(),
+            accepted_bet_escrow_account_info.clone(),
+            escrow_rent_recipient_account_info.clone(),
+            pda_account_info.clone()
+        ],
+        &[&bet_escrow_transfer_seeds[..]]
+    )?;
+
+    // the accepted bet is finalized once its fees are paid
+    accepted_bet_state_account.fees_pending = false;
+    accepted_bet_state_account.save(accepted_bet_state_account_info)?;
 
-    // pack the bet_state_account
-    bet_state_account.serialize(&mut &mut bet_state_account_info.data.borrow_mut()[..])?;
+    bet_state_account.finalized_count = bet_state_account.finalized_count.checked_add(1).ok_or(BetError::AmountOverflow)?;
+    bet_state_account.updated_at = clock.unix_timestamp;
+    bet_state_account.last_interaction_slot = clock.slot;
+    bet_state_account.save(bet_state_account_info)?;
 
     Ok(())
 }
 
-pub fn process_finalize_bet<'a>(
+pub fn process_simulate_finalize<'a>(
     program_id: &'a Pubkey,
     accounts: &'a [AccountInfo<'a>],
 ) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
-    let finalizer_main_account_info = next_account_info(account_info_iter)?;
-    let finalizer_payment_account_info = next_account_info(account_info_iter)?;
-    let commission_fee_account_info = next_account_info(account_info_iter)?;
     let bet_state_account_info = next_account_info(account_info_iter)?;
     let accepted_bet_state_account_info = next_account_info(account_info_iter)?;
-    let accepted_bet_escrow_account_info = next_account_info(account_info_iter)?;
-    let creator_payment_account_info = next_account_info(account_info_iter)?;
-    let acceptor_payment_account_info = next_account_info(account_info_iter)?;
     let betting_market_account_info = next_account_info(account_info_iter)?;
     let pyth_oracle_price_account_info = next_account_info(account_info_iter)?;
-    let token_program_account_info = next_account_info(account_info_iter)?;
-    spl_token::check_program_account(token_program_account_info.key)?;
-    let system_program_account_info = next_account_info(account_info_iter)?;
-    if check_id(system_program_account_info.key) == false {
-        return Err(BetError::InvalidSystemProgram.into());
-    }
-    let pda_account_info = next_account_info(account_info_iter)?;
     let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
 
-    if !finalizer_main_account_info.is_signer {
-        return Err(BetError::IncorrectSigner.into());
+    // check program is owner of the state accounts
+    if bet_state_account_info.owner != program_id
+        || accepted_bet_state_account_info.owner != program_id
+        || betting_market_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
     }
 
     // unpack the state accounts
     let bet_state_account = Bet::from_account_info(bet_state_account_info)?;
-    let mut accepted_bet_state_account = AcceptedBet::from_account_info(accepted_bet_state_account_info)?;
-    let betting_market_account = BettingMarket::from_account_info(betting_market_account_info)?;
-    // check bet hasn't already been finalized
-    if accepted_bet_state_account.finalized {
+    let accepted_bet_state_account = AcceptedBet::from_account_info(accepted_bet_state_account_info)?;
+
+    // same checks as finalize
+    if accepted_bet_state_account.is_finalized() {
         msg!("Bet already finalized");
         return Err(BetError::BetFinalized.into());
     }
-    // check it is correct betting market account
-    if bet_state_account.betting_market != *betting_market_account_info.key {
-        msg!("Wrong betting market account");
-        return Err(BetError::InvalidAccounts.into());
-    }
-    // check it is correct pyth oracle account
-    if bet_state_account.pyth_oracle_price_account != *pyth_oracle_price_account_info.key {
-        msg!("Wrong pyth price account");
-        return Err(BetError::InvalidAccounts.into());
-    }
-    // check it is correct commission fee account
-    if betting_market_account.fee_commission_account != *commission_fee_account_info.key {
-        msg!("Wrong commission fee account");
-        return Err(BetError::InvalidAccounts.into());
-    }
-    // check it is correct creator account
-    if bet_state_account.creator_payment_account != *creator_payment_account_info.key {
-        msg!("Wrong bet creator payment account");
-        return Err(BetError::InvalidAccounts.into());
+    if accepted_bet_state_account.fees_pending {
+        msg!("Bet already settled, DistributeFees finishes it");
+        return Err(BetError::BetSettled.into());
     }
-    // check it is correct acceptor payment account
-    if accepted_bet_state_account.acceptor_payment_account != *acceptor_payment_account_info.key {
-        msg!("Wrong bet acceptor payment account");
-        return Err(BetError::InvalidAccounts.into());
+    if accepted_bet_state_account.bet != *bet_state_account_info.key {
+        msg!("Wrong bet account: expected {}, got {}", accepted_bet_state_account.bet, bet_state_account_info.key);
+        return Err(BetError::WrongBetAccount.into());
     }
-    // check it is correct escrow account
-    if accepted_bet_state_account.accepted_bet_escrow_account != * accepted_bet_state_account_info.key {
-        msg!("Wrong escrow account");
-        return Err(BetError::InvalidAccounts.into());
+    if bet_state_account.betting_market != *betting_market_account_info.key {
+        msg!("Wrong betting market account: expected {}, got {}", bet_state_account.betting_market, betting_market_account_info.key);
+        return Err(BetError::WrongBettingMarket.into());
     }
-
-    // check time is after bet expiration time
-    if clock.unix_timestamp < bet_state_account.expiration_time {
-        msg!("Time is before bet expiration time");
-        return Err(BetError::BeforeExpiryTime.into());
+    if bet_state_account.pyth_oracle_price_account != *pyth_oracle_price_account_info.key {
+        msg!("Wrong pyth price account: expected {}, got {}", bet_state_account.pyth_oracle_price_account, pyth_oracle_price_account_info.key);
+        return Err(BetError::WrongOracleAccount.into());
     }
 
-    // get price from pyth oracle
+    // get price from pyth oracle, markets with a settlement window use the median of the observations recorded so far
     let pyth_price_data = pyth_oracle_price_account_info.try_borrow_data()?;
-    let price_account: Price = *load_price( &pyth_price_data ).unwrap();
-    let price: PriceConf = price_account.get_current_price().unwrap();
-
-    // determine the bet winner
-    let bet_winner_account_info: &AccountInfo;
-    match bet_state_account.bet_direction {
-        Direction::Above => {
-            if price.price >= bet_state_account.bet_price {
-                bet_winner_account_info = creator_payment_account_info;
-            } else {
-                bet_winner_account_info = acceptor_payment_account_info;
-            }
-        },
-        Direction::Below => {
-            if price.price <= bet_state_account.bet_price {
-                bet_winner_account_info = creator_payment_account_info;
-            } else {
-                bet_winner_account_info = acceptor_payment_account_info;
-            }
-        } 
-    }
+    // a feed that isn't trading gives its last aggregate price, not settleable until finalize may refund
+    let price_account: Price = *load_price(&pyth_price_data).map_err(|_| BetError::InvalidAccountInput)?;
+    let betting_market_account = BettingMarket::from_account_info(betting_market_account_info)?;
+    let (settlement_price, publish_slot, observations_ready) = if betting_market_account.settles_on_observations() {
+        let observations = bet_state_account.settlement_observations();
+        if observations.is_empty() {
+            (price_account.agg.price, price_account.agg.pub_slot, false)
+        } else {
+            (
+                median_price(observations),
+                latest_observation_slot(observations),
+                settlement_window_closed(&bet_state_account, &betting_market_account, bet_state_account.secs_since_expiry(clock.unix_timestamp, clock.slot))
+                    && bet_state_account.settlement_observations_count >= betting_market_account.min_settlement_observations
+            )
+        }
+    } else {
+        (price_account.agg.price, price_account.agg.pub_slot, true)
+    };
+    let degraded = degraded_settlement(&bet_state_account, &betting_market_account, clock, publish_slot);
 
-    // calculate commission amount
-    let commission_amount = accepted_bet_state_account.bet_size / 50;
-    let finalizer_amount = commission_amount / 4;
-    let winner_amount = accepted_bet_state_account.bet_size - commission_amount - finalizer_amount;
+    // finalize reads a changed or unavailable feed only without observations, RefundExpiredAcceptedBet refunds the
+    // stakes once it may
+    let secs_since_expiry = bet_state_account.secs_since_expiry(clock.unix_timestamp, clock.slot);
+    let oracle_feed_changed = !betting_market_account.settles_on_observations() && check_oracle_feed(&bet_state_account, &price_account).is_err();
+    let oracle_unavailable = !betting_market_account.settles_on_observations() && price_account.get_current_price().is_none();
+    let refund_ready = (!oracle_feed_changed || secs_since_expiry >= ORACLE_FEED_CHANGED_REFUND_SECS)
+        && (!oracle_unavailable || secs_since_expiry >= ORACLE_UNAVAILABLE_REFUND_SECS);
+    let outcome = if oracle_feed_changed || oracle_unavailable {
+        Outcome::Refund
+    } else {
+        determine_winner(accepted_bet_state_account.creator_direction, settlement_price, bet_state_account.bet_price, betting_market_account.tie_policy)?
+    };
+    let refund = outcome == Outcome::Refund;
 
-    // send payments to commission, winner and finalizer
-    if betting_market_account.sol_payment {
-        // transfer to commission account
-        msg!("Calling system program to transfer lamports to commission account");
-        let transfer_lamports_from_escrow_to_commission_ix = system_instruction::transfer(
-            &accepted_bet_escrow_account_info.key,
-            &commission_fee_account_info.key,
-            commission_amount
-        );
-        invoke(
-            &transfer_lamports_from_escrow_to_commission_ix,
-            &[
-                system_program_account_info.clone(),
-                accepted_bet_escrow_account_info.clone(),
-                commission_fee_account_info.clone()
-            ]
-        )?;
+    // the accepted escrow holds the creator's matched stake plus the acceptor payment
+    let acceptor_payment_amount = acceptor_payment_amount(accepted_bet_state_account.bet_size, accepted_bet_state_account.odds);
+    let pot = accepted_bet_state_account.bet_size.checked_add(acceptor_payment_amount).ok_or(BetError::AmountOverflow)?;
+    let creator_is_winner = outcome != Outcome::AcceptorWins;
+    let loser_stake = if creator_is_winner { acceptor_payment_amount } else { accepted_bet_state_account.bet_size };
+    let loser_refund = if refund {
+        loser_stake
+    } else {
+        loser_stake - curve_winnings(bet_state_account.payout_curve, loser_stake, settlement_price, bet_state_account.bet_price)?
+    };
+    let (commission_amount, finalizer_amount, winner_amount, loser_amount) = settlement_amounts(
+        pot,
+        accepted_bet_state_account.bet_size,
+        if degraded || refund {
+            0
+        } else {
+            finalizer_reward_bps(&betting_market_account, secs_since_expiry)
+        },
+        loser_refund,
+        bet_state_account.fee_exempt || refund
+    )?;
+    emit_event(&BetEvent::FinalizeSimulated {
+        bet: *bet_state_account_info.key,
+        accepted_bet: *accepted_bet_state_account_info.key,
+        settleable: secs_since_expiry >= betting_market_account.clock_tolerance_secs.saturating_neg() && observations_ready && refund_ready,
+        price: settlement_price,
+        creator_wins: creator_is_winner,
+        winner_payment_account: if creator_is_winner {
+            bet_state_account.creator_payment_account
+        } else {
+            accepted_bet_state_account.acceptor_payment_account
+        },
+        winner_amount,
+        loser_amount,
+        commission_amount,
+        finalizer_amount,
+        degraded,
+        oracle_feed_changed,
+        oracle_unavailable,
+    })?;
 
-        // transfer to finalizer
-        msg!("Calling system program to transfer lamports to finalizer account");
-        let transfer_lamports_from_escrow_to_finalizer_ix = system_instruction::transfer(
-            &accepted_bet_escrow_account_info.key,
-            &finalizer_payment_account_info.key,
-            finalizer_amount
-        );
-        invoke(
-            &transfer_lamports_from_escrow_to_finalizer_ix,
-            &[
-                system_program_account_info.clone(),
-                accepted_bet_escrow_account_info.clone(),
-                finalizer_payment_account_info.clone()
-            ]
-        )?;
+    Ok(())
+}
 
-        // transfer to winner
-        msg!("Calling system program to transfer lamports to finalizer account");
-        let transfer_lamports_from_escrow_to_winner_ix = system_instruction::transfer(
-            &accepted_bet_escrow_account_info.key,
-            &bet_winner_account_info.key,
-            winner_amount
-        );
-        invoke(
-            &transfer_lamports_from_escrow_to_winner_ix,
+// adds a settled position to a user's stats, creating the stats account on their first one
+#[allow(clippy::too_many_arguments)]
+fn update_user_stats<'a>(
+    program_id: &Pubkey,
+    stats_account_info: &AccountInfo<'a>,
+    rent_sysvar_account_info: &AccountInfo<'a>,
+    system_program_account_info: &AccountInfo<'a>,
+    payer_account_info: &AccountInfo<'a>,
+    betting_market: &Pubkey,
+    user: &Pubkey,
+    won: bool,
+    stake: u64,
+    payout: u64,
+) -> ProgramResult {
+    let (stats_address, bump_seed) = find_user_stats(program_id, betting_market, user);
+    if stats_address != *stats_account_info.key {
+        msg!("Incorrect user stats account: expected {}, got {}", stats_address, stats_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+    if stats_account_info.owner != program_id {
+        create_or_allocate_account_raw(
+            *program_id,
+            stats_account_info,
+            rent_sysvar_account_info,
+            system_program_account_info,
+            payer_account_info,
+            MAX_USER_STATS_DATA_LEN,
             &[
-                system_program_account_info.clone(),
-                accepted_bet_escrow_account_info.clone(),
-                bet_winner_account_info.clone()
+                STATS_SEED.as_bytes(),
+                betting_market.as_ref(),
+                user.as_ref(),
+                &[bump_seed]
             ]
         )?;
+    }
+    let mut stats = UserStats::from_account_info(stats_account_info)?;
+    if !stats.is_initialized {
+        stats.is_initialized = true;
+        stats.betting_market = *betting_market;
+        stats.user = *user;
+        stats.bump_seed = bump_seed;
+    }
+    if won {
+        stats.wins = stats.wins.checked_add(1).ok_or(BetError::AmountOverflow)?;
     } else {
-        // get pda address, bump seed and seeds
-        let bet_escrow_account_seeds = &[
-            PREFIX.as_bytes(),
-            bet_state_account.bet_escrow_account.as_ref(),
-        ];
-        let (bet_escrow_account_pda, bump_seed) = Pubkey::find_program_address(bet_escrow_account_seeds, program_id);
-        let bet_escrow_transfer_seeds = &[
-            PREFIX.as_bytes(),
-            bet_state_account.bet_escrow_account.as_ref(),
-            &[bump_seed]
-        ];
+        stats.losses = stats.losses.checked_add(1).ok_or(BetError::AmountOverflow)?;
+    }
+    stats.volume = stats.volume.checked_add(stake).ok_or(BetError::AmountOverflow)?;
+    let pnl = (payout as i128) - (stake as i128);
+    stats.net_pnl = (stats.net_pnl as i128)
+        .checked_add(pnl)
+        .and_then(|net_pnl| net_pnl.try_into().ok())
+        .ok_or(BetError::AmountOverflow)?;
+    stats.save(stats_account_info)
+}
 
-        // transfer tokens to commission account
-        msg!("Calling token program to transfer tokens to commission account");
-        let transfer_tokens_from_escrow_to_commission_ix = spl_token::instruction::transfer(
-            token_program_account_info.key, 
-            accepted_bet_escrow_account_info.key, 
-            commission_fee_account_info.key, 
-            &bet_escrow_account_pda, 
-            &[&bet_escrow_account_pda], 
-            commission_amount
-        )?;
-        invoke_signed(
-            &transfer_tokens_from_escrow_to_commission_ix, 
-            &[
-                token_program_account_info.clone(),
-                accepted_bet_escrow_account_info.clone(),
-                commission_fee_account_info.clone(),
-                pda_account_info.clone()
-            ], 
-            &[bet_escrow_transfer_seeds]
-        )?;
+// creates the accepted bet's receipt PDA and writes the receipt to it, with the PDA's bump
+fn write_receipt<'a>(
+    program_id: &Pubkey,
+    receipt_account_info: &AccountInfo<'a>,
+    rent_sysvar_account_info: &AccountInfo<'a>,
+    system_program_account_info: &AccountInfo<'a>,
+    payer_account_info: &AccountInfo<'a>,
+    mut receipt: Receipt,
+) -> ProgramResult {
+    let (receipt_address, bump_seed) = find_receipt(program_id, &receipt.accepted_bet);
+    if receipt_address != *receipt_account_info.key {
+        msg!("Incorrect receipt account: expected {}, got {}", receipt_address, receipt_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+    if receipt_account_info.owner == program_id {
+        return Err(BetError::AccountAlreadyInitialized.into());
+    }
+    create_or_allocate_account_raw(
+        *program_id,
+        receipt_account_info,
+        rent_sysvar_account_info,
+        system_program_account_info,
+        payer_account_info,
+        MAX_RECEIPT_DATA_LEN,
+        &[
+            RECEIPT_SEED.as_bytes(),
+            receipt.accepted_bet.as_ref(),
+            &[bump_seed]
+        ]
+    )?;
+    receipt.bump_seed = bump_seed;
+    receipt.save(receipt_account_info)
+}
 
-        // transfer tokens to winner payment account
-        msg!("Calling token program to transfer tokens to commission account");
-        let transfer_tokens_from_escrow_to_winner_ix = spl_token::instruction::transfer(
-            token_program_account_info.key, 
-            accepted_bet_escrow_account_info.key, 
-            bet_winner_account_info.key, 
-            &bet_escrow_account_pda, 
-            &[&bet_escrow_account_pda], 
-            winner_amount
-        )?;
-        invoke_signed(
-            &transfer_tokens_from_escrow_to_winner_ix, 
-            &[
-                token_program_account_info.clone(),
-                accepted_bet_escrow_account_info.clone(),
-                bet_winner_account_info.clone(),
-                pda_account_info.clone()
-            ], 
-            &[bet_escrow_transfer_seeds]
-        )?;
+// the FeedExposure of the bet's market and feed, checked against the PDA of its stored bump
+fn load_feed_exposure(program_id: &Pubkey, feed_exposure_account_info: &AccountInfo, bet: &Bet) -> Result<FeedExposure, ProgramError> {
+    if feed_exposure_account_info.owner != program_id {
+        return Err(BetError::IncorrectOwner.into());
+    }
+    let feed_exposure_account = FeedExposure::from_account_info(feed_exposure_account_info)?;
+    let feed_exposure_address = Pubkey::create_program_address(
+        &[
+            FEED_SEED.as_bytes(),
+            bet.betting_market.as_ref(),
+            bet.pyth_oracle_price_account.as_ref(),
+            &[feed_exposure_account.bump_seed]
+        ],
+        program_id
+    )?;
+    if feed_exposure_address != *feed_exposure_account_info.key {
+        msg!("Incorrect feed exposure account: expected {}, got {}", feed_exposure_address, feed_exposure_account_info.key);
+        return Err(BetError::InvalidAccounts.into());
+    }
+    Ok(feed_exposure_account)
+}
 
-        // transfer tokens to finalizer payment account
-        msg!("Calling token program to transfer tokens to finalizer account");
-        let transfer_tokens_from_escrow_to_finalizer_ix = spl_token::instruction::transfer(
-            token_program_account_info.key, 
-            accepted_bet_escrow_account_info.key, 
-            finalizer_payment_account_info.key, 
-            &bet_escrow_account_pda, 
-            &[&bet_escrow_account_pda], 
-            finalizer_amount
-        )?;
-        invoke_signed(
-            &transfer_tokens_from_escrow_to_finalizer_ix, 
-            &[
-                token_program_account_info.clone(),
-                accepted_bet_escrow_account_info.clone(),
-                finalizer_payment_account_info.clone(),
-                pda_account_info.clone()
-            ], 
-            &[bet_escrow_transfer_seeds]
-        )?;
+// true if the price was published after the market's preferred settlement window
+fn degraded_settlement(bet: &Bet, market: &BettingMarket, clock: &Clock, publish_slot: u64) -> bool {
+    if market.preferred_settlement_window_secs == 0 {
+        return false;
     }
+    bet.secs_since_expiry(estimated_publish_time(clock, publish_slot), publish_slot) > market.preferred_settlement_window_secs
+}
 
-    // update accepted bet state, set finalized to true
-    accepted_bet_state_account.finalized = true;
+// the unix time of a publish slot, estimated from the slots since it at DEFAULT_MS_PER_SLOT. Slots usually take longer,
+// so the estimate errs late
+fn estimated_publish_time(clock: &Clock, publish_slot: u64) -> i64 {
+    let secs_since_publish = clock.slot.saturating_sub(publish_slot).saturating_mul(DEFAULT_MS_PER_SLOT) / 1000;
+    clock.unix_timestamp.saturating_sub(secs_since_publish.try_into().unwrap_or(i64::MAX))
+}
 
-    // pack state account
-    accepted_bet_state_account.serialize(&mut &mut accepted_bet_state_account_info.data.borrow_mut()[..])?;
+// the minimum duration can't be negative or above a set horizon (0 = unlimited)
+fn check_duration_bounds(min_duration_secs: i64, max_expiration_horizon_secs: i64) -> ProgramResult {
+    if min_duration_secs < 0
+        || (max_expiration_horizon_secs > 0 && min_duration_secs > max_expiration_horizon_secs) {
+        msg!("Minimum duration {} must be between 0 and the horizon {}", min_duration_secs, max_expiration_horizon_secs);
+        return Err(BetError::InvalidInstruction.into());
+    }
+    Ok(())
+}
 
+// the tolerance lets an accept land up to tolerance after the accept cutoff and a finalize up to tolerance
+// before expiration. Keeping it within half the cutoff means the last tolerated accept still lands before
+// the first tolerated finalize, so a bet is never both acceptable and finalizable
+fn check_clock_tolerance(clock_tolerance_secs: i64, accept_cutoff_secs: i64) -> ProgramResult {
+    if !(0..=MAX_CLOCK_TOLERANCE_SECS).contains(&clock_tolerance_secs)
+        || clock_tolerance_secs.saturating_mul(2) > accept_cutoff_secs {
+        msg!("Clock tolerance {} must be between 0 and {}, and at most half the accept cutoff {}", clock_tolerance_secs, MAX_CLOCK_TOLERANCE_SECS, accept_cutoff_secs);
+        return Err(BetError::InvalidInstruction.into());
+    }
     Ok(())
 }
 
-/// validates pyth AccountInfos - Thank you Solend
+/// validates pyth AccountInfos - Thank you Solend. Returns the sha256 of the product's symbol
 #[inline(always)]
 fn validate_pyth_keys(
     oracle_program_id: &Pubkey,
     pyth_product_info: &AccountInfo,
     pyth_price_info: &AccountInfo,
-) -> ProgramResult {
+) -> Result<[u8; 32], ProgramError> {
 
     if oracle_program_id != pyth_product_info.owner {
         msg!("Pyth product account provided is not owned by the Pyth oracle program");
-        return Err(BetError::InvalidOracleConfig.into());
+        return Err(BetError::OracleWrongOwner.into());
     }
     if oracle_program_id != pyth_price_info.owner {
         msg!("Pyth price account provided is not owned by the Pyth oracle program");
-        return Err(BetError::InvalidOracleConfig.into());
+        return Err(BetError::OracleWrongOwner.into());
     }
 
     let pyth_product_data = pyth_product_info.try_borrow_data()?;
@@ -875,15 +5229,15 @@ fn validate_pyth_keys(
         .map_err(|_| ProgramError::InvalidAccountData)?;
     if pyth_product.magic != pyth::MAGIC {
         msg!("Pyth product account provided is not a valid Pyth account");
-        return Err(BetError::InvalidOracleConfig.into());
+        return Err(BetError::OracleBadMagic.into());
     }
     if pyth_product.ver != pyth::VERSION_2 {
         msg!("Pyth product account provided has a different version than expected");
-        return Err(BetError::InvalidOracleConfig.into());
+        return Err(BetError::OracleWrongVersion.into());
     }
     if pyth_product.atype != pyth::AccountType::Product as u32 {
         msg!("Pyth product account provided is not a valid Pyth product account");
-        return Err(BetError::InvalidOracleConfig.into());
+        return Err(BetError::OracleWrongAccountType.into());
     }
 
     let pyth_price_pubkey_bytes: &[u8; 32] = pyth_price_info
@@ -892,9 +5246,78 @@ fn validate_pyth_keys(
         .try_into()
         .map_err(|_| BetError::InvalidAccountInput)?;
     if &pyth_product.px_acc.val != pyth_price_pubkey_bytes {
-        msg!("Pyth product price account does not match the Pyth price provided");
-        return Err(BetError::InvalidOracleConfig.into());
+        msg!(
+            "Pyth product {} does not reference the provided price account",
+            pyth_product.attribute("symbol").unwrap_or("<no symbol>")
+        );
+        return Err(BetError::OracleProductPriceMismatch.into());
+    }
+
+    Ok(hash(pyth_product.attribute("symbol").unwrap_or("").as_bytes()).to_bytes())
+}
+
+// the oracle's current price, only while the feed is Trading. Unknown, Halted and Auction feeds, and stale ones, have
+// no price to accept or settle on, and the instruction can be retried once the feed trades again
+fn current_price(price_account: &Price) -> Result<PriceConf, ProgramError> {
+    price_account.get_current_price().ok_or_else(|| {
+        msg!("Oracle price status is {:?}, not Trading", price_account.get_current_price_status());
+        BetError::OraclePriceUnavailable.into()
+    })
+}
+
+// the bet's start price. The oracle is read either way, a supplied price is recorded instead of the live one if the oracle's
+// current or previous aggregate was published at its slot within the market's tolerance, and that slot is recent enough
+fn start_price(market: &BettingMarket, price_account: &Price, supplied: Option<&SuppliedStartPrice>, clock: &Clock) -> Result<i64, ProgramError> {
+    let live_price = current_price(price_account)?.price;
+    let supplied = match supplied {
+        Some(supplied) if market.max_start_price_age_slots > 0 => supplied,
+        _ => return Ok(live_price)
+    };
+    let published_price = if supplied.publish_slot == price_account.agg.pub_slot {
+        Some(price_account.agg.price)
+    } else if supplied.publish_slot == price_account.prev_slot {
+        Some(price_account.prev_price)
+    } else {
+        None
+    };
+    let recent = supplied.publish_slot <= clock.slot && clock.slot - supplied.publish_slot <= market.max_start_price_age_slots;
+    match published_price {
+        Some(published_price) if recent && within_tolerance_bps(supplied.price, published_price, market.start_price_tolerance_bps) => Ok(supplied.price),
+        _ => {
+            msg!("Supplied start price {} at slot {} doesn't match the oracle, using the live price {}", supplied.price, supplied.publish_slot, live_price);
+            Ok(live_price)
+        }
+    }
+}
+
+// the price account still links back to the bet's product and reports the exponent the bet was created with. Pyth
+// can repurpose a price account for another product, bets created before the feed identity was stored aren't checked
+fn check_oracle_feed(bet: &Bet, price_account: &Price) -> ProgramResult {
+    if !bet.oracle_feed_locked() {
+        return Ok(());
+    }
+    if price_account.prod.val != bet.pyth_oracle_product_account.to_bytes() {
+        msg!("Price account {} no longer belongs to product {}", bet.pyth_oracle_price_account, bet.pyth_oracle_product_account);
+        return Err(BetError::OracleFeedChanged.into());
+    }
+    if price_account.expo != bet.oracle_expo {
+        msg!("Price account exponent changed: expected {}, got {}", bet.oracle_expo, price_account.expo);
+        return Err(BetError::OracleFeedChanged.into());
+    }
+    Ok(())
+}
+pub fn process_emit_event<'a>(
+    program_id: &'a Pubkey,
+    accounts: &'a [AccountInfo<'a>],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let event_authority_account_info = next_account_info(account_info_iter)?;
+
+    // only the program itself can sign for the event authority, so events can't be forged by other callers
+    let (event_authority, _bump_seed) = find_event_authority(program_id);
+    if event_authority != *event_authority_account_info.key || !event_authority_account_info.is_signer {
+        return Err(BetError::IncorrectSigner.into());
     }
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -114,6 +114,24 @@ pub struct Product {
     pub attr: [u8; PROD_ATTR_SIZE], // key/value pairs of reference attr.
 }
 
+impl Product {
+    /// Looks up a reference attribute, e.g. "symbol", in the length prefixed key/value strings
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        let attr_len = (self.size as usize).saturating_sub(PROD_HDR_SIZE).min(PROD_ATTR_SIZE);
+        let mut attr = &self.attr[..attr_len];
+        while let Some((&key_len, rest)) = attr.split_first() {
+            let attr_key = rest.get(..key_len as usize)?;
+            let (&value_len, rest) = rest[key_len as usize..].split_first()?;
+            let value = rest.get(..value_len as usize)?;
+            if attr_key == key.as_bytes() {
+                return std::str::from_utf8(value).ok();
+            }
+            attr = &rest[value_len as usize..];
+        }
+        None
+    }
+}
+
 #[cfg(target_endian = "little")]
 unsafe impl Zeroable for Product {}
 
@@ -0,0 +1,137 @@
+//! Builders for fabricating Pyth accounts in tests, with the exact layouts the program parses
+use bytemuck::{bytes_of, Zeroable};
+use pyth_client::{AccountType, PriceStatus, PriceType};
+use solana_program::pubkey::Pubkey;
+use crate::pyth;
+
+/// Builds the data of a Pyth price account, loadable with pyth_client::load_price
+#[derive(Clone)]
+pub struct PriceAccountBuilder {
+    pub price: i64,
+    pub expo: i32,
+    pub conf: u64,
+    pub status: PriceStatus,
+    pub valid_slot: u64,
+    pub product: Pubkey,
+}
+
+impl Default for PriceAccountBuilder {
+    fn default() -> Self {
+        PriceAccountBuilder {
+            price: 0,
+            expo: -8,
+            conf: 0,
+            status: PriceStatus::Trading,
+            valid_slot: 0,
+            product: Pubkey::default(),
+        }
+    }
+}
+
+impl PriceAccountBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn price(mut self, price: i64) -> Self {
+        self.price = price;
+        self
+    }
+
+    pub fn expo(mut self, expo: i32) -> Self {
+        self.expo = expo;
+        self
+    }
+
+    pub fn conf(mut self, conf: u64) -> Self {
+        self.conf = conf;
+        self
+    }
+
+    pub fn status(mut self, status: PriceStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn valid_slot(mut self, valid_slot: u64) -> Self {
+        self.valid_slot = valid_slot;
+        self
+    }
+
+    pub fn product(mut self, product: Pubkey) -> Self {
+        self.product = product;
+        self
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let mut price_account = pyth_client::Price::zeroed();
+        price_account.magic = pyth::MAGIC;
+        price_account.ver = pyth::VERSION_2;
+        price_account.atype = AccountType::Price as u32;
+        price_account.size = std::mem::size_of::<pyth_client::Price>() as u32;
+        price_account.ptype = PriceType::Price;
+        price_account.expo = self.expo;
+        price_account.valid_slot = self.valid_slot;
+        price_account.last_slot = self.valid_slot;
+        price_account.prod.val = self.product.to_bytes();
+        price_account.agg.price = self.price;
+        price_account.agg.conf = self.conf;
+        price_account.agg.status = self.status;
+        price_account.agg.pub_slot = self.valid_slot;
+        bytes_of(&price_account).to_vec()
+    }
+}
+
+/// Builds the data of a Pyth product account, accepted by validate_pyth_keys
+#[derive(Clone, Default)]
+pub struct ProductAccountBuilder {
+    pub attributes: Vec<(String, String)>,
+    pub px_acc: Pubkey,
+}
+
+impl ProductAccountBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn attribute(mut self, key: &str, value: &str) -> Self {
+        self.attributes.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn symbol(self, symbol: &str) -> Self {
+        self.attribute("symbol", symbol)
+    }
+
+    pub fn asset_type(self, asset_type: &str) -> Self {
+        self.attribute("asset_type", asset_type)
+    }
+
+    pub fn px_acc(mut self, px_acc: Pubkey) -> Self {
+        self.px_acc = px_acc;
+        self
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let mut product_account = pyth::Product::zeroed();
+        product_account.magic = pyth::MAGIC;
+        product_account.ver = pyth::VERSION_2;
+        product_account.atype = pyth::AccountType::Product as u32;
+        product_account.px_acc.val = self.px_acc.to_bytes();
+
+        // attributes are stored as length prefixed key/value strings
+        let mut offset = 0;
+        for (key, value) in self.attributes.iter() {
+            for s in [key, value].iter() {
+                let bytes = s.as_bytes();
+                assert!(bytes.len() <= u8::MAX as usize, "pyth attribute too long");
+                assert!(offset + 1 + bytes.len() <= pyth::PROD_ATTR_SIZE, "pyth attributes too long");
+                product_account.attr[offset] = bytes.len() as u8;
+                product_account.attr[offset + 1..offset + 1 + bytes.len()].copy_from_slice(bytes);
+                offset += 1 + bytes.len();
+            }
+        }
+        product_account.size = (pyth::PROD_HDR_SIZE + offset) as u32;
+        bytes_of(&product_account).to_vec()
+    }
+}
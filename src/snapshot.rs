@@ -0,0 +1,327 @@
+//! Buckets a market's bets for display, from the Bet and AcceptedBet accounts of two getProgramAccounts calls,
+//! and lists a creator's bets by nonce. The fetch_ entry points make the calls through an AccountFetcher
+use solana_program::{program_error::ProgramError, pubkey::Pubkey, sysvar};
+use std::convert::TryInto;
+use crate::{
+    offsets::{BET_STATUS_OFFSET, BET_BETTING_MARKET_OFFSET, BET_CREATOR_MAIN_ACCOUNT_OFFSET},
+    state::{Bet, BetStatus, BettingMarket, AcceptedBet, MAX_BET_DATA_LENGTH, MAX_ACCEPTED_BET_DATA_LEN},
+    utils::anchor_discriminator
+};
+
+/// A bet with what a UI shows about it
+#[derive(Clone, Debug)]
+pub struct BetSummary {
+    pub bet_state_account: Pubkey,
+    pub bet: Bet,
+    pub accepted_bets: Vec<(Pubkey, AcceptedBet)>,
+    pub remaining_capacity: u64,
+    pub secs_to_expiry: i64, // negative once expired, estimated for slot expiries
+}
+
+/// A market's bets, each in one bucket
+#[derive(Clone, Debug, Default)]
+pub struct MarketSnapshot {
+    pub open: Vec<BetSummary>, // acceptable, no accepts this period
+    pub partially_accepted: Vec<BetSummary>, // acceptable, with accepts this period
+    pub awaiting_settlement: Vec<BetSummary>, // no longer acceptable, with accepts left to finalize
+    pub settled: Vec<BetSummary>, // cancelled, or no accepts left to finalize
+}
+
+/// The RPC reads the fetch_ entry points make. The crate doesn't depend on solana-client, a wrapper of its RpcClient
+/// implements this with get_program_accounts_with_config, mapping the filters to RpcFilterType::DataSize and
+/// RpcFilterType::Memcmp, and get_account_data
+pub trait AccountFetcher {
+    type Error;
+
+    /// getProgramAccounts of the program, the accounts of the data size matching every memcmp (offset, bytes)
+    fn get_program_accounts(&self, program_id: &Pubkey, filters: &(u64, Vec<(usize, Vec<u8>)>)) -> Result<Vec<(Pubkey, Vec<u8>)>, Self::Error>;
+
+    /// The data of one account
+    fn get_account_data(&self, address: &Pubkey) -> Result<Vec<u8>, Self::Error>;
+}
+
+/// Why a fetch_ entry point failed
+#[derive(Debug)]
+pub enum FetchError<E> {
+    Rpc(E), // the fetcher's error
+    InvalidMarket(ProgramError), // the market account doesn't decode
+    InvalidClock, // the clock sysvar is shorter than its layout
+}
+
+// unix_timestamp and slot of the Clock sysvar's bincode layout: slot, epoch_start_timestamp, epoch,
+// leader_schedule_epoch, unix_timestamp
+fn clock_now_and_slot(data: &[u8]) -> Option<(i64, u64)> {
+    let slot = u64::from_le_bytes(data.get(0..8)?.try_into().ok()?);
+    let unix_timestamp = i64::from_le_bytes(data.get(32..40)?.try_into().ok()?);
+    Some((unix_timestamp, slot))
+}
+
+/// getProgramAccounts filters (data size, memcmp (offset, bytes)) selecting the Bet accounts of a market
+pub fn bet_accounts_filters(betting_market_account: &Pubkey) -> (u64, Vec<(usize, Vec<u8>)>) {
+    let mut memcmps = vec![(BET_BETTING_MARKET_OFFSET, betting_market_account.to_bytes().to_vec())];
+    if cfg!(feature = "anchor-compat") {
+        memcmps.push((0, anchor_discriminator("account", "Bet").to_vec()));
+    }
+    (MAX_BET_DATA_LENGTH as u64, memcmps)
+}
+
+/// getProgramAccounts filters (data size, memcmp (offset, bytes)) selecting all AcceptedBet accounts,
+/// they don't store the market so classify_bets joins them to the market's bets
+pub fn accepted_bet_accounts_filters() -> (u64, Vec<(usize, Vec<u8>)>) {
+    let mut memcmps = vec![];
+    if cfg!(feature = "anchor-compat") {
+        memcmps.push((0, anchor_discriminator("account", "AcceptedBet").to_vec()));
+    }
+    (MAX_ACCEPTED_BET_DATA_LEN as u64, memcmps)
+}
+
+/// getProgramAccounts filters (data size, memcmp (offset, bytes)) selecting the Bet accounts of a market in a status.
+/// Accounts last written before the status have Open's byte whatever their status, decoding them gives the right one
+pub fn bet_accounts_by_status_filters(betting_market_account: &Pubkey, status: BetStatus) -> (u64, Vec<(usize, Vec<u8>)>) {
+    let (data_size, mut memcmps) = bet_accounts_filters(betting_market_account);
+    memcmps.push((BET_STATUS_OFFSET, vec![status as u8]));
+    (data_size, memcmps)
+}
+
+/// getProgramAccounts filters (data size, memcmp (offset, bytes)) selecting the Bet accounts of a creator in a market
+pub fn creator_bet_accounts_filters(betting_market_account: &Pubkey, creator: &Pubkey) -> (u64, Vec<(usize, Vec<u8>)>) {
+    let (data_size, mut memcmps) = bet_accounts_filters(betting_market_account);
+    memcmps.push((BET_CREATOR_MAIN_ACCOUNT_OFFSET, creator.to_bytes().to_vec()));
+    (data_size, memcmps)
+}
+
+/// A creator's bet as (nonce, bet state account, bet)
+pub type CreatorBet = (u64, Pubkey, Bet);
+
+/// The creator's bets from the accounts fetched with creator_bet_accounts_filters, sorted by nonce. Bets not created
+/// from a nonce have NO_NONCE and come last
+pub fn find_bets_by_creator(
+    betting_market_account: &Pubkey,
+    creator: &Pubkey,
+    bet_accounts: &[(Pubkey, Vec<u8>)],
+) -> Vec<CreatorBet> {
+    let mut bets: Vec<CreatorBet> = bet_accounts.iter()
+        .filter_map(|(key, data)| {
+            Bet::from_account_data(data).ok()
+                .filter(|bet| bet.is_initialized() && bet.betting_market == *betting_market_account && bet.creator_main_account == *creator)
+                .map(|bet| (bet.nonce, *key, bet))
+        })
+        .collect();
+    bets.sort_by_key(|(nonce, key, _)| (*nonce, *key));
+    bets
+}
+
+/// Buckets the market's bets at the given time and slot. Accepted bets are joined to their bet by its pubkey,
+/// accounts that don't decode or belong to another market are skipped. The cancel condition prices need the
+/// oracle price, so a bet counts as acceptable until its cutoff
+pub fn classify_bets(
+    betting_market_account: &Pubkey,
+    market: &BettingMarket,
+    bet_accounts: &[(Pubkey, Vec<u8>)],
+    accepted_bet_accounts: &[(Pubkey, Vec<u8>)],
+    now: i64,
+    slot: u64,
+) -> MarketSnapshot {
+    let accepted_bets: Vec<(Pubkey, AcceptedBet)> = accepted_bet_accounts.iter()
+        .filter_map(|(key, data)| {
+            AcceptedBet::from_account_data(data).ok()
+                .filter(|accepted_bet| accepted_bet.is_initialized())
+                .map(|accepted_bet| (*key, accepted_bet))
+        })
+        .collect();
+
+    let mut snapshot = MarketSnapshot::default();
+    for (key, data) in bet_accounts {
+        let bet = match Bet::from_account_data(data) {
+            Ok(bet) if bet.is_initialized() && bet.betting_market == *betting_market_account => bet,
+            _ => continue
+        };
+        let summary = BetSummary {
+            bet_state_account: *key,
+            accepted_bets: accepted_bets.iter().filter(|(_, accepted_bet)| accepted_bet.bet == *key).cloned().collect(),
+            remaining_capacity: bet.remaining_acceptable(),
+            secs_to_expiry: bet.secs_since_expiry(now, slot).saturating_neg(),
+            bet,
+        };
+
+        // acceptable until the market's cutoff before expiration, as AcceptBet checks it. Extended bets take no accepts
+        let acceptable = summary.bet.status != BetStatus::Cancelled
+            && summary.bet.extensions_used == 0
+            && summary.remaining_capacity > 0
+            && summary.secs_to_expiry >= market.accept_cutoff_secs.saturating_sub(market.clock_tolerance_secs);
+        let unfinalized = summary.accepted_bets.iter().any(|(_, accepted_bet)| !accepted_bet.is_finalized());
+        if acceptable && summary.bet.accepted_count == summary.bet.period_accepted_base {
+            snapshot.open.push(summary);
+        } else if acceptable {
+            snapshot.partially_accepted.push(summary);
+        } else if unfinalized {
+            snapshot.awaiting_settlement.push(summary);
+        } else {
+            snapshot.settled.push(summary);
+        }
+    }
+    snapshot
+}
+
+/// find_bets_by_creator over the creator's bet accounts fetched with creator_bet_accounts_filters
+pub fn fetch_bets_by_creator<F: AccountFetcher>(
+    fetcher: &F,
+    program_id: &Pubkey,
+    betting_market_account: &Pubkey,
+    creator: &Pubkey,
+) -> Result<Vec<CreatorBet>, FetchError<F::Error>> {
+    let bet_accounts = fetcher.get_program_accounts(program_id, &creator_bet_accounts_filters(betting_market_account, creator))
+        .map_err(FetchError::Rpc)?;
+    Ok(find_bets_by_creator(betting_market_account, creator, &bet_accounts))
+}
+
+/// classify_bets over the market, its bet and accepted bet accounts, at the time and slot of the clock sysvar
+pub fn fetch_market_snapshot<F: AccountFetcher>(
+    fetcher: &F,
+    program_id: &Pubkey,
+    betting_market_account: &Pubkey,
+) -> Result<MarketSnapshot, FetchError<F::Error>> {
+    let market_data = fetcher.get_account_data(betting_market_account).map_err(FetchError::Rpc)?;
+    let market = BettingMarket::from_account_data(&market_data).map_err(FetchError::InvalidMarket)?;
+    let clock_data = fetcher.get_account_data(&sysvar::clock::id()).map_err(FetchError::Rpc)?;
+    let (now, slot) = clock_now_and_slot(&clock_data).ok_or(FetchError::InvalidClock)?;
+    let bet_accounts = fetcher.get_program_accounts(program_id, &bet_accounts_filters(betting_market_account))
+        .map_err(FetchError::Rpc)?;
+    let accepted_bet_accounts = fetcher.get_program_accounts(program_id, &accepted_bet_accounts_filters())
+        .map_err(FetchError::Rpc)?;
+    Ok(classify_bets(betting_market_account, &market, &bet_accounts, &accepted_bet_accounts, now, slot))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::BetError,
+        state::{MAX_BETTING_MARKET_DATA_LEN, MAX_TAG_LEN},
+        tx::bet_tag_filter,
+        utils::{puffed_out_string, serialize_account_data}
+    };
+    use std::collections::HashMap;
+
+    // answers like an RPC node over the accounts it holds, applying the filters
+    struct MemoryFetcher {
+        owners: HashMap<Pubkey, Pubkey>,
+        accounts: HashMap<Pubkey, Vec<u8>>,
+    }
+
+    impl AccountFetcher for MemoryFetcher {
+        type Error = String;
+
+        fn get_program_accounts(&self, program_id: &Pubkey, filters: &(u64, Vec<(usize, Vec<u8>)>)) -> Result<Vec<(Pubkey, Vec<u8>)>, String> {
+            let (data_size, memcmps) = filters;
+            Ok(self.accounts.iter()
+                .filter(|(key, data)| {
+                    self.owners.get(key) == Some(program_id)
+                        && data.len() as u64 == *data_size
+                        && memcmps.iter().all(|(offset, bytes)| data.get(*offset..offset + bytes.len()) == Some(bytes.as_slice()))
+                })
+                .map(|(key, data)| (*key, data.clone()))
+                .collect())
+        }
+
+        fn get_account_data(&self, address: &Pubkey) -> Result<Vec<u8>, String> {
+            self.accounts.get(address).cloned().ok_or_else(|| format!("{} not found", address))
+        }
+    }
+
+    fn bet_data(market: &Pubkey, creator: &Pubkey, nonce: u64) -> Vec<u8> {
+        tagged_bet_data(market, creator, nonce, "")
+    }
+
+    fn tagged_bet_data(market: &Pubkey, creator: &Pubkey, nonce: u64, tag: &str) -> Vec<u8> {
+        let mut data = vec![0; MAX_BET_DATA_LENGTH];
+        let mut bet = Bet::from_account_data(&data).unwrap();
+        bet.status = BetStatus::Open;
+        bet.betting_market = *market;
+        bet.creator_main_account = *creator;
+        bet.bet_size = 1_000;
+        bet.expiration_time = 2_000;
+        bet.nonce = nonce;
+        bet.tag = puffed_out_string(tag, MAX_TAG_LEN);
+        serialize_account_data(&bet, &mut data, "Bet").unwrap();
+        data
+    }
+
+    fn fetcher(program_id: &Pubkey, market: &Pubkey, bets: &[(Pubkey, Vec<u8>)]) -> MemoryFetcher {
+        let mut fetcher = MemoryFetcher { owners: HashMap::new(), accounts: HashMap::new() };
+        fetcher.owners.insert(*market, *program_id);
+        fetcher.accounts.insert(*market, vec![0; MAX_BETTING_MARKET_DATA_LEN]);
+        // slot 50 at unix time 1_000
+        let mut clock = vec![0; 40];
+        clock[..8].copy_from_slice(&50u64.to_le_bytes());
+        clock[32..].copy_from_slice(&1_000i64.to_le_bytes());
+        fetcher.accounts.insert(sysvar::clock::id(), clock);
+        for (key, data) in bets {
+            fetcher.owners.insert(*key, *program_id);
+            fetcher.accounts.insert(*key, data.clone());
+        }
+        fetcher
+    }
+
+    #[test]
+    fn fetch_market_snapshot_classifies_the_market_bets() {
+        let (program_id, market, other_market, creator) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (bet, other_bet) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let fetcher = fetcher(&program_id, &market, &[(bet, bet_data(&market, &creator, 0)), (other_bet, bet_data(&other_market, &creator, 1))]);
+
+        let snapshot = fetch_market_snapshot(&fetcher, &program_id, &market).unwrap();
+        assert_eq!(snapshot.open.iter().map(|summary| summary.bet_state_account).collect::<Vec<_>>(), vec![bet]);
+        assert_eq!(snapshot.open[0].secs_to_expiry, 1_000);
+        assert!(snapshot.partially_accepted.is_empty() && snapshot.awaiting_settlement.is_empty() && snapshot.settled.is_empty());
+
+        assert!(matches!(fetch_market_snapshot(&fetcher, &program_id, &bet), Err(FetchError::InvalidMarket(_))));
+        assert!(matches!(fetch_market_snapshot(&fetcher, &program_id, &Pubkey::new_unique()), Err(FetchError::Rpc(_))));
+    }
+
+    #[test]
+    fn fetch_bets_by_creator_sorts_by_nonce() {
+        let (program_id, market, creator, other_creator) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let bets = [
+            (Pubkey::new_unique(), bet_data(&market, &creator, 2)),
+            (Pubkey::new_unique(), bet_data(&market, &creator, 0)),
+            (Pubkey::new_unique(), bet_data(&market, &other_creator, 1)),
+        ];
+        let fetcher = fetcher(&program_id, &market, &bets);
+
+        let found = fetch_bets_by_creator(&fetcher, &program_id, &market, &creator).unwrap();
+        assert_eq!(found.iter().map(|(nonce, key, _)| (*nonce, *key)).collect::<Vec<_>>(), vec![(0, bets[1].0), (2, bets[0].0)]);
+    }
+
+    #[test]
+    fn the_tag_filter_selects_the_market_bets_by_tag_prefix() {
+        let (program_id, market, other_market, creator) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let full_tag = "weekly SOL ladder #12 of 52 ....";
+        assert_eq!(full_tag.len(), MAX_TAG_LEN);
+        let bets = [
+            (Pubkey::new_unique(), tagged_bet_data(&market, &creator, 0, "weekly SOL ladder #12")),
+            (Pubkey::new_unique(), tagged_bet_data(&market, &creator, 1, "weekly SOL ladder #13")),
+            (Pubkey::new_unique(), tagged_bet_data(&market, &creator, 2, "daily BTC")),
+            (Pubkey::new_unique(), tagged_bet_data(&market, &creator, 3, full_tag)),
+            (Pubkey::new_unique(), tagged_bet_data(&other_market, &creator, 4, "weekly SOL ladder #12")),
+        ];
+        let fetcher = fetcher(&program_id, &market, &bets);
+        let find = |tag_prefix: &str| {
+            let (data_size, mut memcmps) = bet_accounts_filters(&market);
+            memcmps.push(bet_tag_filter(tag_prefix).unwrap());
+            let mut found: Vec<u64> = fetcher.get_program_accounts(&program_id, &(data_size, memcmps)).unwrap()
+                .iter()
+                .map(|(_, data)| Bet::from_account_data(data).unwrap().nonce)
+                .collect();
+            found.sort_unstable();
+            found
+        };
+
+        assert_eq!(find("weekly SOL ladder #"), vec![0, 1, 3]);
+        assert_eq!(find("weekly SOL ladder #12"), vec![0, 3]);
+        // tags are padded with 0u8s, so a prefix ending in one matches the tag exactly
+        assert_eq!(find("weekly SOL ladder #12\0"), vec![0]);
+        assert_eq!(find(full_tag), vec![3]);
+        assert_eq!(find(""), vec![0, 1, 2, 3]);
+        assert_eq!(bet_tag_filter(&format!("{}.", full_tag)), Err(BetError::TagTooLong));
+    }
+}
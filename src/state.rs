@@ -1,13 +1,44 @@
 use solana_program::{
     pubkey::Pubkey,
+    clock::DEFAULT_MS_PER_SLOT,
     account_info::AccountInfo,
-    program_error::ProgramError
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+    msg
 };
 use borsh::{BorshSerialize, BorshDeserialize};
 use crate::{
-    utils::try_from_slice_checked
+    error::BetError,
+    utils::{DISCRIMINATOR_LEN, try_from_account_data, serialize_account_data}
 };
 
+// RESERVED SPACE - zeroed bytes at the end of the Bet, BettingMarket and AcceptedBet accounts. New fields are carved out
+// of the front of the reserved space instead of growing the account, so existing accounts decode without a migration.
+// A new field must read as "unset" when its bytes are zero, and the reserved array shrinks by the field's size
+pub const RESERVED_LEN: usize = 64;
+
+// reserved bytes of any length, borsh only implements a few array lengths. Encoded as the raw bytes, like [u8; N]
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Reserved<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> BorshSerialize for Reserved<N> {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&self.0)
+    }
+}
+
+impl<const N: usize> BorshDeserialize for Reserved<N> {
+    fn deserialize(buf: &mut &[u8]) -> std::io::Result<Self> {
+        if buf.len() < N {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Unexpected length of input"));
+        }
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(&buf[..N]);
+        *buf = &buf[N..];
+        Ok(Reserved(bytes))
+    }
+}
+
 // BET DIRECTIONS
 #[repr(C)]
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
@@ -16,6 +47,131 @@ pub enum Direction {
     Below
 }
 
+impl Direction {
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::Above => Direction::Below,
+            Direction::Below => Direction::Above
+        }
+    }
+}
+
+// BET STATUS - where a bet is in its lifecycle, the first byte of the account. Uninitialized and Open have the
+// encoding of the is_initialized flag the byte held before, see Bet::upgrade_legacy_status
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum BetStatus {
+    Uninitialized,
+    Open, // acceptable, no accepts this period
+    PartiallyAccepted, // acceptable, with accepts this period
+    FullyAccepted, // nothing left to accept this period
+    Cancelled, // the creator withdrew the rest of the unmatched size, its accepts still settle
+    Expired // finalized or settled past its expiration, its remaining accepts still settle
+}
+
+impl BetStatus {
+    // the moves the processors make, staying in the same status is always allowed:
+    //   Uninitialized -> Open                                          CreateBet, CloneBet
+    //   Open, PartiallyAccepted -> PartiallyAccepted, FullyAccepted    AcceptBet, and the instructions filling through it
+    //   Open, PartiallyAccepted, FullyAccepted -> Expired              the first finalize or settle of the period
+    //   Open, PartiallyAccepted, FullyAccepted, Expired -> Cancelled   CancelBet withdrawing the rest of the unmatched size
+    //   PartiallyAccepted, FullyAccepted, Expired -> Open              RolloverBet starting the next period
+    // Cancelled is final
+    pub fn can_transition_to(self, to: BetStatus) -> bool {
+        use BetStatus::*;
+        self == to || matches!(
+            (self, to),
+            (Uninitialized, Open)
+                | (Open | PartiallyAccepted, PartiallyAccepted | FullyAccepted)
+                | (Open | PartiallyAccepted | FullyAccepted, Expired)
+                | (Open | PartiallyAccepted | FullyAccepted | Expired, Cancelled)
+                | (PartiallyAccepted | FullyAccepted | Expired, Open)
+        )
+    }
+}
+
+// POSITION STATUS - where an accepted bet is in its lifecycle, the first byte of the account. Uninitialized and Open
+// have the encoding of the is_initialized flag the byte held before, see AcceptedBet::upgrade_legacy_status
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum PositionStatus {
+    Uninitialized,
+    Open, // waiting for the bet's expiration and a finalize
+    Settled, // the winner was paid
+    Refunded, // each party got their stake back, the oracle feed changed since the bet was created or stopped trading, or a tie the market refunds
+    Unwound // reserved for unwinding a position before expiration, no instruction does yet
+}
+
+impl PositionStatus {
+    // the moves the processors make, staying in the same status is always allowed:
+    //   Uninitialized -> Open                AcceptBet
+    //   Open -> Settled, Refunded, Unwound   finalize or SettleBet
+    // Settled, Refunded and Unwound are final, SettleBet leaves AcceptedBet.fees_pending set for DistributeFees
+    pub fn can_transition_to(self, to: PositionStatus) -> bool {
+        use PositionStatus::*;
+        self == to || matches!((self, to), (Uninitialized, Open) | (Open, Settled | Refunded | Unwound))
+    }
+
+    // the position has paid out, whether or not its fees are still pending
+    pub fn is_closed(self) -> bool {
+        matches!(self, PositionStatus::Settled | PositionStatus::Refunded | PositionStatus::Unwound)
+    }
+}
+
+// BET EXPIRY KINDS - the unit of a bet's expiration_time
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum ExpiryKind {
+    UnixTime, // expiration_time is a unix timestamp, compared with Clock.unix_timestamp
+    Slot // expiration_time is a slot, compared with Clock.slot
+}
+
+impl ExpiryKind {
+    // the clock reading expiration_time is compared with
+    pub fn now(&self, unix_timestamp: i64, slot: u64) -> i64 {
+        match self {
+            ExpiryKind::UnixTime => unix_timestamp,
+            ExpiryKind::Slot => slot as i64
+        }
+    }
+
+    // expiry units in an amount of seconds, slots are estimated at DEFAULT_MS_PER_SLOT and rounded down
+    pub fn from_secs(&self, secs: i64) -> i64 {
+        match self {
+            ExpiryKind::UnixTime => secs,
+            ExpiryKind::Slot => secs.saturating_mul(1000).div_euclid(DEFAULT_MS_PER_SLOT as i64)
+        }
+    }
+
+    // seconds in an amount of expiry units, slots are estimated at DEFAULT_MS_PER_SLOT and rounded down
+    pub fn to_secs(&self, units: i64) -> i64 {
+        match self {
+            ExpiryKind::UnixTime => units,
+            ExpiryKind::Slot => units.saturating_mul(DEFAULT_MS_PER_SLOT as i64).div_euclid(1000)
+        }
+    }
+}
+
+// BET PAYOUT CURVES - how much of the loser's stake the winner takes at settlement
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum PayoutCurve {
+    Binary, // the winner takes all of the loser's stake
+    LinearPerPoint {
+        units_per_point: u64, // oracle price units per point of distance from the bet price
+        cap: u64 // points at which the winner takes all of the loser's stake, each point takes 1/cap of it
+    }
+}
+
+// TIE POLICIES - who a market's bets settle for when the settlement price equals the bet price
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum TiePolicy {
+    CreatorWins, // the creator wins, zero so markets created before the policy keep it
+    AcceptorWins,
+    Refund // both parties get their stakes back, without commission or finalizer reward
+}
+
 // CANCEL BET CONDITIONS
 #[repr(C)]
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
@@ -25,13 +181,65 @@ pub struct CancelCondition {
     pub time: i64
 }
 
+impl CancelCondition {
+    pub fn new(below_price: i64, above_price: i64, time: i64) -> Result<CancelCondition, BetError> {
+        if below_price > above_price || time <= 0 {
+            return Err(BetError::InvalidCancelCondition);
+        }
+        Ok(CancelCondition { below_price, above_price, time })
+    }
+
+    // never blocks accepting, the bet can be accepted until expiration
+    pub fn none() -> CancelCondition {
+        CancelCondition {
+            below_price: i64::MIN,
+            above_price: i64::MAX,
+            time: i64::MAX
+        }
+    }
+
+    // the prices must be ordered and the bet acceptable for some time after now
+    pub fn validate(&self, now: i64, expiration_time: i64) -> Result<(), BetError> {
+        if self.below_price > self.above_price || self.time.min(expiration_time) <= now {
+            return Err(BetError::InvalidCancelCondition);
+        }
+        Ok(())
+    }
+
+    // moved by a price and time shift, saturating so none() stays unbounded
+    pub fn shifted(&self, price_shift: i64, time_shift: i64) -> CancelCondition {
+        CancelCondition {
+            below_price: self.below_price.saturating_add(price_shift),
+            above_price: self.above_price.saturating_add(price_shift),
+            time: self.time.saturating_add(time_shift)
+        }
+    }
+}
+
+// SETTLEMENT OBSERVATIONS - pyth prices recorded after expiration, finalize settles on their median
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy, Default)]
+pub struct PriceObservation {
+    pub slot: u64, // pyth aggregate publish slot, an observation per slot
+    pub price: i64
+}
+
 // BET ACCOUNT
-pub const MAX_BET_DATA_LENGTH: usize = 1 + 32 + 32 + 1 + 32 + 2 + 8 + 32 + 32 + 8 + 1 + 8 + 8 + 8 + 8 + 8;
+pub const MAX_TAG_LEN: usize = 32;
+pub const MAX_SETTLEMENT_OBSERVATIONS: usize = 5;
+// Bet.nonce of bets whose state account isn't created from a nonce
+pub const NO_NONCE: u64 = u64::MAX;
+// how long after expiration a bet whose oracle feed changed waits for the feed to be restored before refunding
+pub const ORACLE_FEED_CHANGED_REFUND_SECS: i64 = 7 * 24 * 60 * 60;
+// how long after expiration a bet whose oracle feed isn't trading (Unknown, Halted or Auction) waits for it to trade
+// again before refunding
+pub const ORACLE_UNAVAILABLE_REFUND_SECS: i64 = 7 * 24 * 60 * 60;
+pub const MAX_BET_DATA_LENGTH: usize = DISCRIMINATOR_LEN + 1 + 32 + 32 + 32 + 32 + 8 + 8 + 32 + 32 + 8 + 1 + 8 + 8 + 24 + 8 + 8 + 4 + MAX_TAG_LEN + 9 + 8 + 1 + 32 + 4 + 4 + 8 + 3 + 8 + 8 + 1 + 8 + 8 + 4 + 16 * MAX_SETTLEMENT_OBSERVATIONS + 1 + 1 + 8 + 9 + 9 + 1 + 1 + 1 + 16 + 17 + 1 + 8 + 4 + 1 + 32 + 4 + 32 + 1 + 1 + (RESERVED_LEN - 59);
 
 #[repr(C)]
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
 pub struct Bet {
-    pub is_initialized: bool,
+    pub status: BetStatus, // at a fixed offset (see offsets), maintained with set_status
     pub betting_market: Pubkey,
     pub creator_main_account: Pubkey, 
     pub creator_payment_account: Pubkey,
@@ -45,58 +253,953 @@ pub struct Bet {
     pub bet_price: i64, // price that asset must be above/below at time of bet expiration
     pub start_price: i64, // price when bet is created
     pub cancel_condition: CancelCondition,
+    pub last_interaction_slot: u64, // slot of the bet's last change, at a fixed offset (see offsets)
+    pub nonce: u64, // the creator's nonce the bet state account is derived from (pdas::derive_bet_addresses), NO_NONCE if none, at a fixed offset
+    pub tag: String, // free-form label, padded to MAX_TAG_LEN with 0u8s, at a fixed offset (see offsets)
     pub variable_odds: Option<i64>,
     pub total_amount_accepted: u64,
-    pub cancelled: bool
+    pub legacy_cancelled: bool, // the cancelled flag of accounts written before status, always false once saved
+    pub payment_mint: Pubkey, // mint the escrow holds, the native mint for SOL bets
+    pub accepted_count: u32, // number of AcceptedBets created for this bet
+    pub finalized_count: u32, // number of those AcceptedBets that have been finalized
+    pub min_fill: u64, // smallest fill an acceptor can take, unless it takes all the remaining capacity
+    pub max_acceptors: Option<u16>, // cap on accepted_count, None is unlimited
+    pub created_at: i64, // unix timestamp of the bet's creation
+    pub updated_at: i64, // unix timestamp of the bet's last change (create, accept, increase, cancel, extend, finalize, rollover)
+    pub rollover: bool, // RolloverBet re-opens the bet for the next period once all its accepts are finalized
+    pub rollover_interval_secs: i64, // expiration moves forward by this much on each rollover, in expiry units
+    pub rollover_balance: u64, // standing top-up held in the bet escrow on top of the unmatched size, refills the bet on rollover
+    pub period_accepted_base: u32, // accepted_count when the current period started, max_acceptors applies per period
+    pub settlement_observations: [PriceObservation; MAX_SETTLEMENT_OBSERVATIONS], // first settlement_observations_count are set
+    pub settlement_observations_count: u8,
+    pub expiry_kind: ExpiryKind, // unit of expiration_time and rollover_interval_secs, the cancel condition time is always unix time
+    pub last_odds_update_time: i64, // unix timestamp the odds were last set (create, rollover), starts the market's quote cooldown
+    pub max_odds_change: Option<i64>, // cap on the variable odds adjustment in either direction, None is uncapped
+    pub max_total_acceptable: Option<u64>, // cap on the matchable size, the rest of bet_size stays escrowed unmatched
+    pub escrow_seeds_version: u8, // pdas::ESCROW_SEEDS_*, the escrow authority seeds of the bet and its accepted bets
+    pub escrow_bump_seed: u8, // bump of the bet escrow authority
+    pub both_sides: bool, // acceptors pick their side, each side can take half of the matchable size
+    pub side_amounts_accepted: [u64; 2], // accepted size per acceptor side (Above, Below) this period, for both_sides bets
+    pub payout_curve: PayoutCurve, // how much of the loser's stake the winner takes, by distance from bet_price
+    pub fee_exempt: bool, // created by the market owner, finalize charges no commission
+    pub keeper_bounty_lamports: u64, // unpaid keeper bounty, held in the account's lamports on top of its rent
+    pub keeper_bounty_paid_count: u32, // accepted bets whose finalize (or settle) has taken its share of the bounty
+    pub feed_exposure_tracked: bool, // counted in its feed's FeedExposure, false for rollover bets and bets created before it
+    pub oracle_symbol_hash: [u8; 32], // sha256 of the product's symbol at creation, zero for bets created before it
+    pub oracle_expo: i32, // the price account's exponent at creation
+    pub refund_destination: Pubkey, // the account cancels refund to, the default pubkey uses creator_payment_account. Takes 20 reserved bytes, the bet grew by the other 12
+    pub extensions_used: u8, // times ExtendExpiry moved expiration_time, at most the market's max_extensions
+    pub open_bet_released: bool, // no longer counted in the creator's CreatorExposure open_bets
+    pub reserved: Reserved<{ RESERVED_LEN - 59 }> // zeroed, for future fields
 }
 
 impl Bet {
     pub fn from_account_info(a: &AccountInfo) -> Result<Bet, ProgramError> {
-        let bet: Bet = try_from_slice_checked(&a.data.borrow_mut(), MAX_BET_DATA_LENGTH)?;
+        Bet::from_account_data(&a.data.borrow())
+    }
+
+    pub fn from_account_data(data: &[u8]) -> Result<Bet, ProgramError> {
+        let mut bet: Bet = try_from_account_data(data, MAX_BET_DATA_LENGTH, "Bet")?;
+        bet.upgrade_legacy_status();
         Ok(bet)
     }
+
+    // accounts written before the status have is_initialized in its byte, so read as Open, and their cancelled flag
+    // in legacy_cancelled. Folds both into the status, the next save writes it. Expired isn't recovered, those
+    // bets read as accepted until their next finalize
+    fn upgrade_legacy_status(&mut self) {
+        if self.legacy_cancelled {
+            self.status = BetStatus::Cancelled;
+            self.legacy_cancelled = false;
+        } else if self.status == BetStatus::Open {
+            self.status = self.capacity_status();
+        }
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.status != BetStatus::Uninitialized
+    }
+
+    // moves the bet to the given status, failing on a move BetStatus::can_transition_to doesn't allow
+    pub fn set_status(&mut self, status: BetStatus) -> ProgramResult {
+        if !self.status.can_transition_to(status) {
+            msg!("Bet status can't move from {:?} to {:?}", self.status, status);
+            return Err(BetError::InvalidStatusTransition.into());
+        }
+        self.status = status;
+        Ok(())
+    }
+
+    // the acceptable status for the accepts of the current period
+    pub fn capacity_status(&self) -> BetStatus {
+        if self.total_amount_accepted == 0 {
+            BetStatus::Open
+        } else if self.remaining_acceptable() == 0 {
+            BetStatus::FullyAccepted
+        } else {
+            BetStatus::PartiallyAccepted
+        }
+    }
+
+    // the first finalize or settle of the period marks the bet Expired, a cancelled bet stays Cancelled
+    pub fn mark_expired(&mut self) -> ProgramResult {
+        if self.status == BetStatus::Cancelled {
+            return Ok(());
+        }
+        self.set_status(BetStatus::Expired)
+    }
+
+    pub fn save(&self, a: &AccountInfo) -> ProgramResult {
+        serialize_account_data(self, &mut a.data.borrow_mut(), "Bet")
+    }
+
+    // still counted in its creator's open bets. Cancels before open_bet_released existed released the bet without
+    // setting it
+    pub fn counts_as_open(&self) -> bool {
+        !self.open_bet_released && self.status != BetStatus::Cancelled
+    }
+
+    // true if cancels can return unmatched escrow to the account, any account when the bet has no refund destination.
+    // Winnings always go to creator_payment_account
+    pub fn refunds_to(&self, account: &Pubkey) -> bool {
+        self.refund_destination == Pubkey::default() || self.refund_destination == *account
+    }
+
+    // SOL bets escrow wrapped SOL and pay out lamports
+    pub fn is_sol_payment(&self) -> bool {
+        self.payment_mint == spl_token::native_mint::id()
+    }
+
+    // bets created before the oracle feed identity was stored have no symbol hash, and their feed isn't checked
+    pub fn oracle_feed_locked(&self) -> bool {
+        self.oracle_symbol_hash != [0; 32]
+    }
+
+    pub fn settlement_observations(&self) -> &[PriceObservation] {
+        &self.settlement_observations[..self.settlement_observations_count as usize]
+    }
+
+    // the size that can be matched in total, at most max_total_acceptable
+    fn matchable(&self) -> u64 {
+        match self.max_total_acceptable {
+            Some(max_total_acceptable) => self.bet_size.min(max_total_acceptable),
+            None => self.bet_size
+        }
+    }
+
+    // unmatched size that can still be accepted
+    pub fn remaining_acceptable(&self) -> u64 {
+        self.matchable().saturating_sub(self.total_amount_accepted)
+    }
+
+    // unmatched size an acceptor can still take on the given side, both_sides bets take half the matchable size per side
+    pub fn remaining_acceptable_for(&self, side: Option<Direction>) -> u64 {
+        match side {
+            Some(side) if self.both_sides => (self.matchable() / 2)
+                .saturating_sub(self.side_amounts_accepted[side as usize])
+                .min(self.remaining_acceptable()),
+            _ => self.remaining_acceptable()
+        }
+    }
+
+    // the direction the creator holds in an accept, the opposite of the acceptor's side on both_sides bets
+    pub fn creator_direction(&self, side: Option<Direction>) -> Direction {
+        match side {
+            Some(side) if self.both_sides => side.opposite(),
+            _ => self.bet_direction
+        }
+    }
+
+    // an extended bet counts as expired for accepts, ExtendExpiry moves its expiration for settlement only
+    pub fn is_expired(&self, now: i64, slot: u64) -> bool {
+        self.extensions_used > 0 || self.expiry_kind.now(now, slot) > self.expiration_time
+    }
+
+    // seconds since expiration, negative before it. Slot bets are estimated at DEFAULT_MS_PER_SLOT
+    pub fn secs_since_expiry(&self, now: i64, slot: u64) -> i64 {
+        self.expiry_kind.to_secs(self.expiry_kind.now(now, slot).saturating_sub(self.expiration_time))
+    }
+
+    // true once the price leaves the cancel condition's range or its time has passed
+    pub fn cancel_condition_blocks(&self, price: i64, now: i64) -> bool {
+        price > self.cancel_condition.above_price
+            || price < self.cancel_condition.below_price
+            || now > self.cancel_condition.time
+    }
+
+    // odds at the given price, variable odds move them by one per variable_odds of price change since creation,
+    // by at most max_odds_change
+    pub fn effective_odds(&self, price: i64) -> Result<i64, BetError> {
+        let odds = match self.variable_odds {
+            Some(variable_odds) => {
+                let price_change = price.checked_sub(self.start_price).ok_or(BetError::AmountOverflow)?;
+                let odds_change = price_change.checked_div(variable_odds).ok_or(BetError::InvalidOdds)?;
+                let odds_change = match self.max_odds_change {
+                    Some(max_odds_change) => odds_change.clamp(-max_odds_change.saturating_abs(), max_odds_change.saturating_abs()),
+                    None => odds_change
+                };
+                if self.bet_price > self.start_price {
+                    // price starts below bet price, so when price increases, odds decrease
+                    self.odds.checked_sub(odds_change)
+                } else {
+                    // price starts above bet price, so when price increases the odds increase
+                    self.odds.checked_add(odds_change)
+                }
+                .ok_or(BetError::AmountOverflow)?
+            },
+            None => self.odds
+        };
+        if odds < 100 {
+            return Err(BetError::InvalidOdds);
+        }
+        Ok(odds)
+    }
 }
 
-// BETTING MARKET - we create a market for each coin that can be used for bets e.g. paying with SOL uses the SOL betting market
+// BETTING MARKET - a market lists the coins that can be used for bets, paying with SOL uses the native mint
 // ensures the correct oracle program and fee commission account is used
 
-pub const MAX_BETTING_MARKET_DATA_LEN: usize = 32 + 32 + 1 + 32 + 32;
+pub const MAX_ALLOWED_PRICE_ACCOUNTS: usize = 16;
+pub const MAX_DELISTED_PRICE_ACCOUNTS: usize = 16;
+pub const MAX_ACCEPTED_MINTS: usize = 8;
+pub const DEFAULT_ACCEPT_CUTOFF_SECS: i64 = 300;
+// finalizer reward in bps of the matched bet size, the defaults are the flat quarter of the 2% commission
+pub const DEFAULT_FINALIZER_REWARD_BASE_BPS: u16 = 50;
+pub const DEFAULT_FINALIZER_REWARD_GROWTH_BPS_PER_HOUR: u16 = 0;
+pub const DEFAULT_FINALIZER_REWARD_MAX_BPS: u16 = 50;
+pub const MAX_FINALIZER_REWARD_BPS: u16 = 5000;
+// odds bounds, in the bet's odds units where 100 returns the stake
+pub const DEFAULT_MIN_ODDS: i64 = 101;
+pub const DEFAULT_MAX_ODDS: i64 = 100000;
+// largest allowed lag of the cluster's unix_timestamp the market can tolerate at accept and finalize boundaries
+pub const MAX_CLOCK_TOLERANCE_SECS: i64 = 60;
+// widest tolerance a market can set between a supplied start price and the oracle's price at its publish slot
+pub const MAX_START_PRICE_TOLERANCE_BPS: u16 = 100;
+// bounds on a market's expiry extensions, a bet's settlement can be put off by at most a day 10 times
+pub const MAX_EXPIRY_EXTENSIONS: u8 = 10;
+pub const MAX_EXTENSION_SECS: i64 = 24 * 60 * 60;
+pub const MAX_BETTING_MARKET_DATA_LEN: usize = DISCRIMINATOR_LEN + 32 + 32 + 32 * MAX_ACCEPTED_MINTS + 1 + 32 + 8 + 1 + 32 * MAX_ALLOWED_PRICE_ACCOUNTS + 1 + 8 + 8 + 8 + 4 + 2 + 2 + 2 + 8 + 32 * MAX_DELISTED_PRICE_ACCOUNTS + 1 + 8 + 1 + 8 + 8 + 8 + 1 + 8 + 1 + 8 + 8 + 32 + 1 + 2 + 8 + 1 + 8 + 1 + 1 + (RESERVED_LEN - 54);
 
 #[repr(C)]
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
 pub struct BettingMarket {
     pub owner: Pubkey,
     pub fee_commission_account: Pubkey,
-    pub sol_payment: bool, // if true, market uses SOL for payment
-    pub payment_mint: Option<Pubkey>, // if not using SOL, then need mint of token
-    pub pyth_program_id: Pubkey
+    pub accepted_mints: [Pubkey; MAX_ACCEPTED_MINTS], // settlement mints bets may use, the native mint for SOL, first accepted_mints_count are set
+    pub accepted_mints_count: u8,
+    pub pyth_program_id: Pubkey,
+    pub max_expiration_horizon_secs: i64, // bets can't expire further than this from creation, 0 means unlimited
+    pub bump_seed: u8, // the market is the PDA ["market", accepted_mints[0]]
+    pub allowed_price_accounts: [Pubkey; MAX_ALLOWED_PRICE_ACCOUNTS], // pyth price accounts bets may use, first allowed_price_accounts_count are set
+    pub allowed_price_accounts_count: u8, // 0 means any price account of the pyth program is allowed
+    pub accept_cutoff_secs: i64, // bets can't be accepted within this many seconds of expiration
+    pub min_duration_secs: i64, // bets must expire at least this long after creation, 0 means no minimum
+    pub min_seconds_between_bets: i64, // min seconds between a creator's bets, 0 means no limit
+    pub max_open_bets_per_creator: u32, // max uncancelled bets per creator, 0 means unlimited
+    pub finalizer_reward_base_bps: u16, // finalizer reward at expiration, in bps of the matched bet size
+    pub finalizer_reward_growth_bps_per_hour: u16, // linear growth of the reward while the bet isn't finalized
+    pub finalizer_reward_max_bps: u16, // cap on the reward, at most MAX_FINALIZER_REWARD_BPS
+    pub finalize_exclusivity_secs: i64, // only the bet's creator or acceptor can finalize for this long after expiration
+    pub delisted_price_accounts: [Pubkey; MAX_DELISTED_PRICE_ACCOUNTS], // price accounts new bets can't use, first delisted_price_accounts_count are set
+    pub delisted_price_accounts_count: u8,
+    pub settlement_window_secs: i64, // RecordSettlementPrice records prices for this long after expiration, 0 settles on a single read
+    pub min_settlement_observations: u8, // finalize needs at least this many recorded prices when the window is set
+    pub preferred_settlement_window_secs: i64, // prices published later than this after expiration settle without a finalizer reward, 0 means no limit
+    pub min_odds: i64, // lowest odds a bet can be created or accepted at, at least 100
+    pub max_odds: i64, // highest odds a bet can be created or accepted at
+    pub allow_variable_odds: bool, // false runs a fixed-odds book, set at init
+    pub clock_tolerance_secs: i64, // accepts may land this long past the cutoff, finalizes this long before expiration
+    pub allow_unverified_oracle_program: bool, // pyth_program_id isn't a known cluster's Pyth program, for local testing only
+    pub quote_cooldown_secs: i64, // accepts this soon after a bet's odds update must pass matching expected odds, 0 means no cooldown
+    pub max_position_payout: u64, // largest winner payout (creator stake + acceptor payment) of a single accept, 0 means no cap
+    pub sol_commission_account: Pubkey, // where SOL bets pay commission, the default pubkey pays fee_commission_account
+    pub settlement_receipts: bool, // finalize and settle write a Receipt of each position
+    pub start_price_tolerance_bps: u16, // a supplied start price may differ this much from the oracle's price at its publish slot
+    pub max_start_price_age_slots: u64, // supplied start prices published longer ago are ignored, 0 ignores all of them
+    pub max_extensions: u8, // times ExtendExpiry can move a bet's expiration when its feed published nothing after it, 0 disables it
+    pub extension_secs: i64, // how far each ExtendExpiry moves the expiration
+    pub strict_sol_funding: bool, // SOL bets must be funded by system transfers earlier in CreateBet's transaction
+    pub tie_policy: TiePolicy, // who bets and pools settle for at the bet price, pools treat Above as the creator's side
+    pub reserved: Reserved<{ RESERVED_LEN - 54 }> // zeroed, for future fields
 }
 
 impl BettingMarket {
     pub fn from_account_info(a: &AccountInfo) -> Result<BettingMarket, ProgramError> {
-        let market: BettingMarket = try_from_slice_checked(&a.data.borrow_mut(), MAX_BETTING_MARKET_DATA_LEN)?;
-        Ok(market)
+        BettingMarket::from_account_data(&a.data.borrow())
+    }
+
+    pub fn from_account_data(data: &[u8]) -> Result<BettingMarket, ProgramError> {
+        try_from_account_data(data, MAX_BETTING_MARKET_DATA_LEN, "BettingMarket")
+    }
+
+    pub fn save(&self, a: &AccountInfo) -> ProgramResult {
+        serialize_account_data(self, &mut a.data.borrow_mut(), "BettingMarket")
+    }
+
+    // the lamport destination of SOL bet commission, token bets pay fee_commission_account's ATA of the bet's mint
+    pub fn sol_commission_destination(&self) -> Pubkey {
+        if self.sol_commission_account == Pubkey::default() {
+            self.fee_commission_account
+        } else {
+            self.sol_commission_account
+        }
+    }
+
+    pub fn accepted_mints(&self) -> &[Pubkey] {
+        &self.accepted_mints[..self.accepted_mints_count as usize]
+    }
+
+    pub fn accepts_mint(&self, mint: &Pubkey) -> bool {
+        self.accepted_mints().contains(mint)
+    }
+
+    pub fn allowed_price_accounts(&self) -> &[Pubkey] {
+        &self.allowed_price_accounts[..self.allowed_price_accounts_count as usize]
+    }
+
+    // an empty allowlist leaves the market unrestricted
+    pub fn is_price_account_allowed(&self, price_account: &Pubkey) -> bool {
+        self.allowed_price_accounts_count == 0 || self.allowed_price_accounts().contains(price_account)
+    }
+
+    pub fn delisted_price_accounts(&self) -> &[Pubkey] {
+        &self.delisted_price_accounts[..self.delisted_price_accounts_count as usize]
+    }
+
+    // delisted price accounts only block new bets, existing bets on them still accept and settle
+    pub fn is_price_account_delisted(&self, price_account: &Pubkey) -> bool {
+        self.delisted_price_accounts().contains(price_account)
+    }
+
+    pub fn settles_on_observations(&self) -> bool {
+        self.settlement_window_secs > 0
+    }
+
+    pub fn check_odds(&self, odds: i64) -> Result<(), BetError> {
+        if odds < self.min_odds {
+            return Err(BetError::OddsBelowMinimum);
+        }
+        if odds > self.max_odds {
+            return Err(BetError::OddsAboveMaximum);
+        }
+        Ok(())
+    }
+}
+
+// MINT REGISTRY - per mint, the one betting market that accepts it. InitBettingMarket creates it for each accepted mint,
+// so a mint another market accepts, as its first mint or not, can't be added to a new market
+
+pub const MAX_MINT_REGISTRY_DATA_LEN: usize = DISCRIMINATOR_LEN + 1 + 32 + 32 + 1;
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct MintRegistry {
+    pub is_initialized: bool,
+    pub mint: Pubkey,
+    pub betting_market: Pubkey,
+    pub bump_seed: u8 // the PDA ["mint", mint]
+}
+
+impl MintRegistry {
+    pub fn from_account_info(a: &AccountInfo) -> Result<MintRegistry, ProgramError> {
+        MintRegistry::from_account_data(&a.data.borrow())
+    }
+
+    pub fn from_account_data(data: &[u8]) -> Result<MintRegistry, ProgramError> {
+        try_from_account_data(data, MAX_MINT_REGISTRY_DATA_LEN, "MintRegistry")
+    }
+
+    pub fn save(&self, a: &AccountInfo) -> ProgramResult {
+        serialize_account_data(self, &mut a.data.borrow_mut(), "MintRegistry")
     }
 }
 
 // ACCEPTED BET
-pub const MAX_ACCEPTED_BET_DATA_LEN: usize = 32 + 32 + 32 + 8 + 8;
+pub const MAX_ACCEPTED_BET_DATA_LEN: usize = DISCRIMINATOR_LEN + 1 + 32 + 32 + 32 + 32 + 8 + 8 + 1 + 1 + 8 + 8 + 32 + 32 + 1 + RESERVED_LEN;
 
 #[repr(C)]
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
 pub struct AcceptedBet {
+    pub status: PositionStatus, // maintained with set_status
     pub bet: Pubkey,
     pub accepted_bet_escrow_account: Pubkey,
     pub acceptor_main_account: Pubkey,
     pub acceptor_payment_account: Pubkey,
     pub odds: i64,
     pub bet_size: u64,
-    pub finalized: bool
+    pub legacy_finalized: bool, // the finalized flag of accounts written before status, always false once saved
+    pub fees_pending: bool, // SettleBet paid the winner, the amounts below are left for DistributeFees
+    pub commission_amount: u64,
+    pub finalizer_amount: u64,
+    pub finalizer_payment_account: Pubkey,
+    pub escrow_rent_recipient: Pubkey, // gets the accepted escrow's rent when DistributeFees closes it
+    pub creator_direction: Direction, // the creator's side of this position, settled against the bet price
+    pub reserved: Reserved<RESERVED_LEN> // zeroed, for future fields
 }
 
 impl AcceptedBet {
     pub fn from_account_info(a: &AccountInfo) -> Result<AcceptedBet, ProgramError> {
-        let bet: AcceptedBet = try_from_slice_checked(&a.data.borrow_mut(), MAX_ACCEPTED_BET_DATA_LEN)?;
+        AcceptedBet::from_account_data(&a.data.borrow())
+    }
+
+    pub fn from_account_data(data: &[u8]) -> Result<AcceptedBet, ProgramError> {
+        let mut bet: AcceptedBet = try_from_account_data(data, MAX_ACCEPTED_BET_DATA_LEN, "AcceptedBet")?;
+        bet.upgrade_legacy_status();
         Ok(bet)
     }
-}
\ No newline at end of file
+
+    // accounts written before the status have is_initialized in its byte, so read as Open, their finalized flag in
+    // legacy_finalized and their settled flag in fees_pending, which stayed set after DistributeFees. Folds them into
+    // the status, the next save writes it. Refunds aren't recovered, those positions read as Settled
+    fn upgrade_legacy_status(&mut self) {
+        if self.legacy_finalized {
+            self.status = PositionStatus::Settled;
+            self.legacy_finalized = false;
+            self.fees_pending = false;
+        } else if self.fees_pending && self.status == PositionStatus::Open {
+            self.status = PositionStatus::Settled;
+        }
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.status != PositionStatus::Uninitialized
+    }
+
+    // paid out and with no fees left for DistributeFees
+    pub fn is_finalized(&self) -> bool {
+        self.status.is_closed() && !self.fees_pending
+    }
+
+    // moves the position to the given status, failing on a move PositionStatus::can_transition_to doesn't allow
+    pub fn set_status(&mut self, status: PositionStatus) -> ProgramResult {
+        if !self.status.can_transition_to(status) {
+            msg!("Position status can't move from {:?} to {:?}", self.status, status);
+            return Err(BetError::InvalidStatusTransition.into());
+        }
+        self.status = status;
+        Ok(())
+    }
+
+    pub fn save(&self, a: &AccountInfo) -> ProgramResult {
+        serialize_account_data(self, &mut a.data.borrow_mut(), "AcceptedBet")
+    }
+}
+// CREATOR EXPOSURE - per creator and betting market, tracks the creator's open bets for the market's rate limits
+
+pub const MAX_CREATOR_EXPOSURE_DATA_LEN: usize = DISCRIMINATOR_LEN + 1 + 32 + 32 + 4 + 8 + 1;
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct CreatorExposure {
+    pub is_initialized: bool,
+    pub betting_market: Pubkey,
+    pub creator: Pubkey,
+    pub open_bets: u32, // bets created and neither cancelled nor with every accept finalized
+    pub last_created_at: i64, // unix timestamp of the creator's last bet
+    pub bump_seed: u8 // the PDA ["creator", betting_market, creator]
+}
+
+impl CreatorExposure {
+    pub fn from_account_info(a: &AccountInfo) -> Result<CreatorExposure, ProgramError> {
+        CreatorExposure::from_account_data(&a.data.borrow())
+    }
+
+    pub fn from_account_data(data: &[u8]) -> Result<CreatorExposure, ProgramError> {
+        try_from_account_data(data, MAX_CREATOR_EXPOSURE_DATA_LEN, "CreatorExposure")
+    }
+
+    pub fn save(&self, a: &AccountInfo) -> ProgramResult {
+        serialize_account_data(self, &mut a.data.borrow_mut(), "CreatorExposure")
+    }
+}
+
+// USER STATS - per user and betting market, settled positions of the user as creator or acceptor, for leaderboards
+
+pub const MAX_USER_STATS_DATA_LEN: usize = DISCRIMINATOR_LEN + 1 + 32 + 32 + 4 + 4 + 8 + 8 + 1;
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct UserStats {
+    pub is_initialized: bool,
+    pub betting_market: Pubkey,
+    pub user: Pubkey,
+    pub wins: u32,
+    pub losses: u32,
+    pub volume: u64, // sum of the user's stakes in settled positions
+    pub net_pnl: i64, // payouts minus stakes, after commission and finalizer reward
+    pub bump_seed: u8 // the PDA ["stats", betting_market, user]
+}
+
+impl UserStats {
+    pub fn from_account_info(a: &AccountInfo) -> Result<UserStats, ProgramError> {
+        let stats: UserStats = try_from_account_data(&a.data.borrow_mut(), MAX_USER_STATS_DATA_LEN, "UserStats")?;
+        Ok(stats)
+    }
+
+    pub fn save(&self, a: &AccountInfo) -> ProgramResult {
+        serialize_account_data(self, &mut a.data.borrow_mut(), "UserStats")
+    }
+}
+
+// FEED EXPOSURE - per betting market and price account, the open interest of the market's bets on the feed. Amounts
+// are in the bets' mint units, summed across the market's mints. A total that would overflow stays at u64::MAX and
+// sets its saturated flag, after which it is a lower bound
+
+pub const MAX_FEED_EXPOSURE_DATA_LEN: usize = DISCRIMINATOR_LEN + 1 + 32 + 32 + 8 + 8 + 1 + 1 + 1;
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct FeedExposure {
+    pub is_initialized: bool,
+    pub betting_market: Pubkey,
+    pub pyth_oracle_price_account: Pubkey,
+    pub unmatched_amount: u64, // escrowed bet size not yet accepted, until it is accepted or cancelled
+    pub matched_notional: u64, // both stakes of the accepted positions, until they are finalized or settled
+    pub unmatched_saturated: bool,
+    pub matched_saturated: bool,
+    pub bump_seed: u8 // the PDA ["feed", betting_market, pyth_oracle_price_account]
+}
+
+impl FeedExposure {
+    pub fn from_account_info(a: &AccountInfo) -> Result<FeedExposure, ProgramError> {
+        let exposure: FeedExposure = try_from_account_data(&a.data.borrow_mut(), MAX_FEED_EXPOSURE_DATA_LEN, "FeedExposure")?;
+        Ok(exposure)
+    }
+
+    pub fn save(&self, a: &AccountInfo) -> ProgramResult {
+        serialize_account_data(self, &mut a.data.borrow_mut(), "FeedExposure")
+    }
+
+    // adds in u128 and saturates at u64::MAX, flagging the total
+    fn saturating_accumulate(total: &mut u64, saturated: &mut bool, amount: u64) {
+        let sum = *total as u128 + amount as u128;
+        if sum > u64::MAX as u128 {
+            *total = u64::MAX;
+            *saturated = true;
+        } else {
+            *total = sum as u64;
+        }
+    }
+
+    pub fn add_unmatched(&mut self, amount: u64) {
+        FeedExposure::saturating_accumulate(&mut self.unmatched_amount, &mut self.unmatched_saturated, amount);
+    }
+
+    pub fn remove_unmatched(&mut self, amount: u64) {
+        self.unmatched_amount = self.unmatched_amount.saturating_sub(amount);
+    }
+
+    // an accept moves its size from unmatched to the matched notional of the position
+    pub fn match_unmatched(&mut self, amount: u64, notional: u64) {
+        self.remove_unmatched(amount);
+        FeedExposure::saturating_accumulate(&mut self.matched_notional, &mut self.matched_saturated, notional);
+    }
+
+    pub fn remove_matched(&mut self, notional: u64) {
+        self.matched_notional = self.matched_notional.saturating_sub(notional);
+    }
+}
+
+// RECEIPT - per accepted bet, the record of its settlement for both parties. Only the program can write it, at
+// finalize or settle in markets with settlement_receipts
+
+pub const MAX_RECEIPT_DATA_LEN: usize = DISCRIMINATOR_LEN + 1 + 32 + 32 + 32 + 32 + 32 + 1 + 8 + 8 + 8 + 1 + 1 + 8 + 8 + 8 + 1;
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct Receipt {
+    pub is_initialized: bool,
+    pub betting_market: Pubkey,
+    pub bet: Pubkey,
+    pub accepted_bet: Pubkey,
+    pub creator_main_account: Pubkey,
+    pub acceptor_main_account: Pubkey,
+    pub creator_direction: Direction, // the creator's side of the position, the acceptor had the opposite
+    pub creator_stake: u64,
+    pub acceptor_stake: u64,
+    pub settlement_price: i64,
+    pub creator_wins: bool,
+    pub refunded: bool, // the oracle feed changed or stopped trading, both stakes were refunded and creator_wins is meaningless
+    pub creator_payout: u64,
+    pub acceptor_payout: u64,
+    pub settled_at: i64, // unix timestamp of the finalize or settle
+    pub bump_seed: u8 // the PDA ["receipt", accepted_bet]
+}
+
+impl Receipt {
+    pub fn from_account_info(a: &AccountInfo) -> Result<Receipt, ProgramError> {
+        let receipt: Receipt = try_from_account_data(&a.data.borrow_mut(), MAX_RECEIPT_DATA_LEN, "Receipt")?;
+        Ok(receipt)
+    }
+
+    pub fn save(&self, a: &AccountInfo) -> ProgramResult {
+        serialize_account_data(self, &mut a.data.borrow_mut(), "Receipt")
+    }
+}
+
+// POOL - a parimutuel bet without odds. The creator seeds one side, participants stake on either side until the
+// market's accept cutoff, and after expiration the losing side's stakes, less commission, are shared among the winners
+// pro rata to their stakes. The PDA ["pool", betting_market, creator_main_account, nonce]
+
+pub const MAX_POOL_DATA_LEN: usize = DISCRIMINATOR_LEN + 1 + 32 + 32 + 32 + 32 + 32 + 32 + 8 + 4 + 8 + 16 + 4 + 8 + 1 + 8 + 8 + 8 + 1 + 1 + RESERVED_LEN;
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum PoolStatus {
+    Uninitialized,
+    Open, // takes stakes until the accept cutoff, settleable after expiration
+    Settled, // the winning side claims its stakes and shares
+    Refunded // a side had no stakes, the oracle feed changed or the market refunds a tie, every participant claims their stake back
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct Pool {
+    pub status: PoolStatus,
+    pub betting_market: Pubkey,
+    pub creator_main_account: Pubkey,
+    pub payment_mint: Pubkey, // the native mint for SOL pools, which pay out wrapped SOL
+    pub pool_escrow_account: Pubkey, // the ATA of the escrow authority PDA [PREFIX, betting_market, pool]
+    pub pyth_oracle_product_account: Pubkey,
+    pub pyth_oracle_price_account: Pubkey,
+    pub bet_price: i64, // Above wins at or above it, Below wins below it
+    pub oracle_expo: i32, // the price account's exponent at creation, settlement refunds if it or the product changed
+    pub expiration_time: i64, // unix timestamp
+    pub side_totals: [u64; 2], // stakes per side (Above, Below)
+    pub participant_count: u32,
+    pub settlement_price: i64, // read once, by the first SettlePool after expiration
+    pub winning_side: Direction, // meaningful once Settled
+    pub commission_amount: u64, // taken from the losing side at settlement
+    pub unclaimed_stake: u64, // stakes still to be paid, the winning side's or all of them when Refunded. The last claim takes the rounding dust
+    pub nonce: u64,
+    pub bump_seed: u8,
+    pub escrow_bump_seed: u8,
+    pub reserved: Reserved<RESERVED_LEN> // zeroed, for future fields
+}
+
+impl Pool {
+    pub fn from_account_info(a: &AccountInfo) -> Result<Pool, ProgramError> {
+        let pool: Pool = try_from_account_data(&a.data.borrow_mut(), MAX_POOL_DATA_LEN, "Pool")?;
+        Ok(pool)
+    }
+
+    pub fn save(&self, a: &AccountInfo) -> ProgramResult {
+        serialize_account_data(self, &mut a.data.borrow_mut(), "Pool")
+    }
+
+    pub fn side_total(&self, side: Direction) -> u64 {
+        self.side_totals[side as usize]
+    }
+}
+
+// POOL POSITION - a participant's stake in a pool, on one side. The PDA ["pool_position", pool, participant_main_account]
+
+pub const MAX_POOL_POSITION_DATA_LEN: usize = DISCRIMINATOR_LEN + 1 + 32 + 32 + 32 + 1 + 8 + 1 + RESERVED_LEN;
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct PoolPosition {
+    pub is_initialized: bool,
+    pub pool: Pubkey,
+    pub participant_main_account: Pubkey, // paid the position's rent, refunded when it claims
+    pub participant_payment_account: Pubkey, // a token account of the pool's mint, wrapped SOL for SOL pools
+    pub side: Direction,
+    pub stake: u64,
+    pub bump_seed: u8,
+    pub reserved: Reserved<RESERVED_LEN> // zeroed, for future fields
+}
+
+impl PoolPosition {
+    pub fn from_account_info(a: &AccountInfo) -> Result<PoolPosition, ProgramError> {
+        let position: PoolPosition = try_from_account_data(&a.data.borrow_mut(), MAX_POOL_POSITION_DATA_LEN, "PoolPosition")?;
+        Ok(position)
+    }
+
+    pub fn save(&self, a: &AccountInfo) -> ProgramResult {
+        serialize_account_data(self, &mut a.data.borrow_mut(), "PoolPosition")
+    }
+}
+
+// PROGRAM INFO - one per deployment, the PDA ["program_info"], the program's version and the features it supports so
+// clients can tell deployments apart. UpdateProgramInfo writes the running build's values, the upgrade authority calls
+// it after each upgrade
+
+// feature bits of ProgramInfo.features
+pub const FEATURE_PARTIAL_FILLS: u64 = 1 << 0; // bets take several acceptors, min_fill and max_acceptors
+pub const FEATURE_TOKEN_2022: u64 = 1 << 1; // bets in Token-2022 mints
+pub const FEATURE_TWO_PHASE_SETTLEMENT: u64 = 1 << 2; // SettleBet and DistributeFees
+pub const FEATURE_SETTLEMENT_RECEIPTS: u64 = 1 << 3; // the market's settlement_receipts flag
+pub const FEATURE_ACCEPT_ORDERS: u64 = 1 << 4; // PlaceAcceptOrder, MatchOrder and CancelAcceptOrder
+pub const FEATURE_ACCEPT_MULTIPLE: u64 = 1 << 5; // AcceptMultiple
+pub const FEATURE_BET_STATUS: u64 = 1 << 6; // BetStatus and PositionStatus in place of the is_initialized flags
+pub const FEATURE_ANCHOR_COMPAT: u64 = 1 << 7; // built with anchor-compat, accounts and instructions have discriminators
+pub const FEATURE_POOLS: u64 = 1 << 8; // CreatePool, JoinPool and SettlePool
+
+// the features of this build
+pub const PROGRAM_FEATURES: u64 = FEATURE_PARTIAL_FILLS
+    | FEATURE_TOKEN_2022
+    | FEATURE_TWO_PHASE_SETTLEMENT
+    | FEATURE_SETTLEMENT_RECEIPTS
+    | FEATURE_ACCEPT_ORDERS
+    | FEATURE_ACCEPT_MULTIPLE
+    | FEATURE_BET_STATUS
+    | FEATURE_POOLS
+    | if cfg!(feature = "anchor-compat") { FEATURE_ANCHOR_COMPAT } else { 0 };
+
+pub const MAX_PROGRAM_INFO_DATA_LEN: usize = DISCRIMINATOR_LEN + 1 + 2 + 2 + 2 + 8 + 8 + 1 + RESERVED_LEN;
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct ProgramInfo {
+    pub is_initialized: bool,
+    pub version_major: u16,
+    pub version_minor: u16,
+    pub version_patch: u16,
+    pub features: u64, // FEATURE_* bits
+    pub updated_at: i64, // unix timestamp of the last UpdateProgramInfo
+    pub bump_seed: u8, // the PDA ["program_info"]
+    pub reserved: Reserved<RESERVED_LEN> // zeroed, for future fields
+}
+
+impl ProgramInfo {
+    pub fn from_account_info(a: &AccountInfo) -> Result<ProgramInfo, ProgramError> {
+        let info: ProgramInfo = try_from_account_data(&a.data.borrow_mut(), MAX_PROGRAM_INFO_DATA_LEN, "ProgramInfo")?;
+        Ok(info)
+    }
+
+    pub fn save(&self, a: &AccountInfo) -> ProgramResult {
+        serialize_account_data(self, &mut a.data.borrow_mut(), "ProgramInfo")
+    }
+
+    pub fn has_feature(&self, feature: u64) -> bool {
+        self.features & feature == feature
+    }
+
+    // the semver of this build, from the crate version
+    pub fn build_version() -> (u16, u16, u16) {
+        (
+            env!("CARGO_PKG_VERSION_MAJOR").parse().unwrap_or(0),
+            env!("CARGO_PKG_VERSION_MINOR").parse().unwrap_or(0),
+            env!("CARGO_PKG_VERSION_PATCH").parse().unwrap_or(0)
+        )
+    }
+}
+
+// ACCEPT ORDER - per acceptor and betting market, a standing accept that anyone can fill against qualifying bets with
+// MatchOrder. The escrow holds the acceptor payment of the remaining size at max_odds plus the matcher fee on it
+
+pub const MAX_MATCHER_FEE_BPS: u16 = 1000;
+pub const MAX_ACCEPT_ORDER_DATA_LEN: usize = DISCRIMINATOR_LEN + 1 + 32 + 32 + 32 + 32 + 32 + 32 + 1 + 8 + 8 + 8 + 8 + 8 + 2 + 1 + 1 + RESERVED_LEN;
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct AcceptOrder {
+    pub is_initialized: bool,
+    pub betting_market: Pubkey,
+    pub acceptor_main_account: Pubkey, // the acceptor of every fill
+    pub acceptor_payment_account: Pubkey, // receives the fills' payouts and the refund at cancel
+    pub payment_mint: Pubkey,
+    pub pyth_oracle_price_account: Pubkey, // the feed of the bets it fills
+    pub order_escrow_account: Pubkey,
+    pub direction: Direction, // the acceptor's side, filled against bets whose creator holds the opposite direction
+    pub min_odds: i64,
+    pub max_odds: i64, // the escrow is funded for fills at these odds
+    pub remaining_size: u64, // bet size still to fill
+    pub min_expiration_time: i64, // unix timestamps bounding the expiration of the bets it fills
+    pub max_expiration_time: i64,
+    pub matcher_fee_bps: u16, // paid to the matcher from the escrow, in bps of each fill's acceptor payment
+    pub bump_seed: u8, // the PDA ["order", betting_market, acceptor_main_account]
+    pub escrow_bump_seed: u8, // the escrow authority PDA [PREFIX, betting_market, order]
+    pub reserved: Reserved<RESERVED_LEN> // zeroed, for future fields
+}
+
+impl AcceptOrder {
+    pub fn from_account_info(a: &AccountInfo) -> Result<AcceptOrder, ProgramError> {
+        let order: AcceptOrder = try_from_account_data(&a.data.borrow_mut(), MAX_ACCEPT_ORDER_DATA_LEN, "AcceptOrder")?;
+        Ok(order)
+    }
+
+    pub fn save(&self, a: &AccountInfo) -> ProgramResult {
+        serialize_account_data(self, &mut a.data.borrow_mut(), "AcceptOrder")
+    }
+
+    // the AcceptBet side the order takes on the bet: its direction on both_sides bets, None on bets whose creator
+    // holds the opposite direction. Bets on another market, feed or mint, or expiring outside the window don't match
+    pub fn side_for(&self, bet: &Bet) -> Result<Option<Direction>, BetError> {
+        if bet.betting_market != self.betting_market
+            || bet.pyth_oracle_price_account != self.pyth_oracle_price_account
+            || bet.payment_mint != self.payment_mint
+            || bet.expiry_kind != ExpiryKind::UnixTime
+            || bet.expiration_time < self.min_expiration_time
+            || bet.expiration_time > self.max_expiration_time {
+            return Err(BetError::OrderDoesNotMatch);
+        }
+        if bet.both_sides {
+            Ok(Some(self.direction))
+        } else if bet.bet_direction == self.direction.opposite() {
+            Ok(None)
+        } else {
+            Err(BetError::OrderDoesNotMatch)
+        }
+    }
+
+    // checks the fill odds are within the order's bounds
+    pub fn check_odds(&self, odds: i64) -> Result<(), BetError> {
+        if odds < self.min_odds || odds > self.max_odds {
+            return Err(BetError::OrderDoesNotMatch);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{anchor_discriminator, try_from_slice_checked};
+
+    fn mismatch<T: std::fmt::Debug>(result: Result<T, ProgramError>) {
+        assert_eq!(result.unwrap_err(), ProgramError::from(BetError::DataTypeMismatch));
+    }
+
+    // the exact size decodes, with a zeroed discriminator or the type's own in anchor-compat builds. One byte short
+    // or over, or another type's discriminator, is rejected
+    fn check_decoding<T: BorshDeserialize + std::fmt::Debug>(len: usize, name: &str, other_name: &str) {
+        let data = vec![0; len];
+        try_from_account_data::<T>(&data, len, name).unwrap();
+        try_from_slice_checked::<T>(&data[DISCRIMINATOR_LEN..], len - DISCRIMINATOR_LEN).unwrap();
+        mismatch(try_from_account_data::<T>(&data[..len - 1], len, name));
+        mismatch(try_from_account_data::<T>(&[data.as_slice(), &[0]].concat(), len, name));
+        mismatch(try_from_slice_checked::<T>(&data[DISCRIMINATOR_LEN + 1..], len - DISCRIMINATOR_LEN));
+        mismatch(try_from_slice_checked::<T>(&[&data[DISCRIMINATOR_LEN..], &[0][..]].concat(), len - DISCRIMINATOR_LEN));
+        mismatch(try_from_account_data::<T>(&[], len, name));
+        if cfg!(feature = "anchor-compat") {
+            let mut data = data;
+            data[..DISCRIMINATOR_LEN].copy_from_slice(&anchor_discriminator("account", name));
+            try_from_account_data::<T>(&data, len, name).unwrap();
+            data[..DISCRIMINATOR_LEN].copy_from_slice(&anchor_discriminator("account", other_name));
+            mismatch(try_from_account_data::<T>(&data, len, name));
+            data[0] ^= 1;
+            mismatch(try_from_account_data::<T>(&data, len, name));
+        }
+    }
+
+    #[test]
+    fn state_accounts_decode_only_at_their_size_and_discriminator() {
+        check_decoding::<Bet>(MAX_BET_DATA_LENGTH, "Bet", "AcceptedBet");
+        check_decoding::<BettingMarket>(MAX_BETTING_MARKET_DATA_LEN, "BettingMarket", "Bet");
+        check_decoding::<MintRegistry>(MAX_MINT_REGISTRY_DATA_LEN, "MintRegistry", "CreatorExposure");
+        check_decoding::<AcceptedBet>(MAX_ACCEPTED_BET_DATA_LEN, "AcceptedBet", "Bet");
+        check_decoding::<CreatorExposure>(MAX_CREATOR_EXPOSURE_DATA_LEN, "CreatorExposure", "UserStats");
+        check_decoding::<UserStats>(MAX_USER_STATS_DATA_LEN, "UserStats", "CreatorExposure");
+        check_decoding::<FeedExposure>(MAX_FEED_EXPOSURE_DATA_LEN, "FeedExposure", "CreatorExposure");
+        check_decoding::<Receipt>(MAX_RECEIPT_DATA_LEN, "Receipt", "AcceptedBet");
+        check_decoding::<Pool>(MAX_POOL_DATA_LEN, "Pool", "PoolPosition");
+        check_decoding::<PoolPosition>(MAX_POOL_POSITION_DATA_LEN, "PoolPosition", "Pool");
+        check_decoding::<ProgramInfo>(MAX_PROGRAM_INFO_DATA_LEN, "ProgramInfo", "BettingMarket");
+        check_decoding::<AcceptOrder>(MAX_ACCEPT_ORDER_DATA_LEN, "AcceptOrder", "AcceptedBet");
+    }
+
+    #[test]
+    fn from_account_data_checks_the_size() {
+        mismatch(Bet::from_account_data(&[0; MAX_BET_DATA_LENGTH - 1]));
+        mismatch(BettingMarket::from_account_data(&[0; MAX_BETTING_MARKET_DATA_LEN + 1]));
+        mismatch(AcceptedBet::from_account_data(&[0; MAX_ACCEPTED_BET_DATA_LEN - 1]));
+        mismatch(CreatorExposure::from_account_data(&[0; MAX_CREATOR_EXPOSURE_DATA_LEN + 1]));
+    }
+
+    // a variable odds bet at 200, moving one per 10 of price change by at most 5
+    fn variable_odds_bet(start_price: i64, bet_price: i64) -> Bet {
+        let mut bet = Bet::from_account_data(&[0; MAX_BET_DATA_LENGTH]).unwrap();
+        bet.odds = 200;
+        bet.start_price = start_price;
+        bet.bet_price = bet_price;
+        bet.variable_odds = Some(10);
+        bet.max_odds_change = Some(5);
+        bet
+    }
+
+    #[test]
+    fn effective_odds_clamp_when_price_starts_below_bet_price() {
+        let bet = variable_odds_bet(1_000, 2_000);
+        assert_eq!(bet.effective_odds(1_040), Ok(196));
+        assert_eq!(bet.effective_odds(1_050), Ok(195));
+        assert_eq!(bet.effective_odds(1_060), Ok(195));
+        assert_eq!(bet.effective_odds(960), Ok(204));
+        assert_eq!(bet.effective_odds(950), Ok(205));
+        assert_eq!(bet.effective_odds(940), Ok(205));
+    }
+
+    #[test]
+    fn effective_odds_clamp_when_price_starts_above_bet_price() {
+        let bet = variable_odds_bet(2_000, 1_000);
+        assert_eq!(bet.effective_odds(2_040), Ok(204));
+        assert_eq!(bet.effective_odds(2_050), Ok(205));
+        assert_eq!(bet.effective_odds(2_060), Ok(205));
+        assert_eq!(bet.effective_odds(1_960), Ok(196));
+        assert_eq!(bet.effective_odds(1_950), Ok(195));
+        assert_eq!(bet.effective_odds(1_940), Ok(195));
+    }
+
+    #[test]
+    fn effective_odds_clamp_uses_the_magnitude_of_max_odds_change() {
+        let mut bet = variable_odds_bet(1_000, 2_000);
+        bet.max_odds_change = Some(-5);
+        assert_eq!(bet.effective_odds(1_060), Ok(195));
+        assert_eq!(bet.effective_odds(940), Ok(205));
+        bet.max_odds_change = None;
+        assert_eq!(bet.effective_odds(1_060), Ok(194));
+        assert_eq!(bet.effective_odds(940), Ok(206));
+    }
+
+    #[test]
+    fn bet_status_transitions() {
+        use BetStatus::*;
+        let statuses = [Uninitialized, Open, PartiallyAccepted, FullyAccepted, Cancelled, Expired];
+        let allowed = [
+            (Uninitialized, Open),
+            (Open, PartiallyAccepted), (Open, FullyAccepted), (Open, Expired), (Open, Cancelled),
+            (PartiallyAccepted, FullyAccepted), (PartiallyAccepted, Expired), (PartiallyAccepted, Cancelled), (PartiallyAccepted, Open),
+            (FullyAccepted, Expired), (FullyAccepted, Cancelled), (FullyAccepted, Open),
+            (Expired, Cancelled), (Expired, Open),
+        ];
+        let mut bet = Bet::from_account_data(&[0; MAX_BET_DATA_LENGTH]).unwrap();
+        for from in statuses {
+            for to in statuses {
+                let legal = from == to || allowed.contains(&(from, to));
+                assert_eq!(from.can_transition_to(to), legal, "{:?} -> {:?}", from, to);
+                bet.status = from;
+                match bet.set_status(to) {
+                    Ok(()) => assert!(legal && bet.status == to, "{:?} -> {:?}", from, to),
+                    Err(error) => {
+                        assert!(!legal, "{:?} -> {:?}", from, to);
+                        assert_eq!(error, BetError::InvalidStatusTransition.into());
+                        assert_eq!(bet.status, from);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn position_status_transitions() {
+        use PositionStatus::*;
+        let statuses = [Uninitialized, Open, Settled, Refunded, Unwound];
+        let allowed = [(Uninitialized, Open), (Open, Settled), (Open, Refunded), (Open, Unwound)];
+        let mut position = AcceptedBet::from_account_data(&[0; MAX_ACCEPTED_BET_DATA_LEN]).unwrap();
+        for from in statuses {
+            for to in statuses {
+                let legal = from == to || allowed.contains(&(from, to));
+                assert_eq!(from.can_transition_to(to), legal, "{:?} -> {:?}", from, to);
+                position.status = from;
+                match position.set_status(to) {
+                    Ok(()) => assert!(legal && position.status == to, "{:?} -> {:?}", from, to),
+                    Err(error) => {
+                        assert!(!legal, "{:?} -> {:?}", from, to);
+                        assert_eq!(error, BetError::InvalidStatusTransition.into());
+                        assert_eq!(position.status, from);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! Token program helpers that work for both spl-token and Token-2022
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    msg,
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack},
+    pubkey::Pubkey,
+    system_program,
+    sysvar
+};
+use crate::error::BetError;
+
+pub mod spl_token_2022 {
+    solana_program::declare_id!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+}
+
+// Token-2022 stores the account type after the base account length, then the extensions as TLV entries
+const ACCOUNT_TYPE_OFFSET: usize = spl_token::state::Account::LEN;
+const ACCOUNT_TYPE_MINT: u8 = 1;
+
+// extension types the escrow accounting can't handle - transfers must move exactly the requested amount,
+// and nobody but the escrow authority may move the escrowed tokens
+const EXTENSION_TRANSFER_FEE_CONFIG: u16 = 1;
+const EXTENSION_NON_TRANSFERABLE: u16 = 9;
+const EXTENSION_PERMANENT_DELEGATE: u16 = 12;
+const EXTENSION_TRANSFER_HOOK: u16 = 14;
+
+fn unsupported_extension_name(extension_type: u16) -> Option<&'static str> {
+    match extension_type {
+        EXTENSION_TRANSFER_FEE_CONFIG => Some("TransferFeeConfig"),
+        EXTENSION_NON_TRANSFERABLE => Some("NonTransferable"),
+        EXTENSION_PERMANENT_DELEGATE => Some("PermanentDelegate"),
+        EXTENSION_TRANSFER_HOOK => Some("TransferHook"),
+        _ => None
+    }
+}
+
+/// Checks the key is the spl-token or Token-2022 program
+pub fn check_token_program(token_program_id: &Pubkey) -> ProgramResult {
+    if *token_program_id != spl_token::id() && *token_program_id != spl_token_2022::id() {
+        msg!("Invalid token program {}", token_program_id);
+        return Err(BetError::InvalidTokenProgram.into());
+    }
+    Ok(())
+}
+
+/// Rejects Token-2022 mints with extensions that change the amount a transfer moves or block transfers
+pub fn check_mint_extensions(mint_account_info: &AccountInfo) -> ProgramResult {
+    if *mint_account_info.owner != spl_token_2022::id() {
+        return Ok(());
+    }
+    let data = mint_account_info.data.borrow();
+    if data.len() <= ACCOUNT_TYPE_OFFSET {
+        return Ok(());
+    }
+    if data[ACCOUNT_TYPE_OFFSET] != ACCOUNT_TYPE_MINT {
+        return Err(BetError::InvalidMint.into());
+    }
+
+    // each entry is a u16 type, u16 length and the value
+    let mut offset = ACCOUNT_TYPE_OFFSET + 1;
+    while offset + 4 <= data.len() {
+        let extension_type = u16::from_le_bytes([data[offset], data[offset + 1]]);
+        let length = u16::from_le_bytes([data[offset + 2], data[offset + 3]]) as usize;
+        if let Some(name) = unsupported_extension_name(extension_type) {
+            msg!("Mint {} has the unsupported {} extension", mint_account_info.key, name);
+            return Err(BetError::UnsupportedMintExtension.into());
+        }
+        offset += 4 + length;
+    }
+    Ok(())
+}
+
+/// Rejects a frozen escrow before any transfer from it fails inside the token program
+pub fn check_not_frozen(token_account_info: &AccountInfo) -> ProgramResult {
+    let token_account = spl_token::state::Account::unpack_from_slice(&token_account_info.data.borrow())?;
+    if token_account.is_frozen() {
+        msg!("Token account {} is frozen", token_account_info.key);
+        return Err(BetError::EscrowFrozen.into());
+    }
+    Ok(())
+}
+
+/// True if the account is an initialized token account of the mint, owned by the token program. Payouts to
+/// anything else would fail inside the token program, so finalize pays the winner's ATA instead
+pub fn is_payout_account(token_account_info: &AccountInfo, token_program_id: &Pubkey, mint: &Pubkey) -> bool {
+    if token_account_info.owner != token_program_id {
+        return false;
+    }
+    let data = token_account_info.data.borrow();
+    if data.len() < spl_token::state::Account::LEN {
+        return false;
+    }
+    match spl_token::state::Account::unpack_from_slice(&data) {
+        Ok(token_account) => token_account.is_initialized() && token_account.mint == *mint,
+        Err(_) => false
+    }
+}
+
+/// Checks every destination paid a nonzero amount can receive the payout, named by its role in the log. SOL bets pay
+/// lamports to a wallet (system owned, or not funded yet) or a wrapped SOL token account. Token bets pay an initialized
+/// token account of the mint, owned by the bet's token program. Fails with WrongPaymentAccount on the first destination
+/// that breaks its rule. Every payout path, fallbacks and beneficiaries included, checks its destinations with this
+pub fn validate_payout_destinations(
+    destinations: &[(&str, &AccountInfo, u64)],
+    payment_mint: &Pubkey,
+    token_program_id: &Pubkey
+) -> ProgramResult {
+    let sol_payment = *payment_mint == spl_token::native_mint::id();
+    for (role, destination_account_info, amount) in destinations {
+        if *amount == 0 {
+            continue;
+        }
+        let valid = if sol_payment {
+            *destination_account_info.owner == system_program::id()
+                || is_payout_account(destination_account_info, token_program_id, payment_mint)
+        } else {
+            is_payout_account(destination_account_info, token_program_id, payment_mint)
+        };
+        if !valid {
+            msg!(
+                "{} {} can't receive a {} payout",
+                role,
+                destination_account_info.key,
+                if sol_payment { "lamport" } else { "token" }
+            );
+            return Err(BetError::WrongPaymentAccount.into());
+        }
+    }
+    Ok(())
+}
+
+/// Token transfer instruction for the given token program, the layout is the same for both programs
+pub fn transfer(
+    token_program_id: &Pubkey,
+    source: &Pubkey,
+    destination: &Pubkey,
+    authority: &Pubkey,
+    amount: u64
+) -> Result<Instruction, ProgramError> {
+    let mut ix = spl_token::instruction::transfer(&spl_token::id(), source, destination, authority, &[], amount)?;
+    ix.program_id = *token_program_id;
+    Ok(ix)
+}
+
+/// Token initialize account 2 instruction for the given token program, the owner is passed in the data
+pub fn initialize_account2(
+    token_program_id: &Pubkey,
+    account: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey
+) -> Result<Instruction, ProgramError> {
+    let mut ix = spl_token::instruction::initialize_account2(&spl_token::id(), account, mint, owner)?;
+    ix.program_id = *token_program_id;
+    Ok(ix)
+}
+
+/// Token close account instruction for the given token program
+pub fn close_account(
+    token_program_id: &Pubkey,
+    account: &Pubkey,
+    destination: &Pubkey,
+    authority: &Pubkey
+) -> Result<Instruction, ProgramError> {
+    let mut ix = spl_token::instruction::close_account(&spl_token::id(), account, destination, authority, &[])?;
+    ix.program_id = *token_program_id;
+    Ok(ix)
+}
+
+/// Token sync native instruction for the given token program
+pub fn sync_native(token_program_id: &Pubkey, account: &Pubkey) -> Result<Instruction, ProgramError> {
+    let mut ix = spl_token::instruction::sync_native(&spl_token::id(), account)?;
+    ix.program_id = *token_program_id;
+    Ok(ix)
+}
+
+/// Associated token account address for the given token program
+pub fn get_associated_token_address(wallet: &Pubkey, mint: &Pubkey, token_program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[wallet.as_ref(), token_program_id.as_ref(), mint.as_ref()],
+        &spl_associated_token_account::id()
+    ).0
+}
+
+/// Create associated token account instruction for the given token program
+pub fn create_associated_token_account(
+    funder: &Pubkey,
+    wallet: &Pubkey,
+    mint: &Pubkey,
+    token_program_id: &Pubkey
+) -> Instruction {
+    Instruction {
+        program_id: spl_associated_token_account::id(),
+        accounts: vec![
+            AccountMeta::new(*funder, true),
+            AccountMeta::new(get_associated_token_address(wallet, mint, token_program_id), false),
+            AccountMeta::new_readonly(*wallet, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(*token_program_id, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: vec![],
+    }
+}
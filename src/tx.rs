@@ -0,0 +1,199 @@
+//! Transaction builders that bundle the client side account creation with the program instruction
+use solana_program::{
+    instruction::Instruction,
+    pubkey::{Pubkey, PubkeyError},
+    rent::Rent,
+    system_instruction,
+};
+use crate::{
+    error::BetError,
+    instruction::{create_bet, accept_bet, SuppliedStartPrice},
+    offsets::BET_TAG_OFFSET,
+    pdas::{bet_seed, derive_bet_addresses, BetAddresses},
+    state::{CancelCondition, Direction, ExpiryKind, PayoutCurve, ProgramInfo, MAX_BET_DATA_LENGTH, MAX_TAG_LEN, MAX_PROGRAM_INFO_DATA_LEN},
+    token::get_associated_token_address,
+    utils::try_from_account_data
+};
+
+pub use crate::math::quote_accept_bet;
+pub use crate::budgets::*;
+
+/// Params of a CreateBet transaction
+#[derive(Clone, Debug)]
+pub struct CreateBetParams {
+    pub creator_payment_account: Option<Pubkey>, // None is the creator's ATA of payment_mint (unused for SOL bets)
+    pub payment_mint: Pubkey, // the native mint for SOL bets
+    pub token_program: Pubkey, // the program that owns payment_mint
+    pub betting_market_account: Pubkey,
+    pub pyth_oracle_product_account: Pubkey,
+    pub pyth_oracle_price_account: Pubkey,
+    pub bet_size: u64,
+    pub odds: i64,
+    pub expiration_time: i64,
+    pub bet_direction: Direction,
+    pub bet_price: i64,
+    pub cancel_condition: CancelCondition,
+    pub variable_odds: Option<i64>,
+    pub min_fill: u64,
+    pub max_acceptors: Option<u16>,
+    pub tag: String,
+    pub rollover_interval_secs: Option<i64>,
+    pub expiry_kind: ExpiryKind,
+    pub max_odds_change: Option<i64>,
+    pub max_total_acceptable: Option<u64>,
+    pub nonce: Option<u64>, // set by build_create_bet_with_seed_transaction
+    pub both_sides: bool,
+    pub payout_curve: PayoutCurve,
+    pub keeper_bounty_lamports: u64,
+    pub supplied_start_price: Option<SuppliedStartPrice>,
+    pub refund_destination: Option<Pubkey>,
+}
+
+/// Params of an AcceptBet transaction
+#[derive(Clone, Debug)]
+pub struct AcceptBetParams {
+    pub acceptor_payment_account: Option<Pubkey>, // None is the acceptor's ATA of payment_mint (unused for SOL bets)
+    pub bet_state_account: Pubkey,
+    pub accepted_count: u32, // the bet's current accepted_count
+    pub payment_mint: Pubkey,
+    pub token_program: Pubkey,
+    pub betting_market_account: Pubkey,
+    pub pyth_oracle_price_account: Pubkey,
+    pub bet_size: u64,
+    pub expected_start_price: Option<i64>,
+    pub deadline: Option<i64>,
+    pub expected_odds: Option<i64>,
+    pub side: Option<Direction>,
+}
+
+/// Builds the instructions of a CreateBet transaction: the compute unit limit, the rent exempt bet state account owned by the
+/// program, then CreateBet. The escrow is created and funded by CreateBet, the transaction must be signed by the creator and
+/// the bet state account.
+pub fn build_create_bet_transaction(
+    program_id: &Pubkey,
+    rent: &Rent,
+    creator_main_account: &Pubkey, // pays the rent of the bet state and escrow accounts
+    bet_state_account: &Pubkey, // new keypair
+    params: &CreateBetParams,
+) -> Vec<Instruction> {
+    let creator_payment_account = params.creator_payment_account.unwrap_or_else(||
+        get_associated_token_address(creator_main_account, &params.payment_mint, &params.token_program)
+    );
+    vec![
+        set_compute_unit_limit(create_bet_units(&params.payment_mint)),
+        system_instruction::create_account(
+            creator_main_account,
+            bet_state_account,
+            rent.minimum_balance(MAX_BET_DATA_LENGTH),
+            MAX_BET_DATA_LENGTH as u64,
+            program_id,
+        ),
+        create_bet(
+            *program_id,
+            *creator_main_account,
+            creator_payment_account,
+            *bet_state_account,
+            params.payment_mint,
+            params.token_program,
+            params.betting_market_account,
+            params.pyth_oracle_product_account,
+            params.pyth_oracle_price_account,
+            params.bet_size,
+            params.odds,
+            params.expiration_time,
+            params.bet_direction,
+            params.bet_price,
+            params.cancel_condition.clone(),
+            params.variable_odds,
+            params.min_fill,
+            params.max_acceptors,
+            params.tag.clone(),
+            params.rollover_interval_secs,
+            params.expiry_kind,
+            params.max_odds_change,
+            params.max_total_acceptable,
+            params.nonce,
+            params.both_sides,
+            params.payout_curve,
+            params.keeper_bounty_lamports,
+            params.supplied_start_price,
+            params.refund_destination,
+        ),
+    ]
+}
+
+/// Builds the instructions of a CreateBet transaction whose bet state account is created from the creator's nonce, so only
+/// the creator signs. Returns the bet's addresses from pdas::derive_bet_addresses with the instructions
+pub fn build_create_bet_with_seed_transaction(
+    program_id: &Pubkey,
+    rent: &Rent,
+    creator_main_account: &Pubkey, // pays the rent of the bet state and escrow accounts
+    nonce: u64, // unique per bet of the creator, not NO_NONCE
+    params: &CreateBetParams,
+) -> Result<(BetAddresses, Vec<Instruction>), PubkeyError> {
+    let addresses = derive_bet_addresses(program_id, &params.betting_market_account, creator_main_account, nonce)?;
+    let params = CreateBetParams { nonce: Some(nonce), ..params.clone() };
+    let mut instructions = build_create_bet_transaction(program_id, rent, creator_main_account, &addresses.bet_state_account, &params);
+    instructions[1] = system_instruction::create_account_with_seed(
+        creator_main_account,
+        &addresses.bet_state_account,
+        creator_main_account,
+        &bet_seed(nonce),
+        rent.minimum_balance(MAX_BET_DATA_LENGTH),
+        MAX_BET_DATA_LENGTH as u64,
+        program_id,
+    );
+    Ok((addresses, instructions))
+}
+
+/// Builds the instructions of an AcceptBet transaction, the compute unit limit then AcceptBet.
+/// The accepted bet state account and its escrow are PDAs created by AcceptBet, so only the acceptor signs.
+pub fn build_accept_bet_transaction(
+    program_id: &Pubkey,
+    acceptor_main_account: &Pubkey, // pays the rent of the accepted bet state and escrow accounts
+    params: &AcceptBetParams,
+) -> Vec<Instruction> {
+    let acceptor_payment_account = params.acceptor_payment_account.unwrap_or_else(||
+        get_associated_token_address(acceptor_main_account, &params.payment_mint, &params.token_program)
+    );
+    vec![
+        set_compute_unit_limit(accept_bet_units(&params.payment_mint)),
+        accept_bet(
+            *program_id,
+            *acceptor_main_account,
+            acceptor_payment_account,
+            params.bet_state_account,
+            params.accepted_count,
+            params.payment_mint,
+            params.token_program,
+            params.betting_market_account,
+            params.pyth_oracle_price_account,
+            None,
+            params.bet_size,
+            params.expected_start_price,
+            params.deadline,
+            params.expected_odds,
+            params.side,
+        ),
+    ]
+}
+
+/// Memcmp filter (offset, bytes) selecting bets whose tag starts with tag_prefix
+pub fn bet_tag_filter(tag_prefix: &str) -> Result<(usize, Vec<u8>), BetError> {
+    if tag_prefix.len() > MAX_TAG_LEN {
+        return Err(BetError::TagTooLong);
+    }
+    Ok((BET_TAG_OFFSET, tag_prefix.as_bytes().to_vec()))
+}
+
+/// The FEATURE_* bits a deployment supports, from the data of its pdas::find_program_info account as fetched by the
+/// caller. None, no account, is a deployment from before ProgramInfo or whose upgrade authority hasn't run
+/// UpdateProgramInfo yet, and reports no features
+pub fn program_features(program_info_data: Option<&[u8]>) -> Result<u64, BetError> {
+    match program_info_data {
+        Some(data) => try_from_account_data::<ProgramInfo>(data, MAX_PROGRAM_INFO_DATA_LEN, "ProgramInfo")
+            .map(|program_info| if program_info.is_initialized { program_info.features } else { 0 })
+            .map_err(|_| BetError::DataTypeMismatch),
+        None => Ok(0)
+    }
+}
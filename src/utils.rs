@@ -1,6 +1,5 @@
-use borsh::{BorshDeserialize};
+use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
-    borsh::try_from_slice_unchecked,
     program_error::ProgramError,
     system_instruction,
     account_info::AccountInfo,
@@ -9,28 +8,191 @@ use solana_program::{
     sysvar::{rent::Rent, Sysvar},
     msg,
     program::{invoke, invoke_signed},
+    hash::hash,
+    sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+    bpf_loader_upgradeable,
+    system_program,
 };
-use std::convert::TryInto;
+use std::{any::type_name, convert::TryInto};
 use crate::{
-    error::BetError
+    error::BetError,
+    pdas::find_program_data
 };
 
 pub const PREFIX: &str = "yoyobet";
+pub const MARKET_SEED: &str = "market";
+pub const MINT_SEED: &str = "mint";
+pub const CREATOR_SEED: &str = "creator";
+pub const STATS_SEED: &str = "stats";
+pub const ORDER_SEED: &str = "order";
+pub const FEED_SEED: &str = "feed";
+pub const RECEIPT_SEED: &str = "receipt";
+pub const PROGRAM_INFO_SEED: &str = "program_info";
+pub const POOL_SEED: &str = "pool";
+pub const POOL_POSITION_SEED: &str = "pool_position";
+pub const ESCROW_SEED: &str = "escrow";
+pub const EVENT_AUTHORITY_SEED: &str = "__event_authority";
+
+// Anchor style 8 byte discriminator prefixing the state accounts, none in native mode
+#[cfg(feature = "anchor-compat")]
+pub const DISCRIMINATOR_LEN: usize = 8;
+#[cfg(not(feature = "anchor-compat"))]
+pub const DISCRIMINATOR_LEN: usize = 0;
+
+// rejects being invoked by another program, the transaction's current top-level instruction must be this program's
+pub fn assert_not_cpi(program_id: &Pubkey, instructions_sysvar_account_info: &AccountInfo) -> ProgramResult {
+    let current_index = load_current_index_checked(instructions_sysvar_account_info)?;
+    let current_instruction = load_instruction_at_checked(current_index as usize, instructions_sysvar_account_info)?;
+    if current_instruction.program_id != *program_id {
+        msg!("Instruction invoked via CPI from {}", current_instruction.program_id);
+        return Err(BetError::CpiNotAllowed.into());
+    }
+    Ok(())
+}
+
+// rejects an instruction passing the same account in two of the given roles, logging the colliding role pair
+pub fn assert_distinct_accounts(accounts: &[(&str, &AccountInfo)]) -> ProgramResult {
+    for (i, (role, account_info)) in accounts.iter().enumerate() {
+        if let Some((other_role, _)) = accounts[i + 1..].iter().find(|(_, other)| other.key == account_info.key) {
+            msg!("{} and {} are the same account {}", role, other_role, account_info.key);
+            return Err(BetError::DuplicateAccount.into());
+        }
+    }
+    Ok(())
+}
+
+// the system program's bincode Transfer and TransferWithSeed layouts: u32 variant then u64 lamports, paying the
+// account at the variant's recipient index
+const SYSTEM_TRANSFER_VARIANT: u32 = 2;
+const SYSTEM_TRANSFER_WITH_SEED_VARIANT: u32 = 11;
+
+// the lamports the transaction's earlier top-level system program transfers sent to the destination
+pub fn lamports_transferred_earlier(instructions_sysvar_account_info: &AccountInfo, destination: &Pubkey) -> Result<u64, ProgramError> {
+    let current_index = load_current_index_checked(instructions_sysvar_account_info)?;
+    let mut lamports: u64 = 0;
+    for index in 0..current_index as usize {
+        let instruction = load_instruction_at_checked(index, instructions_sysvar_account_info)?;
+        if instruction.program_id != system_program::id() || instruction.data.len() < 12 {
+            continue;
+        }
+        let recipient_index = match u32::from_le_bytes(instruction.data[..4].try_into().unwrap()) {
+            SYSTEM_TRANSFER_VARIANT => 1,
+            SYSTEM_TRANSFER_WITH_SEED_VARIANT => 2,
+            _ => continue
+        };
+        if instruction.accounts.get(recipient_index).map(|account| account.pubkey) == Some(*destination) {
+            lamports = lamports.saturating_add(u64::from_le_bytes(instruction.data[4..12].try_into().unwrap()));
+        }
+    }
+    Ok(lamports)
+}
+
+// the upgradeable loader's bincode ProgramData layout: u32 variant 3, u64 deploy slot, then Option<Pubkey> upgrade authority
+const PROGRAM_DATA_VARIANT: u32 = 3;
+const PROGRAM_DATA_AUTHORITY_OFFSET: usize = 4 + 8;
+
+// requires the signer to be this program's upgrade authority, read from the program's ProgramData account
+pub fn assert_upgrade_authority(program_id: &Pubkey, program_data_account_info: &AccountInfo, authority_account_info: &AccountInfo) -> ProgramResult {
+    if !authority_account_info.is_signer {
+        return Err(BetError::IncorrectSigner.into());
+    }
+    let (program_data_address, _) = find_program_data(program_id);
+    if *program_data_account_info.key != program_data_address || *program_data_account_info.owner != bpf_loader_upgradeable::id() {
+        msg!("Incorrect program data account: expected {}, got {}", program_data_address, program_data_account_info.key);
+        return Err(BetError::InvalidAccountInput.into());
+    }
+
+    let data = program_data_account_info.try_borrow_data()?;
+    if data.len() < PROGRAM_DATA_AUTHORITY_OFFSET + 1
+        || u32::from_le_bytes(data[..4].try_into().unwrap()) != PROGRAM_DATA_VARIANT {
+        msg!("Program data account isn't an upgradeable loader ProgramData account");
+        return Err(BetError::DataTypeMismatch.into());
+    }
+    // a program made immutable has no upgrade authority, so nobody passes
+    let upgrade_authority = match data.get(PROGRAM_DATA_AUTHORITY_OFFSET + 1..PROGRAM_DATA_AUTHORITY_OFFSET + 33) {
+        Some(authority) if data[PROGRAM_DATA_AUTHORITY_OFFSET] == 1 => Some(Pubkey::new(authority)),
+        _ => None
+    };
+    if upgrade_authority != Some(*authority_account_info.key) {
+        msg!("Signer is not the program's upgrade authority");
+        return Err(BetError::UnauthorizedAccount.into());
+    }
+    Ok(())
+}
 
 pub fn try_from_slice_checked<T: BorshDeserialize>(
     data: &[u8],
     data_size: usize,
 ) -> Result<T, ProgramError> {
-    if data.len() != data_size
-    {
+    if data.len() != data_size {
+        msg!("Expected {} bytes of {}, got {}", data_size, type_name::<T>(), data.len());
         return Err(BetError::DataTypeMismatch.into());
     }
 
-    let result: T = try_from_slice_unchecked(data)?;
+    // deserialize from the front of the slice, padding after the encoded data (e.g. unset Options) is fine,
+    // the length check above bounds what can be consumed
+    let result = T::deserialize(&mut &data[..]).map_err(|e| {
+        msg!("Data isn't a valid {}: {}", type_name::<T>(), e);
+        ProgramError::from(BetError::DataTypeMismatch)
+    })?;
 
     Ok(result)
 }
 
+/// Anchor discriminator sha256("<namespace>:<name>")[..8], namespace "account" for state accounts and "global" for instructions
+pub fn anchor_discriminator(namespace: &str, name: &str) -> [u8; 8] {
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash(format!("{}:{}", namespace, name).as_bytes()).to_bytes()[..8]);
+    discriminator
+}
+
+/// Deserializes a state account, in anchor-compat mode the discriminator must match (or be zeroed, for accounts not yet written)
+pub fn try_from_account_data<T: BorshDeserialize>(
+    data: &[u8],
+    data_size: usize,
+    account_name: &str,
+) -> Result<T, ProgramError> {
+    if data.len() != data_size {
+        msg!("Expected {} account of {} bytes, found {} bytes", account_name, data_size, data.len());
+        return Err(BetError::DataTypeMismatch.into());
+    }
+    let (discriminator, state_data) = data.split_at(DISCRIMINATOR_LEN);
+    if cfg!(feature = "anchor-compat")
+        && discriminator.iter().any(|b| *b != 0)
+        && discriminator != anchor_discriminator("account", account_name) {
+        msg!("Expected {} account, found discriminator {:?}", account_name, discriminator);
+        return Err(BetError::DataTypeMismatch.into());
+    }
+    try_from_slice_checked(state_data, data_size - DISCRIMINATOR_LEN)
+}
+
+/// Serializes a state account, behind its discriminator in anchor-compat mode
+pub fn serialize_account_data<T: BorshSerialize>(
+    state: &T,
+    data: &mut [u8],
+    account_name: &str,
+) -> ProgramResult {
+    let (discriminator, state_data) = data.split_at_mut(DISCRIMINATOR_LEN);
+    if cfg!(feature = "anchor-compat") {
+        discriminator.copy_from_slice(&anchor_discriminator("account", account_name));
+    }
+    state.serialize(&mut &mut state_data[..])?;
+    Ok(())
+}
+
+// moves lamports out of a program owned account, which must be left either empty or rent exempt for its data
+pub fn debit_lamports(source_account_info: &AccountInfo, destination_account_info: &AccountInfo, amount: u64) -> ProgramResult {
+    let remaining = source_account_info.lamports().checked_sub(amount).ok_or(BetError::AmountOverflow)?;
+    let rent_minimum = Rent::get()?.minimum_balance(source_account_info.data_len());
+    if remaining != 0 && remaining < rent_minimum {
+        msg!("Debiting {} lamports leaves {} with {}, below its rent exempt minimum of {}", amount, source_account_info.key, remaining, rent_minimum);
+        return Err(BetError::WouldBreakRentExemption.into());
+    }
+    **source_account_info.try_borrow_mut_lamports()? -= amount;
+    **destination_account_info.try_borrow_mut_lamports()? += amount;
+    Ok(())
+}
+
 /// Create account almost from scratch, lifted from
 /// https://github.com/solana-labs/solana-program-library/tree/master/associated-token-account/program/src/processor.rs#L51-L98
 #[inline(always)]
@@ -52,7 +214,7 @@ pub fn create_or_allocate_account_raw<'a>(
     if required_lamports > 0 {
         msg!("Transfer {} lamports to the new account", required_lamports);
         invoke(
-            &system_instruction::transfer(&payer_info.key, new_account_info.key, required_lamports),
+            &system_instruction::transfer(payer_info.key, new_account_info.key, required_lamports),
             &[
                 payer_info.clone(),
                 new_account_info.clone(),
@@ -67,14 +229,14 @@ pub fn create_or_allocate_account_raw<'a>(
     invoke_signed(
         &system_instruction::allocate(new_account_info.key, size.try_into().unwrap()),
         accounts,
-        &[&signer_seeds],
+        &[signer_seeds],
     )?;
 
     msg!("Assign the account to the owning program");
     invoke_signed(
         &system_instruction::assign(new_account_info.key, &program_id),
         accounts,
-        &[&signer_seeds],
+        &[signer_seeds],
     )?;
 
     Ok(())
@@ -82,11 +244,11 @@ pub fn create_or_allocate_account_raw<'a>(
 
 /// Pads the string to the desired size with `0u8`s.
 /// NOTE: it is assumed that the string's size is never larger than the given size.
-pub fn puffed_out_string(s: &String, size: usize) -> String {
+pub fn puffed_out_string(s: &str, size: usize) -> String {
     let mut array_of_zeroes = vec![];
     let puff_amount = size - s.len();
     while array_of_zeroes.len() < puff_amount {
         array_of_zeroes.push(0u8);
     }
-    s.clone() + std::str::from_utf8(&array_of_zeroes).unwrap()
+    s.to_owned() + std::str::from_utf8(&array_of_zeroes).unwrap()
 }
\ No newline at end of file
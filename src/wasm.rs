@@ -0,0 +1,74 @@
+//! Client surface for browser frontends: quotes, PDA derivation and account decoding, without AccountInfo, syscalls or sysvars
+use crate::{
+    error::BetError,
+    state::{
+        Bet, BettingMarket, MintRegistry, AcceptedBet, CreatorExposure, UserStats, FeedExposure, Receipt, AcceptOrder, ProgramInfo, Pool, PoolPosition,
+        MAX_BETTING_MARKET_DATA_LEN, MAX_MINT_REGISTRY_DATA_LEN, MAX_CREATOR_EXPOSURE_DATA_LEN, MAX_USER_STATS_DATA_LEN,
+        MAX_FEED_EXPOSURE_DATA_LEN, MAX_RECEIPT_DATA_LEN, MAX_ACCEPT_ORDER_DATA_LEN, MAX_PROGRAM_INFO_DATA_LEN,
+        MAX_POOL_DATA_LEN, MAX_POOL_POSITION_DATA_LEN
+    },
+    utils::try_from_account_data
+};
+
+pub use crate::math::*;
+pub use crate::pdas::*;
+
+/// Decodes a Bet account's data
+pub fn decode_bet(data: &[u8]) -> Result<Bet, BetError> {
+    Bet::from_account_data(data).map_err(|_| BetError::DataTypeMismatch)
+}
+
+/// Decodes a BettingMarket account's data
+pub fn decode_betting_market(data: &[u8]) -> Result<BettingMarket, BetError> {
+    try_from_account_data(data, MAX_BETTING_MARKET_DATA_LEN, "BettingMarket").map_err(|_| BetError::DataTypeMismatch)
+}
+
+/// Decodes a MintRegistry account's data
+pub fn decode_mint_registry(data: &[u8]) -> Result<MintRegistry, BetError> {
+    try_from_account_data(data, MAX_MINT_REGISTRY_DATA_LEN, "MintRegistry").map_err(|_| BetError::DataTypeMismatch)
+}
+
+/// Decodes an AcceptedBet account's data
+pub fn decode_accepted_bet(data: &[u8]) -> Result<AcceptedBet, BetError> {
+    AcceptedBet::from_account_data(data).map_err(|_| BetError::DataTypeMismatch)
+}
+
+/// Decodes a CreatorExposure account's data
+pub fn decode_creator_exposure(data: &[u8]) -> Result<CreatorExposure, BetError> {
+    try_from_account_data(data, MAX_CREATOR_EXPOSURE_DATA_LEN, "CreatorExposure").map_err(|_| BetError::DataTypeMismatch)
+}
+
+/// Decodes a UserStats account's data
+pub fn decode_user_stats(data: &[u8]) -> Result<UserStats, BetError> {
+    try_from_account_data(data, MAX_USER_STATS_DATA_LEN, "UserStats").map_err(|_| BetError::DataTypeMismatch)
+}
+
+/// Decodes a FeedExposure account's data
+pub fn decode_feed_exposure(data: &[u8]) -> Result<FeedExposure, BetError> {
+    try_from_account_data(data, MAX_FEED_EXPOSURE_DATA_LEN, "FeedExposure").map_err(|_| BetError::DataTypeMismatch)
+}
+
+/// Decodes a Receipt account's data
+pub fn decode_receipt(data: &[u8]) -> Result<Receipt, BetError> {
+    try_from_account_data(data, MAX_RECEIPT_DATA_LEN, "Receipt").map_err(|_| BetError::DataTypeMismatch)
+}
+
+/// Decodes an AcceptOrder account's data
+pub fn decode_accept_order(data: &[u8]) -> Result<AcceptOrder, BetError> {
+    try_from_account_data(data, MAX_ACCEPT_ORDER_DATA_LEN, "AcceptOrder").map_err(|_| BetError::DataTypeMismatch)
+}
+
+/// Decodes a ProgramInfo account's data
+pub fn decode_program_info(data: &[u8]) -> Result<ProgramInfo, BetError> {
+    try_from_account_data(data, MAX_PROGRAM_INFO_DATA_LEN, "ProgramInfo").map_err(|_| BetError::DataTypeMismatch)
+}
+
+/// Decodes a Pool account's data
+pub fn decode_pool(data: &[u8]) -> Result<Pool, BetError> {
+    try_from_account_data(data, MAX_POOL_DATA_LEN, "Pool").map_err(|_| BetError::DataTypeMismatch)
+}
+
+/// Decodes a PoolPosition account's data
+pub fn decode_pool_position(data: &[u8]) -> Result<PoolPosition, BetError> {
+    try_from_account_data(data, MAX_POOL_POSITION_DATA_LEN, "PoolPosition").map_err(|_| BetError::DataTypeMismatch)
+}
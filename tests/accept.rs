@@ -0,0 +1,377 @@
+//! AcceptBet's acceptor supplied bounds
+mod common;
+
+use common::*;
+use solana_sdk::{account::AccountSharedData, instruction::Instruction, packet::PACKET_DATA_SIZE, pubkey::Pubkey, signature::Signer, transaction::Transaction};
+use yoyo_bets::{
+    error::BetError,
+    instruction::{self, AcceptMultipleBet, AcceptMultipleFill},
+    math::MAX_TIMESTAMP_HORIZON_SECS,
+    state::{Bet, BetStatus, Direction, PositionStatus, DEFAULT_ACCEPT_CUTOFF_SECS},
+    utils::serialize_account_data,
+};
+
+// the account keys and wire size of a transaction of the instruction signed by the payer alone: the signature count,
+// the signature and the message
+fn transaction_size(instruction: &Instruction, payer: &User) -> (usize, usize) {
+    let transaction = Transaction::new_with_payer(std::slice::from_ref(instruction), Some(&payer.pubkey()));
+    let message = transaction.message.serialize();
+    (transaction.message.account_keys.len(), 1 + 64 + message.len())
+}
+
+async fn accept_with_deadline(harness: &mut Harness, acceptor: &User, bet: &CreatedBet, bet_size: u64, deadline: i64) -> Instruction {
+    let accepted_count = harness.bet(bet).await.accepted_count;
+    instruction::accept_bet(
+        id(),
+        acceptor.pubkey(),
+        acceptor.payment_account,
+        bet.address,
+        accepted_count,
+        bet.mint,
+        spl_token::id(),
+        harness.market,
+        harness.price,
+        None,
+        bet_size,
+        None,
+        Some(deadline),
+        None,
+        None,
+    )
+}
+
+async fn accept_expecting_start_price(harness: &mut Harness, acceptor: &User, bet: &CreatedBet, bet_size: u64, expected_start_price: i64) -> Instruction {
+    let accepted_count = harness.bet(bet).await.accepted_count;
+    instruction::accept_bet(
+        id(),
+        acceptor.pubkey(),
+        acceptor.payment_account,
+        bet.address,
+        accepted_count,
+        bet.mint,
+        spl_token::id(),
+        harness.market,
+        harness.price,
+        None,
+        bet_size,
+        Some(expected_start_price),
+        None,
+        None,
+        None,
+    )
+}
+
+#[solana_program_test::tokio::test]
+async fn an_accept_quoted_on_a_stale_start_price_is_rejected() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let acceptor = harness.user(Payment::Token);
+    let mut params = BetParams::new(1_000_000, 200, Direction::Above, 10_500);
+    params.variable_odds = Some(10);
+    let bet = harness.create_bet(&creator, Payment::Token, &params).await.unwrap();
+
+    // the acceptor quotes on the start price they fetched, then the stored one changes under them
+    let quoted_start_price = harness.bet(&bet).await.start_price;
+    assert_eq!(quoted_start_price, 10_000);
+    let mut account = harness.account(&bet.address).await;
+    let mut bet_state = Bet::from_account_data(&account.data).unwrap();
+    bet_state.start_price = 10_100;
+    serialize_account_data(&bet_state, &mut account.data, "Bet").unwrap();
+    harness.context.set_account(&bet.address, &AccountSharedData::from(account));
+
+    let stale = accept_expecting_start_price(&mut harness, &acceptor, &bet, 500_000, quoted_start_price).await;
+    assert_bet_error(harness.process(&[stale], &[&acceptor.keypair]).await, 0, BetError::StateMismatch);
+    assert_eq!(harness.bet(&bet).await.accepted_count, 0);
+
+    // after refetching, the accept goes through
+    let refetched = accept_expecting_start_price(&mut harness, &acceptor, &bet, 500_000, 10_100).await;
+    harness.process(&[refetched], &[&acceptor.keypair]).await.unwrap();
+    assert_eq!(harness.bet(&bet).await.accepted_count, 1);
+}
+
+#[solana_program_test::tokio::test]
+async fn fills_below_the_minimum_are_rejected_unless_they_take_the_rest() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let acceptor = harness.user(Payment::Token);
+    let mut params = BetParams::new(1_000_000, 200, Direction::Above, 10_500);
+
+    // the minimum can't be above the bet's size, CreateBet follows the state account's creation in the transaction
+    params.min_fill = 1_000_001;
+    assert_bet_error(harness.create_bet(&creator, Payment::Token, &params).await.map(|_| ()), 1, BetError::InvalidCreateMinFill);
+    params.min_fill = 300_000;
+    let bet = harness.create_bet(&creator, Payment::Token, &params).await.unwrap();
+
+    assert_bet_error(harness.accept_bet(&acceptor, &bet, 299_999).await.map(|_| ()), 0, BetError::FillBelowMinimum);
+    harness.accept_bet(&acceptor, &bet, 300_000).await.unwrap();
+    harness.accept_bet(&acceptor, &bet, 500_000).await.unwrap();
+
+    // the last 200,000 are below the minimum, they can only be taken whole
+    assert_bet_error(harness.accept_bet(&acceptor, &bet, 199_999).await.map(|_| ()), 0, BetError::FillBelowMinimum);
+    harness.accept_bet(&acceptor, &bet, 200_000).await.unwrap();
+    let bet_state = harness.bet(&bet).await;
+    assert_eq!((bet_state.status, bet_state.accepted_count), (BetStatus::FullyAccepted, 3));
+}
+
+#[solana_program_test::tokio::test]
+async fn the_acceptor_after_the_cap_is_rejected() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let acceptors = [harness.user(Payment::Token), harness.user(Payment::Token), harness.user(Payment::Token)];
+    let mut params = BetParams::new(1_000_000, 200, Direction::Above, 10_500);
+
+    // no cap is None, a cap of 0 is rejected
+    params.max_acceptors = Some(0);
+    assert_bet_error(harness.create_bet(&creator, Payment::Token, &params).await.map(|_| ()), 1, BetError::InvalidCreateMaxAcceptors);
+    params.max_acceptors = Some(2);
+    let bet = harness.create_bet(&creator, Payment::Token, &params).await.unwrap();
+
+    harness.accept_bet(&acceptors[0], &bet, 300_000).await.unwrap();
+    harness.accept_bet(&acceptors[1], &bet, 300_000).await.unwrap();
+    assert_bet_error(harness.accept_bet(&acceptors[2], &bet, 300_000).await.map(|_| ()), 0, BetError::MaxAcceptorsReached);
+    // the cap counts accepts, not capacity, so the rest stays with the creator
+    let bet_state = harness.bet(&bet).await;
+    assert_eq!((bet_state.status, bet_state.accepted_count), (BetStatus::PartiallyAccepted, 2));
+    assert_eq!(harness.token_amount(&bet.escrow).await, 400_000);
+}
+
+async fn set_accept_cutoff(harness: &mut Harness, accept_cutoff_secs: i64) {
+    let update = instruction::update_betting_market(
+        id(),
+        harness.market_owner.pubkey(),
+        harness.market,
+        None,
+        Some(accept_cutoff_secs),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None
+    );
+    let market_owner = clone_keypair(&harness.market_owner);
+    harness.process(&[update], &[&market_owner]).await.unwrap();
+}
+
+#[solana_program_test::tokio::test]
+async fn accepts_close_at_the_market_cutoff_before_expiration() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let acceptor = harness.user(Payment::Token);
+    let bet = harness.create_bet(&creator, Payment::Token, &BetParams::new(1_000_000, 200, Direction::Above, 10_500)).await.unwrap();
+
+    // the default cutoff is 300 seconds, its first second still takes accepts
+    harness.set_time(NOW + 3600 - DEFAULT_ACCEPT_CUTOFF_SECS).await;
+    harness.accept_bet(&acceptor, &bet, 250_000).await.unwrap();
+    harness.set_time(NOW + 3600 - DEFAULT_ACCEPT_CUTOFF_SECS + 1).await;
+    assert_bet_error(harness.accept_bet(&acceptor, &bet, 250_000).await.map(|_| ()), 0, BetError::TooCloseToExpiry);
+
+    // a shorter cutoff applies to the later accepts of existing bets, a longer one closes them earlier
+    set_accept_cutoff(&mut harness, 60).await;
+    harness.set_time(NOW + 3540).await;
+    harness.accept_bet(&acceptor, &bet, 250_000).await.unwrap();
+    harness.set_time(NOW + 3541).await;
+    assert_bet_error(harness.accept_bet(&acceptor, &bet, 250_000).await.map(|_| ()), 0, BetError::TooCloseToExpiry);
+    set_accept_cutoff(&mut harness, 600).await;
+    harness.set_time(NOW + 3001).await;
+    assert_bet_error(harness.accept_bet(&acceptor, &bet, 250_000).await.map(|_| ()), 0, BetError::TooCloseToExpiry);
+    assert_eq!(harness.bet(&bet).await.accepted_count, 2);
+}
+
+#[solana_program_test::tokio::test]
+async fn deadline_is_checked_against_now_and_the_horizon_only() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let acceptor = harness.user(Payment::Token);
+    let bet = harness.create_bet(&creator, Payment::Token, &BetParams::new(1_000_000, 200, Direction::Above, 10_500)).await.unwrap();
+
+    let passed = accept_with_deadline(&mut harness, &acceptor, &bet, 500_000, NOW - 1).await;
+    assert_bet_error(harness.process(&[passed], &[&acceptor.keypair]).await, 0, BetError::AcceptDeadlineExceeded);
+    // a millisecond timestamp is past the horizon
+    let milliseconds = accept_with_deadline(&mut harness, &acceptor, &bet, 500_000, NOW * 1000).await;
+    assert_bet_error(harness.process(&[milliseconds], &[&acceptor.keypair]).await, 0, BetError::InvalidAcceptDeadline);
+    let past_horizon = accept_with_deadline(&mut harness, &acceptor, &bet, 500_000, NOW + MAX_TIMESTAMP_HORIZON_SECS + 1).await;
+    assert_bet_error(harness.process(&[past_horizon], &[&acceptor.keypair]).await, 0, BetError::InvalidAcceptDeadline);
+
+    // the deadline's own second is still open, as is the horizon's last
+    let at_deadline = accept_with_deadline(&mut harness, &acceptor, &bet, 500_000, NOW).await;
+    harness.process(&[at_deadline], &[&acceptor.keypair]).await.unwrap();
+    let at_horizon = accept_with_deadline(&mut harness, &acceptor, &bet, 500_000, NOW + MAX_TIMESTAMP_HORIZON_SECS).await;
+    harness.process(&[at_horizon], &[&acceptor.keypair]).await.unwrap();
+    let bet_state = harness.bet(&bet).await;
+    assert_eq!((bet_state.status, bet_state.accepted_count), (BetStatus::FullyAccepted, 2));
+}
+
+#[solana_program_test::tokio::test]
+async fn an_accept_signed_before_its_deadline_fails_once_the_clock_passes_it() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let acceptor = harness.user(Payment::Token);
+    let bet = harness.create_bet(&creator, Payment::Token, &BetParams::new(1_000_000, 200, Direction::Above, 10_500)).await.unwrap();
+
+    // both are built while their deadline is ahead, then land later
+    let late = accept_with_deadline(&mut harness, &acceptor, &bet, 500_000, NOW + 30).await;
+    let on_time = accept_with_deadline(&mut harness, &acceptor, &bet, 500_000, NOW + 31).await;
+    harness.set_time(NOW + 31).await;
+    assert_bet_error(harness.process(&[late], &[&acceptor.keypair]).await, 0, BetError::AcceptDeadlineExceeded);
+    harness.process(&[on_time], &[&acceptor.keypair]).await.unwrap();
+    assert_eq!(harness.bet(&bet).await.accepted_count, 1);
+}
+
+#[solana_program_test::tokio::test]
+async fn accept_transactions_stay_small() {
+    let mut harness = Harness::start().await;
+    let sol_creator = harness.user(Payment::Sol);
+    let sol_acceptor = harness.user(Payment::Sol);
+    let token_creator = harness.user(Payment::Token);
+    let token_acceptor = harness.user(Payment::Token);
+    let sol_bet = harness.create_bet(&sol_creator, Payment::Sol, &BetParams::new(SOL, 200, Direction::Above, 10_500)).await.unwrap();
+    let token_bet = harness.create_bet(&token_creator, Payment::Token, &BetParams::new(1_000_000, 200, Direction::Above, 10_500)).await.unwrap();
+
+    // a SOL acceptor pays from their wallet, which is also their payment account: 13 accounts and the program
+    let (sol_accept, _) = harness.accept_instruction(&sol_acceptor, &sol_bet, SOL).await;
+    assert_eq!(sol_accept.accounts.len(), 14);
+    assert_eq!(transaction_size(&sol_accept, &sol_acceptor), (14, 580));
+    // a token acceptor adds their token account
+    let (token_accept, _) = harness.accept_instruction(&token_acceptor, &token_bet, 1_000_000).await;
+    assert_eq!(transaction_size(&token_accept, &token_acceptor), (15, 612));
+
+    harness.process_paid_by(&[sol_accept], &[&sol_acceptor.keypair]).await.unwrap();
+    harness.process_paid_by(&[token_accept], &[&token_acceptor.keypair]).await.unwrap();
+    assert_eq!(harness.bet(&sol_bet).await.status, BetStatus::FullyAccepted);
+    assert_eq!(harness.bet(&token_bet).await.status, BetStatus::FullyAccepted);
+}
+
+// AcceptMultiple of the whole of each bet, passing each with its accepted count
+fn accept_all(harness: &Harness, acceptor: &User, bets: &[(&CreatedBet, u32)], skip_failed: bool) -> Instruction {
+    instruction::accept_multiple(
+        id(),
+        acceptor.pubkey(),
+        acceptor.payment_account,
+        harness.mint,
+        spl_token::id(),
+        harness.market,
+        bets.iter()
+            .map(|(bet, accepted_count)| AcceptMultipleBet {
+                bet_state_account: bet.address,
+                accepted_count: *accepted_count,
+                pyth_oracle_price_account: harness.price,
+                fill: AcceptMultipleFill { bet_size: 500_000, expected_odds: None, side: None },
+            })
+            .collect(),
+        None,
+        skip_failed,
+    )
+}
+
+#[solana_program_test::tokio::test]
+async fn skip_failed_skips_a_fill_failing_any_check_with_nothing_changed() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let acceptor = harness.user(Payment::Token);
+    let other_acceptor = harness.user(Payment::Token);
+    let first = harness.create_bet(&creator, Payment::Token, &BetParams::new(1_000_000, 200, Direction::Above, 10_500)).await.unwrap();
+    let second = harness.create_bet(&creator, Payment::Token, &BetParams::new(1_000_000, 200, Direction::Above, 10_500)).await.unwrap();
+    harness.accept_bet(&other_acceptor, &first, 500_000).await.unwrap();
+
+    // the first bet is passed with a stale accepted count, its accepted bet address is checked after the bet's own
+    // checks, which it passes
+    let stale = harness.accepted_at(&acceptor, &first, 0);
+    assert_bet_error(harness.process(&[accept_all(&harness, &acceptor, &[(&first, 0), (&second, 0)], false)], &[&acceptor.keypair]).await, 0, BetError::InvalidAccounts);
+    harness.process(&[accept_all(&harness, &acceptor, &[(&first, 0), (&second, 0)], true)], &[&acceptor.keypair]).await.unwrap();
+
+    // the skipped fill created nothing and left the bet as it was, the other bet was filled
+    assert!(!harness.exists(&stale.address).await);
+    assert!(!harness.exists(&stale.escrow).await);
+    let first_state = harness.bet(&first).await;
+    assert_eq!((first_state.accepted_count, first_state.total_amount_accepted), (1, 500_000));
+    assert_eq!(harness.token_amount(&first.escrow).await, 500_000);
+    assert_eq!(harness.bet(&second).await.total_amount_accepted, 500_000);
+    assert_eq!(harness.balance(&acceptor, Payment::Token).await, WALLET_TOKENS - 500_000);
+}
+
+#[solana_program_test::tokio::test]
+async fn accept_multiple_fills_four_bets_in_one_packet() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let acceptor = harness.user(Payment::Token);
+    let mut bets = vec![];
+    for _ in 0..4 {
+        bets.push(harness.create_bet(&creator, Payment::Token, &BetParams::new(1_000_000, 200, Direction::Above, 10_500)).await.unwrap());
+    }
+
+    let accept = instruction::accept_multiple(
+        id(),
+        acceptor.pubkey(),
+        acceptor.payment_account,
+        harness.mint,
+        spl_token::id(),
+        harness.market,
+        bets.iter()
+            .map(|bet| AcceptMultipleBet {
+                bet_state_account: bet.address,
+                accepted_count: 0,
+                pyth_oracle_price_account: harness.price,
+                fill: AcceptMultipleFill { bet_size: 1_000_000, expected_odds: None, side: None },
+            })
+            .collect(),
+        None,
+        false,
+    );
+    let (account_keys, size) = transaction_size(&accept, &acceptor);
+    assert_eq!(account_keys, 30);
+    assert!(size <= PACKET_DATA_SIZE, "{} bytes", size);
+
+    harness.process_paid_by(&[accept], &[&acceptor.keypair]).await.unwrap();
+    for bet in &bets {
+        assert_eq!(harness.bet(bet).await.status, BetStatus::FullyAccepted);
+    }
+    assert_eq!(harness.balance(&acceptor, Payment::Token).await, WALLET_TOKENS - 4_000_000);
+    let accepted = harness.accepted_at(&acceptor, &bets[3], 0);
+    assert_eq!(harness.accepted(&accepted).await.status, PositionStatus::Open);
+    assert_eq!(harness.token_amount(&accepted.escrow).await, 2_000_000);
+}
+
+#[solana_program_test::tokio::test]
+async fn accept_best_bet_checks_the_oracle_before_reading_its_price() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let acceptor = harness.user(Payment::Token);
+    let bet = harness.create_bet(&creator, Payment::Token, &BetParams::new(1_000_000, 200, Direction::Above, 10_500)).await.unwrap();
+    let (mint, market) = (harness.mint, harness.market);
+    let accept_best = |price_account| instruction::accept_best_bet(
+        id(),
+        acceptor.pubkey(),
+        acceptor.payment_account,
+        mint,
+        spl_token::id(),
+        market,
+        price_account,
+        &[(bet.address, 0)],
+        1_000_000,
+        None,
+        None,
+        false,
+    );
+
+    // an account no candidate settles on
+    let other_account = accept_best(Pubkey::new_unique());
+    assert_bet_error(harness.process(&[other_account], &[&acceptor.keypair]).await, 0, BetError::CandidateMismatch);
+
+    // the candidates' oracle holding something other than a Pyth price account
+    let candidates_oracle = accept_best(harness.price);
+    harness.context.set_account(&harness.price, &AccountSharedData::from(pyth_account(vec![0; 64])));
+    assert_bet_error(harness.process(&[candidates_oracle], &[&acceptor.keypair]).await, 0, BetError::InvalidAccountInput);
+    assert_eq!(harness.balance(&acceptor, Payment::Token).await, WALLET_TOKENS);
+}
@@ -0,0 +1,84 @@
+//! The market owner's instructions must be top-level instructions of a transaction the owner signed
+mod common;
+
+use common::*;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction, InstructionError},
+    pubkey::Pubkey,
+    signature::Signer,
+    transaction::TransactionError,
+    transport::TransportError,
+};
+use yoyo_bets::{error::BetError, instruction, state::BettingMarket};
+
+fn proxy_id() -> Pubkey {
+    Pubkey::new_from_array([8; 32])
+}
+
+async fn start() -> Harness {
+    let mut program_test = program_test();
+    program_test.add_builtin_program("proxy", proxy_id(), runner::process_proxy);
+    Harness::start_with(program_test).await
+}
+
+// the same instruction, invoked by the proxy program with CPI
+fn via_cpi(instruction: Instruction) -> Instruction {
+    let mut accounts = vec![AccountMeta::new_readonly(instruction.program_id, false)];
+    accounts.extend(instruction.accounts);
+    Instruction { program_id: proxy_id(), accounts, data: instruction.data }
+}
+
+async fn market_state(harness: &mut Harness) -> BettingMarket {
+    let address = harness.market;
+    BettingMarket::from_account_data(&harness.account(&address).await.data).unwrap()
+}
+
+#[solana_program_test::tokio::test]
+async fn oracle_instructions_run_as_top_level_instructions() {
+    let mut harness = start().await;
+    let market_owner = clone_keypair(&harness.market_owner);
+    let (owner, market_address, price) = (market_owner.pubkey(), harness.market, harness.price);
+
+    harness.process(&[instruction::add_allowed_oracle(id(), owner, market_address, price)], &[&market_owner]).await.unwrap();
+    harness.process(&[instruction::delist_oracle(id(), owner, market_address, price, true)], &[&market_owner]).await.unwrap();
+    let market = market_state(&mut harness).await;
+    assert_eq!((market.allowed_price_accounts(), market.delisted_price_accounts()), (&[price][..], &[price][..]));
+
+    harness.process(&[instruction::delist_oracle(id(), owner, market_address, price, false)], &[&market_owner]).await.unwrap();
+    harness.process(&[instruction::remove_allowed_oracle(id(), owner, market_address, price)], &[&market_owner]).await.unwrap();
+    let market = market_state(&mut harness).await;
+    assert!(market.allowed_price_accounts().is_empty() && market.delisted_price_accounts().is_empty());
+}
+
+#[solana_program_test::tokio::test]
+async fn owner_instructions_invoked_with_cpi_are_rejected() {
+    let mut harness = start().await;
+    let market_owner = clone_keypair(&harness.market_owner);
+    let (owner, market_address, price) = (market_owner.pubkey(), harness.market, harness.price);
+
+    // the owner signs the outer transaction, another program can't relay their signature into the market's admin
+    let add = instruction::add_allowed_oracle(id(), owner, market_address, price);
+    assert_bet_error(harness.process(&[via_cpi(add)], &[&market_owner]).await, 0, BetError::CpiNotAllowed);
+    let delist = instruction::delist_oracle(id(), owner, market_address, price, true);
+    assert_bet_error(harness.process(&[via_cpi(delist)], &[&market_owner]).await, 0, BetError::CpiNotAllowed);
+
+    harness.process(&[instruction::add_allowed_oracle(id(), owner, market_address, price)], &[&market_owner]).await.unwrap();
+    let remove = instruction::remove_allowed_oracle(id(), owner, market_address, price);
+    assert_bet_error(harness.process(&[via_cpi(remove)], &[&market_owner]).await, 0, BetError::CpiNotAllowed);
+    let market = market_state(&mut harness).await;
+    assert_eq!(market.allowed_price_accounts(), &[price][..]);
+    assert!(market.delisted_price_accounts().is_empty());
+}
+
+#[solana_program_test::tokio::test]
+async fn owner_instructions_require_the_instructions_sysvar() {
+    let mut harness = start().await;
+    let market_owner = clone_keypair(&harness.market_owner);
+    let (owner, market_address, price) = (market_owner.pubkey(), harness.market, harness.price);
+
+    let mut add = instruction::add_allowed_oracle(id(), owner, market_address, price);
+    add.accounts.last_mut().unwrap().pubkey = price;
+    let error = harness.process(&[add], &[&market_owner]).await.unwrap_err();
+    assert!(matches!(error, TransportError::TransactionError(TransactionError::InstructionError(0, InstructionError::UnsupportedSysvar))));
+    assert!(market_state(&mut harness).await.allowed_price_accounts().is_empty());
+}
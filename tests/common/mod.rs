@@ -0,0 +1,523 @@
+//! Shared setup for the solana-program-test suites: a market accepting SOL and one token mint, a Pyth feed the tests
+//! move, and builders for the bet lifecycle signed by fresh wallets. Transaction fees are paid by the context's payer,
+//! so the wallets' balances only move by what the program moves
+#![allow(dead_code)]
+
+pub mod runner;
+
+use solana_program::{program_option::COption, program_pack::Pack, system_instruction, system_program, sysvar::clock::Clock};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    instruction::{Instruction, InstructionError},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+    transport::TransportError,
+};
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+use yoyo_bets::{
+    constants::pyth_devnet,
+    error::BetError,
+    instruction::{self, CANCEL_ALL_REMAINING},
+    pdas::{bet_seed, find_accepted_bet, find_accepted_escrow, find_creator_exposure, find_escrow_authority, find_market, get_escrow_address},
+    pyth_fixtures::{PriceAccountBuilder, ProductAccountBuilder},
+    state::{AcceptedBet, Bet, CancelCondition, CreatorExposure, Direction, ExpiryKind, PayoutCurve, MAX_BET_DATA_LENGTH},
+    token::get_associated_token_address,
+};
+
+// the program's address in the tests' bank
+pub fn id() -> Pubkey {
+    Pubkey::new_from_array([7; 32])
+}
+
+// the unix time the tests start at, bets expire relative to it
+pub const NOW: i64 = 1_700_000_000;
+pub const SOL: u64 = 1_000_000_000;
+// the test token's decimals, one whole token is 10^6
+pub const TOKEN_DECIMALS: u8 = 6;
+pub const PRICE_EXPO: i32 = -2;
+// what each fresh wallet starts with
+pub const WALLET_LAMPORTS: u64 = 100 * SOL;
+pub const WALLET_TOKENS: u64 = 1_000_000_000;
+
+/// A bet's payment mint, SOL bets escrow wrapped SOL and pay out lamports
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Payment {
+    Sol,
+    Token,
+}
+
+/// The CreateBet args the tests vary, the rest are the defaults of a plain fixed odds bet
+#[derive(Clone, Debug)]
+pub struct BetParams {
+    pub bet_size: u64,
+    pub odds: i64,
+    pub direction: Direction,
+    pub bet_price: i64,
+    pub expiration_time: i64,
+    pub cancel_condition: CancelCondition,
+    pub variable_odds: Option<i64>,
+    pub min_fill: u64,
+    pub max_acceptors: Option<u16>,
+    pub tag: String,
+    pub keeper_bounty_lamports: u64,
+    pub refund_destination: Option<Pubkey>,
+}
+
+impl BetParams {
+    pub fn new(bet_size: u64, odds: i64, direction: Direction, bet_price: i64) -> Self {
+        BetParams {
+            bet_size,
+            odds,
+            direction,
+            bet_price,
+            expiration_time: NOW + 3600,
+            cancel_condition: CancelCondition::none(),
+            variable_odds: None,
+            min_fill: 0,
+            max_acceptors: None,
+            tag: String::new(),
+            keeper_bounty_lamports: 0,
+            refund_destination: None,
+        }
+    }
+}
+
+/// A wallet with its payment account for one mint, the wallet itself for SOL
+pub struct User {
+    pub keypair: Keypair,
+    pub payment_account: Pubkey,
+}
+
+impl User {
+    pub fn pubkey(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+}
+
+/// A created bet and the accounts its lifecycle needs
+pub struct CreatedBet {
+    pub address: Pubkey,
+    pub payment: Payment,
+    pub mint: Pubkey,
+    pub escrow: Pubkey,
+}
+
+/// An accept of a bet
+pub struct Accepted {
+    pub address: Pubkey,
+    pub escrow: Pubkey,
+}
+
+pub struct Harness {
+    pub context: ProgramTestContext,
+    pub market_owner: Keypair,
+    pub market: Pubkey,
+    pub fee_wallet: Pubkey,
+    pub product: Pubkey,
+    pub price: Pubkey,
+    pub mint: Pubkey,
+    next_nonce: u64,
+}
+
+pub fn program_test() -> ProgramTest {
+    let mut program_test = ProgramTest::default();
+    program_test.add_builtin_program("yoyo_bets", id(), runner::process_yoyo_bets);
+    program_test.add_builtin_program("spl_token", spl_token::id(), runner::process_spl_token);
+    program_test.add_builtin_program("spl_associated_token_account", spl_associated_token_account::id(), runner::process_associated_token_account);
+    program_test
+}
+
+impl Harness {
+    /// Starts a bank with the market of the native mint initialized, also accepting the token mint
+    pub async fn start() -> Harness {
+        Harness::start_with(program_test()).await
+    }
+
+    pub async fn start_with(mut program_test: ProgramTest) -> Harness {
+        let market_owner = Keypair::new();
+        let fee_wallet = Pubkey::new_unique();
+        let product = Pubkey::new_unique();
+        let price = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let market = find_market(&id(), &spl_token::native_mint::id()).0;
+
+        program_test.add_account(market_owner.pubkey(), system_account(WALLET_LAMPORTS));
+        // the fee wallet holds a balance so commission transfers into it stay rent exempt
+        program_test.add_account(fee_wallet, system_account(SOL));
+        program_test.add_account(spl_token::native_mint::id(), mint_account(9));
+        program_test.add_account(mint, mint_account(TOKEN_DECIMALS));
+        program_test.add_account(
+            get_associated_token_address(&fee_wallet, &mint, &spl_token::id()),
+            token_account(&mint, &fee_wallet, 0),
+        );
+        program_test.add_account(product, pyth_account(ProductAccountBuilder::new().symbol("Crypto.SOL/USD").px_acc(price).build()));
+        program_test.add_account(price, pyth_account(PriceAccountBuilder::new().price(10_000).expo(PRICE_EXPO).product(product).build()));
+
+        let context = program_test.start_with_context().await;
+        runner::install_syscall_stubs();
+
+        let mut harness = Harness {
+            context,
+            market_owner,
+            market,
+            fee_wallet,
+            product,
+            price,
+            mint,
+            next_nonce: 0,
+        };
+        harness.set_time(NOW).await;
+        let init = instruction::init_betting_market(
+            id(),
+            harness.market_owner.pubkey(),
+            fee_wallet,
+            pyth_devnet::id(),
+            vec![spl_token::native_mint::id(), mint],
+            0,
+            0,
+            true,
+            false,
+        );
+        let market_owner = clone_keypair(&harness.market_owner);
+        harness.process(&[init], &[&market_owner]).await.unwrap();
+        harness
+    }
+
+    /// Processes the instructions in one transaction paid by the context's payer
+    pub async fn process(&mut self, instructions: &[Instruction], signers: &[&Keypair]) -> Result<(), TransportError> {
+        let payer = clone_keypair(&self.context.payer);
+        let mut all_signers = vec![&payer];
+        all_signers.extend_from_slice(signers);
+        self.process_paid_by(instructions, &all_signers).await
+    }
+
+    /// Processes the instructions in one transaction paid by the first signer
+    pub async fn process_paid_by(&mut self, instructions: &[Instruction], signers: &[&Keypair]) -> Result<(), TransportError> {
+        let clock: Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        self.next_slot(clock.unix_timestamp).await;
+        let blockhash = self.context.banks_client.get_latest_blockhash().await.unwrap();
+        let transaction = Transaction::new_signed_with_payer(instructions, Some(&signers[0].pubkey()), &signers.to_vec(), blockhash);
+        self.context.banks_client.process_transaction(transaction).await
+    }
+
+    /// The fee of each signature of a transaction
+    pub async fn lamports_per_signature(&mut self) -> u64 {
+        #[allow(deprecated)]
+        let (fee_calculator, _blockhash, _last_valid_block_height) = self.context.banks_client.get_fees().await.unwrap();
+        fee_calculator.lamports_per_signature
+    }
+
+    pub async fn set_time(&mut self, unix_timestamp: i64) {
+        let mut clock: Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        clock.unix_timestamp = unix_timestamp;
+        self.context.set_sysvar(&clock);
+    }
+
+    /// Publishes a trading price on the feed
+    pub async fn set_price(&mut self, price: i64) {
+        let data = PriceAccountBuilder::new().price(price).expo(PRICE_EXPO).product(self.product).build();
+        self.context.set_account(&self.price, &AccountSharedData::from(pyth_account(data)));
+    }
+
+    // moves to a later slot so a repeated transaction gets a new blockhash, and with it a signature the bank hasn't
+    // already recorded a result for. Warping resets the clock, so it is set again afterwards
+    async fn next_slot(&mut self, unix_timestamp: i64) {
+        let clock: Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        self.context.warp_to_slot(clock.slot + 2).unwrap();
+        let mut clock: Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        clock.unix_timestamp = unix_timestamp;
+        self.context.set_sysvar(&clock);
+    }
+
+    /// A fresh wallet with WALLET_LAMPORTS, and for token bets a token account of the mint with WALLET_TOKENS
+    pub fn user(&mut self, payment: Payment) -> User {
+        let keypair = Keypair::new();
+        self.context.set_account(&keypair.pubkey(), &AccountSharedData::from(system_account(WALLET_LAMPORTS)));
+        let payment_account = match payment {
+            Payment::Sol => keypair.pubkey(),
+            Payment::Token => {
+                let payment_account = Pubkey::new_unique();
+                let account = token_account(&self.mint, &keypair.pubkey(), WALLET_TOKENS);
+                self.context.set_account(&payment_account, &AccountSharedData::from(account));
+                payment_account
+            }
+        };
+        User { keypair, payment_account }
+    }
+
+    pub fn mint_of(&self, payment: Payment) -> Pubkey {
+        match payment {
+            Payment::Sol => spl_token::native_mint::id(),
+            Payment::Token => self.mint,
+        }
+    }
+
+    /// The instructions of a CreateBet from the creator's next nonce, with the state account they first create
+    pub fn create_bet_instructions(&mut self, creator: &User, payment: Payment, params: &BetParams) -> (Vec<Instruction>, CreatedBet) {
+        let nonce = self.next_nonce;
+        self.next_nonce += 1;
+        let mint = self.mint_of(payment);
+        let address = Pubkey::create_with_seed(&creator.pubkey(), &bet_seed(nonce), &id()).unwrap();
+        let create_state_account = system_instruction::create_account_with_seed(
+            &creator.pubkey(),
+            &address,
+            &creator.pubkey(),
+            &bet_seed(nonce),
+            rent_exempt(MAX_BET_DATA_LENGTH),
+            MAX_BET_DATA_LENGTH as u64,
+            &id(),
+        );
+        let create_bet = instruction::create_bet(
+            id(),
+            creator.pubkey(),
+            creator.payment_account,
+            address,
+            mint,
+            spl_token::id(),
+            self.market,
+            self.product,
+            self.price,
+            params.bet_size,
+            params.odds,
+            params.expiration_time,
+            params.direction,
+            params.bet_price,
+            params.cancel_condition.clone(),
+            params.variable_odds,
+            params.min_fill,
+            params.max_acceptors,
+            params.tag.clone(),
+            None,
+            ExpiryKind::UnixTime,
+            None,
+            None,
+            Some(nonce),
+            false,
+            PayoutCurve::Binary,
+            params.keeper_bounty_lamports,
+            None,
+            params.refund_destination,
+        );
+        let bet = CreatedBet {
+            address,
+            payment,
+            mint,
+            escrow: get_escrow_address(&id(), &self.market, &address, &mint, &spl_token::id()),
+        };
+        (vec![create_state_account, create_bet], bet)
+    }
+
+    pub async fn create_bet(&mut self, creator: &User, payment: Payment, params: &BetParams) -> Result<CreatedBet, TransportError> {
+        let (instructions, bet) = self.create_bet_instructions(creator, payment, params);
+        self.process(&instructions, &[&creator.keypair]).await.map(|_| bet)
+    }
+
+    pub async fn accept_instruction(&mut self, acceptor: &User, bet: &CreatedBet, bet_size: u64) -> (Instruction, Accepted) {
+        let accepted_count = self.bet(bet).await.accepted_count;
+        let accept = instruction::accept_bet(
+            id(),
+            acceptor.pubkey(),
+            acceptor.payment_account,
+            bet.address,
+            accepted_count,
+            bet.mint,
+            spl_token::id(),
+            self.market,
+            self.price,
+            None,
+            bet_size,
+            None,
+            None,
+            None,
+            None,
+        );
+        (accept, self.accepted_at(acceptor, bet, accepted_count))
+    }
+
+    /// The accept of a bet by an acceptor at the bet's accepted_count
+    pub fn accepted_at(&self, acceptor: &User, bet: &CreatedBet, accepted_count: u32) -> Accepted {
+        let address = find_accepted_bet(&id(), &bet.address, &acceptor.pubkey(), accepted_count).0;
+        Accepted { address, escrow: find_accepted_escrow(&id(), &address).0 }
+    }
+
+    pub async fn accept_bet(&mut self, acceptor: &User, bet: &CreatedBet, bet_size: u64) -> Result<Accepted, TransportError> {
+        let (accept, accepted) = self.accept_instruction(acceptor, bet, bet_size).await;
+        self.process(&[accept], &[&acceptor.keypair]).await.map(|_| accepted)
+    }
+
+    pub async fn cancel_bet(&mut self, creator: &User, bet: &CreatedBet, payment_account: Pubkey, amount: u64) -> Result<(), TransportError> {
+        let cancel = instruction::cancel_bet(
+            id(),
+            creator.pubkey(),
+            payment_account,
+            bet.address,
+            bet.mint,
+            spl_token::id(),
+            self.market,
+            self.price,
+            amount,
+        );
+        self.process(&[cancel], &[&creator.keypair]).await
+    }
+
+    pub async fn cancel_all(&mut self, creator: &User, bet: &CreatedBet) -> Result<(), TransportError> {
+        self.cancel_bet(creator, bet, creator.payment_account, CANCEL_ALL_REMAINING).await
+    }
+
+    /// The FinalizeBet of an accept, the finalizer is paid into their payment account
+    pub async fn finalize_instruction(&mut self, finalizer: &User, bet: &CreatedBet, accepted: &Accepted) -> Instruction {
+        let bet_state = self.bet(bet).await;
+        let accepted_state = self.accepted(accepted).await;
+        let commission_fee_account = match bet.payment {
+            Payment::Sol => self.fee_wallet,
+            Payment::Token => get_associated_token_address(&self.fee_wallet, &bet.mint, &spl_token::id()),
+        };
+        instruction::finalize_bet(
+            id(),
+            finalizer.pubkey(),
+            finalizer.payment_account,
+            commission_fee_account,
+            bet.address,
+            accepted.address,
+            accepted.escrow,
+            bet_state.creator_main_account,
+            bet_state.creator_payment_account,
+            accepted_state.acceptor_payment_account,
+            self.market,
+            self.price,
+            spl_token::id(),
+            None,
+            None,
+            false,
+            None,
+        )
+    }
+
+    pub async fn finalize_bet(&mut self, finalizer: &User, bet: &CreatedBet, accepted: &Accepted) -> Result<(), TransportError> {
+        let finalize = self.finalize_instruction(finalizer, bet, accepted).await;
+        self.process(&[finalize], &[&finalizer.keypair]).await
+    }
+
+    pub async fn creator_exposure(&mut self, creator: &User) -> CreatorExposure {
+        let address = find_creator_exposure(&id(), &self.market, &creator.pubkey()).0;
+        CreatorExposure::from_account_data(&self.account(&address).await.data).unwrap()
+    }
+
+    pub async fn bet(&mut self, bet: &CreatedBet) -> Bet {
+        Bet::from_account_data(&self.account(&bet.address).await.data).unwrap()
+    }
+
+    pub async fn accepted(&mut self, accepted: &Accepted) -> AcceptedBet {
+        AcceptedBet::from_account_data(&self.account(&accepted.address).await.data).unwrap()
+    }
+
+    pub async fn account(&mut self, address: &Pubkey) -> Account {
+        self.context.banks_client.get_account(*address).await.unwrap().unwrap_or_else(|| panic!("no account {}", address))
+    }
+
+    pub async fn exists(&mut self, address: &Pubkey) -> bool {
+        self.context.banks_client.get_account(*address).await.unwrap().is_some()
+    }
+
+    pub async fn lamports(&mut self, address: &Pubkey) -> u64 {
+        self.context.banks_client.get_balance(*address).await.unwrap()
+    }
+
+    pub async fn token_amount(&mut self, address: &Pubkey) -> u64 {
+        TokenAccount::unpack(&self.account(address).await.data).unwrap().amount
+    }
+
+    /// What a user has of the payment, lamports of the wallet for SOL and tokens of the payment account otherwise
+    pub async fn balance(&mut self, user: &User, payment: Payment) -> u64 {
+        match payment {
+            Payment::Sol => self.lamports(&user.pubkey()).await,
+            Payment::Token => self.token_amount(&user.payment_account).await,
+        }
+    }
+
+    /// The fee wallet's ATA of the token mint, which token bets pay commission into
+    pub fn fee_token_account(&self) -> Pubkey {
+        get_associated_token_address(&self.fee_wallet, &self.mint, &spl_token::id())
+    }
+
+    /// The commission collected so far in the payment
+    pub async fn fee_balance(&mut self, payment: Payment) -> u64 {
+        match payment {
+            Payment::Sol => self.lamports(&self.fee_wallet.clone()).await,
+            Payment::Token => self.token_amount(&self.fee_token_account()).await,
+        }
+    }
+
+    pub fn escrow_authority(&self, state_account: &Pubkey) -> Pubkey {
+        find_escrow_authority(&id(), &self.market, state_account).0
+    }
+}
+
+/// The lamports an account of the size needs to be rent exempt, with the default rent the tests' bank uses
+pub fn rent_exempt(data_len: usize) -> u64 {
+    solana_program::rent::Rent::default().minimum_balance(data_len)
+}
+
+pub fn system_account(lamports: u64) -> Account {
+    Account::new(lamports, 0, &system_program::id())
+}
+
+pub fn mint_account(decimals: u8) -> Account {
+    let mut data = vec![0; Mint::LEN];
+    Mint {
+        mint_authority: COption::None,
+        supply: u64::MAX / 2,
+        decimals,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    Account { lamports: rent_exempt(Mint::LEN), data, owner: spl_token::id(), executable: false, rent_epoch: 0 }
+}
+
+pub fn token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut data = vec![0; TokenAccount::LEN];
+    TokenAccount {
+        mint: *mint,
+        owner: *owner,
+        amount,
+        delegate: COption::None,
+        state: AccountState::Initialized,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    Account { lamports: rent_exempt(TokenAccount::LEN), data, owner: spl_token::id(), executable: false, rent_epoch: 0 }
+}
+
+/// A wrapped SOL token account, its lamports are the rent exempt reserve plus the amount
+pub fn wrapped_sol_account(owner: &Pubkey, amount: u64) -> Account {
+    let mut account = token_account(&spl_token::native_mint::id(), owner, amount);
+    let mut state = TokenAccount::unpack(&account.data).unwrap();
+    state.is_native = COption::Some(account.lamports);
+    state.pack_into_slice(&mut account.data);
+    account.lamports += amount;
+    account
+}
+
+pub fn pyth_account(data: Vec<u8>) -> Account {
+    Account { lamports: rent_exempt(data.len()), data, owner: pyth_devnet::id(), executable: false, rent_epoch: 0 }
+}
+
+pub fn clone_keypair(keypair: &Keypair) -> Keypair {
+    Keypair::from_bytes(&keypair.to_bytes()).unwrap()
+}
+
+/// Asserts the transaction failed with the program error in its instruction at the index
+pub fn assert_bet_error(result: Result<(), TransportError>, instruction_index: u8, error: BetError) {
+    match result {
+        Err(TransportError::TransactionError(TransactionError::InstructionError(index, InstructionError::Custom(code)))) => {
+            let message = format!("expected {:?}", error);
+            assert_eq!((index, code), (instruction_index, error as u32), "{}", message);
+        }
+        other => panic!("expected {:?}, got {:?}", error, other),
+    }
+}
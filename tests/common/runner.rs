@@ -0,0 +1,333 @@
+//! Runs the program, spl-token and the associated token program natively under solana-program-test. The 1.9 builtin
+//! runner can't resize account data in a CPI, and every account the program creates (PDAs through the system program,
+//! escrows through the ATA program) is sized in one. This is its runner with room for each account to grow by
+//! MAX_PERMITTED_DATA_INCREASE, as the BPF loader's serialization leaves. The SPL programs run natively too, the
+//! bundled shared objects don't run on the host's BPF interpreter
+use solana_program_runtime::{ic_msg, invoke_context::InvokeContext, stable_log, timings::ExecuteTimings};
+use solana_sdk::{
+    account::{ReadableAccount, WritableAccount},
+    account_info::AccountInfo,
+    entrypoint::{ProcessInstruction, ProgramResult, MAX_PERMITTED_DATA_INCREASE, SUCCESS},
+    instruction::{AccountMeta, Instruction, InstructionError},
+    message::{Message, SanitizedMessage},
+    program::invoke,
+    program_error::{PrintProgramError, ProgramError, UNSUPPORTED_SYSVAR},
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+use std::{cell::RefCell, collections::HashMap, convert::TryFrom, mem::transmute, rc::Rc, sync::Arc};
+
+thread_local! {
+    static INVOKE_CONTEXT: RefCell<Option<usize>> = const { RefCell::new(None) };
+    // room each account data buffer of the running instructions has, by its address
+    static DATA_CAPACITY: RefCell<HashMap<usize, usize>> = RefCell::new(HashMap::new());
+}
+
+fn set_invoke_context(new: &mut InvokeContext) {
+    INVOKE_CONTEXT.with(|invoke_context| invoke_context.replace(Some(new as *mut InvokeContext as usize)));
+}
+
+fn get_invoke_context<'a, 'b>() -> &'a mut InvokeContext<'b> {
+    let ptr = INVOKE_CONTEXT.with(|invoke_context| invoke_context.borrow().expect("invoke context not set"));
+    unsafe { &mut *(ptr as *mut InvokeContext<'b>) }
+}
+
+/// Routes the program's syscalls to the runner, call after ProgramTest::add_program installed its own
+pub fn install_syscall_stubs() {
+    set_syscall_stubs(Box::new(NativeSyscallStubs {}));
+}
+
+struct AccountStorage {
+    key: Pubkey,
+    lamports: u64,
+    data: Vec<u8>,
+    data_len: usize,
+    owner: Pubkey,
+}
+
+/// The program's builtin entrypoint, for ProgramTest::add_builtin_program
+pub fn process_yoyo_bets(first_instruction_account: usize, input: &[u8], invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+    run(yoyo_bets_entrypoint, first_instruction_account, input, invoke_context)
+}
+
+pub fn process_spl_token(first_instruction_account: usize, input: &[u8], invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+    run(spl_token::processor::Processor::process, first_instruction_account, input, invoke_context)
+}
+
+pub fn process_associated_token_account(first_instruction_account: usize, input: &[u8], invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+    run(spl_associated_token_account::processor::process_instruction, first_instruction_account, input, invoke_context)
+}
+
+/// A program forwarding its instruction with CPI, to the program passed as its first account with the other accounts
+pub fn process_proxy(first_instruction_account: usize, input: &[u8], invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+    run(proxy_entrypoint, first_instruction_account, input, invoke_context)
+}
+
+fn proxy_entrypoint(_program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
+    let (program, instruction_accounts) = accounts.split_first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let account_metas = instruction_accounts
+        .iter()
+        .map(|account| if account.is_writable { AccountMeta::new(*account.key, account.is_signer) } else { AccountMeta::new_readonly(*account.key, account.is_signer) })
+        .collect();
+    invoke(&Instruction::new_with_bytes(*program.key, input, account_metas), accounts)
+}
+
+// the processor ties the program id and accounts to one lifetime, both outlive the call. Errors are printed as the
+// BPF entrypoint does
+fn yoyo_bets_entrypoint(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
+    let program_id: &'static Pubkey = unsafe { transmute(program_id) };
+    let accounts: &'static [AccountInfo<'static>] = unsafe { transmute(accounts) };
+    yoyo_bets::processor::process_instruction(program_id, accounts, input).inspect_err(|error| error.print::<yoyo_bets::error::BetError>())
+}
+
+fn run(
+    process_instruction: ProcessInstruction,
+    _first_instruction_account: usize,
+    input: &[u8],
+    invoke_context: &mut InvokeContext,
+) -> Result<(), InstructionError> {
+    set_invoke_context(invoke_context);
+
+    let log_collector = invoke_context.get_log_collector();
+    let program_id = *invoke_context.get_caller()?;
+    stable_log::program_invoke(&log_collector, &program_id, invoke_context.get_stack_height());
+
+    // skip the program account, and share one storage between duplicate accounts
+    let keyed_accounts = &invoke_context.get_keyed_accounts()?[1..];
+    let mut storage: Vec<AccountStorage> = Vec::new();
+    for keyed_account in keyed_accounts {
+        let key = *keyed_account.unsigned_key();
+        if storage.iter().any(|account| account.key == key) {
+            continue;
+        }
+        let account = keyed_account.account.borrow();
+        let mut data = account.data().to_vec();
+        let data_len = data.len();
+        data.resize(data_len + MAX_PERMITTED_DATA_INCREASE, 0);
+        storage.push(AccountStorage { key, lamports: account.lamports(), data, data_len, owner: *account.owner() });
+    }
+
+    let mut account_refs = HashMap::new();
+    let mut buffers = Vec::new();
+    for account in storage.iter_mut() {
+        let capacity = account.data.len();
+        let buffer = account.data.as_mut_ptr() as usize;
+        DATA_CAPACITY.with(|data_capacity| data_capacity.borrow_mut().insert(buffer, capacity));
+        buffers.push(buffer);
+        account_refs.insert(
+            account.key,
+            (
+                Rc::new(RefCell::new(&mut account.lamports)),
+                Rc::new(RefCell::new(&mut account.data[..account.data_len])),
+                &account.owner,
+            ),
+        );
+    }
+
+    let account_infos: Vec<AccountInfo> = keyed_accounts
+        .iter()
+        .map(|keyed_account| {
+            let key = keyed_account.unsigned_key();
+            let (lamports, data, owner) = &account_refs[key];
+            AccountInfo {
+                key,
+                is_signer: keyed_account.signer_key().is_some(),
+                is_writable: keyed_account.is_writable(),
+                lamports: lamports.clone(),
+                data: data.clone(),
+                owner,
+                executable: keyed_account.executable().unwrap(),
+                rent_epoch: keyed_account.rent_epoch().unwrap(),
+            }
+        })
+        .collect();
+
+    let result = process_instruction(&program_id, &account_infos, input);
+    DATA_CAPACITY.with(|data_capacity| {
+        let mut data_capacity = data_capacity.borrow_mut();
+        for buffer in buffers.iter() {
+            data_capacity.remove(buffer);
+        }
+    });
+    if let Err(error) = result {
+        let error = u64::from(error);
+        stable_log::program_failure(&log_collector, &program_id, &error.into());
+        return Err(error.into());
+    }
+    stable_log::program_success(&log_collector, &program_id);
+
+    // commit the AccountInfos, whose data may have been resized by a CPI, back into the keyed accounts
+    for keyed_account in keyed_accounts {
+        let mut account = keyed_account.account.borrow_mut();
+        let (lamports, data, _owner) = &account_refs[keyed_account.unsigned_key()];
+        account.set_lamports(**lamports.borrow());
+        account.set_data(data.borrow().to_vec());
+    }
+
+    Ok(())
+}
+
+fn get_sysvar<T: Sysvar + Clone>(
+    sysvar: Result<Arc<T>, InstructionError>,
+    var_addr: *mut u8,
+) -> u64 {
+    let invoke_context = get_invoke_context();
+    if invoke_context
+        .get_compute_meter()
+        .borrow_mut()
+        .consume(invoke_context.get_compute_budget().sysvar_base_cost + T::size_of() as u64)
+        .is_err()
+    {
+        panic!("Exceeded compute budget");
+    }
+    match sysvar {
+        Ok(sysvar_data) => unsafe {
+            *(var_addr as *mut _ as *mut T) = T::clone(&sysvar_data);
+            SUCCESS
+        },
+        Err(_) => UNSUPPORTED_SYSVAR,
+    }
+}
+
+struct NativeSyscallStubs {}
+
+impl SyscallStubs for NativeSyscallStubs {
+    fn sol_log(&self, message: &str) {
+        let invoke_context = get_invoke_context();
+        ic_msg!(invoke_context, "Program log: {}", message);
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let invoke_context = get_invoke_context();
+        let log_collector = invoke_context.get_log_collector();
+
+        let caller = *invoke_context.get_caller().expect("get_caller");
+        let message = Message::new(std::slice::from_ref(instruction), None);
+        let program_id_index = message.instructions[0].program_id_index as usize;
+        let program_id = message.account_keys[program_id_index];
+        let caller_privileges = message
+            .account_keys
+            .iter()
+            .enumerate()
+            .map(|(i, _)| message.is_writable(i))
+            .collect::<Vec<bool>>();
+
+        stable_log::program_invoke(&log_collector, &program_id, invoke_context.get_stack_height());
+
+        // copy the caller's AccountInfos into the accounts the callee sees
+        let mut account_indices = Vec::with_capacity(message.account_keys.len());
+        let mut accounts = Vec::with_capacity(message.account_keys.len());
+        for (i, account_key) in message.account_keys.iter().enumerate() {
+            let ((account_index, account), account_info) = invoke_context
+                .get_account(account_key)
+                .zip(account_infos.iter().find(|account_info| account_info.unsigned_key() == account_key))
+                .ok_or(InstructionError::MissingAccount)
+                .unwrap();
+            {
+                let mut account = account.borrow_mut();
+                account.copy_into_owner_from_slice(account_info.owner.as_ref());
+                account.set_data_from_slice(&account_info.try_borrow_data().unwrap());
+                account.set_lamports(account_info.lamports());
+                account.set_executable(account_info.executable);
+                account.set_rent_epoch(account_info.rent_epoch);
+            }
+            let account_info = if message.is_writable(i) { Some(account_info) } else { None };
+            account_indices.push(account_index);
+            accounts.push((account, account_info));
+        }
+        let (program_account_index, _program_account) = invoke_context.get_account(&program_id).unwrap();
+        let program_indices = vec![program_account_index];
+
+        // the signers are the caller's signers and the PDAs of the given seeds
+        for account_info in account_infos {
+            for instruction_account in &instruction.accounts {
+                if *account_info.unsigned_key() == instruction_account.pubkey
+                    && instruction_account.is_signer
+                    && !account_info.is_signer
+                {
+                    let program_signer = signers_seeds.iter().any(|seeds| {
+                        Pubkey::create_program_address(seeds, &caller).unwrap() == instruction_account.pubkey
+                    });
+                    assert!(program_signer, "Missing signer for {}", instruction_account.pubkey);
+                }
+            }
+        }
+
+        invoke_context.record_instruction(invoke_context.get_stack_height(), instruction.clone());
+
+        let message = SanitizedMessage::Legacy(message);
+        invoke_context
+            .process_instruction(
+                &message,
+                &message.instructions()[0],
+                &program_indices,
+                &account_indices,
+                &caller_privileges,
+                &mut ExecuteTimings::default(),
+            )
+            .result
+            .map_err(|err| ProgramError::try_from(err).unwrap_or_else(|err| panic!("{}", err)))?;
+
+        // copy the writable accounts back into the caller's AccountInfos, growing or shrinking their data within the
+        // room the runner left
+        for (account, account_info) in accounts.iter() {
+            if let Some(account_info) = account_info {
+                let account = account.borrow();
+                **account_info.try_borrow_mut_lamports().unwrap() = account.lamports();
+                if account_info.owner != account.owner() {
+                    #[allow(mutable_transmutes)]
+                    let account_info_owner = unsafe { transmute::<&Pubkey, &mut Pubkey>(account_info.owner) };
+                    *account_info_owner = *account.owner();
+                }
+                let new_data = account.data();
+                let mut data = account_info.try_borrow_mut_data()?;
+                if data.len() != new_data.len() {
+                    let buffer = data.as_mut_ptr();
+                    let capacity = DATA_CAPACITY
+                        .with(|data_capacity| data_capacity.borrow().get(&(buffer as usize)).copied())
+                        .unwrap_or_else(|| panic!("Account {} can't be resized", account_info.key));
+                    assert!(new_data.len() <= capacity, "Account {} grew past {} bytes", account_info.key, capacity);
+                    *data = unsafe { std::slice::from_raw_parts_mut(buffer, new_data.len()) };
+                }
+                data.copy_from_slice(new_data);
+            }
+        }
+
+        stable_log::program_success(&log_collector, &program_id);
+        Ok(())
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        get_sysvar(get_invoke_context().get_sysvar_cache().get_clock(), var_addr)
+    }
+
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        get_sysvar(get_invoke_context().get_sysvar_cache().get_epoch_schedule(), var_addr)
+    }
+
+    #[allow(deprecated)]
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        get_sysvar(get_invoke_context().get_sysvar_cache().get_fees(), var_addr)
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        get_sysvar(get_invoke_context().get_sysvar_cache().get_rent(), var_addr)
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        let (program_id, data) = &get_invoke_context().return_data;
+        Some((*program_id, data.to_vec()))
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        let invoke_context = get_invoke_context();
+        let caller = *invoke_context.get_caller().unwrap();
+        invoke_context.return_data = (caller, data.to_vec());
+    }
+}
@@ -0,0 +1,119 @@
+//! The compute each instruction consumes in the BPF build, checked against the limits of src/budgets.rs. Each
+//! instruction runs in a transaction capped at its limit, so going over fails it with ComputationalBudgetExceeded.
+//! The native runner doesn't meter compute, so these need the program's shared object and are ignored by default:
+//!
+//!     cargo build-bpf && BPF_OUT_DIR=target/deploy cargo test --test compute_budgets -- --ignored
+//!
+//! spl-token and the associated token program still run natively (see runner), so the part of the token limits their
+//! CPIs take isn't metered here
+mod common;
+
+use common::*;
+use borsh::BorshSerialize;
+use solana_program::instruction::Instruction;
+use solana_program_test::{find_file, ProgramTest};
+use yoyo_bets::{
+    budgets::{
+        compute_budget_program, CU_ACCEPT_BET_SOL, CU_ACCEPT_BET_TOKEN, CU_CANCEL_BET_SOL, CU_CANCEL_BET_TOKEN, CU_CREATE_BET_SOL,
+        CU_CREATE_BET_TOKEN, CU_DISTRIBUTE_TOKEN, CU_FINALIZE_SOL, CU_FINALIZE_TOKEN, CU_SETTLE_TOKEN,
+    },
+    instruction::{self, BetInstruction, CANCEL_ALL_REMAINING},
+    state::Direction,
+};
+
+// the ComputeBudget RequestUnits instruction, the 1.9 bank caps the transaction at the units it requests. Deployed
+// clusters take budgets::set_compute_unit_limit instead, which this bank doesn't know
+fn request_units(units: u32) -> Instruction {
+    let mut data = vec![0];
+    data.extend_from_slice(&units.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+    Instruction { program_id: compute_budget_program::id(), accounts: vec![], data }
+}
+
+// the harness with the program loaded from its shared object instead of as a builtin
+async fn start_bpf() -> Harness {
+    assert!(find_file("yoyo_bets.so").is_some(), "yoyo_bets.so not found, build it with cargo build-bpf and set BPF_OUT_DIR");
+    let mut program_test = ProgramTest::default();
+    program_test.add_program("yoyo_bets", id(), None);
+    program_test.add_builtin_program("spl_token", spl_token::id(), runner::process_spl_token);
+    program_test.add_builtin_program("spl_associated_token_account", spl_associated_token_account::id(), runner::process_associated_token_account);
+    Harness::start_with(program_test).await
+}
+
+// creates, half accepts, cancels the rest of and finalizes a bet, each within its payment kind's limits
+async fn run_within_budgets(payment: Payment, create_units: u32, accept_units: u32, cancel_units: u32, finalize_units: u32) {
+    let mut harness = start_bpf().await;
+    let creator = harness.user(payment);
+    let acceptor = harness.user(payment);
+    let keeper = harness.user(payment);
+
+    let (mut create, bet) = harness.create_bet_instructions(&creator, payment, &BetParams::new(WALLET_TOKENS / 2, 200, Direction::Above, 10_500));
+    create.insert(0, request_units(create_units));
+    harness.process(&create, &[&creator.keypair]).await.unwrap();
+
+    let (accept, accepted) = harness.accept_instruction(&acceptor, &bet, WALLET_TOKENS / 4).await;
+    harness.process(&[request_units(accept_units), accept], &[&acceptor.keypair]).await.unwrap();
+
+    let cancel = instruction::cancel_bet(
+        id(),
+        creator.pubkey(),
+        creator.payment_account,
+        bet.address,
+        bet.mint,
+        spl_token::id(),
+        harness.market,
+        harness.price,
+        CANCEL_ALL_REMAINING,
+    );
+    harness.process(&[request_units(cancel_units), cancel], &[&creator.keypair]).await.unwrap();
+
+    harness.set_price(11_000).await;
+    harness.set_time(NOW + 3600).await;
+    let finalize = harness.finalize_instruction(&keeper, &bet, &accepted).await;
+    harness.process(&[request_units(finalize_units), finalize], &[&keeper.keypair]).await.unwrap();
+}
+
+#[solana_program_test::tokio::test]
+#[ignore = "needs the BPF build of the program"]
+async fn sol_bet_instructions_stay_within_their_budgets() {
+    run_within_budgets(Payment::Sol, CU_CREATE_BET_SOL, CU_ACCEPT_BET_SOL, CU_CANCEL_BET_SOL, CU_FINALIZE_SOL).await;
+}
+
+#[solana_program_test::tokio::test]
+#[ignore = "needs the BPF build of the program"]
+async fn token_bet_instructions_stay_within_their_budgets() {
+    run_within_budgets(Payment::Token, CU_CREATE_BET_TOKEN, CU_ACCEPT_BET_TOKEN, CU_CANCEL_BET_TOKEN, CU_FINALIZE_TOKEN).await;
+}
+
+#[solana_program_test::tokio::test]
+#[ignore = "needs the BPF build of the program"]
+async fn settle_and_distribute_fees_stay_within_their_budgets() {
+    let mut harness = start_bpf().await;
+    let creator = harness.user(Payment::Token);
+    let acceptor = harness.user(Payment::Token);
+    let keeper = harness.user(Payment::Token);
+
+    let bet = harness.create_bet(&creator, Payment::Token, &BetParams::new(WALLET_TOKENS / 2, 200, Direction::Above, 10_500)).await.unwrap();
+    let accepted = harness.accept_bet(&acceptor, &bet, WALLET_TOKENS / 2).await.unwrap();
+    harness.set_price(11_000).await;
+    harness.set_time(NOW + 3600).await;
+
+    // SettleBet takes the FinalizeBet accounts
+    let mut settle = harness.finalize_instruction(&keeper, &bet, &accepted).await;
+    settle.data = BetInstruction::SettleBet().try_to_vec().unwrap();
+    harness.process(&[request_units(CU_SETTLE_TOKEN), settle], &[&keeper.keypair]).await.unwrap();
+
+    let accepted_state = harness.accepted(&accepted).await;
+    let distribute = instruction::distribute_fees(
+        id(),
+        bet.address,
+        accepted.address,
+        accepted.escrow,
+        harness.fee_token_account(),
+        accepted_state.finalizer_payment_account,
+        accepted_state.escrow_rent_recipient,
+        harness.market,
+        spl_token::id(),
+    );
+    harness.process(&[request_units(CU_DISTRIBUTE_TOKEN), distribute], &[]).await.unwrap();
+}
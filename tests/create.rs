@@ -0,0 +1,277 @@
+//! CreateBet's checks on the bet's escrow, on how it is funded, on its oracle accounts, on the market's bounds for
+//! its expiration and on its tag
+mod common;
+
+use common::*;
+use solana_program::{program_pack::Pack, pubkey::Pubkey, system_instruction};
+use solana_sdk::{account::AccountSharedData, signature::Signer};
+use yoyo_bets::{
+    error::BetError,
+    instruction,
+    offsets::{BET_FIXED_FIELDS_END, BET_TAG_LEN_OFFSET, BET_TAG_OFFSET},
+    pyth_fixtures::ProductAccountBuilder,
+    state::{BetStatus, Direction, MAX_BET_DATA_LENGTH, MAX_CREATOR_EXPOSURE_DATA_LEN, MAX_FEED_EXPOSURE_DATA_LEN, MAX_TAG_LEN},
+};
+
+#[solana_program_test::tokio::test]
+async fn an_escrow_created_ahead_of_the_bet_for_someone_else_is_rejected() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let (instructions, bet) = harness.create_bet_instructions(&creator, Payment::Token, &BetParams::new(1_000_000, 200, Direction::Above, 10_500));
+
+    // a token account of the mint at the escrow's address, owned by a third party
+    let third_party = Pubkey::new_unique();
+    harness.context.set_account(&bet.escrow, &AccountSharedData::from(token_account(&harness.mint, &third_party, 0)));
+    assert_bet_error(harness.process(&instructions, &[&creator.keypair]).await, 1, BetError::EscrowNotOwnedByCreator);
+    assert_eq!(harness.balance(&creator, Payment::Token).await, WALLET_TOKENS);
+    assert!(!harness.exists(&bet.address).await);
+}
+
+#[solana_program_test::tokio::test]
+async fn an_escrow_created_ahead_of_the_bet_for_its_escrow_authority_is_used() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let (instructions, bet) = harness.create_bet_instructions(&creator, Payment::Token, &BetParams::new(1_000_000, 200, Direction::Above, 10_500));
+
+    let escrow_authority = harness.escrow_authority(&bet.address);
+    harness.context.set_account(&bet.escrow, &AccountSharedData::from(token_account(&harness.mint, &escrow_authority, 0)));
+    harness.process(&instructions, &[&creator.keypair]).await.unwrap();
+    assert_eq!(harness.token_amount(&bet.escrow).await, 1_000_000);
+    assert_eq!(harness.bet(&bet).await.status, BetStatus::Open);
+}
+
+#[solana_program_test::tokio::test]
+async fn creating_over_an_existing_bet_fails_before_any_funds_move() {
+    for payment in [Payment::Sol, Payment::Token] {
+        let mut harness = Harness::start().await;
+        let creator = harness.user(payment);
+        let (instructions, bet) = harness.create_bet_instructions(&creator, payment, &BetParams::new(1_000_000, 200, Direction::Above, 10_500));
+        harness.process(&instructions, &[&creator.keypair]).await.unwrap();
+        let creator_balance = harness.balance(&creator, payment).await;
+        let escrow_lamports = harness.account(&bet.escrow).await.lamports;
+
+        // the same CreateBet again, without the state account's creation
+        assert_bet_error(harness.process(&instructions[1..], &[&creator.keypair]).await, 0, BetError::AccountAlreadyInitialized);
+        assert_eq!(harness.balance(&creator, payment).await, creator_balance, "{:?}", payment);
+        assert_eq!(harness.account(&bet.escrow).await.lamports, escrow_lamports, "{:?}", payment);
+        assert_eq!(harness.creator_exposure(&creator).await.open_bets, 1);
+    }
+}
+
+async fn require_strict_sol_funding(harness: &mut Harness) {
+    let update = instruction::update_betting_market(
+        id(),
+        harness.market_owner.pubkey(),
+        harness.market,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some(true),
+        None
+    );
+    let market_owner = clone_keypair(&harness.market_owner);
+    harness.process(&[update], &[&market_owner]).await.unwrap();
+}
+
+// what the creator pays for a SOL bet besides its size: the rent of the bet state, escrow and exposure accounts
+fn creation_rent() -> u64 {
+    rent_exempt(MAX_BET_DATA_LENGTH)
+        + rent_exempt(spl_token::state::Account::LEN)
+        + rent_exempt(MAX_CREATOR_EXPOSURE_DATA_LEN)
+        + rent_exempt(MAX_FEED_EXPOSURE_DATA_LEN)
+}
+
+#[solana_program_test::tokio::test]
+async fn strict_markets_take_sol_bets_funded_earlier_in_the_same_transaction() {
+    let mut harness = Harness::start().await;
+    require_strict_sol_funding(&mut harness).await;
+    let creator = harness.user(Payment::Sol);
+
+    let (mut instructions, bet) = harness.create_bet_instructions(&creator, Payment::Sol, &BetParams::new(SOL, 200, Direction::Above, 10_500));
+    instructions.insert(1, system_instruction::transfer(&creator.pubkey(), &bet.escrow, SOL));
+    harness.process(&instructions, &[&creator.keypair]).await.unwrap();
+
+    // the transfer paid the size, CreateBet only the escrow's rent on top of it
+    assert_eq!(harness.lamports(&creator.pubkey()).await, WALLET_LAMPORTS - creation_rent() - SOL);
+    assert_eq!(harness.token_amount(&bet.escrow).await, SOL);
+    assert_eq!(harness.bet(&bet).await.status, BetStatus::Open);
+
+    // token bets are funded by CreateBet as before
+    let token_creator = harness.user(Payment::Token);
+    harness.create_bet(&token_creator, Payment::Token, &BetParams::new(1_000_000, 200, Direction::Above, 10_500)).await.unwrap();
+}
+
+#[solana_program_test::tokio::test]
+async fn strict_markets_reject_sol_bets_funded_in_an_earlier_transaction() {
+    let mut harness = Harness::start().await;
+    require_strict_sol_funding(&mut harness).await;
+    let creator = harness.user(Payment::Sol);
+
+    let (instructions, bet) = harness.create_bet_instructions(&creator, Payment::Sol, &BetParams::new(SOL, 200, Direction::Above, 10_500));
+    assert_bet_error(harness.process(&instructions, &[&creator.keypair]).await, 1, BetError::EscrowNotFundedInTransaction);
+
+    // the escrow is funded first, on its own
+    harness.process(&[system_instruction::transfer(&creator.pubkey(), &bet.escrow, SOL)], &[&creator.keypair]).await.unwrap();
+    assert_bet_error(harness.process(&instructions, &[&creator.keypair]).await, 1, BetError::EscrowNotFundedInTransaction);
+    assert!(!harness.exists(&bet.address).await);
+}
+
+#[solana_program_test::tokio::test]
+async fn other_markets_count_an_escrow_funded_in_an_earlier_transaction() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Sol);
+
+    let (instructions, bet) = harness.create_bet_instructions(&creator, Payment::Sol, &BetParams::new(SOL, 200, Direction::Above, 10_500));
+    harness.process(&[system_instruction::transfer(&creator.pubkey(), &bet.escrow, SOL)], &[&creator.keypair]).await.unwrap();
+    harness.process(&instructions, &[&creator.keypair]).await.unwrap();
+
+    // the earlier transfer counts towards the size, the creator paid it once
+    assert_eq!(harness.lamports(&creator.pubkey()).await, WALLET_LAMPORTS - creation_rent() - SOL);
+    assert_eq!(harness.token_amount(&bet.escrow).await, SOL);
+}
+
+async fn set_duration_bounds(harness: &mut Harness, max_expiration_horizon_secs: Option<i64>, min_duration_secs: Option<i64>) {
+    let update = instruction::update_betting_market(
+        id(),
+        harness.market_owner.pubkey(),
+        harness.market,
+        max_expiration_horizon_secs,
+        None,
+        min_duration_secs,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None
+    );
+    let market_owner = clone_keypair(&harness.market_owner);
+    harness.process(&[update], &[&market_owner]).await.unwrap();
+}
+
+#[solana_program_test::tokio::test]
+async fn expiration_is_allowed_up_to_the_market_horizon() {
+    let mut harness = Harness::start().await;
+    set_duration_bounds(&mut harness, Some(7200), None).await;
+    let creator = harness.user(Payment::Token);
+    let mut params = BetParams::new(1_000_000, 200, Direction::Above, 10_500);
+
+    // CreateBet follows the state account's creation in the transaction
+    params.expiration_time = NOW + 7201;
+    assert_bet_error(harness.create_bet(&creator, Payment::Token, &params).await.map(|_| ()), 1, BetError::ExpirationTooFar);
+    params.expiration_time = NOW + 7200;
+    harness.create_bet(&creator, Payment::Token, &params).await.unwrap();
+
+    // a zero horizon is unlimited
+    set_duration_bounds(&mut harness, Some(0), None).await;
+    params.expiration_time = NOW + 365 * 24 * 60 * 60;
+    let bet = harness.create_bet(&creator, Payment::Token, &params).await.unwrap();
+    assert_eq!(harness.bet(&bet).await.expiration_time, NOW + 365 * 24 * 60 * 60);
+}
+
+#[solana_program_test::tokio::test]
+async fn expiration_is_allowed_from_the_market_minimum_duration() {
+    let mut harness = Harness::start().await;
+    set_duration_bounds(&mut harness, None, Some(3600)).await;
+    let creator = harness.user(Payment::Token);
+    let mut params = BetParams::new(1_000_000, 200, Direction::Above, 10_500);
+
+    params.expiration_time = NOW + 3599;
+    assert_bet_error(harness.create_bet(&creator, Payment::Token, &params).await.map(|_| ()), 1, BetError::DurationTooShort);
+    params.expiration_time = NOW + 3600;
+    harness.create_bet(&creator, Payment::Token, &params).await.unwrap();
+
+    // the duration counts from the creation, not from an earlier bet's
+    harness.set_time(NOW + 100).await;
+    assert_bet_error(harness.create_bet(&creator, Payment::Token, &params).await.map(|_| ()), 1, BetError::DurationTooShort);
+    params.expiration_time = NOW + 3700;
+    harness.create_bet(&creator, Payment::Token, &params).await.unwrap();
+
+    // a minimum equal to the horizon leaves that one duration
+    set_duration_bounds(&mut harness, Some(7200), Some(7200)).await;
+    for (expiration_time, error) in [(NOW + 7299, BetError::DurationTooShort), (NOW + 7301, BetError::ExpirationTooFar)] {
+        params.expiration_time = expiration_time;
+        assert_bet_error(harness.create_bet(&creator, Payment::Token, &params).await.map(|_| ()), 1, error);
+    }
+    params.expiration_time = NOW + 7300;
+    harness.create_bet(&creator, Payment::Token, &params).await.unwrap();
+}
+
+#[solana_program_test::tokio::test]
+async fn each_corruption_of_the_product_account_has_its_own_error() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let params = BetParams::new(1_000_000, 200, Direction::Above, 10_500);
+
+    let valid = ProductAccountBuilder::new().symbol("Crypto.SOL/USD").px_acc(harness.price).build();
+    // flips a byte of the header: the magic at 0, the version at 4 and the account type at 8
+    let corrupted = |offset: usize| {
+        let mut data = valid.clone();
+        data[offset] ^= 0xff;
+        pyth_account(data)
+    };
+    let mut wrong_owner = pyth_account(valid.clone());
+    wrong_owner.owner = Pubkey::new_unique();
+    let other_feed = pyth_account(ProductAccountBuilder::new().symbol("Crypto.BTC/USD").px_acc(Pubkey::new_unique()).build());
+    let cases = [
+        (wrong_owner, BetError::OracleWrongOwner),
+        (corrupted(0), BetError::OracleBadMagic),
+        (corrupted(4), BetError::OracleWrongVersion),
+        (corrupted(8), BetError::OracleWrongAccountType),
+        (other_feed, BetError::OracleProductPriceMismatch),
+    ];
+    for (product, error) in cases {
+        harness.context.set_account(&harness.product, &AccountSharedData::from(product));
+        assert_bet_error(harness.create_bet(&creator, Payment::Token, &params).await.map(|_| ()), 1, error);
+    }
+
+    harness.context.set_account(&harness.product, &AccountSharedData::from(pyth_account(valid)));
+    harness.create_bet(&creator, Payment::Token, &params).await.unwrap();
+}
+
+#[solana_program_test::tokio::test]
+async fn tags_fill_their_fixed_slot_and_no_more() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let mut params = BetParams::new(1_000_000, 200, Direction::Above, 10_500);
+
+    params.tag = "x".repeat(MAX_TAG_LEN + 1);
+    let (instructions, too_long) = harness.create_bet_instructions(&creator, Payment::Token, &params);
+    assert_bet_error(harness.process(&instructions, &[&creator.keypair]).await, 1, BetError::InvalidCreateTag);
+    assert!(!harness.exists(&too_long.address).await);
+
+    // a full tag and a short one padded with 0u8s, each at the tag offset for memcmp filters
+    for tag in ["x".repeat(MAX_TAG_LEN), "weekly SOL ladder #12".to_string()] {
+        params.tag = tag.clone();
+        let bet = harness.create_bet(&creator, Payment::Token, &params).await.unwrap();
+        let data = harness.account(&bet.address).await.data;
+        assert_eq!(&data[BET_TAG_LEN_OFFSET..BET_TAG_OFFSET], &(MAX_TAG_LEN as u32).to_le_bytes());
+        assert_eq!(&data[BET_TAG_OFFSET..BET_TAG_OFFSET + tag.len()], tag.as_bytes());
+        assert!(data[BET_TAG_OFFSET + tag.len()..BET_FIXED_FIELDS_END].iter().all(|byte| *byte == 0));
+        assert_eq!(harness.bet(&bet).await.tag.trim_end_matches('\0'), tag);
+    }
+}
@@ -0,0 +1,112 @@
+//! ExtendExpiry moves a bet's expiration out while its feed has published no price since expiry, up to the market's cap
+mod common;
+
+use common::*;
+use pyth_client::PriceStatus;
+use solana_program::sysvar::clock::Clock;
+use solana_sdk::{account::AccountSharedData, signature::Signer, transport::TransportError};
+use yoyo_bets::{
+    error::BetError,
+    instruction::{self, ExpiryExtensions},
+    pyth_fixtures::PriceAccountBuilder,
+    state::Direction,
+};
+
+async fn allow_extensions(harness: &mut Harness, max_extensions: u8, extension_secs: i64) {
+    let update = instruction::update_betting_market(
+        id(),
+        harness.market_owner.pubkey(),
+        harness.market,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some(ExpiryExtensions { max_extensions, extension_secs }),
+        None,
+        None
+    );
+    let market_owner = clone_keypair(&harness.market_owner);
+    harness.process(&[update], &[&market_owner]).await.unwrap();
+}
+
+// the feed isn't trading and its last valid aggregate was published at the slot
+fn set_feed_halted(harness: &mut Harness, valid_slot: u64) {
+    let data = PriceAccountBuilder::new()
+        .price(10_000)
+        .expo(PRICE_EXPO)
+        .product(harness.product)
+        .status(PriceStatus::Unknown)
+        .valid_slot(valid_slot)
+        .build();
+    harness.context.set_account(&harness.price, &AccountSharedData::from(pyth_account(data)));
+}
+
+// an accepted bet expiring at NOW + 3600
+async fn accepted_bet(harness: &mut Harness) -> CreatedBet {
+    let creator = harness.user(Payment::Token);
+    let acceptor = harness.user(Payment::Token);
+    let bet = harness.create_bet(&creator, Payment::Token, &BetParams::new(1_000_000, 200, Direction::Above, 10_500)).await.unwrap();
+    harness.accept_bet(&acceptor, &bet, 1_000_000).await.unwrap();
+    bet
+}
+
+async fn extend(harness: &mut Harness, bet: &CreatedBet) -> Result<(), TransportError> {
+    let extend = instruction::extend_expiry(id(), bet.address, harness.market, harness.price);
+    harness.process(&[extend], &[]).await
+}
+
+#[solana_program_test::tokio::test]
+async fn quiet_feed_extends_the_expiration_until_the_cap() {
+    let mut harness = Harness::start().await;
+    allow_extensions(&mut harness, 2, 600).await;
+    let bet = accepted_bet(&mut harness).await;
+
+    harness.set_time(NOW + 3600).await;
+    set_feed_halted(&mut harness, 0);
+    extend(&mut harness, &bet).await.unwrap();
+    let bet_state = harness.bet(&bet).await;
+    assert_eq!((bet_state.expiration_time, bet_state.extensions_used), (NOW + 4200, 1));
+
+    // the extended bet isn't expired yet, then is again once the extension passes
+    assert_bet_error(extend(&mut harness, &bet).await, 0, BetError::ExpiryNotExtendable);
+    harness.set_time(NOW + 4200).await;
+    extend(&mut harness, &bet).await.unwrap();
+    let bet_state = harness.bet(&bet).await;
+    assert_eq!((bet_state.expiration_time, bet_state.extensions_used), (NOW + 4800, 2));
+
+    // the market's cap is reached, the expiration stays where it is
+    harness.set_time(NOW + 4800).await;
+    assert_bet_error(extend(&mut harness, &bet).await, 0, BetError::ExpiryExtensionsExhausted);
+    let bet_state = harness.bet(&bet).await;
+    assert_eq!((bet_state.expiration_time, bet_state.extensions_used), (NOW + 4800, 2));
+}
+
+#[solana_program_test::tokio::test]
+async fn a_price_since_expiry_rejects_the_extension() {
+    let mut harness = Harness::start().await;
+    allow_extensions(&mut harness, 2, 600).await;
+    let bet = accepted_bet(&mut harness).await;
+    harness.set_time(NOW + 3600).await;
+
+    // the feed is trading, finalize can settle on its price
+    assert_bet_error(extend(&mut harness, &bet).await, 0, BetError::OraclePricePublished);
+
+    // it stopped trading, but published its last valid price at the current slot, after expiration
+    let clock: Clock = harness.context.banks_client.get_sysvar().await.unwrap();
+    set_feed_halted(&mut harness, clock.slot);
+    assert_bet_error(extend(&mut harness, &bet).await, 0, BetError::OraclePricePublished);
+    let bet_state = harness.bet(&bet).await;
+    assert_eq!((bet_state.expiration_time, bet_state.extensions_used), (NOW + 3600, 0));
+}
@@ -0,0 +1,538 @@
+//! Create, accept, cancel and finalize across SOL and token bets, with the exact balances each party ends with. Each
+//! payment kind covers Above and Below, fixed and variable odds, either side winning, a tie at the bet price under each tie policy, full and
+//! partial cancels, cancels after expiration, and the timestamps each change leaves on the bet. Two phase settlement, pools and expiry extensions have their own suites
+mod common;
+
+use common::*;
+use solana_program::{program_pack::Pack, pubkey::Pubkey};
+use solana_sdk::{account::AccountSharedData, signature::Signer};
+use yoyo_bets::{
+    error::BetError,
+    instruction,
+    state::{BetStatus, CancelCondition, Direction, PositionStatus, TiePolicy, MAX_ACCEPTED_BET_DATA_LEN, MAX_BET_DATA_LENGTH, MAX_CREATOR_EXPOSURE_DATA_LEN, MAX_FEED_EXPOSURE_DATA_LEN},
+};
+
+// the rent a SOL creator's first bet in the market pays: the bet state account, its escrow and the creator's and feed's
+// exposure accounts. The escrow's rent stays in the escrow until a full cancel closes it
+fn first_bet_rent() -> u64 {
+    rent_exempt(MAX_BET_DATA_LENGTH)
+        + rent_exempt(spl_token::state::Account::LEN)
+        + rent_exempt(MAX_CREATOR_EXPOSURE_DATA_LEN)
+        + rent_exempt(MAX_FEED_EXPOSURE_DATA_LEN)
+}
+
+// the rent an accept pays, the accepted bet state account and its escrow
+fn accept_rent() -> u64 {
+    rent_exempt(MAX_ACCEPTED_BET_DATA_LEN) + rent_exempt(spl_token::state::Account::LEN)
+}
+
+#[solana_program_test::tokio::test]
+async fn sol_bet_above_fixed_odds_creator_wins() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Sol);
+    let acceptor = harness.user(Payment::Sol);
+    let keeper = harness.user(Payment::Sol);
+
+    let bet = harness.create_bet(&creator, Payment::Sol, &BetParams::new(SOL, 200, Direction::Above, 10_500)).await.unwrap();
+    assert_eq!(harness.lamports(&creator.pubkey()).await, WALLET_LAMPORTS - first_bet_rent() - SOL);
+    assert_eq!(harness.token_amount(&bet.escrow).await, SOL);
+
+    // even odds, the acceptor matches the whole size
+    let accepted = harness.accept_bet(&acceptor, &bet, SOL).await.unwrap();
+    assert_eq!(harness.lamports(&acceptor.pubkey()).await, WALLET_LAMPORTS - accept_rent() - SOL);
+    assert_eq!(harness.token_amount(&accepted.escrow).await, 2 * SOL);
+    assert_eq!(harness.token_amount(&bet.escrow).await, 0);
+    assert_eq!(harness.bet(&bet).await.status, BetStatus::FullyAccepted);
+
+    harness.set_price(11_000).await;
+    harness.set_time(NOW + 3599).await;
+    assert_bet_error(harness.finalize_bet(&keeper, &bet, &accepted).await, 0, BetError::BeforeExpiryTime);
+
+    let fee_before = harness.fee_balance(Payment::Sol).await;
+    harness.set_time(NOW + 3600).await;
+    harness.finalize_bet(&keeper, &bet, &accepted).await.unwrap();
+
+    // 2% commission and the 0.5% finalizer reward of the 1 SOL size come out of the 2 SOL pot, the acceptor gets the
+    // escrow's rent back
+    assert_eq!(harness.lamports(&creator.pubkey()).await, WALLET_LAMPORTS - first_bet_rent() - SOL + 1_975_000_000);
+    assert_eq!(harness.lamports(&acceptor.pubkey()).await, WALLET_LAMPORTS - accept_rent() - SOL + rent_exempt(spl_token::state::Account::LEN));
+    assert_eq!(harness.fee_balance(Payment::Sol).await, fee_before + 20_000_000);
+    assert_eq!(harness.lamports(&keeper.pubkey()).await, WALLET_LAMPORTS + 5_000_000);
+    assert!(!harness.exists(&accepted.escrow).await);
+    let escrow_authority = harness.escrow_authority(&accepted.address);
+    assert_eq!(harness.lamports(&escrow_authority).await, 0);
+    assert_eq!(harness.accepted(&accepted).await.status, PositionStatus::Settled);
+    let bet_state = harness.bet(&bet).await;
+    assert_eq!((bet_state.status, bet_state.finalized_count), (BetStatus::Expired, 1));
+
+    assert_bet_error(harness.finalize_bet(&keeper, &bet, &accepted).await, 0, BetError::BetFinalized);
+}
+
+#[solana_program_test::tokio::test]
+async fn sol_bet_below_fixed_odds_acceptor_wins() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Sol);
+    let acceptor = harness.user(Payment::Sol);
+    let keeper = harness.user(Payment::Sol);
+
+    let bet = harness.create_bet(&creator, Payment::Sol, &BetParams::new(SOL, 150, Direction::Below, 9_500)).await.unwrap();
+    // odds of 1.50 take half the size from the acceptor
+    let accepted = harness.accept_bet(&acceptor, &bet, SOL).await.unwrap();
+    assert_eq!(harness.lamports(&acceptor.pubkey()).await, WALLET_LAMPORTS - accept_rent() - SOL / 2);
+
+    let fee_before = harness.fee_balance(Payment::Sol).await;
+    harness.set_time(NOW + 3600).await;
+    harness.finalize_bet(&keeper, &bet, &accepted).await.unwrap();
+
+    // the price stayed above 95.00, the acceptor takes the 1.5 SOL pot less commission and reward
+    assert_eq!(harness.lamports(&creator.pubkey()).await, WALLET_LAMPORTS - first_bet_rent() - SOL);
+    assert_eq!(
+        harness.lamports(&acceptor.pubkey()).await,
+        WALLET_LAMPORTS - accept_rent() - SOL / 2 + 1_475_000_000 + rent_exempt(spl_token::state::Account::LEN)
+    );
+    assert_eq!(harness.fee_balance(Payment::Sol).await, fee_before + 20_000_000);
+    assert_eq!(harness.lamports(&keeper.pubkey()).await, WALLET_LAMPORTS + 5_000_000);
+}
+
+#[solana_program_test::tokio::test]
+async fn token_bet_above_variable_odds_acceptor_wins() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let acceptor = harness.user(Payment::Token);
+    let keeper = harness.user(Payment::Token);
+
+    let mut params = BetParams::new(1_000_000, 200, Direction::Above, 10_500);
+    params.variable_odds = Some(100);
+    let bet = harness.create_bet(&creator, Payment::Token, &params).await.unwrap();
+    assert_eq!(harness.balance(&creator, Payment::Token).await, WALLET_TOKENS - 1_000_000);
+
+    // the price rose 3.00 towards the bet price, so the odds fell by 3
+    harness.set_price(10_300).await;
+    let accepted = harness.accept_bet(&acceptor, &bet, 1_000_000).await.unwrap();
+    assert_eq!(harness.accepted(&accepted).await.odds, 197);
+    assert_eq!(harness.balance(&acceptor, Payment::Token).await, WALLET_TOKENS - 970_000);
+    assert_eq!(harness.token_amount(&accepted.escrow).await, 1_970_000);
+
+    harness.set_price(10_400).await;
+    harness.set_time(NOW + 3600).await;
+    harness.finalize_bet(&keeper, &bet, &accepted).await.unwrap();
+
+    assert_eq!(harness.balance(&creator, Payment::Token).await, WALLET_TOKENS - 1_000_000);
+    assert_eq!(harness.balance(&acceptor, Payment::Token).await, WALLET_TOKENS - 970_000 + 1_945_000);
+    assert_eq!(harness.fee_balance(Payment::Token).await, 20_000);
+    assert_eq!(harness.balance(&keeper, Payment::Token).await, WALLET_TOKENS + 5_000);
+    // a token escrow is emptied, its rent stays with it for CloseSettledAccounts
+    assert_eq!(harness.token_amount(&accepted.escrow).await, 0);
+}
+
+#[solana_program_test::tokio::test]
+async fn token_bet_below_fixed_odds_creator_wins_two_acceptors() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let first_acceptor = harness.user(Payment::Token);
+    let second_acceptor = harness.user(Payment::Token);
+    let keeper = harness.user(Payment::Token);
+
+    let bet = harness.create_bet(&creator, Payment::Token, &BetParams::new(1_000_000, 300, Direction::Below, 9_800)).await.unwrap();
+    let first = harness.accept_bet(&first_acceptor, &bet, 500_000).await.unwrap();
+    assert_eq!(harness.bet(&bet).await.status, BetStatus::PartiallyAccepted);
+    let second = harness.accept_bet(&second_acceptor, &bet, 500_000).await.unwrap();
+    assert_eq!(harness.bet(&bet).await.status, BetStatus::FullyAccepted);
+    // odds of 3.00 take twice the matched size from each acceptor
+    assert_eq!(harness.balance(&first_acceptor, Payment::Token).await, WALLET_TOKENS - 1_000_000);
+    assert_eq!(harness.balance(&second_acceptor, Payment::Token).await, WALLET_TOKENS - 1_000_000);
+
+    harness.set_price(9_700).await;
+    harness.set_time(NOW + 3600).await;
+    harness.finalize_bet(&keeper, &bet, &first).await.unwrap();
+    harness.finalize_bet(&keeper, &bet, &second).await.unwrap();
+
+    // each 1.5 token pot pays 2% of its 0.5 matched as commission and 0.5% as the reward
+    assert_eq!(harness.balance(&creator, Payment::Token).await, WALLET_TOKENS - 1_000_000 + 2 * 1_487_500);
+    assert_eq!(harness.balance(&first_acceptor, Payment::Token).await, WALLET_TOKENS - 1_000_000);
+    assert_eq!(harness.balance(&second_acceptor, Payment::Token).await, WALLET_TOKENS - 1_000_000);
+    assert_eq!(harness.fee_balance(Payment::Token).await, 2 * 10_000);
+    assert_eq!(harness.balance(&keeper, Payment::Token).await, WALLET_TOKENS + 2 * 2_500);
+    assert_eq!(harness.bet(&bet).await.finalized_count, 2);
+}
+
+#[solana_program_test::tokio::test]
+async fn sol_bet_cancelled_before_any_accept_refunds_escrow() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Sol);
+    let acceptor = harness.user(Payment::Sol);
+
+    let bet = harness.create_bet(&creator, Payment::Sol, &BetParams::new(SOL, 200, Direction::Above, 10_500)).await.unwrap();
+    harness.cancel_all(&creator, &bet).await.unwrap();
+
+    // closing the escrow returns the size and the escrow's rent, the state accounts keep theirs
+    assert_eq!(
+        harness.lamports(&creator.pubkey()).await,
+        WALLET_LAMPORTS - first_bet_rent() + rent_exempt(spl_token::state::Account::LEN)
+    );
+    assert!(!harness.exists(&bet.escrow).await);
+    assert_eq!(harness.bet(&bet).await.status, BetStatus::Cancelled);
+
+    // the escrow is gone, so an accept is turned away before its status is read
+    assert_bet_error(harness.accept_bet(&acceptor, &bet, SOL).await.map(|_| ()), 0, BetError::InvalidTokenProgram);
+    assert_eq!(harness.lamports(&acceptor.pubkey()).await, WALLET_LAMPORTS);
+    assert_bet_error(harness.cancel_all(&creator, &bet).await, 0, BetError::BetCancelled);
+}
+
+#[solana_program_test::tokio::test]
+async fn token_bet_partial_cancel_then_accept_the_rest() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let acceptor = harness.user(Payment::Token);
+    let keeper = harness.user(Payment::Token);
+
+    let bet = harness.create_bet(&creator, Payment::Token, &BetParams::new(1_000_000, 200, Direction::Above, 10_500)).await.unwrap();
+    harness.cancel_bet(&creator, &bet, creator.payment_account, 400_000).await.unwrap();
+    assert_eq!(harness.balance(&creator, Payment::Token).await, WALLET_TOKENS - 600_000);
+    assert_eq!(harness.token_amount(&bet.escrow).await, 600_000);
+    let bet_state = harness.bet(&bet).await;
+    assert_eq!((bet_state.bet_size, bet_state.status), (600_000, BetStatus::Open));
+
+    assert_bet_error(harness.accept_bet(&acceptor, &bet, 600_001).await.map(|_| ()), 0, BetError::AcceptExceedsCapacity);
+    let accepted = harness.accept_bet(&acceptor, &bet, 600_000).await.unwrap();
+    // nothing is left to withdraw
+    assert_bet_error(harness.cancel_all(&creator, &bet).await, 0, BetError::InvalidCancelAmount);
+
+    harness.set_price(10_600).await;
+    harness.set_time(NOW + 3600).await;
+    harness.finalize_bet(&keeper, &bet, &accepted).await.unwrap();
+    assert_eq!(harness.balance(&creator, Payment::Token).await, WALLET_TOKENS - 600_000 + 1_200_000 - 12_000 - 3_000);
+    assert_eq!(harness.balance(&acceptor, Payment::Token).await, WALLET_TOKENS - 600_000);
+    assert_eq!(harness.fee_balance(Payment::Token).await, 12_000);
+    assert_eq!(harness.balance(&keeper, Payment::Token).await, WALLET_TOKENS + 3_000);
+}
+
+#[solana_program_test::tokio::test]
+async fn token_bet_with_a_refund_destination_only_cancels_to_it() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let cold = harness.user(Payment::Token);
+
+    let mut params = BetParams::new(1_000_000, 200, Direction::Above, 10_500);
+    params.refund_destination = Some(cold.payment_account);
+    let bet = harness.create_bet(&creator, Payment::Token, &params).await.unwrap();
+    assert_eq!(harness.bet(&bet).await.refund_destination, cold.payment_account);
+
+    assert_bet_error(harness.cancel_bet(&creator, &bet, creator.payment_account, 400_000).await, 0, BetError::WrongPaymentAccount);
+    harness.cancel_bet(&creator, &bet, cold.payment_account, 400_000).await.unwrap();
+    assert_eq!(harness.balance(&cold, Payment::Token).await, WALLET_TOKENS + 400_000);
+    assert_eq!(harness.balance(&creator, Payment::Token).await, WALLET_TOKENS - 1_000_000);
+    assert_eq!(harness.token_amount(&bet.escrow).await, 600_000);
+}
+
+#[solana_program_test::tokio::test]
+async fn cancel_condition_stops_accepts_and_cancel_refunds_the_rest() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Sol);
+    let first_acceptor = harness.user(Payment::Sol);
+    let second_acceptor = harness.user(Payment::Sol);
+
+    let mut params = BetParams::new(SOL, 200, Direction::Above, 10_500);
+    params.cancel_condition = CancelCondition::new(9_000, 11_000, NOW + 1800).unwrap();
+    let bet = harness.create_bet(&creator, Payment::Sol, &params).await.unwrap();
+
+    // above the cancel condition's range no one can accept
+    harness.set_price(11_001).await;
+    assert_bet_error(harness.accept_bet(&first_acceptor, &bet, SOL / 4).await.map(|_| ()), 0, BetError::BetNoLongerValid);
+
+    // back in range the bet is acceptable again
+    harness.set_price(10_000).await;
+    let accepted = harness.accept_bet(&first_acceptor, &bet, SOL / 4).await.unwrap();
+
+    // past the cancel condition's time it isn't
+    harness.set_time(NOW + 1801).await;
+    assert_bet_error(harness.accept_bet(&second_acceptor, &bet, SOL / 4).await.map(|_| ()), 0, BetError::BetNoLongerValid);
+    assert_eq!(harness.lamports(&second_acceptor.pubkey()).await, WALLET_LAMPORTS);
+
+    // the creator withdraws the unmatched 3/4, the accept still settles at expiration
+    harness.cancel_all(&creator, &bet).await.unwrap();
+    assert_eq!(
+        harness.lamports(&creator.pubkey()).await,
+        WALLET_LAMPORTS - first_bet_rent() - SOL / 4 + rent_exempt(spl_token::state::Account::LEN)
+    );
+    let bet_state = harness.bet(&bet).await;
+    assert_eq!((bet_state.status, bet_state.bet_size, bet_state.total_amount_accepted), (BetStatus::Cancelled, SOL / 4, SOL / 4));
+
+    harness.set_time(NOW + 3600).await;
+    harness.set_price(10_400).await;
+    let keeper = harness.user(Payment::Sol);
+    harness.finalize_bet(&keeper, &bet, &accepted).await.unwrap();
+    assert_eq!(
+        harness.lamports(&first_acceptor.pubkey()).await,
+        WALLET_LAMPORTS - accept_rent() - SOL / 4 + SOL / 2 - 5_000_000 - 1_250_000 + rent_exempt(spl_token::state::Account::LEN)
+    );
+    assert_eq!(harness.lamports(&keeper.pubkey()).await, WALLET_LAMPORTS + 1_250_000);
+}
+
+#[solana_program_test::tokio::test]
+async fn sol_bet_below_variable_odds_creator_wins() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Sol);
+    let acceptor = harness.user(Payment::Sol);
+    let keeper = harness.user(Payment::Sol);
+
+    let mut params = BetParams::new(SOL, 200, Direction::Below, 9_500);
+    params.variable_odds = Some(100);
+    let bet = harness.create_bet(&creator, Payment::Sol, &params).await.unwrap();
+
+    // the price fell 2.00 towards the bet price, so the odds fell by 2
+    harness.set_price(9_800).await;
+    let accepted = harness.accept_bet(&acceptor, &bet, SOL).await.unwrap();
+    assert_eq!(harness.accepted(&accepted).await.odds, 198);
+    assert_eq!(harness.lamports(&acceptor.pubkey()).await, WALLET_LAMPORTS - accept_rent() - 980_000_000);
+    assert_eq!(harness.token_amount(&accepted.escrow).await, 1_980_000_000);
+
+    harness.set_price(9_400).await;
+    harness.set_time(NOW + 3600).await;
+    harness.finalize_bet(&keeper, &bet, &accepted).await.unwrap();
+
+    assert_eq!(harness.lamports(&creator.pubkey()).await, WALLET_LAMPORTS - first_bet_rent() - SOL + 1_955_000_000);
+    assert_eq!(
+        harness.lamports(&acceptor.pubkey()).await,
+        WALLET_LAMPORTS - accept_rent() - 980_000_000 + rent_exempt(spl_token::state::Account::LEN)
+    );
+    assert_eq!(harness.lamports(&keeper.pubkey()).await, WALLET_LAMPORTS + 5_000_000);
+}
+
+#[solana_program_test::tokio::test]
+async fn settling_at_the_bet_price_pays_the_creator() {
+    for payment in [Payment::Sol, Payment::Token] {
+        let mut harness = Harness::start().await;
+        let creator = harness.user(payment);
+        let acceptor = harness.user(payment);
+        let keeper = harness.user(payment);
+
+        let bet = harness.create_bet(&creator, payment, &BetParams::new(1_000_000, 200, Direction::Above, 10_500)).await.unwrap();
+        let accepted = harness.accept_bet(&acceptor, &bet, 1_000_000).await.unwrap();
+        let creator_before = harness.balance(&creator, payment).await;
+        harness.set_price(10_500).await;
+        harness.set_time(NOW + 3600).await;
+        harness.finalize_bet(&keeper, &bet, &accepted).await.unwrap();
+        assert_eq!(harness.balance(&creator, payment).await, creator_before + 2_000_000 - 20_000 - 5_000, "{:?}", payment);
+    }
+}
+
+async fn set_tie_policy(harness: &mut Harness, tie_policy: TiePolicy) {
+    let update = instruction::update_betting_market(
+        id(),
+        harness.market_owner.pubkey(),
+        harness.market,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some(tie_policy)
+    );
+    let market_owner = clone_keypair(&harness.market_owner);
+    harness.process(&[update], &[&market_owner]).await.unwrap();
+}
+
+#[solana_program_test::tokio::test]
+async fn a_market_giving_ties_to_the_acceptor_pays_the_acceptor_at_the_bet_price() {
+    for payment in [Payment::Sol, Payment::Token] {
+        let mut harness = Harness::start().await;
+        set_tie_policy(&mut harness, TiePolicy::AcceptorWins).await;
+        let creator = harness.user(payment);
+        let acceptor = harness.user(payment);
+        let keeper = harness.user(payment);
+
+        let bet = harness.create_bet(&creator, payment, &BetParams::new(1_000_000, 200, Direction::Above, 10_500)).await.unwrap();
+        let accepted = harness.accept_bet(&acceptor, &bet, 1_000_000).await.unwrap();
+        let creator_before = harness.balance(&creator, payment).await;
+        harness.set_price(10_500).await;
+        harness.set_time(NOW + 3600).await;
+        harness.finalize_bet(&keeper, &bet, &accepted).await.unwrap();
+        assert_eq!(harness.balance(&creator, payment).await, creator_before, "{:?}", payment);
+        assert_eq!(harness.accepted(&accepted).await.status, PositionStatus::Settled);
+    }
+}
+
+#[solana_program_test::tokio::test]
+async fn a_market_refunding_ties_returns_both_stakes_at_the_bet_price() {
+    for payment in [Payment::Sol, Payment::Token] {
+        let mut harness = Harness::start().await;
+        set_tie_policy(&mut harness, TiePolicy::Refund).await;
+        let creator = harness.user(payment);
+        let acceptor = harness.user(payment);
+        let keeper = harness.user(payment);
+
+        let bet = harness.create_bet(&creator, payment, &BetParams::new(1_000_000, 200, Direction::Below, 10_500)).await.unwrap();
+        let accepted = harness.accept_bet(&acceptor, &bet, 1_000_000).await.unwrap();
+        let creator_before = harness.balance(&creator, payment).await;
+        let keeper_before = harness.balance(&keeper, payment).await;
+        let fee_before = harness.fee_balance(payment).await;
+        harness.set_price(10_500).await;
+        harness.set_time(NOW + 3600).await;
+        harness.finalize_bet(&keeper, &bet, &accepted).await.unwrap();
+
+        // no commission and no finalizer reward
+        assert_eq!(harness.balance(&creator, payment).await, creator_before + 1_000_000, "{:?}", payment);
+        assert_eq!(harness.balance(&keeper, payment).await, keeper_before);
+        assert_eq!(harness.fee_balance(payment).await, fee_before);
+        assert_eq!(harness.accepted(&accepted).await.status, PositionStatus::Refunded);
+    }
+}
+
+#[solana_program_test::tokio::test]
+async fn token_bet_cancelled_before_any_accept_refunds_escrow() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let acceptor = harness.user(Payment::Token);
+
+    let bet = harness.create_bet(&creator, Payment::Token, &BetParams::new(1_000_000, 200, Direction::Above, 10_500)).await.unwrap();
+    harness.cancel_all(&creator, &bet).await.unwrap();
+
+    // a token escrow is emptied, its rent stays with it
+    assert_eq!(harness.balance(&creator, Payment::Token).await, WALLET_TOKENS);
+    assert_eq!(harness.token_amount(&bet.escrow).await, 0);
+    assert_eq!(harness.bet(&bet).await.status, BetStatus::Cancelled);
+    assert_bet_error(harness.accept_bet(&acceptor, &bet, 1_000_000).await.map(|_| ()), 0, BetError::BetCancelled);
+    assert_eq!(harness.balance(&acceptor, Payment::Token).await, WALLET_TOKENS);
+}
+
+#[solana_program_test::tokio::test]
+async fn sol_bet_partial_cancel_then_accept_the_rest() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Sol);
+    let acceptor = harness.user(Payment::Sol);
+    let keeper = harness.user(Payment::Sol);
+
+    // a partial cancel pays out wrapped SOL, so it goes to the creator's wrapped SOL account
+    let wrapped = Pubkey::new_unique();
+    harness.context.set_account(&wrapped, &AccountSharedData::from(wrapped_sol_account(&creator.pubkey(), 0)));
+
+    let bet = harness.create_bet(&creator, Payment::Sol, &BetParams::new(SOL, 200, Direction::Below, 9_500)).await.unwrap();
+    harness.cancel_bet(&creator, &bet, wrapped, 400_000_000).await.unwrap();
+    assert_eq!(harness.token_amount(&wrapped).await, 400_000_000);
+    assert_eq!(harness.lamports(&creator.pubkey()).await, WALLET_LAMPORTS - first_bet_rent() - SOL);
+    assert_eq!(harness.token_amount(&bet.escrow).await, 600_000_000);
+
+    let accepted = harness.accept_bet(&acceptor, &bet, 600_000_000).await.unwrap();
+    assert_bet_error(harness.cancel_all(&creator, &bet).await, 0, BetError::InvalidCancelAmount);
+
+    // the acceptor wins the 1.2 SOL pot less 2% and 0.5% of the 0.6 SOL matched
+    harness.set_price(9_600).await;
+    harness.set_time(NOW + 3600).await;
+    harness.finalize_bet(&keeper, &bet, &accepted).await.unwrap();
+    assert_eq!(harness.lamports(&creator.pubkey()).await, WALLET_LAMPORTS - first_bet_rent() - SOL);
+    assert_eq!(
+        harness.lamports(&acceptor.pubkey()).await,
+        WALLET_LAMPORTS - accept_rent() - 600_000_000 + 1_185_000_000 + rent_exempt(spl_token::state::Account::LEN)
+    );
+    assert_eq!(harness.lamports(&keeper.pubkey()).await, WALLET_LAMPORTS + 3_000_000);
+}
+
+#[solana_program_test::tokio::test]
+async fn an_unaccepted_bet_past_expiration_only_cancels() {
+    for payment in [Payment::Sol, Payment::Token] {
+        let mut harness = Harness::start().await;
+        let creator = harness.user(payment);
+        let acceptor = harness.user(payment);
+
+        let bet = harness.create_bet(&creator, payment, &BetParams::new(1_000_000, 200, Direction::Above, 10_500)).await.unwrap();
+        let creator_after_create = harness.balance(&creator, payment).await;
+        harness.set_time(NOW + 3600).await;
+        assert_bet_error(harness.accept_bet(&acceptor, &bet, 1_000_000).await.map(|_| ()), 0, BetError::TooCloseToExpiry);
+        assert_eq!(harness.balance(&acceptor, payment).await, if payment == Payment::Sol { WALLET_LAMPORTS } else { WALLET_TOKENS });
+
+        // the whole size comes back, a SOL creator also gets the escrow's rent
+        harness.cancel_all(&creator, &bet).await.unwrap();
+        let escrow_rent = if payment == Payment::Sol { rent_exempt(spl_token::state::Account::LEN) } else { 0 };
+        assert_eq!(harness.balance(&creator, payment).await, creator_after_create + 1_000_000 + escrow_rent, "{:?}", payment);
+        assert_eq!(harness.bet(&bet).await.status, BetStatus::Cancelled);
+    }
+}
+
+#[solana_program_test::tokio::test]
+async fn a_partly_accepted_bet_cancels_the_rest_after_expiration_and_still_settles() {
+    for payment in [Payment::Sol, Payment::Token] {
+        let mut harness = Harness::start().await;
+        let creator = harness.user(payment);
+        let acceptor = harness.user(payment);
+        let keeper = harness.user(payment);
+
+        let bet = harness.create_bet(&creator, payment, &BetParams::new(1_000_000, 200, Direction::Above, 10_500)).await.unwrap();
+        let accepted = harness.accept_bet(&acceptor, &bet, 400_000).await.unwrap();
+        let creator_after_accept = harness.balance(&creator, payment).await;
+
+        harness.set_price(10_600).await;
+        harness.set_time(NOW + 3600).await;
+        harness.cancel_all(&creator, &bet).await.unwrap();
+        let escrow_rent = if payment == Payment::Sol { rent_exempt(spl_token::state::Account::LEN) } else { 0 };
+        assert_eq!(harness.balance(&creator, payment).await, creator_after_accept + 600_000 + escrow_rent, "{:?}", payment);
+
+        harness.finalize_bet(&keeper, &bet, &accepted).await.unwrap();
+        assert_eq!(harness.balance(&creator, payment).await, creator_after_accept + 600_000 + escrow_rent + 800_000 - 8_000 - 2_000, "{:?}", payment);
+        assert_eq!(harness.accepted(&accepted).await.status, PositionStatus::Settled);
+    }
+}
+
+#[solana_program_test::tokio::test]
+async fn an_unreadable_oracle_fails_finalize_with_an_error() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let acceptor = harness.user(Payment::Token);
+    let keeper = harness.user(Payment::Token);
+
+    let bet = harness.create_bet(&creator, Payment::Token, &BetParams::new(1_000_000, 200, Direction::Above, 10_500)).await.unwrap();
+    let accepted = harness.accept_bet(&acceptor, &bet, 1_000_000).await.unwrap();
+
+    // the bet's price account is owned by the Pyth program but doesn't hold a price account
+    harness.set_time(NOW + 3600).await;
+    harness.context.set_account(&harness.price, &AccountSharedData::from(pyth_account(vec![0; 64])));
+    assert_bet_error(harness.finalize_bet(&keeper, &bet, &accepted).await, 0, BetError::InvalidAccountInput);
+    let simulate = instruction::simulate_finalize(id(), bet.address, accepted.address, harness.market, harness.price);
+    assert_bet_error(harness.process(&[simulate], &[]).await, 0, BetError::InvalidAccountInput);
+    assert_eq!(harness.accepted(&accepted).await.status, PositionStatus::Open);
+}
+
+#[solana_program_test::tokio::test]
+async fn created_at_stays_while_updated_at_follows_each_change() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let acceptor = harness.user(Payment::Token);
+    let keeper = harness.user(Payment::Token);
+
+    harness.set_time(NOW + 10).await;
+    let bet = harness.create_bet(&creator, Payment::Token, &BetParams::new(1_000_000, 200, Direction::Above, 10_500)).await.unwrap();
+    let created = harness.bet(&bet).await;
+    assert_eq!((created.created_at, created.updated_at), (NOW + 10, NOW + 10));
+
+    harness.set_time(NOW + 100).await;
+    let accepted = harness.accept_bet(&acceptor, &bet, 500_000).await.unwrap();
+    let after_accept = harness.bet(&bet).await;
+    assert_eq!((after_accept.created_at, after_accept.updated_at), (NOW + 10, NOW + 100));
+    assert!(after_accept.last_interaction_slot > created.last_interaction_slot);
+
+    // a failed accept changes nothing
+    harness.set_time(NOW + 200).await;
+    assert_bet_error(harness.accept_bet(&acceptor, &bet, 1_000_000).await.map(|_| ()), 0, BetError::AcceptExceedsCapacity);
+    let after_failure = harness.bet(&bet).await;
+    assert_eq!((after_failure.updated_at, after_failure.last_interaction_slot), (NOW + 100, after_accept.last_interaction_slot));
+
+    harness.set_price(11_000).await;
+    harness.set_time(NOW + 3700).await;
+    harness.finalize_bet(&keeper, &bet, &accepted).await.unwrap();
+    let finalized = harness.bet(&bet).await;
+    assert_eq!((finalized.created_at, finalized.updated_at), (NOW + 10, NOW + 3700));
+    assert!(finalized.last_interaction_slot > after_accept.last_interaction_slot);
+}
@@ -0,0 +1,99 @@
+//! InitBettingMarket, the markets' canonical addresses and the registry keeping each mint in one market
+mod common;
+
+use common::*;
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+use solana_sdk::{account::AccountSharedData, signature::Signer};
+use yoyo_bets::{
+    constants::pyth_devnet,
+    error::BetError,
+    instruction,
+    pdas::{find_market, find_mint_registry},
+    state::{BettingMarket, MintRegistry},
+};
+
+#[solana_program_test::tokio::test]
+async fn second_market_for_the_same_primary_mint_is_rejected() {
+    let mut harness = Harness::start().await;
+    let other_owner = harness.user(Payment::Sol);
+    let market_address = harness.market;
+    let market_lamports = harness.lamports(&market_address).await;
+
+    // a different owner and fee wallet can't claim the native mint's market
+    let init = instruction::init_betting_market(
+        id(),
+        other_owner.pubkey(),
+        other_owner.pubkey(),
+        pyth_devnet::id(),
+        vec![spl_token::native_mint::id()],
+        0,
+        0,
+        true,
+        false,
+    );
+    assert_eq!(init.accounts[1].pubkey, market_address);
+    assert_bet_error(harness.process(&[init], &[&other_owner.keypair]).await, 0, BetError::AccountAlreadyInitialized);
+    assert_eq!(harness.lamports(&market_address).await, market_lamports);
+    let market = BettingMarket::from_account_data(&harness.account(&market_address).await.data).unwrap();
+    assert_eq!(market.owner, harness.market_owner.pubkey());
+    assert_eq!(market.fee_commission_account, harness.fee_wallet);
+}
+
+// a mint no market accepts yet
+fn new_mint(harness: &mut Harness) -> Pubkey {
+    let mint = Pubkey::new_unique();
+    harness.context.set_account(&mint, &AccountSharedData::from(mint_account(TOKEN_DECIMALS)));
+    mint
+}
+
+fn init_market(owner: &User, accepted_mints: Vec<Pubkey>) -> Instruction {
+    instruction::init_betting_market(id(), owner.pubkey(), owner.pubkey(), pyth_devnet::id(), accepted_mints, 0, 0, true, false)
+}
+
+fn mint_registry(data: &[u8]) -> MintRegistry {
+    MintRegistry::from_account_data(data).unwrap()
+}
+
+#[solana_program_test::tokio::test]
+async fn market_for_another_primary_mint_gets_its_own_address() {
+    let mut harness = Harness::start().await;
+    let other_owner = harness.user(Payment::Sol);
+    let mint = new_mint(&mut harness);
+    let other_mint = new_mint(&mut harness);
+
+    harness.process(&[init_market(&other_owner, vec![mint, other_mint])], &[&other_owner.keypair]).await.unwrap();
+
+    let (address, bump_seed) = find_market(&id(), &mint);
+    assert_ne!(address, harness.market);
+    let market = BettingMarket::from_account_data(&harness.account(&address).await.data).unwrap();
+    assert_eq!((market.owner, market.bump_seed), (other_owner.pubkey(), bump_seed));
+    assert_eq!(&market.accepted_mints[..2], &[mint, other_mint]);
+
+    // each accepted mint is registered to the market
+    for mint in [mint, other_mint] {
+        let (registry_address, registry_bump_seed) = find_mint_registry(&id(), &mint);
+        let registry = mint_registry(&harness.account(&registry_address).await.data);
+        assert_eq!((registry.mint, registry.betting_market, registry.bump_seed), (mint, address, registry_bump_seed));
+    }
+}
+
+#[solana_program_test::tokio::test]
+async fn a_mint_another_market_accepts_is_rejected() {
+    let mut harness = Harness::start().await;
+    let other_owner = harness.user(Payment::Sol);
+    let mint = new_mint(&mut harness);
+
+    // the token mint is a secondary mint of the native mint's market, another market can't take it first or second
+    for accepted_mints in [vec![harness.mint], vec![mint, harness.mint]] {
+        let primary_mint = accepted_mints[0];
+        let init = init_market(&other_owner, accepted_mints);
+        assert_bet_error(harness.process(&[init], &[&other_owner.keypair]).await, 0, BetError::MintInAnotherMarket);
+        assert!(!harness.exists(&find_market(&id(), &primary_mint).0).await);
+    }
+    assert!(!harness.exists(&find_mint_registry(&id(), &mint).0).await);
+    let registry = mint_registry(&harness.account(&find_mint_registry(&id(), &harness.mint).0).await.data);
+    assert_eq!(registry.betting_market, harness.market);
+
+    // the rejected market's unclaimed mint is still free
+    harness.process(&[init_market(&other_owner, vec![mint])], &[&other_owner.keypair]).await.unwrap();
+}
@@ -0,0 +1,186 @@
+//! CreatePool, JoinPool and SettlePool: the pool's payouts add up to its escrow, and the market settings SettlePool honors
+mod common;
+
+use common::*;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Signer};
+use yoyo_bets::{
+    error::BetError,
+    instruction::{self, SettlementObservations},
+    pdas::{find_escrow_authority, find_pool},
+    state::{Direction, TiePolicy},
+    token::get_associated_token_address,
+};
+
+const POOL_NONCE: u64 = 0;
+
+async fn update_market(
+    harness: &mut Harness,
+    finalize_exclusivity_secs: Option<i64>,
+    settlement_observations: Option<SettlementObservations>,
+    tie_policy: Option<TiePolicy>
+) {
+    let update = instruction::update_betting_market(
+        id(),
+        harness.market_owner.pubkey(),
+        harness.market,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        finalize_exclusivity_secs,
+        settlement_observations,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        tie_policy
+    );
+    let market_owner = clone_keypair(&harness.market_owner);
+    harness.process(&[update], &[&market_owner]).await.unwrap();
+}
+
+// a token pool at a bet price of the feed's current price, seeded by the creator on Below
+fn create_pool(harness: &Harness, creator: &User, seed_amount: u64) -> Instruction {
+    instruction::create_pool(
+        id(),
+        creator.pubkey(),
+        creator.payment_account,
+        harness.mint,
+        spl_token::id(),
+        harness.market,
+        harness.product,
+        harness.price,
+        seed_amount,
+        Direction::Below,
+        10_000,
+        NOW + 3600,
+        POOL_NONCE,
+    )
+}
+
+fn pool_address(harness: &Harness, creator: &User) -> Pubkey {
+    find_pool(&id(), &harness.market, &creator.pubkey(), POOL_NONCE).0
+}
+
+async fn join(harness: &mut Harness, pool: Pubkey, participant: &User, stake: u64, side: Direction) {
+    let join = instruction::join_pool(id(), participant.pubkey(), participant.payment_account, pool, harness.mint, spl_token::id(), harness.market, stake, side);
+    harness.process(&[join], &[&participant.keypair]).await.unwrap();
+}
+
+fn settle(harness: &Harness, pool: Pubkey, participant: &User, participant_signs: bool) -> Instruction {
+    instruction::settle_pool(
+        id(),
+        pool,
+        participant.pubkey(),
+        participant.payment_account,
+        harness.mint,
+        spl_token::id(),
+        harness.market,
+        harness.fee_wallet,
+        harness.price,
+        participant_signs,
+    )
+}
+
+#[solana_program_test::tokio::test]
+async fn winnings_and_the_last_claims_dust_add_up_to_the_escrow() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let loser = harness.user(Payment::Token);
+    let winners = [harness.user(Payment::Token), harness.user(Payment::Token), harness.user(Payment::Token)];
+
+    harness.process(&[create_pool(&harness, &creator, 1_000_000)], &[&creator.keypair]).await.unwrap();
+    let pool = pool_address(&harness, &creator);
+    join(&mut harness, pool, &loser, 1_000_000, Direction::Below).await;
+    for winner in &winners {
+        join(&mut harness, pool, winner, 1_000_000, Direction::Above).await;
+    }
+    let escrow = get_associated_token_address(&find_escrow_authority(&id(), &harness.market, &pool).0, &harness.mint, &spl_token::id());
+    assert_eq!(harness.token_amount(&escrow).await, 5_000_000);
+
+    // the price is the bet price, a tie goes to Above
+    harness.set_time(NOW + 3600).await;
+    for participant in [&creator, &loser] {
+        harness.process(&[settle(&harness, pool, participant, false)], &[]).await.unwrap();
+        assert_eq!(harness.balance(participant, Payment::Token).await, WALLET_TOKENS - 1_000_000);
+    }
+
+    // the 2% commission on the losing 2,000,000 leaves 1,960,000, each winner's third of it rounds down to 653,333
+    for winner in &winners {
+        harness.process(&[settle(&harness, pool, winner, false)], &[]).await.unwrap();
+    }
+    let payouts = [
+        harness.balance(&winners[0], Payment::Token).await - (WALLET_TOKENS - 1_000_000),
+        harness.balance(&winners[1], Payment::Token).await - (WALLET_TOKENS - 1_000_000),
+        harness.balance(&winners[2], Payment::Token).await - (WALLET_TOKENS - 1_000_000),
+    ];
+    assert_eq!(payouts, [1_653_333, 1_653_333, 1_653_334]);
+    let commission = harness.fee_balance(Payment::Token).await;
+    assert_eq!(commission, 40_000);
+    // the last claim took the dust, every token staked was paid out
+    assert_eq!(payouts.iter().sum::<u64>() + commission, 5_000_000);
+    assert_eq!(harness.token_amount(&escrow).await, 0);
+}
+
+#[solana_program_test::tokio::test]
+async fn pools_are_rejected_on_markets_settling_on_observations() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    update_market(&mut harness, None, Some(SettlementObservations { window_secs: 60, min_count: 1 }), None).await;
+
+    assert_bet_error(harness.process(&[create_pool(&harness, &creator, 1_000_000)], &[&creator.keypair]).await, 0, BetError::PoolSettlesOnObservations);
+    assert!(!harness.exists(&pool_address(&harness, &creator)).await);
+    assert_eq!(harness.balance(&creator, Payment::Token).await, WALLET_TOKENS);
+}
+
+#[solana_program_test::tokio::test]
+async fn only_the_participant_claims_during_the_exclusivity_window() {
+    let mut harness = Harness::start().await;
+    update_market(&mut harness, Some(600), None, None).await;
+    let creator = harness.user(Payment::Token);
+    let winner = harness.user(Payment::Token);
+    let other_winner = harness.user(Payment::Token);
+
+    harness.process(&[create_pool(&harness, &creator, 1_000_000)], &[&creator.keypair]).await.unwrap();
+    let pool = pool_address(&harness, &creator);
+    join(&mut harness, pool, &winner, 1_000_000, Direction::Above).await;
+    join(&mut harness, pool, &other_winner, 1_000_000, Direction::Above).await;
+
+    harness.set_time(NOW + 3600 + 599).await;
+    assert_bet_error(harness.process(&[settle(&harness, pool, &winner, false)], &[]).await, 0, BetError::FinalizeReservedForParties);
+    harness.process(&[settle(&harness, pool, &winner, true)], &[&winner.keypair]).await.unwrap();
+    assert_eq!(harness.balance(&winner, Payment::Token).await, WALLET_TOKENS + 490_000);
+
+    // after the window anyone can claim a position for its participant
+    harness.set_time(NOW + 3600 + 600).await;
+    harness.process(&[settle(&harness, pool, &other_winner, false)], &[]).await.unwrap();
+    assert_eq!(harness.balance(&other_winner, Payment::Token).await, WALLET_TOKENS + 490_000);
+}
+
+#[solana_program_test::tokio::test]
+async fn a_tie_refunds_every_stake_on_a_market_refunding_ties() {
+    let mut harness = Harness::start().await;
+    update_market(&mut harness, None, None, Some(TiePolicy::Refund)).await;
+    let creator = harness.user(Payment::Token);
+    let participant = harness.user(Payment::Token);
+
+    harness.process(&[create_pool(&harness, &creator, 1_000_000)], &[&creator.keypair]).await.unwrap();
+    let pool = pool_address(&harness, &creator);
+    join(&mut harness, pool, &participant, 2_000_000, Direction::Above).await;
+
+    // the price is the bet price, neither side wins and no commission is taken
+    harness.set_time(NOW + 3600).await;
+    for user in [&creator, &participant] {
+        harness.process(&[settle(&harness, pool, user, false)], &[]).await.unwrap();
+        assert_eq!(harness.balance(user, Payment::Token).await, WALLET_TOKENS);
+    }
+    assert_eq!(harness.fee_balance(Payment::Token).await, 0);
+}
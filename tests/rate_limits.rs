@@ -0,0 +1,140 @@
+//! The market's per creator rate limits on CreateBet, and when a bet stops counting towards its creator's open bets
+mod common;
+
+use borsh::BorshSerialize;
+use common::*;
+use solana_sdk::signature::Signer;
+use yoyo_bets::{
+    error::BetError,
+    instruction::{self, BetInstruction},
+    state::{BetStatus, Direction},
+};
+
+async fn set_rate_limits(harness: &mut Harness, min_seconds_between_bets: i64, max_open_bets_per_creator: u32) {
+    let update = instruction::update_betting_market(
+        id(),
+        harness.market_owner.pubkey(),
+        harness.market,
+        None,
+        None,
+        None,
+        Some(min_seconds_between_bets),
+        Some(max_open_bets_per_creator),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None
+    );
+    let market_owner = clone_keypair(&harness.market_owner);
+    harness.process(&[update], &[&market_owner]).await.unwrap();
+}
+
+fn params() -> BetParams {
+    let mut params = BetParams::new(1_000_000, 200, Direction::Above, 10_500);
+    params.expiration_time = NOW + 7200;
+    params
+}
+
+#[solana_program_test::tokio::test]
+async fn rapid_creates_are_throttled_and_spaced_creates_succeed() {
+    let mut harness = Harness::start().await;
+    set_rate_limits(&mut harness, 60, 0).await;
+    let creator = harness.user(Payment::Token);
+    let other_creator = harness.user(Payment::Token);
+
+    harness.create_bet(&creator, Payment::Token, &params()).await.unwrap();
+    // CreateBet follows the state account's creation in the transaction
+    assert_bet_error(harness.create_bet(&creator, Payment::Token, &params()).await.map(|_| ()), 1, BetError::CreatingTooFast);
+    harness.set_time(NOW + 59).await;
+    assert_bet_error(harness.create_bet(&creator, Payment::Token, &params()).await.map(|_| ()), 1, BetError::CreatingTooFast);
+    // the limit is per creator
+    harness.create_bet(&other_creator, Payment::Token, &params()).await.unwrap();
+
+    harness.set_time(NOW + 60).await;
+    harness.create_bet(&creator, Payment::Token, &params()).await.unwrap();
+    let exposure = harness.creator_exposure(&creator).await;
+    assert_eq!((exposure.open_bets, exposure.last_created_at), (2, NOW + 60));
+    assert_eq!(harness.balance(&creator, Payment::Token).await, WALLET_TOKENS - 2_000_000);
+}
+
+#[solana_program_test::tokio::test]
+async fn finalizing_the_last_accept_releases_the_bet_once() {
+    let mut harness = Harness::start().await;
+    set_rate_limits(&mut harness, 0, 2).await;
+    let creator = harness.user(Payment::Token);
+    let acceptor = harness.user(Payment::Token);
+    let keeper = harness.user(Payment::Token);
+
+    let mut first_params = params();
+    first_params.expiration_time = NOW + 3600;
+    let first = harness.create_bet(&creator, Payment::Token, &first_params).await.unwrap();
+    harness.create_bet(&creator, Payment::Token, &params()).await.unwrap();
+    assert_bet_error(harness.create_bet(&creator, Payment::Token, &params()).await.map(|_| ()), 1, BetError::TooManyOpenBets);
+
+    // half of the first bet is matched, finalizing that accept releases the bet though its other half is unmatched
+    let accepted = harness.accept_bet(&acceptor, &first, 500_000).await.unwrap();
+    harness.set_time(NOW + 3600).await;
+    harness.finalize_bet(&keeper, &first, &accepted).await.unwrap();
+    assert_eq!(harness.creator_exposure(&creator).await.open_bets, 1);
+    assert!(harness.bet(&first).await.open_bet_released);
+
+    // withdrawing the unmatched half doesn't release it again, nor does closing the finalized accept
+    harness.cancel_all(&creator, &first).await.unwrap();
+    assert_eq!(harness.bet(&first).await.status, BetStatus::Cancelled);
+    assert_eq!(harness.creator_exposure(&creator).await.open_bets, 1);
+    let close = instruction::close_settled_accounts(id(), harness.market, creator.pubkey(), first.address, &[(accepted.address, acceptor.pubkey())]);
+    harness.process(&[close], &[]).await.unwrap();
+    assert!(!harness.exists(&accepted.address).await);
+    assert_eq!(harness.creator_exposure(&creator).await.open_bets, 1);
+
+    harness.create_bet(&creator, Payment::Token, &params()).await.unwrap();
+    assert_eq!(harness.creator_exposure(&creator).await.open_bets, 2);
+    assert_bet_error(harness.create_bet(&creator, Payment::Token, &params()).await.map(|_| ()), 1, BetError::TooManyOpenBets);
+}
+
+#[solana_program_test::tokio::test]
+async fn close_settled_accounts_releases_a_bet_finished_by_distribute_fees() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let acceptor = harness.user(Payment::Token);
+    let keeper = harness.user(Payment::Token);
+
+    let bet = harness.create_bet(&creator, Payment::Token, &params()).await.unwrap();
+    let accepted = harness.accept_bet(&acceptor, &bet, 1_000_000).await.unwrap();
+    harness.set_time(NOW + 7200).await;
+
+    // SettleBet takes the FinalizeBet accounts, the accept is finalized once DistributeFees pays the fees
+    let mut settle = harness.finalize_instruction(&keeper, &bet, &accepted).await;
+    settle.data = BetInstruction::SettleBet().try_to_vec().unwrap();
+    harness.process(&[settle], &[&keeper.keypair]).await.unwrap();
+    let accepted_state = harness.accepted(&accepted).await;
+    let distribute = instruction::distribute_fees(
+        id(),
+        bet.address,
+        accepted.address,
+        accepted.escrow,
+        harness.fee_token_account(),
+        accepted_state.finalizer_payment_account,
+        accepted_state.escrow_rent_recipient,
+        harness.market,
+        spl_token::id(),
+    );
+    harness.process(&[distribute], &[]).await.unwrap();
+    assert_eq!(harness.bet(&bet).await.finalized_count, 1);
+    assert_eq!(harness.creator_exposure(&creator).await.open_bets, 1);
+
+    let close = instruction::close_settled_accounts(id(), harness.market, creator.pubkey(), bet.address, &[(accepted.address, acceptor.pubkey())]);
+    harness.process(&[close], &[]).await.unwrap();
+    assert_eq!(harness.creator_exposure(&creator).await.open_bets, 0);
+    assert!(harness.bet(&bet).await.open_bet_released);
+}
@@ -0,0 +1,92 @@
+//! RefundExpiredAcceptedBet returns both stakes of a bet its oracle can't settle once the refund delay has passed, and
+//! FinalizeBet only ever settles
+mod common;
+
+use common::*;
+use borsh::BorshSerialize;
+use pyth_client::PriceStatus;
+use solana_sdk::{account::AccountSharedData, transport::TransportError};
+use yoyo_bets::{
+    error::BetError,
+    instruction::BetInstruction,
+    pyth_fixtures::PriceAccountBuilder,
+    state::{Direction, PositionStatus, ORACLE_FEED_CHANGED_REFUND_SECS, ORACLE_UNAVAILABLE_REFUND_SECS},
+};
+
+async fn refund(harness: &mut Harness, refunder: &User, bet: &CreatedBet, accepted: &Accepted) -> Result<(), TransportError> {
+    // RefundExpiredAcceptedBet takes the FinalizeBet accounts
+    let mut refund = harness.finalize_instruction(refunder, bet, accepted).await;
+    refund.data = BetInstruction::RefundExpiredAcceptedBet().try_to_vec().unwrap();
+    harness.process(&[refund], &[&refunder.keypair]).await
+}
+
+fn set_feed(harness: &mut Harness, expo: i32, status: PriceStatus) {
+    let data = PriceAccountBuilder::new().price(10_000).expo(expo).product(harness.product).status(status).build();
+    harness.context.set_account(&harness.price, &AccountSharedData::from(pyth_account(data)));
+}
+
+#[solana_program_test::tokio::test]
+async fn a_halted_feed_is_refunded_only_after_its_delay() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let acceptor = harness.user(Payment::Token);
+    let keeper = harness.user(Payment::Token);
+    let bet = harness.create_bet(&creator, Payment::Token, &BetParams::new(1_000_000, 200, Direction::Above, 10_500)).await.unwrap();
+    let accepted = harness.accept_bet(&acceptor, &bet, 1_000_000).await.unwrap();
+    set_feed(&mut harness, PRICE_EXPO, PriceStatus::Halted);
+
+    harness.set_time(NOW + 3600 + ORACLE_UNAVAILABLE_REFUND_SECS - 1).await;
+    assert_bet_error(refund(&mut harness, &keeper, &bet, &accepted).await, 0, BetError::OraclePriceUnavailable);
+
+    // finalize still can't settle on the halted feed after the delay, the refund can
+    harness.set_time(NOW + 3600 + ORACLE_UNAVAILABLE_REFUND_SECS).await;
+    assert_bet_error(harness.finalize_bet(&keeper, &bet, &accepted).await, 0, BetError::OraclePriceUnavailable);
+    refund(&mut harness, &keeper, &bet, &accepted).await.unwrap();
+
+    // each stake is back, no commission and no finalizer reward
+    assert_eq!(harness.balance(&creator, Payment::Token).await, WALLET_TOKENS);
+    assert_eq!(harness.balance(&acceptor, Payment::Token).await, WALLET_TOKENS);
+    assert_eq!(harness.balance(&keeper, Payment::Token).await, WALLET_TOKENS);
+    assert_eq!(harness.fee_balance(Payment::Token).await, 0);
+    assert_eq!(harness.accepted(&accepted).await.status, PositionStatus::Refunded);
+    assert_bet_error(refund(&mut harness, &keeper, &bet, &accepted).await, 0, BetError::BetFinalized);
+}
+
+#[solana_program_test::tokio::test]
+async fn a_changed_feed_is_refunded_after_its_delay() {
+    for payment in [Payment::Sol, Payment::Token] {
+        let mut harness = Harness::start().await;
+        let creator = harness.user(payment);
+        let acceptor = harness.user(payment);
+        let keeper = harness.user(payment);
+        let bet = harness.create_bet(&creator, payment, &BetParams::new(1_000_000, 200, Direction::Below, 10_500)).await.unwrap();
+        let accepted = harness.accept_bet(&acceptor, &bet, 1_000_000).await.unwrap();
+        // the price account now reports another exponent
+        set_feed(&mut harness, PRICE_EXPO - 1, PriceStatus::Trading);
+
+        harness.set_time(NOW + 3600 + ORACLE_FEED_CHANGED_REFUND_SECS - 1).await;
+        assert_bet_error(refund(&mut harness, &keeper, &bet, &accepted).await, 0, BetError::OracleFeedChanged);
+        harness.set_time(NOW + 3600 + ORACLE_FEED_CHANGED_REFUND_SECS).await;
+        assert_bet_error(harness.finalize_bet(&keeper, &bet, &accepted).await, 0, BetError::OracleFeedChanged);
+
+        let creator_before = harness.balance(&creator, payment).await;
+        refund(&mut harness, &keeper, &bet, &accepted).await.unwrap();
+        assert_eq!(harness.balance(&creator, payment).await, creator_before + 1_000_000, "{:?}", payment);
+        assert_eq!(harness.accepted(&accepted).await.status, PositionStatus::Refunded);
+    }
+}
+
+#[solana_program_test::tokio::test]
+async fn a_settleable_bet_is_not_refundable() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    let acceptor = harness.user(Payment::Token);
+    let keeper = harness.user(Payment::Token);
+    let bet = harness.create_bet(&creator, Payment::Token, &BetParams::new(1_000_000, 200, Direction::Above, 10_500)).await.unwrap();
+    let accepted = harness.accept_bet(&acceptor, &bet, 1_000_000).await.unwrap();
+
+    harness.set_time(NOW + 3600 + ORACLE_UNAVAILABLE_REFUND_SECS).await;
+    assert_bet_error(refund(&mut harness, &keeper, &bet, &accepted).await, 0, BetError::NotRefundable);
+    harness.finalize_bet(&keeper, &bet, &accepted).await.unwrap();
+    assert_eq!(harness.accepted(&accepted).await.status, PositionStatus::Settled);
+}
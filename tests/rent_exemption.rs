@@ -0,0 +1,68 @@
+//! Escrows and bet state accounts stay rent exempt through partial fills and keeper bounty payouts
+mod common;
+
+use common::*;
+use solana_program::program_pack::Pack;
+use solana_sdk::account::AccountSharedData;
+use yoyo_bets::{
+    error::BetError,
+    state::{BetStatus, Direction, MAX_BET_DATA_LENGTH},
+};
+
+fn escrow_rent() -> u64 {
+    rent_exempt(spl_token::state::Account::LEN)
+}
+
+#[solana_program_test::tokio::test]
+async fn sol_escrows_keep_their_rent_through_partial_fills_around_the_rent_minimum() {
+    for bet_size in [escrow_rent() - 1, escrow_rent() + 1] {
+        let mut harness = Harness::start().await;
+        let creator = harness.user(Payment::Sol);
+        let acceptor = harness.user(Payment::Sol);
+
+        let bet = harness.create_bet(&creator, Payment::Sol, &BetParams::new(bet_size, 200, Direction::Above, 10_500)).await.unwrap();
+        assert_eq!(harness.lamports(&bet.escrow).await, escrow_rent() + bet_size);
+
+        // matching less than half leaves an unmatched remainder smaller than the escrow's own rent
+        let matched = bet_size / 3;
+        let accepted = harness.accept_bet(&acceptor, &bet, matched).await.unwrap();
+        assert_eq!(harness.token_amount(&bet.escrow).await, bet_size - matched);
+        assert_eq!(harness.lamports(&bet.escrow).await, escrow_rent() + bet_size - matched);
+        assert_eq!(harness.lamports(&accepted.escrow).await, escrow_rent() + 2 * matched);
+        assert_eq!(harness.bet(&bet).await.status, BetStatus::PartiallyAccepted);
+
+        // only a full cancel takes the rent, by closing the escrow
+        harness.cancel_all(&creator, &bet).await.unwrap();
+        assert!(!harness.exists(&bet.escrow).await);
+        assert_eq!(harness.lamports(&accepted.escrow).await, escrow_rent() + 2 * matched);
+    }
+}
+
+#[solana_program_test::tokio::test]
+async fn keeper_bounty_payouts_leave_the_bet_state_account_rent_exempt() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Sol);
+    let first_acceptor = harness.user(Payment::Sol);
+    let second_acceptor = harness.user(Payment::Sol);
+    let keeper = harness.user(Payment::Sol);
+
+    let mut params = BetParams::new(SOL, 200, Direction::Above, 10_500);
+    params.keeper_bounty_lamports = 1_000_000;
+    let bet = harness.create_bet(&creator, Payment::Sol, &params).await.unwrap();
+    let first = harness.accept_bet(&first_acceptor, &bet, SOL / 2).await.unwrap();
+    let second = harness.accept_bet(&second_acceptor, &bet, SOL / 2).await.unwrap();
+    assert_eq!(harness.lamports(&bet.address).await, rent_exempt(MAX_BET_DATA_LENGTH) + 1_000_000);
+
+    harness.set_time(NOW + 3600).await;
+    harness.finalize_bet(&keeper, &bet, &first).await.unwrap();
+    assert_eq!(harness.lamports(&bet.address).await, rent_exempt(MAX_BET_DATA_LENGTH) + 500_000);
+
+    // the account lost lamports it held above its rent, paying the other half would take it below the minimum
+    let mut account = harness.account(&bet.address).await;
+    account.lamports = rent_exempt(MAX_BET_DATA_LENGTH) + 499_999;
+    harness.context.set_account(&bet.address, &AccountSharedData::from(account));
+    let keeper_lamports = harness.lamports(&keeper.pubkey()).await;
+    assert_bet_error(harness.finalize_bet(&keeper, &bet, &second).await, 0, BetError::WouldBreakRentExemption);
+    assert_eq!(harness.lamports(&keeper.pubkey()).await, keeper_lamports);
+    assert_eq!(harness.lamports(&bet.address).await, rent_exempt(MAX_BET_DATA_LENGTH) + 499_999);
+}
@@ -0,0 +1,60 @@
+//! Fresh wallets create and accept bets in one transaction each, signed and paid for by the wallet alone
+mod common;
+
+use common::*;
+use solana_program::program_pack::Pack;
+use yoyo_bets::{
+    state::{BetStatus, Direction, PositionStatus, MAX_ACCEPTED_BET_DATA_LEN, MAX_BET_DATA_LENGTH, MAX_CREATOR_EXPOSURE_DATA_LEN, MAX_FEED_EXPOSURE_DATA_LEN},
+};
+
+#[solana_program_test::tokio::test]
+async fn sol_bet_created_in_one_transaction_from_a_fresh_wallet() {
+    let mut harness = Harness::start().await;
+    // the wallet holds SOL and nothing else, no token accounts
+    let creator = harness.user(Payment::Sol);
+    let fee = harness.lamports_per_signature().await;
+
+    let (instructions, bet) = harness.create_bet_instructions(&creator, Payment::Sol, &BetParams::new(SOL, 200, Direction::Above, 10_500));
+    harness.process_paid_by(&instructions, &[&creator.keypair]).await.unwrap();
+
+    // the creator paid the fee, the rent of every account the bet needs and the size, which is escrowed
+    let rent = rent_exempt(MAX_BET_DATA_LENGTH)
+        + rent_exempt(spl_token::state::Account::LEN)
+        + rent_exempt(MAX_CREATOR_EXPOSURE_DATA_LEN)
+        + rent_exempt(MAX_FEED_EXPOSURE_DATA_LEN);
+    assert_eq!(harness.lamports(&creator.pubkey()).await, WALLET_LAMPORTS - fee - rent - SOL);
+    assert_eq!(harness.token_amount(&bet.escrow).await, SOL);
+    assert_eq!(harness.lamports(&bet.escrow).await, rent_exempt(spl_token::state::Account::LEN) + SOL);
+    let bet_state = harness.bet(&bet).await;
+    assert_eq!((bet_state.status, bet_state.bet_size), (BetStatus::Open, SOL));
+}
+
+#[solana_program_test::tokio::test]
+async fn token_bet_accepted_in_one_transaction_from_a_wallet_holding_only_tokens() {
+    let mut harness = Harness::start().await;
+    let creator = harness.user(Payment::Token);
+    // the acceptor has their token account and SOL for the fee and rent, nothing is created for them beforehand
+    let acceptor = harness.user(Payment::Token);
+    let keeper = harness.user(Payment::Token);
+    let fee = harness.lamports_per_signature().await;
+
+    let bet = harness.create_bet(&creator, Payment::Token, &BetParams::new(1_000_000, 250, Direction::Below, 9_900)).await.unwrap();
+    let (accept, accepted) = harness.accept_instruction(&acceptor, &bet, 1_000_000).await;
+    harness.process_paid_by(&[accept], &[&acceptor.keypair]).await.unwrap();
+
+    // one signature, the acceptor paid the fee, the rent of the accepted bet and its escrow, and 1.5x the size
+    let accept_rent = rent_exempt(MAX_ACCEPTED_BET_DATA_LEN) + rent_exempt(spl_token::state::Account::LEN);
+    assert_eq!(harness.lamports(&acceptor.pubkey()).await, WALLET_LAMPORTS - fee - accept_rent);
+    assert_eq!(harness.balance(&acceptor, Payment::Token).await, WALLET_TOKENS - 1_500_000);
+    assert_eq!(harness.token_amount(&accepted.escrow).await, 2_500_000);
+    assert_eq!(harness.token_amount(&bet.escrow).await, 0);
+
+    harness.set_time(NOW + 3600).await;
+    harness.finalize_bet(&keeper, &bet, &accepted).await.unwrap();
+    assert_eq!(harness.accepted(&accepted).await.status, PositionStatus::Settled);
+    // the price stayed above 99.00, the acceptor takes the pot less 2% and 0.5% of the size
+    assert_eq!(harness.balance(&acceptor, Payment::Token).await, WALLET_TOKENS - 1_500_000 + 2_475_000);
+    assert_eq!(harness.balance(&creator, Payment::Token).await, WALLET_TOKENS - 1_000_000);
+    assert_eq!(harness.fee_balance(Payment::Token).await, 20_000);
+    assert_eq!(harness.balance(&keeper, Payment::Token).await, WALLET_TOKENS + 5_000);
+}
@@ -0,0 +1,95 @@
+//! Decoding state accounts from raw account data
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+use yoyo_bets::{
+    error::BetError,
+    state::{AcceptedBet, Bet, BettingMarket, Direction, PositionStatus, Reserved, MAX_ACCEPTED_BET_DATA_LEN, MAX_BET_DATA_LENGTH, MAX_BETTING_MARKET_DATA_LEN, RESERVED_LEN},
+    utils::{serialize_account_data, try_from_account_data},
+};
+
+fn data_type_mismatch() -> ProgramError {
+    BetError::DataTypeMismatch.into()
+}
+
+#[test]
+fn accounts_of_another_size_are_rejected() {
+    // one byte short, one byte over, and another state account's size
+    assert_eq!(Bet::from_account_data(&vec![0; MAX_BET_DATA_LENGTH - 1]).unwrap_err(), data_type_mismatch());
+    assert_eq!(Bet::from_account_data(&vec![0; MAX_BET_DATA_LENGTH + 1]).unwrap_err(), data_type_mismatch());
+    assert_eq!(Bet::from_account_data(&vec![0; MAX_ACCEPTED_BET_DATA_LEN]).unwrap_err(), data_type_mismatch());
+    assert_eq!(AcceptedBet::from_account_data(&vec![0; MAX_BET_DATA_LENGTH]).unwrap_err(), data_type_mismatch());
+    assert_eq!(BettingMarket::from_account_data(&[]).unwrap_err(), data_type_mismatch());
+}
+
+#[test]
+fn zeroed_accounts_of_the_right_size_decode() {
+    assert!(!Bet::from_account_data(&vec![0; MAX_BET_DATA_LENGTH]).unwrap().is_initialized());
+    assert!(!AcceptedBet::from_account_data(&vec![0; MAX_ACCEPTED_BET_DATA_LEN]).unwrap().is_initialized());
+    assert_eq!(BettingMarket::from_account_data(&vec![0; MAX_BETTING_MARKET_DATA_LEN]).unwrap().accepted_mints_count, 0);
+}
+
+// AcceptedBet as a next version would declare it, with a new field carved out of the front of the reserved space
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+struct AcceptedBetNext {
+    status: PositionStatus,
+    bet: Pubkey,
+    accepted_bet_escrow_account: Pubkey,
+    acceptor_main_account: Pubkey,
+    acceptor_payment_account: Pubkey,
+    odds: i64,
+    bet_size: u64,
+    legacy_finalized: bool,
+    fees_pending: bool,
+    commission_amount: u64,
+    finalizer_amount: u64,
+    finalizer_payment_account: Pubkey,
+    escrow_rent_recipient: Pubkey,
+    creator_direction: Direction,
+    unwind_deadline: i64, // the new field, zero reads as unset
+    reserved: Reserved<{ RESERVED_LEN - 8 }>,
+}
+
+fn current_position() -> AcceptedBet {
+    let mut position = AcceptedBet::from_account_data(&vec![0; MAX_ACCEPTED_BET_DATA_LEN]).unwrap();
+    position.status = PositionStatus::Open;
+    position.bet = Pubkey::new_unique();
+    position.acceptor_main_account = Pubkey::new_unique();
+    position.odds = 250;
+    position.bet_size = 1_000;
+    position.creator_direction = Direction::Below;
+    position
+}
+
+#[test]
+fn a_next_version_reads_a_current_account_with_the_new_field_unset() {
+    let position = current_position();
+    let mut data = vec![0; MAX_ACCEPTED_BET_DATA_LEN];
+    serialize_account_data(&position, &mut data, "AcceptedBet").unwrap();
+
+    // same size, so the current account decodes as the next version without a migration
+    let next: AcceptedBetNext = try_from_account_data(&data, MAX_ACCEPTED_BET_DATA_LEN, "AcceptedBet").unwrap();
+    assert_eq!(next.unwind_deadline, 0);
+    assert_eq!(next.status, PositionStatus::Open);
+    assert_eq!(next.bet, position.bet);
+    assert_eq!(next.acceptor_main_account, position.acceptor_main_account);
+    assert_eq!((next.odds, next.bet_size), (250, 1_000));
+    assert_eq!(next.creator_direction, Direction::Below);
+}
+
+#[test]
+fn a_next_version_field_is_read_out_of_the_current_reserved_space() {
+    let position = current_position();
+    let mut data = vec![0; MAX_ACCEPTED_BET_DATA_LEN];
+    serialize_account_data(&position, &mut data, "AcceptedBet").unwrap();
+    let mut next: AcceptedBetNext = try_from_account_data(&data, MAX_ACCEPTED_BET_DATA_LEN, "AcceptedBet").unwrap();
+    next.unwind_deadline = 1_700_000_000;
+    serialize_account_data(&next, &mut data, "AcceptedBet").unwrap();
+
+    // the current version keeps decoding the account, the new field is the front of its reserved space
+    let current = AcceptedBet::from_account_data(&data).unwrap();
+    assert_eq!(current.reserved.0[..8], 1_700_000_000i64.to_le_bytes());
+    assert!(current.reserved.0[8..].iter().all(|byte| *byte == 0));
+    assert_eq!(current.bet, position.bet);
+    assert_eq!(current.bet_size, position.bet_size);
+    assert_eq!(current.creator_direction, position.creator_direction);
+}